@@ -0,0 +1,67 @@
+//! benches/first_tilt
+//!
+//! Compares decoding only the lowest tilt's reflectivity against decoding the full volume, and
+//! reports whether the fast path's median latency meets the 50 ms alerting target.
+//!
+//! Usage: cargo bench --bench first_tilt
+//!
+
+use std::time::{Duration, Instant};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nexrad::high_level::decode_first_tilt;
+use nexrad::DataFile;
+
+const HURRICANE_HARVEY: &str = "resources/KCRP20170825_235733_V06_hurricane_harvey";
+
+/// The end-to-end latency the first tilt fast path must achieve for alerting.
+const LATENCY_TARGET: Duration = Duration::from_millis(50);
+
+/// The number of timed decodes the median latency is taken from.
+const LATENCY_SAMPLES: usize = 51;
+
+fn first_tilt(c: &mut Criterion) {
+    let data = std::fs::read(HURRICANE_HARVEY).expect("read test file");
+
+    let mut group = c.benchmark_group("lowest_tilt");
+    group.sample_size(20);
+
+    group.bench_function("decode_first_tilt", |b| {
+        b.iter(|| decode_first_tilt(black_box(&data)).expect("decode first tilt"));
+    });
+
+    group.bench_function("decode_full_volume", |b| {
+        b.iter(|| DataFile::from_slice(black_box(&data)).expect("decode volume"));
+    });
+
+    group.finish();
+}
+
+fn latency_target(_: &mut Criterion) {
+    let data = std::fs::read(HURRICANE_HARVEY).expect("read test file");
+
+    let mut latencies: Vec<Duration> = (0..LATENCY_SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            decode_first_tilt(black_box(&data)).expect("decode first tilt");
+            start.elapsed()
+        })
+        .collect();
+    latencies.sort();
+    let median = latencies[LATENCY_SAMPLES / 2];
+
+    let verdict = if median <= LATENCY_TARGET {
+        "met"
+    } else {
+        "MISSED"
+    };
+    println!(
+        "first tilt median latency: {:.1} ms over {LATENCY_SAMPLES} decodes, {verdict} the {} ms \
+         target",
+        median.as_secs_f64() * 1000.0,
+        LATENCY_TARGET.as_millis(),
+    );
+}
+
+criterion_group!(benches, first_tilt, latency_target);
+criterion_main!(benches);
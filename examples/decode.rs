@@ -18,7 +18,13 @@ async fn main() -> Result<()> {
         panic!("Usage: cargo run --example decode -- <file>");
     }
 
-    let file = Path::new(&args[1]);
+    decode(Path::new(&args[1]))?;
+
+    Ok(())
+}
+
+/// Decodes the data file at the specified path and reports its elevations.
+pub fn decode(file: &Path) -> Result<DataFile> {
     let datafile = DataFile::new(file)?;
 
     println!(
@@ -26,5 +32,5 @@ async fn main() -> Result<()> {
         datafile.elevation_scans().len()
     );
 
-    Ok(())
+    Ok(datafile)
 }
@@ -7,13 +7,13 @@
 
 #![cfg(feature = "download")]
 
-use chrono::{NaiveDate, NaiveTime};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use std::env;
-use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
-use nexrad::download::{download_file, list_files};
-use nexrad::file_metadata::is_compressed;
+use nexrad::high_level::{FailoverDownloader, VolumeSource};
+use nexrad::raw::is_compressed;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -36,34 +36,42 @@ async fn main() -> Result<()> {
         requested_time = NaiveTime::parse_from_str(time_str, "%H:%M").expect("is valid time");
     }
 
+    let downloader = FailoverDownloader::new(vec![VolumeSource::Aws]);
+    download_nearest(
+        &downloader,
+        site,
+        date.and_time(requested_time),
+        Path::new("."),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Downloads the data file nearest the requested time into the output directory, returning its
+/// path, or `None` if the site has no files on the requested date.
+pub async fn download_nearest(
+    downloader: &FailoverDownloader,
+    site: &str,
+    requested_time: NaiveDateTime,
+    output: &Path,
+) -> Result<Option<PathBuf>> {
+    let date = requested_time.date();
     println!("Listing files for {} on {}...", site, date);
-    let metas = list_files(site, &date).await?;
+    let metas = downloader.list_files(site, &date).await?;
 
     if metas.is_empty() {
         println!("No files found for the specified date/site to download.");
-        return Ok(());
+        return Ok(None);
     }
 
     println!("Found {} files.", metas.len());
 
-    let mut meta = metas.first().expect("found at least one meta");
-
-    let mut min_diff = std::i64::MAX;
-    for m in metas.iter() {
-        let identifier_parts = m.identifier().split('_');
-        let identifier_time = identifier_parts.collect::<Vec<_>>()[1];
-        let identifier_time =
-            NaiveTime::parse_from_str(identifier_time, "%H%M%S").expect("is valid time");
-
-        let diff = (identifier_time.signed_duration_since(requested_time))
-            .num_seconds()
-            .abs();
-
-        if diff < min_diff {
-            min_diff = diff;
-            meta = m;
-        }
-    }
+    let meta = metas
+        .iter()
+        .filter_map(|meta| Some((meta, meta.date_time()?)))
+        .min_by_key(|(_, time)| (*time - requested_time).abs())
+        .map_or(&metas[0], |(meta, _)| meta);
 
     println!(
         "Nearest file to {:?} is {:?}.",
@@ -72,17 +80,16 @@ async fn main() -> Result<()> {
     );
 
     println!("Downloading file \"{}\"...", meta.identifier());
-    let downloaded_file = download_file(meta).await?;
+    let downloaded_file = downloader.download_file(meta).await?;
 
     println!("Data file size (bytes): {}", downloaded_file.len());
 
     let is_compressed = is_compressed(downloaded_file.as_slice());
     println!("File data is compressed: {}", is_compressed);
 
-    println!("Writing file to disk as: {}", meta.identifier());
-    let mut file = std::fs::File::create(meta.identifier()).expect("create file");
-    file.write_all(downloaded_file.as_slice())
-        .expect("write file");
+    let path = output.join(meta.identifier());
+    println!("Writing file to disk as: {}", path.display());
+    std::fs::write(&path, downloaded_file)?;
 
-    Ok(())
+    Ok(Some(path))
 }
@@ -0,0 +1,104 @@
+//! examples/ingest
+//!
+//! This example downloads the data file nearest some time, decodes it, grids its lowest
+//! elevation's reflectivity, and exports the volume, trying a local cache before AWS.
+//!
+//! Usage: cargo run --example ingest -- <site> <date> <time> <outputDir> [cacheDir]
+//!
+
+#![cfg(feature = "download")]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use nexrad::high_level::encode_compressed_file;
+use nexrad::high_level::encode_uf;
+use nexrad::high_level::{grid_sweep, GridSpec};
+use nexrad::high_level::{FailoverDownloader, VolumeSource};
+use nexrad::{DataFile, Product};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 5 {
+        panic!("Usage: cargo run --example ingest -- <site> <date> <time> <outputDir> [cacheDir]");
+    }
+
+    let site = &args[1];
+    let date = NaiveDate::parse_from_str(&args[2], "%Y-%m-%d")?;
+    let time = date.and_time(NaiveTime::parse_from_str(&args[3], "%H:%M")?);
+    let output = Path::new(&args[4]);
+
+    let mut sources = Vec::new();
+    if let Some(cache) = args.get(5) {
+        sources.push(VolumeSource::LocalCache(PathBuf::from(cache)));
+    }
+    sources.push(VolumeSource::Aws);
+    let downloader = FailoverDownloader::new(sources);
+
+    ingest(&downloader, site, time, output).await?;
+
+    for (source, health) in downloader.sources().iter().zip(downloader.health()) {
+        println!(
+            "{:?}: {} succeeded, {} failed",
+            source,
+            health.successes(),
+            health.failures()
+        );
+    }
+
+    Ok(())
+}
+
+/// Downloads the data file nearest the requested time, decodes it, and writes its gridded lowest
+/// elevation's reflectivity and its Universal Format and Archive II exports to the output
+/// directory, returning the paths written.
+pub async fn ingest(
+    downloader: &FailoverDownloader,
+    site: &str,
+    time: NaiveDateTime,
+    output: &Path,
+) -> Result<Vec<PathBuf>> {
+    let date = time.date();
+    println!("Listing files for {} on {}...", site, date);
+    let metas = downloader.list_files(site, &date).await?;
+    let (meta, _) = metas
+        .iter()
+        .filter_map(|meta| Some((meta, meta.date_time()?)))
+        .min_by_key(|(_, file_time)| (*file_time - time).abs())
+        .ok_or_else(|| anyhow!("no files found for {} on {}", site, date))?;
+
+    println!("Downloading file \"{}\"...", meta.identifier());
+    let file = DataFile::from_vec(downloader.download_file(meta).await?)?;
+    println!(
+        "Decoded file with {} elevations.",
+        file.elevation_scans().len()
+    );
+
+    fs::create_dir_all(output)?;
+
+    let radials = file
+        .elevation_scan_at(0)
+        .ok_or_else(|| anyhow!("file has no elevations"))?;
+    let grid = grid_sweep(
+        radials,
+        Product::Reflectivity,
+        &GridSpec::new(460, 460, 1000.0),
+    );
+    let grid_path = output.join(format!("{}_ref_460x460.f32", meta.identifier()));
+    println!("Writing gridded reflectivity to {}", grid_path.display());
+    fs::write(&grid_path, grid.to_f32_le_bytes())?;
+
+    let uf_path = output.join(format!("{}.uf", meta.identifier()));
+    println!("Exporting Universal Format to {}", uf_path.display());
+    fs::write(&uf_path, encode_uf(&file)?)?;
+
+    let archive_path = output.join(format!("{}.ar2v", meta.identifier()));
+    println!("Exporting Archive II to {}", archive_path.display());
+    fs::write(&archive_path, encode_compressed_file(&file)?)?;
+
+    Ok(vec![grid_path, uf_path, archive_path])
+}
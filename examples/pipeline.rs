@@ -0,0 +1,41 @@
+//! examples/pipeline
+//!
+//! This example runs a processing configuration over a directory of Archive II files, writing the
+//! configured products and exports for each site and time in its manifest.
+//!
+//! Usage: cargo run --example pipeline -- <config.toml> <directory>
+//!
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use nexrad::high_level::DirectoryLoader;
+use nexrad::high_level::{Pipeline, ProcessingConfig};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        panic!("Usage: cargo run --example pipeline -- <config.toml> <directory>");
+    }
+
+    run(Path::new(&args[1]), Path::new(&args[2]))?;
+
+    Ok(())
+}
+
+/// Runs the processing configuration at the specified path over a directory of Archive II files,
+/// returning the paths written.
+pub fn run(config: &Path, directory: &Path) -> Result<Vec<PathBuf>> {
+    let config: ProcessingConfig = toml::from_str(&fs::read_to_string(config)?)?;
+    let pipeline = Pipeline::new(config);
+
+    let written = pipeline.run_manifest(&DirectoryLoader::new(directory))?;
+    for path in &written {
+        println!("Wrote {}", path.display());
+    }
+    println!("Wrote {} outputs.", written.len());
+
+    Ok(written)
+}
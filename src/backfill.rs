@@ -0,0 +1,281 @@
+//!
+//! Provides [``Backfill``] for retrieving a site's volumes over a time window as one continuous
+//! stream ordered by time. Completed volumes reach the archive only some minutes after they are
+//! collected, so the window's most recent volumes are assembled from the real-time chunk feed.
+//!
+
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::decoder::DataFile;
+use crate::metadata::FileMetadata;
+
+/// A source of archived volumes, e.g. the archive bucket.
+pub trait ArchiveSource {
+    /// Lists the volumes archived for a site on a date.
+    fn list_volumes(
+        &self,
+        site: &str,
+        date: NaiveDate,
+    ) -> impl Future<Output = Result<Vec<FileMetadata>>> + Send;
+
+    /// Retrieves an archived volume's encoded contents.
+    fn fetch_volume(&self, meta: &FileMetadata) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+/// A source of the chunks volumes are published in as they are collected, e.g. the real-time
+/// chunk bucket.
+pub trait ChunkSource {
+    /// Lists the chunks currently available for a site.
+    fn list_chunks(&self, site: &str) -> impl Future<Output = Result<Vec<ChunkMetadata>>> + Send;
+
+    /// Retrieves a chunk's encoded contents.
+    fn fetch_chunk(&self, chunk: &ChunkMetadata) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+/// A chunk's position within its volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+    /// The first chunk, beginning with the volume header.
+    Start,
+    /// A chunk between the first and last.
+    Intermediate,
+    /// The last chunk, completing the volume.
+    End,
+}
+
+/// Metadata describing a chunk of a volume from the real-time feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkMetadata {
+    site: String,
+    volume_number: u16,
+    time: NaiveDateTime,
+    sequence: u16,
+    kind: ChunkKind,
+}
+
+impl ChunkMetadata {
+    /// Create new chunk metadata.
+    #[must_use]
+    pub fn new(
+        site: String,
+        volume_number: u16,
+        time: NaiveDateTime,
+        sequence: u16,
+        kind: ChunkKind,
+    ) -> Self {
+        Self {
+            site,
+            volume_number,
+            time,
+            sequence,
+            kind,
+        }
+    }
+
+    /// Parses chunk metadata from a real-time feed key, e.g. `KDMX/585/20240101-120000-001-S`.
+    /// Returns `None` if the key is not in that form.
+    #[must_use]
+    pub fn from_key(key: &str) -> Option<Self> {
+        let mut parts = key.split('/');
+        let site = parts.next()?;
+        let volume_number = parts.next()?.parse().ok()?;
+        let name = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let (time, rest) = name.split_at_checked(15)?;
+        let time = NaiveDateTime::parse_from_str(time, "%Y%m%d-%H%M%S").ok()?;
+        let (sequence, kind) = rest.strip_prefix('-')?.split_once('-')?;
+        let kind = match kind {
+            "S" => ChunkKind::Start,
+            "I" => ChunkKind::Intermediate,
+            "E" => ChunkKind::End,
+            _ => return None,
+        };
+
+        Some(Self::new(
+            site.to_string(),
+            volume_number,
+            time,
+            sequence.parse().ok()?,
+            kind,
+        ))
+    }
+
+    /// The chunk's key in the real-time feed.
+    #[must_use]
+    pub fn key(&self) -> String {
+        let kind = match self.kind {
+            ChunkKind::Start => "S",
+            ChunkKind::Intermediate => "I",
+            ChunkKind::End => "E",
+        };
+        format!(
+            "{}/{}/{}-{:03}-{kind}",
+            self.site,
+            self.volume_number,
+            self.time.format("%Y%m%d-%H%M%S"),
+            self.sequence
+        )
+    }
+
+    /// The radar site the chunk was produced at, e.g. KDMX.
+    #[must_use]
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    /// The number of the volume the chunk belongs to, which the feed cycles through.
+    #[must_use]
+    pub fn volume_number(&self) -> u16 {
+        self.volume_number
+    }
+
+    /// When the chunk was published.
+    #[must_use]
+    pub fn time(&self) -> NaiveDateTime {
+        self.time
+    }
+
+    /// The chunk's position within its volume, starting from 1.
+    #[must_use]
+    pub fn sequence(&self) -> u16 {
+        self.sequence
+    }
+
+    /// The chunk's kind.
+    #[must_use]
+    pub fn kind(&self) -> ChunkKind {
+        self.kind
+    }
+}
+
+/// A volume yet to be retrieved.
+enum PendingVolume {
+    Archived(FileMetadata),
+    RealTime(Vec<ChunkMetadata>),
+}
+
+/// A site's volumes over a time window, from the archive followed by the real-time feed. Volumes
+/// are listed when the backfill is created and retrieved one at a time as they are requested.
+pub struct Backfill<A, C> {
+    archive: A,
+    chunks: C,
+    pending: VecDeque<(NaiveDateTime, PendingVolume)>,
+}
+
+impl<A: ArchiveSource, C: ChunkSource> Backfill<A, C> {
+    /// Lists a site's volumes beginning within a time window: every archived volume, followed by
+    /// each complete volume from the real-time feed beginning after the last archived one. Volumes
+    /// still being published are omitted, as are the archive's metadata-only `_MDM` files.
+    ///
+    /// # Errors
+    /// Returns an error if either source's listing cannot be retrieved.
+    pub async fn new(
+        archive: A,
+        chunks: C,
+        site: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+    ) -> Result<Self> {
+        let mut pending = Vec::new();
+        let mut date = start.date();
+        while date <= end.date() {
+            for meta in archive.list_volumes(site, date).await? {
+                if let Some(time) = meta.date_time() {
+                    if (start..=end).contains(&time) {
+                        pending.push((time, PendingVolume::Archived(meta)));
+                    }
+                }
+            }
+            let Some(next) = date.succ_opt() else {
+                break;
+            };
+            date = next;
+        }
+        pending.sort_by_key(|(time, _)| *time);
+        let archived_until = pending.last().map(|(time, _)| *time);
+
+        let mut volumes: BTreeMap<u16, Vec<ChunkMetadata>> = BTreeMap::new();
+        for chunk in chunks.list_chunks(site).await? {
+            volumes.entry(chunk.volume_number).or_default().push(chunk);
+        }
+
+        let mut real_time = Vec::new();
+        for mut volume in volumes.into_values() {
+            volume.sort_by_key(|chunk| chunk.sequence);
+            if !is_complete(&volume) {
+                continue;
+            }
+
+            let time = volume[0].time;
+            let after_archive = archived_until.is_none_or(|archived| time > archived);
+            if after_archive && (start..=end).contains(&time) {
+                real_time.push((time, PendingVolume::RealTime(volume)));
+            }
+        }
+        real_time.sort_by_key(|(time, _)| *time);
+        pending.extend(real_time);
+
+        Ok(Self {
+            archive,
+            chunks,
+            pending: pending.into(),
+        })
+    }
+
+    /// The start times of the volumes yet to be retrieved, in order.
+    #[must_use]
+    pub fn times(&self) -> Vec<NaiveDateTime> {
+        self.pending.iter().map(|(time, _)| *time).collect()
+    }
+
+    /// The number of volumes yet to be retrieved.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Retrieves and decodes the next volume, or returns `None` once every volume has been.
+    /// A volume that fails is skipped by the following call, so the stream may continue past it.
+    pub async fn next_volume(&mut self) -> Option<Result<DataFile>> {
+        let (_, volume) = self.pending.pop_front()?;
+
+        let data = match volume {
+            PendingVolume::Archived(meta) => self.archive.fetch_volume(&meta).await,
+            PendingVolume::RealTime(volume) => {
+                let mut data = Vec::new();
+                for chunk in &volume {
+                    match self.chunks.fetch_chunk(chunk).await {
+                        Ok(chunk) => data.extend(chunk),
+                        Err(error) => return Some(Err(error)),
+                    }
+                }
+                Ok(data)
+            }
+        };
+
+        Some(data.and_then(DataFile::from_vec))
+    }
+}
+
+/// Whether a volume's chunks, sorted by sequence, run without gaps from its start to its end.
+fn is_complete(volume: &[ChunkMetadata]) -> bool {
+    let in_sequence = volume
+        .iter()
+        .zip(1..)
+        .all(|(chunk, sequence)| chunk.sequence == sequence);
+
+    in_sequence
+        && volume
+            .first()
+            .is_some_and(|chunk| chunk.kind == ChunkKind::Start)
+        && volume
+            .last()
+            .is_some_and(|chunk| chunk.kind == ChunkKind::End)
+}
@@ -0,0 +1,489 @@
+//!
+//! Provides [``render_batch``] for rendering imagery of many volumes in parallel from a
+//! [``RenderManifest``], e.g. to generate an archive of imagery for a set of sites and times.
+//! Batches are resumable: outputs which already exist are skipped.
+//!
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use anyhow::Result;
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::Deserialize;
+
+use crate::cancel::CancellationToken;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::records::{DataBlockProduct, Product};
+use crate::render::{render_all_elevations, render_sweep, Palette, RenderOptions};
+use crate::sweep::SweepCapabilities;
+
+/// A source of volumes by site and volume start time.
+pub trait VolumeLoader {
+    /// The volume from the specified site starting at the specified time, or `None` if there is
+    /// no such volume.
+    ///
+    /// # Errors
+    /// Returns an error if the volume exists but cannot be loaded.
+    fn load(&self, site: &str, time: NaiveDateTime) -> Result<Option<DataFile>>;
+}
+
+impl<F: Fn(&str, NaiveDateTime) -> Result<Option<DataFile>>> VolumeLoader for F {
+    fn load(&self, site: &str, time: NaiveDateTime) -> Result<Option<DataFile>> {
+        self(site, time)
+    }
+}
+
+/// Loads volumes from a directory of Archive II files named by site and start time as they are
+/// in the NOAA archive, e.g. `KDMX20230406_000215_V06`. A requested time loads the file nearest
+/// to it within a tolerance, by default a minute, since volume start times rarely fall on the
+/// requested second.
+#[derive(Debug, Clone)]
+pub struct DirectoryLoader {
+    directory: PathBuf,
+    tolerance: TimeDelta,
+}
+
+impl DirectoryLoader {
+    /// Create a loader for files in the specified directory.
+    #[must_use]
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            tolerance: TimeDelta::minutes(1),
+        }
+    }
+
+    /// How far a file's start time may be from the requested time, before or after, for it to be
+    /// loaded.
+    ///
+    /// # Panics
+    /// Panics if the tolerance is negative.
+    #[must_use]
+    pub fn with_tolerance(mut self, tolerance: TimeDelta) -> Self {
+        assert!(
+            tolerance >= TimeDelta::zero(),
+            "tolerance must not be negative"
+        );
+        self.tolerance = tolerance;
+        self
+    }
+}
+
+impl VolumeLoader for DirectoryLoader {
+    fn load(&self, site: &str, time: NaiveDateTime) -> Result<Option<DataFile>> {
+        let mut nearest: Option<(TimeDelta, PathBuf)> = None;
+
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let start = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(site))
+                .and_then(|name| name.get(..15))
+                .and_then(|name| NaiveDateTime::parse_from_str(name, "%Y%m%d_%H%M%S").ok());
+            let Some(start) = start else {
+                continue;
+            };
+
+            let offset = (start - time).abs();
+            let nearer = nearest
+                .as_ref()
+                .is_none_or(|(nearest, _)| offset < *nearest);
+            if offset <= self.tolerance && nearer && path.is_file() {
+                nearest = Some((offset, path));
+            }
+        }
+
+        nearest.map(|(_, path)| DataFile::new(&path)).transpose()
+    }
+}
+
+/// What is rendered for each volume and product.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchImage {
+    /// The lowest sweep containing the product.
+    LowestTilt,
+    /// Every sweep containing the product tiled into a contact sheet with the specified number of
+    /// columns.
+    ContactSheet(usize),
+}
+
+/// The sites, times, and products to render, and where to write each image.
+///
+/// Manifests are deserializable with serde, with unspecified settings taking the defaults of
+/// [``RenderManifest::new``] and times as `YYYY-MM-DDTHH:MM:SS`, e.g. in TOML:
+///
+/// ```toml
+/// sites = ["KDMX", "KOAX"]
+/// times = ["2023-04-06T00:02:15"]
+/// products = ["reflectivity", "velocity"]
+/// output_template = "imagery/{site}/{time}_{product}.png"
+/// image = { contact_sheet = 3 }
+/// options = { size = 1024, palette = "palettes/reflectivity.pal" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderManifest {
+    sites: Vec<String>,
+    times: Vec<NaiveDateTime>,
+    products: Vec<Product>,
+    output_template: String,
+    #[serde(default = "default_image")]
+    image: BatchImage,
+    #[serde(default)]
+    options: RenderOptions,
+    #[serde(
+        default = "available_threads",
+        deserialize_with = "deserialize_threads"
+    )]
+    threads: usize,
+    #[serde(skip)]
+    cancellation_token: Option<CancellationToken>,
+}
+
+fn default_image() -> BatchImage {
+    BatchImage::LowestTilt
+}
+
+fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, usize::from)
+}
+
+fn deserialize_threads<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<usize, D::Error> {
+    match usize::deserialize(deserializer)? {
+        0 => Err(serde::de::Error::custom("threads must be positive")),
+        threads => Ok(threads),
+    }
+}
+
+impl RenderManifest {
+    /// Create a manifest rendering every product for every site and time. Each image is written to
+    /// the output template with `{site}`, `{time}` (as `YYYYMMDD_HHMMSS`), and `{product}` (e.g.
+    /// `ref`) replaced, e.g. `imagery/{site}/{time}_{product}.png`. By default, the lowest tilt is
+    /// rendered with the default render options using every available core.
+    #[must_use]
+    pub fn new(
+        sites: Vec<String>,
+        times: Vec<NaiveDateTime>,
+        products: Vec<Product>,
+        output_template: &str,
+    ) -> Self {
+        Self {
+            sites,
+            times,
+            products,
+            output_template: output_template.to_string(),
+            image: default_image(),
+            options: RenderOptions::new(),
+            threads: available_threads(),
+            cancellation_token: None,
+        }
+    }
+
+    /// What is rendered for each volume and product.
+    #[must_use]
+    pub fn with_image(mut self, image: BatchImage) -> Self {
+        self.image = image;
+        self
+    }
+
+    /// The options each image is rendered with.
+    #[must_use]
+    pub fn with_render_options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The number of volumes rendered concurrently.
+    ///
+    /// # Panics
+    /// Panics if the number of threads is zero.
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "threads must be positive");
+        self.threads = threads;
+        self
+    }
+
+    /// A token which, once cancelled, stops the batch before its next volume.
+    #[must_use]
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// The radar sites rendered.
+    #[must_use]
+    pub fn sites(&self) -> &[String] {
+        &self.sites
+    }
+
+    /// The volume start times rendered for each site.
+    #[must_use]
+    pub fn times(&self) -> &[NaiveDateTime] {
+        &self.times
+    }
+
+    /// The products rendered for each volume.
+    #[must_use]
+    pub fn products(&self) -> &[Product] {
+        &self.products
+    }
+
+    /// The path an image of the specified site, time, and product is written to.
+    #[must_use]
+    pub fn output_path(&self, site: &str, time: NaiveDateTime, product: Product) -> PathBuf {
+        self.named_output_path(site, time, &product_name(product))
+    }
+
+    /// The output template with the specified site, time, and name in place of `{product}`.
+    pub(crate) fn named_output_path(&self, site: &str, time: NaiveDateTime, name: &str) -> PathBuf {
+        PathBuf::from(
+            self.output_template
+                .replace("{site}", site)
+                .replace("{time}", &time.format("%Y%m%d_%H%M%S").to_string())
+                .replace("{product}", name),
+        )
+    }
+}
+
+/// A single image in a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchJob {
+    site: String,
+    time: NaiveDateTime,
+    product: Product,
+}
+
+impl BatchJob {
+    /// The radar site rendered.
+    #[must_use]
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    /// The start time of the volume rendered.
+    #[must_use]
+    pub fn time(&self) -> NaiveDateTime {
+        self.time
+    }
+
+    /// The product rendered.
+    #[must_use]
+    pub fn product(&self) -> Product {
+        self.product
+    }
+}
+
+/// The outcome of each image in a batch.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    rendered: Vec<PathBuf>,
+    skipped: Vec<PathBuf>,
+    missing: Vec<BatchJob>,
+    failed: Vec<(BatchJob, String)>,
+}
+
+impl BatchReport {
+    /// Images rendered and written by this batch.
+    #[must_use]
+    pub fn rendered(&self) -> &[PathBuf] {
+        &self.rendered
+    }
+
+    /// Images skipped because they already existed, e.g. from an earlier interrupted batch.
+    #[must_use]
+    pub fn skipped(&self) -> &[PathBuf] {
+        &self.skipped
+    }
+
+    /// Images whose volume could not be found.
+    #[must_use]
+    pub fn missing(&self) -> &[BatchJob] {
+        &self.missing
+    }
+
+    /// Images which could not be rendered or written, with the reason.
+    #[must_use]
+    pub fn failed(&self) -> &[(BatchJob, String)] {
+        &self.failed
+    }
+}
+
+/// Renders every image in the manifest, loading each volume once for all of its products. Images
+/// whose output already exists are skipped, so an interrupted batch may be resumed by running it
+/// again; images are written to a temporary file first so that an interrupted write never leaves a
+/// truncated image at its output path. Failures of individual images are recorded in the report
+/// rather than stopping the batch.
+///
+/// # Errors
+/// Returns an error if the batch was cancelled.
+pub fn render_batch<L: VolumeLoader + Sync + ?Sized>(
+    manifest: &RenderManifest,
+    loader: &L,
+) -> Result<BatchReport> {
+    let report = Mutex::new(BatchReport::default());
+    for_each_volume(manifest, |site, time| {
+        render_volume(manifest, loader, site, time, &report);
+    })?;
+
+    Ok(report.into_inner().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Calls a function for each of the manifest's sites and times on the manifest's threads, stopping
+/// before the next volume once its cancellation token is cancelled.
+///
+/// # Errors
+/// Returns an error if the manifest was cancelled.
+pub(crate) fn for_each_volume<F: Fn(&str, NaiveDateTime) + Sync>(
+    manifest: &RenderManifest,
+    process: F,
+) -> Result<()> {
+    let volumes: Vec<(&str, NaiveDateTime)> = manifest
+        .sites
+        .iter()
+        .flat_map(|site| {
+            manifest
+                .times
+                .iter()
+                .map(move |time| (site.as_str(), *time))
+        })
+        .collect();
+
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..manifest.threads.min(volumes.len()) {
+            scope.spawn(|| {
+                while let Some((site, time)) = volumes.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if manifest
+                        .cancellation_token
+                        .as_ref()
+                        .is_some_and(CancellationToken::is_cancelled)
+                    {
+                        break;
+                    }
+
+                    process(site, *time);
+                }
+            });
+        }
+    });
+
+    if let Some(token) = &manifest.cancellation_token {
+        token.check()?;
+    }
+
+    Ok(())
+}
+
+/// Renders every product of a single volume, recording each image's outcome.
+fn render_volume<L: VolumeLoader + ?Sized>(
+    manifest: &RenderManifest,
+    loader: &L,
+    site: &str,
+    time: NaiveDateTime,
+    report: &Mutex<BatchReport>,
+) {
+    let record = |update: &mut dyn FnMut(&mut BatchReport)| {
+        update(&mut report.lock().unwrap_or_else(PoisonError::into_inner));
+    };
+    let job = |product: Product| BatchJob {
+        site: site.to_string(),
+        time,
+        product,
+    };
+
+    // Skip existing outputs, only loading the volume if any remain
+    let mut pending = Vec::new();
+    for product in &manifest.products {
+        let path = manifest.output_path(site, time, *product);
+        if path.exists() {
+            record(&mut |report| report.skipped.push(path.clone()));
+        } else {
+            pending.push((*product, path));
+        }
+    }
+
+    if pending.is_empty() {
+        return;
+    }
+
+    let file = match loader.load(site, time) {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            for (product, _) in pending {
+                record(&mut |report| report.missing.push(job(product)));
+            }
+            return;
+        }
+        Err(error) => {
+            for (product, _) in pending {
+                record(&mut |report| report.failed.push((job(product), error.to_string())));
+            }
+            return;
+        }
+    };
+
+    for (product, path) in pending {
+        match render_image(manifest, &file, product, &path) {
+            Ok(()) => record(&mut |report| report.rendered.push(path.clone())),
+            Err(error) => {
+                record(&mut |report| report.failed.push((job(product), error.to_string())));
+            }
+        }
+    }
+}
+
+/// Renders and writes a single image, via a temporary file which is renamed once complete.
+pub(crate) fn render_image(
+    manifest: &RenderManifest,
+    file: &DataFile,
+    product: Product,
+    path: &Path,
+) -> Result<()> {
+    let image = match manifest.image {
+        BatchImage::LowestTilt => {
+            let radials = file
+                .elevation_scans()
+                .values()
+                .find(|radials| SweepCapabilities::from_radials(radials).has(product))
+                .ok_or(Error::MissingRadials)?;
+
+            let palette = manifest
+                .options
+                .palette()
+                .cloned()
+                .unwrap_or_else(|| Palette::for_product(product));
+
+            render_sweep(radials, product, &palette, &manifest.options)
+        }
+        BatchImage::ContactSheet(columns) => {
+            render_all_elevations(file, product, &manifest.options)?.contact_sheet(columns)
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    image.write_png(&partial)?;
+    fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+/// The product's short lowercase name, e.g. `ref` for reflectivity.
+pub(crate) fn product_name(product: Product) -> String {
+    String::from_utf8_lossy(DataBlockProduct::from(product).data_name())
+        .trim()
+        .to_lowercase()
+}
@@ -0,0 +1,202 @@
+//!
+//! Provides [``TerrainBlockage``] for estimating how much of the radar beam is blocked by terrain,
+//! using a digital elevation model (DEM).
+//!
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+use crate::geometry::{beam_height_m, destination};
+use crate::records::VolumeData;
+
+/// Half-power beamwidth of the WSR-88D antenna in degrees.
+pub const WSR88D_BEAMWIDTH: f32 = 0.95;
+
+/// Estimates the fraction of the radar beam blocked before reaching a location.
+pub trait Blockage {
+    /// The fraction of the beam's power, from 0 to 1, blocked between the radar and the specified
+    /// slant range in meters along the specified azimuth and elevation angle in degrees.
+    fn blocked_fraction(&self, elevation: f32, azimuth: f32, range: f32) -> f32;
+}
+
+/// A beam which is never blocked, e.g. for sites without a DEM.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoBlockage;
+
+impl Blockage for NoBlockage {
+    fn blocked_fraction(&self, _elevation: f32, _azimuth: f32, _range: f32) -> f32 {
+        0.0
+    }
+}
+
+/// A digital elevation model, providing terrain heights.
+pub trait Dem {
+    /// The terrain height in meters above mean sea level at the specified latitude and longitude in
+    /// degrees, if covered by the model.
+    fn height_m(&self, lat: f32, long: f32) -> Option<f32>;
+}
+
+impl<F: Fn(f32, f32) -> Option<f32>> Dem for F {
+    fn height_m(&self, lat: f32, long: f32) -> Option<f32> {
+        self(lat, long)
+    }
+}
+
+/// Beam blockage computed from terrain heights sampled from a DEM along each azimuth. Terrain is
+/// sampled once when created, so blockage may then be evaluated for any elevation angle. Locations
+/// not covered by the DEM are treated as sea level.
+///
+/// The blockage along each azimuth is computed once per elevation angle and cached, so evaluating
+/// every range of a sweep costs no more than a single pass along each azimuth.
+#[derive(Debug)]
+pub struct TerrainBlockage {
+    antenna_altitude: f32,
+    beamwidth: f32,
+    azimuth_resolution: f32,
+    range_resolution: f32,
+    terrain: Vec<Vec<f32>>,
+    profiles: Mutex<HashMap<ProfileKey, Arc<[f32]>>>,
+}
+
+/// An azimuth bin and the bits of an elevation angle, identifying a cached blockage profile.
+type ProfileKey = (usize, u32);
+
+impl TerrainBlockage {
+    /// Samples the DEM around the site described by the volume data, every `azimuth_resolution`
+    /// degrees and `range_resolution` meters out to `max_range` meters.
+    ///
+    /// # Panics
+    /// Panics if either resolution is not positive.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn new<D: Dem>(
+        dem: &D,
+        site: &VolumeData,
+        azimuth_resolution: f32,
+        range_resolution: f32,
+        max_range: f32,
+    ) -> Self {
+        assert!(
+            azimuth_resolution > 0.0,
+            "azimuth resolution must be positive"
+        );
+        assert!(range_resolution > 0.0, "range resolution must be positive");
+
+        let azimuth_bins = (360.0 / azimuth_resolution).ceil() as usize;
+        let range_bins = (max_range / range_resolution).ceil() as usize;
+
+        let terrain = (0..azimuth_bins)
+            .map(|azimuth_bin| {
+                let azimuth = (azimuth_bin as f32 + 0.5) * azimuth_resolution;
+                (0..range_bins)
+                    .map(|range_bin| {
+                        let range = (range_bin as f32 + 0.5) * range_resolution;
+                        let (lat, long) = destination(site.lat(), site.long(), azimuth, range);
+                        dem.height_m(lat, long).unwrap_or(0.0)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            antenna_altitude: site.antenna_altitude_m(),
+            beamwidth: WSR88D_BEAMWIDTH,
+            azimuth_resolution,
+            range_resolution,
+            terrain,
+            profiles: Mutex::default(),
+        }
+    }
+
+    /// The half-power beamwidth in degrees. Defaults to the WSR-88D's.
+    #[must_use]
+    pub fn with_beamwidth(mut self, beamwidth: f32) -> Self {
+        self.beamwidth = beamwidth;
+        self.profiles = Mutex::default();
+        self
+    }
+
+    /// The half-power beamwidth in degrees.
+    #[must_use]
+    pub fn beamwidth(&self) -> f32 {
+        self.beamwidth
+    }
+
+    /// The blocked fraction at each range sample along an azimuth at an elevation angle, the
+    /// running maximum of the fraction of the beam below the terrain at each sample.
+    #[allow(clippy::cast_precision_loss)]
+    fn blocked_profile(&self, azimuth_bin: usize, elevation: f32) -> Arc<[f32]> {
+        let key = (azimuth_bin, elevation.to_bits());
+        if let Some(profile) = self.profiles.lock().ok().and_then(|p| p.get(&key).cloned()) {
+            return profile;
+        }
+
+        let mut blocked = 0.0;
+        let profile: Arc<[f32]> = self.terrain[azimuth_bin]
+            .iter()
+            .enumerate()
+            .map(|(range_bin, terrain)| {
+                let sample_range = (range_bin as f32 + 0.5) * self.range_resolution;
+                let beam_center = beam_height_m(sample_range, elevation, self.antenna_altitude);
+                let beam_radius = sample_range * (self.beamwidth / 2.0).to_radians().tan();
+
+                blocked =
+                    circular_fraction_below((terrain - beam_center) / beam_radius).max(blocked);
+                blocked
+            })
+            .collect();
+
+        if let Ok(mut profiles) = self.profiles.lock() {
+            profiles.insert(key, Arc::clone(&profile));
+        }
+        profile
+    }
+}
+
+impl Clone for TerrainBlockage {
+    fn clone(&self) -> Self {
+        Self {
+            antenna_altitude: self.antenna_altitude,
+            beamwidth: self.beamwidth,
+            azimuth_resolution: self.azimuth_resolution,
+            range_resolution: self.range_resolution,
+            terrain: self.terrain.clone(),
+            profiles: Mutex::default(),
+        }
+    }
+}
+
+impl Blockage for TerrainBlockage {
+    /// The largest fraction of the beam's cross section below the terrain at any sample between
+    /// the radar and the specified range, since power blocked nearer the radar never reaches
+    /// farther ranges.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn blocked_fraction(&self, elevation: f32, azimuth: f32, range: f32) -> f32 {
+        if range < 0.0 || self.terrain.is_empty() {
+            return 0.0;
+        }
+
+        let azimuth_bin =
+            (azimuth.rem_euclid(360.0) / self.azimuth_resolution) as usize % self.terrain.len();
+        let profile = self.blocked_profile(azimuth_bin, elevation);
+        let last_bin = (range / self.range_resolution) as usize;
+
+        profile
+            .get(last_bin)
+            .or(profile.last())
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// The fraction of a circle's area below a horizontal line at the specified height, in units of
+/// the circle's radius relative to its center.
+fn circular_fraction_below(height: f32) -> f32 {
+    let height = height.clamp(-1.0, 1.0);
+    (height * (1.0 - height * height).sqrt() + height.asin() + PI / 2.0) / PI
+}
@@ -0,0 +1,364 @@
+//!
+//! Provides [``encode_radial_wind_bufr``] for exporting radial velocity superobservations as WMO
+//! BUFR edition 4 messages, the layout ingested by numerical weather prediction data-assimilation
+//! systems.
+//!
+//! Each sweep's velocities are averaged into coarse polar bins by [``superob_sweep_weighted``],
+//! weighting each gate by its quality index, and each superobservation is encoded as an
+//! uncompressed subset of Table B elements:
+//!
+//! | Descriptor | Element |
+//! |---|---|
+//! | 0 01 018 | Short station or site name |
+//! | 0 04 001–0 04 006 | Year, month, day, hour, minute, and second of the volume |
+//! | 0 05 001, 0 06 001, 0 07 001 | Radar latitude, longitude, and antenna height |
+//! | 0 07 021 | Elevation angle |
+//! | 0 05 021 | Azimuth of the bin's center |
+//! | 2 01 131, 0 06 021, 2 01 000 | Range to the bin's center, widened by 3 bits |
+//! | 0 08 023, 0 21 014 | Mean or median radial velocity |
+//! | 0 08 022 | Number of gates averaged |
+//! | 0 08 023, 0 21 014, 0 08 023 | Standard deviation of the radial velocity |
+//!
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::Deserialize;
+
+use crate::blockage::{Blockage, NoBlockage};
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::quality::{QualityOptions, SweepQuality};
+use crate::records::{Message31, Product};
+use crate::superob::{superob_sweep_weighted, Averaging, Superob, SuperobOptions};
+
+/// The most subsets a BUFR message may hold.
+const MAX_SUBSETS: usize = 65535;
+
+/// The BUFR master table version whose elements are used.
+const MASTER_TABLE_VERSION: u8 = 13;
+
+/// The BUFR data category for radar data.
+const RADAR_DATA_CATEGORY: u8 = 6;
+
+/// First-order statistics codes (code table 0 08 023) qualifying the following elements.
+const STATISTIC_MEAN: u64 = 4;
+const STATISTIC_MEDIAN: u64 = 5;
+const STATISTIC_STANDARD_DEVIATION: u64 = 10;
+
+/// A Table B element's scale, reference value, and width in bits.
+struct Element {
+    scale: i32,
+    reference: i64,
+    bits: u32,
+}
+
+const YEAR: Element = element(0, 0, 12);
+const MONTH: Element = element(0, 0, 4);
+const DAY: Element = element(0, 0, 6);
+const HOUR: Element = element(0, 0, 5);
+const MINUTE: Element = element(0, 0, 6);
+const SECOND: Element = element(0, 0, 6);
+const LATITUDE: Element = element(5, -9_000_000, 25);
+const LONGITUDE: Element = element(5, -18_000_000, 26);
+const STATION_HEIGHT: Element = element(0, -400, 15);
+const ELEVATION: Element = element(2, -9000, 15);
+const AZIMUTH: Element = element(2, 0, 16);
+/// Distance is widened from 13 to 16 bits by the preceding change width operator.
+const DISTANCE: Element = element(-1, 0, 16);
+const STATISTIC: Element = element(0, 0, 6);
+const RADIAL_VELOCITY: Element = element(1, -4096, 13);
+const COUNT: Element = element(0, 0, 16);
+
+const fn element(scale: i32, reference: i64, bits: u32) -> Element {
+    Element {
+        scale,
+        reference,
+        bits,
+    }
+}
+
+/// The descriptors of each subset as `(F, X, Y)`, matching the order values are written in.
+const DESCRIPTORS: [(u8, u8, u8); 21] = [
+    (0, 1, 18),
+    (0, 4, 1),
+    (0, 4, 2),
+    (0, 4, 3),
+    (0, 4, 4),
+    (0, 4, 5),
+    (0, 4, 6),
+    (0, 5, 1),
+    (0, 6, 1),
+    (0, 7, 1),
+    (0, 7, 21),
+    (0, 5, 21),
+    (2, 1, 131),
+    (0, 6, 21),
+    (2, 1, 0),
+    (0, 8, 23),
+    (0, 21, 14),
+    (0, 8, 22),
+    (0, 8, 23),
+    (0, 21, 14),
+    (0, 8, 23),
+];
+
+/// Options controlling how velocities are superobbed and the BUFR messages identified.
+/// Deserializable with serde, with unspecified options taking their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BufrOptions {
+    superob: SuperobOptions,
+    #[serde(skip)]
+    quality: QualityOptions,
+    originating_centre: u16,
+    originating_subcentre: u16,
+}
+
+impl BufrOptions {
+    /// Create the default options: superobservations with the default [`SuperobOptions`], from an
+    /// unspecified originating centre.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How velocities are averaged into superobservations.
+    #[must_use]
+    pub fn with_superob_options(mut self, superob: SuperobOptions) -> Self {
+        self.superob = superob;
+        self
+    }
+
+    /// How velocities are averaged into superobservations.
+    #[must_use]
+    pub fn superob_options(&self) -> &SuperobOptions {
+        &self.superob
+    }
+
+    /// How the quality index weighting each velocity gate is derived. Quality options are not
+    /// deserialized, taking their defaults.
+    #[must_use]
+    pub fn with_quality_options(mut self, quality: QualityOptions) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// How the quality index weighting each velocity gate is derived.
+    #[must_use]
+    pub fn quality_options(&self) -> &QualityOptions {
+        &self.quality
+    }
+
+    /// The WMO originating centre and subcentre identifying the messages' producer.
+    #[must_use]
+    pub fn with_originating_centre(mut self, centre: u16, subcentre: u16) -> Self {
+        self.originating_centre = centre;
+        self.originating_subcentre = subcentre;
+        self
+    }
+}
+
+impl Default for BufrOptions {
+    fn default() -> Self {
+        Self {
+            superob: SuperobOptions::new(),
+            quality: QualityOptions::new(),
+            originating_centre: 65535,
+            originating_subcentre: 0,
+        }
+    }
+}
+
+/// Encodes the radial velocity superobservations of each sweep of a volume as BUFR messages,
+/// concatenated as they are in BUFR files. Each sweep with velocity becomes a message, split if
+/// it has more superobservations than a message can hold; sweeps without superobservations are
+/// omitted, so the result is empty if the volume has no velocity. Velocities are weighted by the
+/// quality index of the reflectivity gate nearest in range, without beam blockage; see
+/// [``encode_radial_wind_bufr_with_blockage``].
+///
+/// # Errors
+/// Returns an error if the volume has no valid start time.
+pub fn encode_radial_wind_bufr(file: &DataFile, options: &BufrOptions) -> Result<Vec<u8>> {
+    encode_radial_wind_bufr_with_blockage(file, &NoBlockage, options)
+}
+
+/// Encodes a volume's radial velocity superobservations as BUFR messages like
+/// [``encode_radial_wind_bufr``], with the quality index weighting each velocity gate reduced by
+/// the specified beam blockage.
+///
+/// # Errors
+/// Returns an error if the volume has no valid start time.
+pub fn encode_radial_wind_bufr_with_blockage<B: Blockage + ?Sized>(
+    file: &DataFile,
+    blockage: &B,
+    options: &BufrOptions,
+) -> Result<Vec<u8>> {
+    let time = file
+        .volume_header()
+        .date_time()
+        .ok_or(Error::MissingVolumeTime)?;
+
+    let mut data = Vec::new();
+    for radials in file.elevation_scans().values() {
+        let quality = SweepQuality::from_radials(radials, blockage, &options.quality);
+        let superobs =
+            superob_sweep_weighted(radials, Product::Velocity, &quality, &options.superob);
+
+        for subsets in superobs.chunks(MAX_SUBSETS) {
+            data.extend(encode_message(radials, subsets, time, options)?);
+        }
+    }
+
+    Ok(data)
+}
+
+/// Encodes a single BUFR message of a sweep's superobservations.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn encode_message(
+    radials: &[Message31],
+    superobs: &[Superob],
+    time: NaiveDateTime,
+    options: &BufrOptions,
+) -> Result<Vec<u8>> {
+    let radial = radials.first().ok_or(Error::MissingRadials)?;
+    let volume_data = radial.volume_data();
+
+    // Section 1: identification
+    let mut identification = Vec::with_capacity(22);
+    identification.extend_from_slice(&length_bytes(22)?);
+    identification.push(0);
+    identification.extend_from_slice(&options.originating_centre.to_be_bytes());
+    identification.extend_from_slice(&options.originating_subcentre.to_be_bytes());
+    identification.extend_from_slice(&[0, 0, RADAR_DATA_CATEGORY, 255, 0]);
+    identification.extend_from_slice(&[MASTER_TABLE_VERSION, 0]);
+    identification.extend_from_slice(&u16::try_from(time.year())?.to_be_bytes());
+    identification.extend_from_slice(&[
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    ]);
+
+    // Section 3: data description, of observed and uncompressed subsets
+    let mut description = Vec::new();
+    let description_length = 7 + DESCRIPTORS.len() * 2;
+    description.extend_from_slice(&length_bytes(description_length)?);
+    description.push(0);
+    description.extend_from_slice(&u16::try_from(superobs.len())?.to_be_bytes());
+    description.push(0x80);
+    for (f, x, y) in DESCRIPTORS {
+        description.extend_from_slice(&[f << 6 | x, y]);
+    }
+
+    // Section 4: data
+    let mut writer = BitWriter::default();
+    let statistic = match options.superob.averaging() {
+        Averaging::Mean => STATISTIC_MEAN,
+        Averaging::Median => STATISTIC_MEDIAN,
+    };
+
+    let mut name = [b' '; 5];
+    for (target, character) in name.iter_mut().zip(radial.header().radar_id()) {
+        *target = *character;
+    }
+
+    for superob in superobs {
+        for character in name {
+            writer.write(u64::from(character), 8);
+        }
+
+        writer.write_value(&YEAR, Some(f64::from(time.year())));
+        writer.write_value(&MONTH, Some(f64::from(time.month())));
+        writer.write_value(&DAY, Some(f64::from(time.day())));
+        writer.write_value(&HOUR, Some(f64::from(time.hour())));
+        writer.write_value(&MINUTE, Some(f64::from(time.minute())));
+        writer.write_value(&SECOND, Some(f64::from(time.second())));
+        writer.write_value(&LATITUDE, volume_data.map(|data| f64::from(data.lat())));
+        writer.write_value(&LONGITUDE, volume_data.map(|data| f64::from(data.long())));
+        writer.write_value(
+            &STATION_HEIGHT,
+            volume_data.map(|data| f64::from(data.antenna_altitude_m())),
+        );
+
+        writer.write_value(&ELEVATION, Some(f64::from(superob.elevation())));
+        writer.write_value(&AZIMUTH, Some(f64::from(superob.azimuth())));
+        writer.write_value(&DISTANCE, Some(f64::from(superob.range())));
+        writer.write(statistic, STATISTIC.bits);
+        writer.write_value(&RADIAL_VELOCITY, Some(f64::from(superob.value())));
+        writer.write_value(&COUNT, Some(superob.count() as f64));
+        writer.write(STATISTIC_STANDARD_DEVIATION, STATISTIC.bits);
+        writer.write_value(
+            &RADIAL_VELOCITY,
+            Some(f64::from(superob.standard_deviation())),
+        );
+        writer.write_value(&STATISTIC, None);
+    }
+
+    let packed = writer.finish();
+    let mut data_section = length_bytes(4 + packed.len())?.to_vec();
+    data_section.push(0);
+    data_section.extend(packed);
+
+    // Section 0: indicator, with the total length, and section 5: end
+    let total = 8 + identification.len() + description.len() + data_section.len() + 4;
+    let mut message = b"BUFR".to_vec();
+    message.extend_from_slice(&length_bytes(total)?);
+    message.push(4);
+    message.extend(identification);
+    message.extend(description);
+    message.extend(data_section);
+    message.extend_from_slice(b"7777");
+
+    Ok(message)
+}
+
+/// A length as the 3-byte big-endian integer sections are sized with.
+fn length_bytes(length: usize) -> Result<[u8; 3]> {
+    match u32::try_from(length)?.to_be_bytes() {
+        [0, bytes @ ..] => Ok(bytes),
+        _ => Err(Error::BufrMessageTooLarge.into()),
+    }
+}
+
+/// Packs values most significant bit first, as BUFR data sections are.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Writes the low bits of a value.
+    fn write(&mut self, value: u64, bits: u32) {
+        for bit in (0..bits).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+
+            if value >> bit & 1 == 1 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    /// Writes an element's value, scaled and offset by its reference value, or all ones if it is
+    /// missing or cannot be represented.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn write_value(&mut self, element: &Element, value: Option<f64>) {
+        let missing = (1u64 << element.bits) - 1;
+        let encoded = value
+            .map(|value| (value * 10f64.powi(element.scale)).round() as i64 - element.reference)
+            .filter(|encoded| *encoded >= 0 && (*encoded as u64) < missing)
+            .map_or(missing, |encoded| encoded as u64);
+
+        self.write(encoded, element.bits);
+    }
+
+    /// The packed bytes, with the final byte padded with zeros.
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
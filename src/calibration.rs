@@ -0,0 +1,281 @@
+//!
+//! Provides [``estimate_reflectivity_bias``] for checking a volume's reflectivity calibration with
+//! the dual-polarization self-consistency method: in rain, the differential phase accumulated
+//! along a path is predictable from reflectivity and differential reflectivity, so a consistent
+//! mismatch reveals a reflectivity bias.
+//!
+
+use crate::decoder::DataFile;
+use crate::geometry::beam_height_m;
+use crate::phase::{PhaseOptions, ProcessedPhase};
+use crate::records::{DataMoment, Message31};
+
+/// Options controlling which gates are considered rain and which paths are used.
+#[derive(Debug, Clone)]
+pub struct CalibrationOptions {
+    min_reflectivity: f32,
+    max_reflectivity: f32,
+    min_correlation: f32,
+    max_height: f32,
+    min_path_gates: usize,
+    min_phase_span: f32,
+    phase: PhaseOptions,
+}
+
+impl CalibrationOptions {
+    /// Create the default options: rain gates between 20 and 50 dBZ with a correlation
+    /// coefficient of at least 0.98, below 3 km, along paths of at least 10 gates accumulating at
+    /// least 6 degrees of differential phase.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The range of reflectivity in dBZ considered rain. The upper bound excludes hail, whose
+    /// differential phase does not follow the rain relation.
+    #[must_use]
+    pub fn with_reflectivity_range(mut self, min_reflectivity: f32, max_reflectivity: f32) -> Self {
+        self.min_reflectivity = min_reflectivity;
+        self.max_reflectivity = max_reflectivity;
+        self
+    }
+
+    /// The smallest correlation coefficient considered rain, excluding mixed phase and
+    /// non-meteorological echo.
+    #[must_use]
+    pub fn with_min_correlation(mut self, min_correlation: f32) -> Self {
+        self.min_correlation = min_correlation;
+        self
+    }
+
+    /// The greatest beam height in meters above mean sea level considered rain, which should be
+    /// below the melting layer.
+    #[must_use]
+    pub fn with_max_height(mut self, max_height: f32) -> Self {
+        self.max_height = max_height;
+        self
+    }
+
+    /// The fewest consecutive rain gates a path must have, and the least differential phase in
+    /// degrees it must accumulate, to be used. Short or weak paths are dominated by phase noise.
+    #[must_use]
+    pub fn with_min_path(mut self, min_path_gates: usize, min_phase_span: f32) -> Self {
+        self.min_path_gates = min_path_gates.max(2);
+        self.min_phase_span = min_phase_span;
+        self
+    }
+
+    /// How differential phase is processed.
+    #[must_use]
+    pub fn with_phase_options(mut self, phase: PhaseOptions) -> Self {
+        self.phase = phase;
+        self
+    }
+}
+
+impl Default for CalibrationOptions {
+    fn default() -> Self {
+        Self {
+            min_reflectivity: 20.0,
+            max_reflectivity: 50.0,
+            min_correlation: 0.98,
+            max_height: 3000.0,
+            min_path_gates: 10,
+            min_phase_span: 6.0,
+            phase: PhaseOptions::new(),
+        }
+    }
+}
+
+/// A volume's estimated reflectivity calibration bias and the evidence behind it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationEstimate {
+    bias: f32,
+    path_count: usize,
+    gate_count: usize,
+    observed_phase: f32,
+    expected_phase: f32,
+}
+
+impl CalibrationEstimate {
+    /// The estimated reflectivity bias in dB, positive if the radar measures reflectivity too
+    /// high. Subtracting it from measured reflectivity corrects the calibration.
+    #[must_use]
+    pub fn bias(&self) -> f32 {
+        self.bias
+    }
+
+    /// The number of rain paths used.
+    #[must_use]
+    pub fn path_count(&self) -> usize {
+        self.path_count
+    }
+
+    /// The number of rain gates along the paths used.
+    #[must_use]
+    pub fn gate_count(&self) -> usize {
+        self.gate_count
+    }
+
+    /// The total differential phase in degrees measured along the paths.
+    #[must_use]
+    pub fn observed_phase(&self) -> f32 {
+        self.observed_phase
+    }
+
+    /// The total differential phase in degrees expected along the paths from their reflectivity
+    /// and differential reflectivity.
+    #[must_use]
+    pub fn expected_phase(&self) -> f32 {
+        self.expected_phase
+    }
+}
+
+/// Estimates a volume's reflectivity calibration bias by the self-consistency of reflectivity,
+/// differential reflectivity, and differential phase in rain. Along each path of consecutive rain
+/// gates, the differential phase expected from the S-band rain relation
+/// `KDP = 1e-5 (11.74 - 4.02 ZDR - 0.140 ZDR² + 0.130 ZDR³) Z` is integrated and compared with the
+/// phase measured; as expected phase is proportional to linear reflectivity, the ratio of the
+/// totals gives the bias. Returns `None` if no path qualifies, e.g. if the volume has no
+/// dual-polarization moments or too little rain.
+///
+/// The estimate assumes differential reflectivity is itself well calibrated, as a ZDR bias also
+/// skews the expected phase.
+#[must_use]
+pub fn estimate_reflectivity_bias(
+    file: &DataFile,
+    options: &CalibrationOptions,
+) -> Option<CalibrationEstimate> {
+    let mut path_count = 0;
+    let mut gate_count = 0;
+    let mut observed_phase = 0.0;
+    let mut expected_phase = 0.0;
+
+    for radial in file.elevation_scans().values().flatten() {
+        for path in rain_paths(radial, options) {
+            path_count += 1;
+            gate_count += path.gates;
+            observed_phase += path.observed_phase;
+            expected_phase += path.expected_phase;
+        }
+    }
+
+    if path_count == 0 || observed_phase <= 0.0 {
+        return None;
+    }
+
+    Some(CalibrationEstimate {
+        bias: 10.0 * (expected_phase / observed_phase).log10(),
+        path_count,
+        gate_count,
+        observed_phase,
+        expected_phase,
+    })
+}
+
+/// A path of consecutive rain gates along a radial.
+struct RainPath {
+    gates: usize,
+    observed_phase: f32,
+    expected_phase: f32,
+}
+
+/// Finds a radial's qualifying rain paths.
+fn rain_paths(radial: &Message31, options: &CalibrationOptions) -> Vec<RainPath> {
+    let (Some(reflectivity), Some(zdr), Some(correlation), Some(volume_data)) = (
+        radial.reflectivity_data(),
+        radial.zdr_data(),
+        radial.rho_data(),
+        radial.volume_data(),
+    ) else {
+        return Vec::new();
+    };
+    let Some(phase) = ProcessedPhase::from_radial(radial, &options.phase) else {
+        return Vec::new();
+    };
+
+    let elevation = radial.header().elev();
+    let ranges = phase.gate_ranges();
+    let interval = ranges
+        .get(1)
+        .zip(ranges.first())
+        .map_or(0.0, |(second, first)| (second - first) / 1000.0);
+
+    // Each gate's expected specific differential phase, if it is rain
+    let expected_kdp: Vec<Option<f32>> = ranges
+        .iter()
+        .zip(phase.phidp())
+        .map(|(range, phidp)| {
+            phidp.as_ref()?;
+            if beam_height_m(*range, elevation, volume_data.antenna_altitude_m())
+                > options.max_height
+            {
+                return None;
+            }
+
+            let reflectivity = value_at_range(reflectivity, *range)?;
+            let zdr = value_at_range(zdr, *range)?;
+            let correlation = value_at_range(correlation, *range)?;
+            let is_rain = (options.min_reflectivity..=options.max_reflectivity)
+                .contains(&reflectivity)
+                && correlation >= options.min_correlation;
+
+            is_rain.then(|| rain_kdp(reflectivity, zdr))
+        })
+        .collect();
+
+    let mut paths = Vec::new();
+    let mut start = 0;
+    while start < expected_kdp.len() {
+        let length = expected_kdp[start..]
+            .iter()
+            .take_while(|kdp| kdp.is_some())
+            .count();
+
+        if length >= options.min_path_gates {
+            let end = start + length - 1;
+            let observed = phase.phidp()[end].unwrap_or(0.0) - phase.phidp()[start].unwrap_or(0.0);
+
+            // The phase accumulates between the first and last gates' centers
+            let expected = expected_kdp[start..end]
+                .iter()
+                .zip(&expected_kdp[start + 1..=end])
+                .map(|(near, far)| near.unwrap_or(0.0) + far.unwrap_or(0.0))
+                .sum::<f32>()
+                * interval;
+
+            if observed >= options.min_phase_span {
+                paths.push(RainPath {
+                    gates: length,
+                    observed_phase: observed,
+                    expected_phase: expected,
+                });
+            }
+        }
+
+        start += length.max(1);
+    }
+
+    paths
+}
+
+/// The specific differential phase in degrees per kilometer of rain with the specified
+/// reflectivity in dBZ and differential reflectivity in dB, at S band.
+fn rain_kdp(reflectivity: f32, zdr: f32) -> f32 {
+    let zdr = zdr.clamp(0.0, 4.0);
+    let shape = 11.74 - 4.02 * zdr - 0.140 * zdr.powi(2) + 0.130 * zdr.powi(3);
+    1e-5 * shape * 10f32.powf(reflectivity / 10.0)
+}
+
+/// The value of the gate of a moment nearest the specified range in meters.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn value_at_range(moment: &DataMoment, range: f32) -> Option<f32> {
+    let first = moment.data().gate_range_m(0);
+    let interval = f32::from(moment.data().data_moment_range_sample_interval());
+    if interval <= 0.0 || range < first - interval / 2.0 {
+        return None;
+    }
+
+    let index = ((range - first) / interval).round() as usize;
+    moment.value(index)?.value()
+}
@@ -0,0 +1,47 @@
+//!
+//! Provides [``CancellationToken``] for cooperatively cancelling long-running computations, e.g. to
+//! abandon work on a volume that has been superseded by a newer one.
+//!
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::Error;
+
+/// A flag shared between a computation and its caller which, once cancelled, causes the
+/// computation to stop at its next checkpoint. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new token which has not been cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests that computations observing this token stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// A checkpoint for computations to call periodically.
+    ///
+    /// # Errors
+    /// Returns [`Error::Cancelled`] if cancellation has been requested.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,515 @@
+//!
+//! Provides [``encode_cfradial``] for exporting a volume as CF/Radial, the `netCDF` convention for
+//! polar radar data read by Py-ART, LROSE, and most research software. Files are written in the
+//! `netCDF` classic format with 64-bit offsets, which every `netCDF` library reads, so no native
+//! library is needed to write them.
+//!
+//! CF/Radial requires one range coordinate for every ray, so each moment's gates are placed on
+//! the volume's finest gate spacing, with coarser gates repeated across the bins they cover.
+//!
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::gate::GateValue;
+use crate::records::{DataMoment, Message31, Product};
+
+/// The value of gates without a valid measurement, including below threshold and range folded
+/// gates.
+const FILL_VALUE: f32 = -9999.0;
+
+/// The length of the character dimension of string variables.
+const STRING_LENGTH: usize = 32;
+
+/// The indices of the file's dimensions.
+const TIME: usize = 0;
+const RANGE: usize = 1;
+const SWEEP: usize = 2;
+const STRING: usize = 3;
+
+/// The `netCDF` classic format's tags for lists in its header.
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+/// The CF/Radial field name, standard name, and units of a product.
+fn field(product: Product) -> (&'static str, Option<&'static str>, &'static str) {
+    match product {
+        Product::Reflectivity => ("DBZ", Some("equivalent_reflectivity_factor"), "dBZ"),
+        Product::Velocity => (
+            "VEL",
+            Some("radial_velocity_of_scatterers_away_from_instrument"),
+            "m/s",
+        ),
+        Product::SpectrumWidth => ("WIDTH", Some("doppler_spectrum_width"), "m/s"),
+        Product::DifferentialReflectivity => {
+            ("ZDR", Some("log_differential_reflectivity_hv"), "dB")
+        }
+        Product::DifferentialPhase => ("PHIDP", Some("differential_phase_hv"), "degrees"),
+        Product::CorrelationCoefficient => ("RHOHV", Some("cross_correlation_ratio_hv"), "1"),
+        Product::ClutterFilterProbability => ("CFP", None, "dB"),
+    }
+}
+
+/// Encodes a volume as a CF/Radial `netCDF` file with a ray for each radial and a field for each
+/// product present in any radial.
+///
+/// # Errors
+/// Returns an error if the volume has no radials, site location, or valid start time.
+#[allow(clippy::cast_precision_loss, clippy::too_many_lines)]
+pub fn encode_cfradial(file: &DataFile) -> Result<Vec<u8>> {
+    let site = file.first_volume_data().ok_or(Error::MissingSiteLocation)?;
+    let start = file
+        .volume_header()
+        .date_time()
+        .ok_or(Error::MissingVolumeTime)?;
+    let instrument = String::from_utf8_lossy(file.volume_header().radar_id())
+        .trim_end_matches('\0')
+        .to_string();
+
+    let sweeps: Vec<&Vec<Message31>> = file
+        .elevation_scans()
+        .values()
+        .filter(|radials| !radials.is_empty())
+        .collect();
+    let rays: Vec<&Message31> = sweeps.iter().copied().flatten().collect();
+    let products: Vec<Product> = Product::ALL
+        .into_iter()
+        .filter(|product| rays.iter().any(|ray| moment(ray, *product).is_some()))
+        .collect();
+    let moments: Vec<&DataMoment> = rays
+        .iter()
+        .flat_map(|ray| products.iter().filter_map(|product| moment(ray, *product)))
+        .collect();
+    let (first_range, spacing, bins) = range_coordinate(&moments).ok_or(Error::MissingRadials)?;
+
+    let ray_time = |ray: &Message31| ray.header().date_time().unwrap_or(start);
+    let end = rays.iter().map(|ray| ray_time(ray)).max().unwrap_or(start);
+
+    let mut netcdf = NetCdf {
+        dimensions: vec![
+            ("time", rays.len()),
+            ("range", bins),
+            ("sweep", sweeps.len()),
+            ("string_length", STRING_LENGTH),
+        ],
+        attributes: vec![
+            Attribute::text("Conventions", "CF/Radial"),
+            Attribute::text("version", "1.4"),
+            Attribute::text("title", "NEXRAD Level II volume"),
+            Attribute::text("source", "NEXRAD WSR-88D"),
+            Attribute::text("instrument_name", &instrument),
+            Attribute::text("platform_type", "fixed"),
+            Attribute::text("instrument_type", "radar"),
+        ],
+        variables: vec![
+            Variable::new("volume_number", &[], Values::Int(vec![0])),
+            Variable::new(
+                "time_coverage_start",
+                &[STRING],
+                text(&[format_time(start)]),
+            ),
+            Variable::new("time_coverage_end", &[STRING], text(&[format_time(end)])),
+            Variable::new("latitude", &[], Values::Double(vec![f64::from(site.lat())]))
+                .with_text("units", "degrees_north"),
+            Variable::new(
+                "longitude",
+                &[],
+                Values::Double(vec![f64::from(site.long())]),
+            )
+            .with_text("units", "degrees_east"),
+            Variable::new(
+                "altitude",
+                &[],
+                Values::Double(vec![f64::from(site.antenna_altitude_m())]),
+            )
+            .with_text("units", "meters")
+            .with_text("positive", "up"),
+            Variable::new(
+                "time",
+                &[TIME],
+                Values::Double(
+                    rays.iter()
+                        .map(|ray| (ray_time(ray) - start).num_milliseconds() as f64 / 1000.0)
+                        .collect(),
+                ),
+            )
+            .with_text("standard_name", "time")
+            .with_text(
+                "units",
+                &format!("seconds since {}", start.format("%Y-%m-%dT%H:%M:%SZ")),
+            ),
+            Variable::new(
+                "range",
+                &[RANGE],
+                Values::Float(
+                    (0..bins)
+                        .map(|bin| range(first_range, spacing, bin))
+                        .collect(),
+                ),
+            )
+            .with_text("standard_name", "projection_range_coordinate")
+            .with_text("units", "meters")
+            .with_text("spacing_is_constant", "true")
+            .with_attribute(Attribute::float(
+                "meters_to_center_of_first_gate",
+                first_range,
+            ))
+            .with_attribute(Attribute::float("meters_between_gates", spacing)),
+            Variable::new(
+                "azimuth",
+                &[TIME],
+                Values::Float(rays.iter().map(|ray| ray.header().azm()).collect()),
+            )
+            .with_text("standard_name", "ray_azimuth_angle")
+            .with_text("units", "degrees"),
+            Variable::new(
+                "elevation",
+                &[TIME],
+                Values::Float(rays.iter().map(|ray| ray.header().elev()).collect()),
+            )
+            .with_text("standard_name", "ray_elevation_angle")
+            .with_text("units", "degrees")
+            .with_text("positive", "up"),
+        ],
+    };
+
+    netcdf.variables.extend(sweep_variables(&sweeps)?);
+
+    for product in products {
+        let mut values = vec![FILL_VALUE; rays.len() * bins];
+        for (ray, row) in rays.iter().zip(values.chunks_exact_mut(bins)) {
+            if let Some(moment) = moment(ray, product) {
+                resample(moment, first_range, spacing, row);
+            }
+        }
+
+        let (name, standard_name, units) = field(product);
+        let mut variable = Variable::new(name, &[TIME, RANGE], Values::Float(values))
+            .with_text("units", units)
+            .with_attribute(Attribute::float("_FillValue", FILL_VALUE))
+            .with_text("coordinates", "elevation azimuth range");
+        if let Some(standard_name) = standard_name {
+            variable = variable.with_text("standard_name", standard_name);
+        }
+        netcdf.variables.push(variable);
+    }
+
+    netcdf.encode()
+}
+
+/// The first range, spacing, and number of bins of a range coordinate spanning every moment's
+/// gates at the finest spacing.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn range_coordinate(moments: &[&DataMoment]) -> Option<(f32, f32, usize)> {
+    let (first_range, spacing) = moments
+        .iter()
+        .map(|moment| moment.data())
+        .filter(|data| data.data_moment_range_sample_interval() > 0)
+        .map(|data| {
+            (
+                f32::from(data.data_moment_range()),
+                f32::from(data.data_moment_range_sample_interval()),
+            )
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.total_cmp(&b.0)))?;
+    let last_range = moments
+        .iter()
+        .filter_map(|moment| {
+            let gates = moment.gate_count();
+            (gates > 0).then(|| moment.data().gate_range_m(gates - 1))
+        })
+        .fold(first_range, f32::max);
+
+    let bins = ((last_range - first_range) / spacing).round().max(0.0) as usize + 1;
+    Some((first_range, spacing, bins))
+}
+
+/// The variables describing each sweep's mode, angle, and rays.
+#[allow(clippy::cast_precision_loss)]
+fn sweep_variables(sweeps: &[&Vec<Message31>]) -> Result<Vec<Variable>> {
+    let mut numbers = Vec::new();
+    let mut starts = Vec::new();
+    let mut ends = Vec::new();
+    let mut fixed_angles = Vec::new();
+    let mut ray_index = 0;
+    for (number, radials) in sweeps.iter().enumerate() {
+        numbers.push(i32::try_from(number)?);
+        starts.push(i32::try_from(ray_index)?);
+        ray_index += radials.len();
+        ends.push(i32::try_from(ray_index - 1)?);
+        fixed_angles.push(
+            radials.iter().map(|ray| ray.header().elev()).sum::<f32>() / radials.len() as f32,
+        );
+    }
+
+    Ok(vec![
+        Variable::new("sweep_number", &[SWEEP], Values::Int(numbers)),
+        Variable::new(
+            "sweep_mode",
+            &[SWEEP, STRING],
+            text(&vec!["azimuth_surveillance".to_string(); sweeps.len()]),
+        ),
+        Variable::new("fixed_angle", &[SWEEP], Values::Float(fixed_angles))
+            .with_text("units", "degrees"),
+        Variable::new("sweep_start_ray_index", &[SWEEP], Values::Int(starts)),
+        Variable::new("sweep_end_ray_index", &[SWEEP], Values::Int(ends)),
+    ])
+}
+
+/// A ray's moment for a product.
+fn moment(ray: &Message31, product: Product) -> Option<&DataMoment> {
+    ray.get_data_moment(&product.into())
+}
+
+/// The range in meters to the center of a bin of the range coordinate.
+#[allow(clippy::cast_precision_loss)]
+fn range(first_range: f32, spacing: f32, bin: usize) -> f32 {
+    first_range + bin as f32 * spacing
+}
+
+/// Places a moment's gate values in the bins of the range coordinate nearest their centers.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn resample(moment: &DataMoment, first_range: f32, spacing: f32, row: &mut [f32]) {
+    let data = moment.data();
+    let gate_spacing = f32::from(data.data_moment_range_sample_interval()).max(1.0);
+    let gate_first = f32::from(data.data_moment_range());
+
+    for (bin, value) in row.iter_mut().enumerate() {
+        let position = (range(first_range, spacing, bin) - gate_first) / gate_spacing;
+        if position < -0.5 {
+            continue;
+        }
+        match moment.value(position.round() as usize) {
+            Some(GateValue::Value(gate)) => *value = gate,
+            Some(_) => {}
+            None => break,
+        }
+    }
+}
+
+/// Formats a time as CF/Radial's ISO 8601 UTC time text.
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Character data for strings padded to the string length dimension.
+fn text(strings: &[String]) -> Values {
+    Values::Char(
+        strings
+            .iter()
+            .flat_map(|string| {
+                let mut bytes = string.as_bytes().to_vec();
+                bytes.resize(STRING_LENGTH, 0);
+                bytes
+            })
+            .collect(),
+    )
+}
+
+/// The values of a `netCDF` attribute or variable.
+enum Values {
+    Char(Vec<u8>),
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+impl Values {
+    /// The `netCDF` type code.
+    fn nc_type(&self) -> u32 {
+        match self {
+            Self::Char(_) => 2,
+            Self::Int(_) => 4,
+            Self::Float(_) => 5,
+            Self::Double(_) => 6,
+        }
+    }
+
+    /// The number of values.
+    fn len(&self) -> usize {
+        match self {
+            Self::Char(values) => values.len(),
+            Self::Int(values) => values.len(),
+            Self::Float(values) => values.len(),
+            Self::Double(values) => values.len(),
+        }
+    }
+
+    /// The size in bytes of each value.
+    fn type_size(&self) -> usize {
+        match self {
+            Self::Char(_) => 1,
+            Self::Int(_) | Self::Float(_) => 4,
+            Self::Double(_) => 8,
+        }
+    }
+
+    /// The size in bytes of the values, padded to a four-byte boundary.
+    fn padded_size(&self) -> usize {
+        (self.len() * self.type_size()).next_multiple_of(4)
+    }
+
+    /// Appends the big-endian values, padded to a four-byte boundary.
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let end = bytes.len() + self.padded_size();
+        match self {
+            Self::Char(values) => bytes.extend_from_slice(values),
+            Self::Int(values) => bytes.extend(values.iter().flat_map(|value| value.to_be_bytes())),
+            Self::Float(values) => {
+                bytes.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+            }
+            Self::Double(values) => {
+                bytes.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+            }
+        }
+        bytes.resize(end, 0);
+    }
+}
+
+/// A named attribute of a file or variable.
+struct Attribute {
+    name: String,
+    values: Values,
+}
+
+impl Attribute {
+    fn text(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            values: Values::Char(value.as_bytes().to_vec()),
+        }
+    }
+
+    fn float(name: &str, value: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            values: Values::Float(vec![value]),
+        }
+    }
+}
+
+/// A variable with its dimensions' indices, attributes, and values.
+struct Variable {
+    name: String,
+    dimensions: Vec<usize>,
+    attributes: Vec<Attribute>,
+    values: Values,
+}
+
+impl Variable {
+    fn new(name: &str, dimensions: &[usize], values: Values) -> Self {
+        Self {
+            name: name.to_string(),
+            dimensions: dimensions.to_vec(),
+            attributes: Vec::new(),
+            values,
+        }
+    }
+
+    fn with_attribute(mut self, attribute: Attribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    fn with_text(self, name: &str, value: &str) -> Self {
+        self.with_attribute(Attribute::text(name, value))
+    }
+}
+
+/// A `netCDF` classic file without a record dimension.
+struct NetCdf {
+    dimensions: Vec<(&'static str, usize)>,
+    attributes: Vec<Attribute>,
+    variables: Vec<Variable>,
+}
+
+impl NetCdf {
+    /// Encodes the file in the classic format with 64-bit offsets.
+    fn encode(&self) -> Result<Vec<u8>> {
+        // The header's size does not depend on the offsets it holds, so it is first encoded to
+        // find where the variables' data begins
+        let header_size = self.encode_header(&vec![0; self.variables.len()])?.len();
+        let mut offsets = Vec::new();
+        let mut offset = header_size as u64;
+        for variable in &self.variables {
+            offsets.push(offset);
+            offset += variable.values.padded_size() as u64;
+        }
+
+        let mut data = self.encode_header(&offsets)?;
+        data.reserve(usize::try_from(offset)?.saturating_sub(data.len()));
+        for variable in &self.variables {
+            variable.values.write(&mut data);
+        }
+        Ok(data)
+    }
+
+    /// Encodes the header with the specified offset of each variable's data.
+    fn encode_header(&self, offsets: &[u64]) -> Result<Vec<u8>> {
+        let mut header = b"CDF\x02".to_vec();
+        push_u32(&mut header, 0)?;
+
+        push_u32(&mut header, NC_DIMENSION)?;
+        push_u32(&mut header, self.dimensions.len())?;
+        for (name, length) in &self.dimensions {
+            push_name(&mut header, name)?;
+            push_u32(&mut header, *length)?;
+        }
+
+        push_attributes(&mut header, &self.attributes)?;
+
+        push_u32(&mut header, NC_VARIABLE)?;
+        push_u32(&mut header, self.variables.len())?;
+        for (variable, offset) in self.variables.iter().zip(offsets) {
+            push_name(&mut header, &variable.name)?;
+            push_u32(&mut header, variable.dimensions.len())?;
+            for dimension in &variable.dimensions {
+                push_u32(&mut header, *dimension)?;
+            }
+            push_attributes(&mut header, &variable.attributes)?;
+            push_u32(&mut header, variable.values.nc_type())?;
+            push_u32(&mut header, variable.values.padded_size())?;
+            header.extend_from_slice(&offset.to_be_bytes());
+        }
+
+        Ok(header)
+    }
+}
+
+/// Appends a big-endian 32-bit count or tag.
+fn push_u32<T: TryInto<u32>>(header: &mut Vec<u8>, value: T) -> Result<()>
+where
+    T::Error: std::error::Error + Send + Sync + 'static,
+{
+    header.extend_from_slice(&value.try_into()?.to_be_bytes());
+    Ok(())
+}
+
+/// Appends a name, padded to a four-byte boundary.
+fn push_name(header: &mut Vec<u8>, name: &str) -> Result<()> {
+    push_u32(header, name.len())?;
+    header.extend_from_slice(name.as_bytes());
+    header.resize(header.len().next_multiple_of(4), 0);
+    Ok(())
+}
+
+/// Appends a list of attributes, which is marked absent if empty.
+fn push_attributes(header: &mut Vec<u8>, attributes: &[Attribute]) -> Result<()> {
+    if attributes.is_empty() {
+        push_u32(header, 0u32)?;
+        return push_u32(header, 0u32);
+    }
+
+    push_u32(header, NC_ATTRIBUTE)?;
+    push_u32(header, attributes.len())?;
+    for attribute in attributes {
+        push_name(header, &attribute.name)?;
+        push_u32(header, attribute.values.nc_type())?;
+        push_u32(header, attribute.values.len())?;
+        attribute.values.write(header);
+    }
+    Ok(())
+}
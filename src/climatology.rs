@@ -0,0 +1,321 @@
+//!
+//! Provides [``EchoClimatology``] for accumulating how often reflectivity exceeds thresholds in
+//! each polar bin across many volumes, e.g. to build clutter maps or site climatologies.
+//!
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use anyhow::Result;
+use bincode::{DefaultOptions, Options};
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::gate::{GateIterator, GateValue};
+use crate::records::Product;
+
+/// Accumulates the frequency with which reflectivity exceeds a set of thresholds in each polar bin,
+/// per elevation angle. The accumulator may be saved and loaded between runs to continue ingesting.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EchoClimatology {
+    thresholds: Vec<f32>,
+    azimuth_resolution: f32,
+    range_resolution: f32,
+    range_bins: usize,
+    volume_count: u64,
+    tilts: BTreeMap<u16, TiltCounts>,
+}
+
+/// Observation and exceedance counts for each polar bin of a single elevation angle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct TiltCounts {
+    observations: Vec<u32>,
+    exceedances: Vec<u32>,
+}
+
+impl EchoClimatology {
+    /// Create a new, empty accumulator for the specified reflectivity thresholds in dBZ. Bins span
+    /// `azimuth_resolution` degrees and `range_resolution` meters out to `max_range` meters.
+    ///
+    /// # Panics
+    /// Panics if either resolution is not positive.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn new(
+        thresholds: Vec<f32>,
+        azimuth_resolution: f32,
+        range_resolution: f32,
+        max_range: f32,
+    ) -> Self {
+        assert!(
+            azimuth_resolution > 0.0,
+            "azimuth resolution must be positive"
+        );
+        assert!(range_resolution > 0.0, "range resolution must be positive");
+
+        Self {
+            thresholds,
+            azimuth_resolution,
+            range_resolution,
+            range_bins: (max_range / range_resolution).ceil() as usize,
+            volume_count: 0,
+            tilts: BTreeMap::new(),
+        }
+    }
+
+    /// Reflectivity thresholds in dBZ whose exceedance is counted.
+    #[must_use]
+    pub fn thresholds(&self) -> &[f32] {
+        &self.thresholds
+    }
+
+    /// Number of volumes ingested.
+    #[must_use]
+    pub fn volume_count(&self) -> u64 {
+        self.volume_count
+    }
+
+    /// Elevation angles with accumulated data, in degrees.
+    pub fn elevations(&self) -> impl Iterator<Item = f32> + '_ {
+        self.tilts.keys().map(|tenths| f32::from(*tenths) / 10.0)
+    }
+
+    /// Number of azimuth bins in each tilt.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn azimuth_bins(&self) -> usize {
+        (360.0 / self.azimuth_resolution).ceil() as usize
+    }
+
+    /// Number of range bins in each tilt.
+    #[must_use]
+    pub fn range_bins(&self) -> usize {
+        self.range_bins
+    }
+
+    /// Accumulates a volume's reflectivity. Each sweep is attributed to its mean elevation angle
+    /// rounded to a tenth of a degree, so repeated cuts at the same angle count as further
+    /// observations. Range folded gates are not counted as observed.
+    pub fn ingest(&mut self, file: &DataFile) {
+        // Without a token accumulation cannot be cancelled, which is its only failure
+        let _ = self.accumulate(file, None);
+    }
+
+    /// Accumulates a volume's reflectivity like [`EchoClimatology::ingest`], checking the token for
+    /// cancellation before each sweep. The volume is counted only once every sweep is accumulated,
+    /// so a cancelled ingest leaves the climatology unchanged.
+    ///
+    /// # Errors
+    /// Returns an error if ingestion was cancelled.
+    pub fn ingest_cancellable(&mut self, file: &DataFile, token: &CancellationToken) -> Result<()> {
+        self.accumulate(file, Some(token))
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn accumulate(&mut self, file: &DataFile, token: Option<&CancellationToken>) -> Result<()> {
+        let bin_count = self.azimuth_bins() * self.range_bins;
+        let threshold_count = self.thresholds.len();
+
+        // Counts are merged into the climatology only once the whole volume is accumulated
+        let mut scratch: BTreeMap<u16, TiltCounts> = BTreeMap::new();
+        for radials in file.elevation_scans().values() {
+            if let Some(token) = token {
+                token.check()?;
+            }
+
+            if radials.is_empty() {
+                continue;
+            }
+
+            let mean_elevation =
+                radials.iter().map(|r| r.header().elev()).sum::<f32>() / radials.len() as f32;
+            let tenths = (mean_elevation * 10.0).round().max(0.0) as u16;
+
+            let tilt = scratch.entry(tenths).or_insert_with(|| TiltCounts {
+                observations: vec![0; bin_count],
+                exceedances: vec![0; bin_count * threshold_count],
+            });
+
+            for radial in radials {
+                let Some(gates) = GateIterator::from_radial(radial, Product::Reflectivity) else {
+                    continue;
+                };
+
+                for (range, azimuth, _, value) in gates {
+                    let Some(bin) = self.bin_index(azimuth, range) else {
+                        continue;
+                    };
+
+                    let dbz = match value {
+                        GateValue::RangeFolded => continue,
+                        GateValue::BelowThreshold => f32::NEG_INFINITY,
+                        GateValue::Value(dbz) => dbz,
+                    };
+
+                    tilt.observations[bin] += 1;
+                    for (index, threshold) in self.thresholds.iter().enumerate() {
+                        if dbz > *threshold {
+                            tilt.exceedances[bin * threshold_count + index] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (tenths, counts) in scratch {
+            match self.tilts.get_mut(&tenths) {
+                Some(tilt) => {
+                    add_counts(&mut tilt.observations, &counts.observations);
+                    add_counts(&mut tilt.exceedances, &counts.exceedances);
+                }
+                None => {
+                    self.tilts.insert(tenths, counts);
+                }
+            }
+        }
+        self.volume_count += 1;
+
+        Ok(())
+    }
+
+    /// The fraction of observations in the bin containing the specified elevation, azimuth, and
+    /// range which exceeded the threshold at `threshold_index`. Returns `None` if the bin has
+    /// never been observed.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn frequency(
+        &self,
+        elevation: f32,
+        threshold_index: usize,
+        azimuth: f32,
+        range: f32,
+    ) -> Option<f32> {
+        let tilt = self.tilts.get(&((elevation * 10.0).round() as u16))?;
+        let bin = self.bin_index(azimuth, range)?;
+
+        Self::bin_frequency(tilt, self.thresholds.len(), threshold_index, bin)
+    }
+
+    /// The exceedance frequency of every bin for an elevation angle and threshold, ordered by
+    /// azimuth bin then range bin. Unobserved bins are `None`.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn frequency_field(
+        &self,
+        elevation: f32,
+        threshold_index: usize,
+    ) -> Option<Vec<Option<f32>>> {
+        let tilt = self.tilts.get(&((elevation * 10.0).round() as u16))?;
+        if threshold_index >= self.thresholds.len() {
+            return None;
+        }
+
+        Some(
+            (0..tilt.observations.len())
+                .map(|bin| Self::bin_frequency(tilt, self.thresholds.len(), threshold_index, bin))
+                .collect(),
+        )
+    }
+
+    /// Writes the accumulator to the specified writer so ingestion may be resumed later.
+    ///
+    /// # Errors
+    /// Returns an error if the accumulator cannot be written.
+    pub fn save<W: Write>(&self, writer: W) -> Result<()> {
+        Ok(Self::options().serialize_into(writer, self)?)
+    }
+
+    /// Reads an accumulator previously written with [`EchoClimatology::save`].
+    ///
+    /// # Errors
+    /// Returns an error if the data is not a valid accumulator, including if its resolutions are
+    /// not positive or a tilt's counts do not cover every bin and threshold.
+    pub fn load<R: Read>(reader: R) -> Result<Self> {
+        let climatology: Self = Self::options().deserialize_from(reader)?;
+
+        let resolutions = [climatology.azimuth_resolution, climatology.range_resolution];
+        if resolutions
+            .iter()
+            .any(|resolution| !resolution.is_finite() || *resolution <= 0.0)
+        {
+            return Err(Error::InvalidClimatology("resolutions must be positive").into());
+        }
+
+        let bin_count = climatology.azimuth_bins() * climatology.range_bins;
+        let threshold_count = climatology.thresholds.len();
+        for tilt in climatology.tilts.values() {
+            if tilt.observations.len() != bin_count
+                || tilt.exceedances.len() != bin_count * threshold_count
+            {
+                return Err(Error::InvalidClimatology("counts do not match its bins").into());
+            }
+        }
+
+        Ok(climatology)
+    }
+
+    /// The index of the bin containing the specified azimuth and range, if within range.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn bin_index(&self, azimuth: f32, range: f32) -> Option<usize> {
+        if range < 0.0 {
+            return None;
+        }
+
+        let range_bin = (range / self.range_resolution) as usize;
+        if range_bin >= self.range_bins {
+            return None;
+        }
+
+        let azimuth_bin =
+            (azimuth.rem_euclid(360.0) / self.azimuth_resolution) as usize % self.azimuth_bins();
+
+        Some(azimuth_bin * self.range_bins + range_bin)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn bin_frequency(
+        tilt: &TiltCounts,
+        threshold_count: usize,
+        threshold_index: usize,
+        bin: usize,
+    ) -> Option<f32> {
+        if threshold_index >= threshold_count {
+            return None;
+        }
+
+        let observations = *tilt.observations.get(bin)?;
+        if observations == 0 {
+            return None;
+        }
+
+        let exceedances = tilt.exceedances[bin * threshold_count + threshold_index];
+        Some(exceedances as f32 / observations as f32)
+    }
+
+    fn options() -> impl Options {
+        DefaultOptions::new()
+            .with_fixint_encoding()
+            .with_big_endian()
+    }
+}
+
+/// Adds one tilt's counts to another's, bin by bin.
+fn add_counts(totals: &mut [u32], counts: &[u32]) {
+    for (total, count) in totals.iter_mut().zip(counts) {
+        *total += count;
+    }
+}
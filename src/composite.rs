@@ -0,0 +1,453 @@
+//!
+//! Provides [``VolumePair``] for differencing or compositing two time-adjacent volumes from the
+//! same radar on a common grid, e.g. reflectivity tendency maps for nowcasting growth and decay,
+//! and [``VolumeSeries``] for interpolating fields between a radar's volumes, e.g. for smooth
+//! animation or to align radar with fixed-interval model timesteps.
+//!
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::cancel::CancellationToken;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::grid::{
+    grid_composite, grid_composite_cancellable, grid_sweep, grid_sweep_cancellable, Grid, GridSpec,
+};
+use crate::records::Product;
+use crate::sweep::SweepCapabilities;
+
+/// How two volumes' values are combined in each grid cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combination {
+    /// The later volume's value minus the earlier volume's. Cells missing either value are `None`.
+    Difference,
+    /// The larger of the two values.
+    Maximum,
+    /// The smaller of the two values.
+    Minimum,
+    /// The mean of the two values.
+    Mean,
+}
+
+/// Which part of each volume is gridded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeLayer {
+    /// The lowest sweep containing the product.
+    LowestTilt,
+    /// The largest value across all sweeps, e.g. composite reflectivity.
+    Composite,
+}
+
+/// Options controlling how a field is interpolated between volumes.
+#[derive(Debug, Clone)]
+pub struct InterpolationOptions {
+    product: Product,
+    volume_layer: VolumeLayer,
+    spec: GridSpec,
+    max_speed: Option<f32>,
+}
+
+impl InterpolationOptions {
+    /// Create options interpolating the product's lowest tilt onto the specified grid without
+    /// advection correction.
+    #[must_use]
+    pub fn new(product: Product, spec: GridSpec) -> Self {
+        Self {
+            product,
+            volume_layer: VolumeLayer::LowestTilt,
+            spec,
+            max_speed: None,
+        }
+    }
+
+    /// Which part of each volume is gridded.
+    #[must_use]
+    pub fn with_volume_layer(mut self, volume_layer: VolumeLayer) -> Self {
+        self.volume_layer = volume_layer;
+        self
+    }
+
+    /// Corrects for advection by estimating how echoes moved between the volumes, searching
+    /// motions up to the specified speed in meters per second, and interpolating along that
+    /// motion rather than in place. Without correction, moving echoes fade out and in rather
+    /// than moving.
+    #[must_use]
+    pub fn with_advection(mut self, max_speed: f32) -> Self {
+        self.max_speed = Some(max_speed);
+        self
+    }
+}
+
+/// Two volumes from the same radar, ordered in time.
+pub struct VolumePair<'a> {
+    earlier: &'a DataFile,
+    later: &'a DataFile,
+    elapsed: Duration,
+}
+
+impl<'a> VolumePair<'a> {
+    /// Pairs two volumes from the same radar whose start times are no more than `max_gap` apart.
+    ///
+    /// # Errors
+    /// Returns an error if the volumes are from different radars, or if their times are unknown,
+    /// out of order, or further apart than `max_gap`.
+    pub fn new(earlier: &'a DataFile, later: &'a DataFile, max_gap: Duration) -> Result<Self> {
+        if earlier.volume_header().radar_id() != later.volume_header().radar_id() {
+            return Err(Error::MismatchedSites.into());
+        }
+
+        let (Some(earlier_time), Some(later_time)) = (
+            earlier.volume_header().date_time(),
+            later.volume_header().date_time(),
+        ) else {
+            return Err(Error::VolumesNotAdjacent.into());
+        };
+
+        let elapsed = later_time - earlier_time;
+        if elapsed <= Duration::zero() || elapsed > max_gap {
+            return Err(Error::VolumesNotAdjacent.into());
+        }
+
+        Ok(Self {
+            earlier,
+            later,
+            elapsed,
+        })
+    }
+
+    /// The time between the volumes' starts.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Grids the product from both volumes and combines them in each cell.
+    #[must_use]
+    pub fn combine(
+        &self,
+        product: Product,
+        volume_layer: VolumeLayer,
+        spec: &GridSpec,
+        combination: Combination,
+    ) -> Grid<Option<f32>> {
+        let earlier = grid_layer(self.earlier, product, volume_layer, spec);
+        let later = grid_layer(self.later, product, volume_layer, spec);
+
+        combine_grids(&earlier, &later, combination)
+    }
+
+    /// Grids the product from both volumes and combines them in each cell like
+    /// [`VolumePair::combine`], checking the token for cancellation while gridding.
+    ///
+    /// # Errors
+    /// Returns an error if combining was cancelled.
+    pub fn combine_cancellable(
+        &self,
+        product: Product,
+        volume_layer: VolumeLayer,
+        spec: &GridSpec,
+        combination: Combination,
+        token: &CancellationToken,
+    ) -> Result<Grid<Option<f32>>> {
+        let earlier = grid_layer_cancellable(self.earlier, product, volume_layer, spec, token)?;
+        let later = grid_layer_cancellable(self.later, product, volume_layer, spec, token)?;
+
+        Ok(combine_grids(&earlier, &later, combination))
+    }
+
+    /// The product's rate of change in each cell, normalized to change per `period`, e.g. dBZ per
+    /// five minutes for reflectivity tendency.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn tendency(
+        &self,
+        product: Product,
+        volume_layer: VolumeLayer,
+        spec: &GridSpec,
+        period: Duration,
+    ) -> Grid<Option<f32>> {
+        let scale =
+            period.num_milliseconds() as f32 / self.elapsed.num_milliseconds().max(1) as f32;
+
+        self.combine(product, volume_layer, spec, Combination::Difference)
+            .map(|difference| difference.map(|difference| difference * scale))
+    }
+
+    /// Interpolates a field at a fraction of the way from the earlier volume to the later, where
+    /// 0 is the earlier volume and 1 the later. Each cell blends the volumes' values linearly in
+    /// time; cells with a value in only one volume take it only if the time is nearer that volume.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_possible_wrap
+    )]
+    pub fn interpolate(&self, fraction: f32, options: &InterpolationOptions) -> Grid<Option<f32>> {
+        let spec = &options.spec;
+        let earlier = grid_layer(self.earlier, options.product, options.volume_layer, spec);
+        let later = grid_layer(self.later, options.product, options.volume_layer, spec);
+        let fraction = fraction.clamp(0.0, 1.0);
+
+        let (shift_x, shift_y) = options.max_speed.map_or((0, 0), |max_speed| {
+            let seconds = self.elapsed.num_milliseconds() as f32 / 1000.0;
+            let max_shift = (max_speed * seconds / spec.cell_size_m()).ceil() as isize;
+            estimate_motion(&earlier, &later, max_shift)
+        });
+
+        // Sample the earlier field behind each cell and the later field ahead of it
+        let sample = |grid: &Grid<Option<f32>>, column: usize, row: usize, scale: f32| {
+            let column = column as isize - (shift_x as f32 * scale).round() as isize;
+            let row = row as isize - (shift_y as f32 * scale).round() as isize;
+            let column = usize::try_from(column).ok()?;
+            let row = usize::try_from(row).ok()?;
+            grid.get(column, row).copied().flatten()
+        };
+
+        let values = (0..spec.rows())
+            .flat_map(|row| (0..spec.columns()).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let earlier = sample(&earlier, column, row, fraction);
+                let later = sample(&later, column, row, fraction - 1.0);
+
+                match (earlier, later) {
+                    (Some(earlier), Some(later)) => Some(earlier + (later - earlier) * fraction),
+                    (Some(earlier), None) if fraction < 0.5 => Some(earlier),
+                    (None, Some(later)) if fraction >= 0.5 => Some(later),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Grid::new(spec.columns(), spec.rows(), values)
+    }
+}
+
+/// A radar's volumes ordered in time, between which fields may be interpolated.
+pub struct VolumeSeries {
+    volumes: Vec<(NaiveDateTime, DataFile)>,
+    max_gap: Duration,
+}
+
+impl VolumeSeries {
+    /// Orders volumes from the same radar by their start times. Fields are only interpolated
+    /// between volumes no more than `max_gap` apart.
+    ///
+    /// # Errors
+    /// Returns an error if the volumes are from different radars or a volume's time is unknown.
+    pub fn new(volumes: Vec<DataFile>, max_gap: Duration) -> Result<Self> {
+        let mut timed = Vec::with_capacity(volumes.len());
+        for volume in volumes {
+            let time = volume
+                .volume_header()
+                .date_time()
+                .ok_or(Error::MissingVolumeTime)?;
+            timed.push((time, volume));
+        }
+
+        if timed.windows(2).any(|pair| {
+            pair[0].1.volume_header().radar_id() != pair[1].1.volume_header().radar_id()
+        }) {
+            return Err(Error::MismatchedSites.into());
+        }
+
+        timed.sort_by_key(|(time, _)| *time);
+
+        Ok(Self {
+            volumes: timed,
+            max_gap,
+        })
+    }
+
+    /// The start time of each volume, in order.
+    pub fn times(&self) -> impl Iterator<Item = NaiveDateTime> + '_ {
+        self.volumes.iter().map(|(time, _)| *time)
+    }
+
+    /// The volumes, in order.
+    pub fn volumes(&self) -> impl Iterator<Item = &DataFile> + '_ {
+        self.volumes.iter().map(|(_, volume)| volume)
+    }
+
+    /// Drops volumes duplicating an earlier volume in the series, e.g. the same volume received
+    /// from both an archive and a real-time feed, returning the number dropped. Volumes are
+    /// duplicates if they start at the same time and their sweeps have the same content hashes,
+    /// per [`DataFile::sweep_hashes`].
+    pub fn dedup(&mut self) -> usize {
+        let count = self.volumes.len();
+
+        let mut kept: HashSet<(NaiveDateTime, BTreeMap<u8, u64>)> = HashSet::new();
+        self.volumes
+            .retain(|(time, volume)| kept.insert((*time, volume.sweep_hashes())));
+
+        count - self.volumes.len()
+    }
+
+    /// Interpolates a field at the specified time from the volumes bracketing it, as
+    /// [``VolumePair::interpolate``]. A time matching a volume's start takes that volume's field.
+    ///
+    /// # Errors
+    /// Returns an error if the time is outside the series or the bracketing volumes are further
+    /// apart than the series' maximum gap.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn interpolate_at(
+        &self,
+        time: NaiveDateTime,
+        options: &InterpolationOptions,
+    ) -> Result<Grid<Option<f32>>> {
+        if let Some((_, volume)) = self.volumes.iter().find(|(start, _)| *start == time) {
+            return Ok(grid_layer(
+                volume,
+                options.product,
+                options.volume_layer,
+                &options.spec,
+            ));
+        }
+
+        let later = self.volumes.partition_point(|(start, _)| *start < time);
+        if later == 0 || later == self.volumes.len() {
+            return Err(Error::TimeOutsideSeries.into());
+        }
+
+        let (earlier_time, earlier) = &self.volumes[later - 1];
+        let (_, later) = &self.volumes[later];
+        let pair = VolumePair::new(earlier, later, self.max_gap)?;
+
+        let fraction = (time - *earlier_time).num_milliseconds() as f32
+            / pair.elapsed().num_milliseconds() as f32;
+
+        Ok(pair.interpolate(fraction, options))
+    }
+}
+
+/// The least fraction of the echo cells of the sparser of two fields which must overlap for a
+/// shift between them to be a candidate motion, so a few coincidentally matching cells at a large
+/// shift cannot outscore the echoes' true motion.
+const MIN_MOTION_OVERLAP: f32 = 0.5;
+
+/// Estimates how far echoes moved between two fields in cells east and south, as the shift of
+/// the earlier field up to `max_shift` cells in each direction which best matches the later
+/// field, by mean absolute difference where both have values. Only shifts overlapping at least
+/// half of the sparser field's echo cells are considered, and without any the echoes are assumed
+/// stationary.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn estimate_motion(
+    earlier: &Grid<Option<f32>>,
+    later: &Grid<Option<f32>>,
+    max_shift: isize,
+) -> (isize, isize) {
+    let echo_cells = |grid: &Grid<Option<f32>>| grid.values().iter().flatten().count();
+    let min_overlap = echo_cells(earlier).min(echo_cells(later)) as f32 * MIN_MOTION_OVERLAP;
+    let min_overlap = (min_overlap.ceil() as usize).max(1);
+
+    // Shifts are tried from smallest to largest so that smaller shifts win ties
+    let mut shifts: Vec<(isize, isize)> = (-max_shift..=max_shift)
+        .flat_map(|shift_y| (-max_shift..=max_shift).map(move |shift_x| (shift_x, shift_y)))
+        .collect();
+    shifts.sort_by_key(|(shift_x, shift_y)| shift_x.unsigned_abs() + shift_y.unsigned_abs());
+
+    let mut best = (0, 0);
+    let mut best_score = f32::INFINITY;
+    for (shift_x, shift_y) in shifts {
+        let mut difference = 0.0;
+        let mut count = 0;
+
+        for row in 0..later.rows() {
+            for column in 0..later.columns() {
+                let (Some(source_column), Some(source_row)) = (
+                    column.checked_add_signed(-shift_x),
+                    row.checked_add_signed(-shift_y),
+                ) else {
+                    continue;
+                };
+
+                if let (Some(Some(earlier)), Some(Some(later))) = (
+                    earlier.get(source_column, source_row),
+                    later.get(column, row),
+                ) {
+                    difference += (later - earlier).abs();
+                    count += 1;
+                }
+            }
+        }
+
+        let score = difference / count.max(1) as f32;
+        if count >= min_overlap && score < best_score {
+            best = (shift_x, shift_y);
+            best_score = score;
+        }
+    }
+
+    best
+}
+
+/// Combines two volumes' grids in each cell.
+fn combine_grids(
+    earlier: &Grid<Option<f32>>,
+    later: &Grid<Option<f32>>,
+    combination: Combination,
+) -> Grid<Option<f32>> {
+    let values = earlier
+        .values()
+        .iter()
+        .zip(later.values())
+        .map(|(earlier, later)| match (combination, *earlier, *later) {
+            (Combination::Difference, Some(earlier), Some(later)) => Some(later - earlier),
+            (Combination::Difference, _, _) => None,
+            (Combination::Maximum, Some(earlier), Some(later)) => Some(earlier.max(later)),
+            (Combination::Minimum, Some(earlier), Some(later)) => Some(earlier.min(later)),
+            (Combination::Mean, Some(earlier), Some(later)) => Some(f32::midpoint(earlier, later)),
+            (_, earlier, later) => earlier.or(later),
+        })
+        .collect();
+
+    Grid::new(earlier.columns(), earlier.rows(), values)
+}
+
+pub(crate) fn grid_layer(
+    file: &DataFile,
+    product: Product,
+    layer: VolumeLayer,
+    spec: &GridSpec,
+) -> Grid<Option<f32>> {
+    match layer {
+        VolumeLayer::Composite => grid_composite(file, product, spec),
+        VolumeLayer::LowestTilt => file
+            .elevation_scans()
+            .values()
+            .find(|radials| SweepCapabilities::from_radials(radials).has(product))
+            .map_or_else(
+                || Grid::filled(spec.columns(), spec.rows(), None),
+                |radials| grid_sweep(radials, product, spec),
+            ),
+    }
+}
+
+/// Grids a layer of a volume like [``grid_layer``], checking the token for cancellation.
+fn grid_layer_cancellable(
+    file: &DataFile,
+    product: Product,
+    layer: VolumeLayer,
+    spec: &GridSpec,
+    token: &CancellationToken,
+) -> Result<Grid<Option<f32>>> {
+    match layer {
+        VolumeLayer::Composite => grid_composite_cancellable(file, product, spec, token),
+        VolumeLayer::LowestTilt => match file
+            .elevation_scans()
+            .values()
+            .find(|radials| SweepCapabilities::from_radials(radials).has(product))
+        {
+            Some(radials) => grid_sweep_cancellable(radials, product, spec, token),
+            None => Ok(Grid::filled(spec.columns(), spec.rows(), None)),
+        },
+    }
+}
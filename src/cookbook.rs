@@ -0,0 +1,183 @@
+//!
+//! Provides recipes for common tasks, each a small function assembled from the crate's building
+//! blocks which can be called directly or read as an example of how to use them: downloading the
+//! latest volume with [``download_latest_volume``], rendering its lowest tilt with
+//! [``render_lowest_tilt``], finding the strongest echo near a point with
+//! [``max_reflectivity_near``], and exporting it for research software with
+//! [``export_cf_radial``].
+//!
+//! ```
+//! use nexrad::cookbook::{max_reflectivity_near, render_lowest_tilt};
+//! use nexrad::high_level::{SimulatedSite, Simulator, SimulatorConfig};
+//! use nexrad::{DataFile, Product};
+//!
+//! // A simulated volume stands in for a downloaded one
+//! let site = SimulatedSite::new("KDMX", 41.73, -93.72, 299);
+//! let config = SimulatorConfig::new(vec![site], 10.0)
+//!     .with_elevations(vec![0.5, 1.5])
+//!     .with_radials_per_sweep(90)
+//!     .with_gates(200);
+//! let volume = Simulator::new(config).next().expect("is endless")?;
+//! let file = DataFile::from_vec(volume.into_data())?;
+//!
+//! let png = render_lowest_tilt(&file, Product::Reflectivity)?;
+//! let strongest = max_reflectivity_near(&file, 41.8, -93.6, 10_000.0);
+//! # assert!(!png.is_empty());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::cfradial::encode_cfradial;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::geometry::{distance_and_azimuth, ground_range_m};
+use crate::records::Product;
+use crate::render::{render_sweep, Palette, RenderOptions};
+use crate::sweep::SweepCapabilities;
+
+/// Downloads and decodes a site's most recent volume in the archive, from today or, shortly after
+/// midnight UTC, yesterday.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let file = nexrad::cookbook::download_latest_volume("KDMX").await?;
+/// println!("{:?}", file.volume_header().date_time());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns an error if no volume from the last day is found, or it cannot be downloaded or
+/// decoded.
+#[cfg(feature = "download")]
+#[cfg_attr(docsrs, doc(cfg(feature = "download")))]
+pub async fn download_latest_volume(site: &str) -> Result<DataFile> {
+    use crate::downloader::{download_file, list_files};
+    use chrono::{Days, Utc};
+
+    let today = Utc::now().date_naive();
+    for date in [Some(today), today.checked_sub_days(Days::new(1))]
+        .into_iter()
+        .flatten()
+    {
+        let files = list_files(site, &date).await?;
+        if let Some(latest) = files
+            .iter()
+            .filter(|meta| meta.date_time().is_some())
+            .max_by_key(|meta| meta.date_time())
+        {
+            return DataFile::from_vec(download_file(latest).await?);
+        }
+    }
+
+    Err(Error::NoRecentVolume(site.to_string()).into())
+}
+
+/// Renders a product from the lowest tilt containing it as a PNG, with the default options and
+/// the product's default palette.
+///
+/// ```
+/// # use nexrad::high_level::{SimulatedSite, Simulator, SimulatorConfig};
+/// # let site = SimulatedSite::new("KDMX", 41.73, -93.72, 299);
+/// # let config = SimulatorConfig::new(vec![site], 10.0).with_radials_per_sweep(90).with_gates(200);
+/// # let volume = Simulator::new(config).next().expect("is endless")?;
+/// # let file = nexrad::DataFile::from_vec(volume.into_data())?;
+/// use nexrad::cookbook::render_lowest_tilt;
+/// use nexrad::Product;
+///
+/// let png = render_lowest_tilt(&file, Product::Velocity)?;
+/// std::fs::write(std::env::temp_dir().join("velocity.png"), png)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error if no tilt contains the product or the image cannot be encoded.
+pub fn render_lowest_tilt(file: &DataFile, product: Product) -> Result<Vec<u8>> {
+    let radials = file
+        .elevation_scans()
+        .values()
+        .find(|radials| SweepCapabilities::from_radials(radials).has(product))
+        .ok_or(Error::MissingRadials)?;
+
+    let palette = Palette::for_product(product);
+    render_sweep(radials, product, &palette, &RenderOptions::new()).to_png()
+}
+
+/// The strongest reflectivity in dBZ at any tilt within a radius in meters of a point, e.g. over a
+/// town or along a stretch of highway. Returns `None` if the volume has no site location or no
+/// reflectivity within the radius.
+///
+/// ```
+/// # use nexrad::high_level::{SimulatedSite, Simulator, SimulatorConfig};
+/// # let site = SimulatedSite::new("KDMX", 41.73, -93.72, 299);
+/// # let config = SimulatorConfig::new(vec![site], 10.0).with_radials_per_sweep(90).with_gates(200);
+/// # let volume = Simulator::new(config).next().expect("is endless")?;
+/// # let file = nexrad::DataFile::from_vec(volume.into_data())?;
+/// use nexrad::cookbook::max_reflectivity_near;
+///
+/// // Within 5 km of downtown Des Moines
+/// if let Some(dbz) = max_reflectivity_near(&file, 41.59, -93.62, 5_000.0) {
+///     println!("{dbz} dBZ");
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[must_use]
+pub fn max_reflectivity_near(file: &DataFile, lat: f32, long: f32, radius: f32) -> Option<f32> {
+    let site = file.first_volume_data()?;
+    let (distance, azimuth) = distance_and_azimuth(site.lat(), site.long(), lat, long);
+    let point = polar_to_xy(distance, azimuth);
+
+    file.elevation_scans()
+        .values()
+        .flatten()
+        .filter_map(|radial| {
+            let moment = radial.reflectivity_data()?;
+            let elevation = radial.header().elev();
+            let azimuth = radial.header().azm();
+
+            (0..moment.gate_count())
+                .filter(|index| {
+                    let range = ground_range_m(moment.data().gate_range_m(*index), elevation);
+                    let gate = polar_to_xy(range, azimuth);
+                    (gate.0 - point.0).hypot(gate.1 - point.1) <= radius
+                })
+                .filter_map(|index| moment.value(index)?.value())
+                .reduce(f32::max)
+        })
+        .reduce(f32::max)
+}
+
+/// Writes a volume to a CF/Radial `netCDF` file, per [``encode_cfradial``], for use in Py-ART,
+/// LROSE, and other research software.
+///
+/// ```
+/// # use nexrad::high_level::{SimulatedSite, Simulator, SimulatorConfig};
+/// # let site = SimulatedSite::new("KDMX", 41.73, -93.72, 299);
+/// # let config = SimulatorConfig::new(vec![site], 10.0).with_radials_per_sweep(90).with_gates(200);
+/// # let volume = Simulator::new(config).next().expect("is endless")?;
+/// # let file = nexrad::DataFile::from_vec(volume.into_data())?;
+/// use nexrad::cookbook::export_cf_radial;
+///
+/// let path = std::env::temp_dir().join("KDMX.nc");
+/// export_cf_radial(&file, &path)?;
+/// # std::fs::remove_file(path)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error if the volume cannot be encoded or the file cannot be written.
+pub fn export_cf_radial(file: &DataFile, path: &Path) -> Result<()> {
+    fs::write(path, encode_cfradial(file)?)?;
+    Ok(())
+}
+
+/// Meters east and north of the radar of a ground range and azimuth in degrees.
+fn polar_to_xy(range: f32, azimuth: f32) -> (f32, f32) {
+    let (sin, cos) = azimuth.to_radians().sin_cos();
+    (range * sin, range * cos)
+}
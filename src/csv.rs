@@ -0,0 +1,214 @@
+//!
+//! Provides [``write_csv``] for exporting a volume's gates as long-format CSV, one row per gate
+//! with its time, location, product, and value, for use in spreadsheets and GIS tools which cannot
+//! read radar formats. Gates below each product's threshold are omitted, and gates and radials may
+//! be decimated to keep files manageable.
+//!
+//! ```text
+//! time_utc,latitude,longitude,height_m,product,value
+//! 2024-01-01 12:00:04,41.74012,-93.71843,312,ref,23.5
+//! ```
+//!
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use anyhow::Result;
+use serde::{Deserialize, Deserializer};
+
+use crate::batch::product_name;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::geometry::{beam_height_m, destination, ground_range_m};
+use crate::records::{Message31, Product, VolumeData};
+
+/// The header row of exported files.
+const HEADER: &str = "time_utc,latitude,longitude,height_m,product,value";
+
+/// Options controlling which gates are exported. Deserializable with serde, with unspecified
+/// options taking their defaults and thresholds as a table of products' least values, e.g.
+/// `thresholds = { reflectivity = 10.0 }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvOptions {
+    products: Vec<Product>,
+    #[serde(deserialize_with = "deserialize_thresholds")]
+    thresholds: Vec<(Product, f32)>,
+    #[serde(deserialize_with = "deserialize_step")]
+    gate_step: usize,
+    #[serde(deserialize_with = "deserialize_step")]
+    radial_step: usize,
+}
+
+impl CsvOptions {
+    /// Create the default options: every reflectivity gate of at least 10 dBZ.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The products exported, each gate's products in the specified order.
+    #[must_use]
+    pub fn with_products(mut self, products: Vec<Product>) -> Self {
+        self.products = products;
+        self
+    }
+
+    /// The least value of a product's gates to export. Products without a threshold export every
+    /// gate with a value.
+    #[must_use]
+    pub fn with_threshold(mut self, product: Product, min: f32) -> Self {
+        self.thresholds.retain(|(existing, _)| *existing != product);
+        self.thresholds.push((product, min));
+        self
+    }
+
+    /// Exports every `gate_step`th gate along each radial and every `radial_step`th radial of each
+    /// sweep, starting from the first.
+    ///
+    /// # Panics
+    /// Panics if either step is zero.
+    #[must_use]
+    pub fn with_decimation(mut self, gate_step: usize, radial_step: usize) -> Self {
+        assert!(gate_step > 0 && radial_step > 0, "steps must be positive");
+        self.gate_step = gate_step;
+        self.radial_step = radial_step;
+        self
+    }
+
+    /// The products exported.
+    #[must_use]
+    pub fn products(&self) -> &[Product] {
+        &self.products
+    }
+
+    /// The least value of a product's gates to export, if it has a threshold.
+    #[must_use]
+    pub fn threshold(&self, product: Product) -> Option<f32> {
+        self.thresholds
+            .iter()
+            .find(|(existing, _)| *existing == product)
+            .map(|(_, min)| *min)
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            products: vec![Product::Reflectivity],
+            thresholds: vec![(Product::Reflectivity, 10.0)],
+            gate_step: 1,
+            radial_step: 1,
+        }
+    }
+}
+
+fn deserialize_thresholds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<(Product, f32)>, D::Error> {
+    BTreeMap::<Product, f32>::deserialize(deserializer)
+        .map(|thresholds| thresholds.into_iter().collect())
+}
+
+fn deserialize_step<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<usize, D::Error> {
+    match usize::deserialize(deserializer)? {
+        0 => Err(serde::de::Error::custom("steps must be positive")),
+        step => Ok(step),
+    }
+}
+
+/// Writes a volume's gates as CSV with a header row, returning the number of gate rows written.
+/// Rows are ordered by sweep, radial, gate, and product. Times are each radial's collection time
+/// in UTC, and heights are of the beam's center above mean sea level.
+///
+/// # Errors
+/// Returns an error if the volume has no site location or valid start time, or the writer fails.
+pub fn write_csv<W: Write>(file: &DataFile, options: &CsvOptions, mut writer: W) -> Result<usize> {
+    let site = file.first_volume_data().ok_or(Error::MissingSiteLocation)?;
+    let volume_time = file
+        .volume_header()
+        .date_time()
+        .ok_or(Error::MissingVolumeTime)?;
+
+    writeln!(writer, "{HEADER}")?;
+
+    let mut rows = 0;
+    for radials in file.elevation_scans().values() {
+        for radial in radials.iter().step_by(options.radial_step) {
+            let time = radial.header().date_time().unwrap_or(volume_time);
+            let time = time.format("%Y-%m-%d %H:%M:%S");
+            rows += write_radial(&mut writer, radial, &site, &time.to_string(), options)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(rows)
+}
+
+/// Encodes a volume's gates as CSV, per [``write_csv``].
+///
+/// # Errors
+/// Returns an error if the volume has no site location or valid start time.
+pub fn encode_csv(file: &DataFile, options: &CsvOptions) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    write_csv(file, options, &mut data)?;
+    Ok(data)
+}
+
+/// Writes a radial's gates, returning the number of rows written.
+fn write_radial<W: Write>(
+    writer: &mut W,
+    radial: &Message31,
+    site: &VolumeData,
+    time: &str,
+    options: &CsvOptions,
+) -> Result<usize> {
+    let site = radial.volume_data().unwrap_or(site);
+    let elevation = radial.header().elev();
+    let azimuth = radial.header().azm();
+
+    let moments: Vec<_> = options
+        .products
+        .iter()
+        .filter_map(|product| {
+            let moment = radial.get_data_moment(&(*product).into())?;
+            Some((product_name(*product), options.threshold(*product), moment))
+        })
+        .collect();
+    let gates = moments
+        .iter()
+        .map(|(_, _, moment)| moment.gate_count())
+        .max()
+        .unwrap_or(0);
+
+    let mut rows = 0;
+    for index in (0..gates).step_by(options.gate_step) {
+        for (name, threshold, moment) in &moments {
+            let Some(value) = moment.value(index).and_then(|value| value.value()) else {
+                continue;
+            };
+            if threshold.is_some_and(|min| value < min) {
+                continue;
+            }
+
+            let range = moment.data().gate_range_m(index);
+            let (lat, long) = destination(
+                site.lat(),
+                site.long(),
+                azimuth,
+                ground_range_m(range, elevation),
+            );
+            let height = beam_height_m(range, elevation, site.antenna_altitude_m());
+
+            writeln!(
+                writer,
+                "{time},{lat:.5},{long:.5},{height:.0},{name},{value}"
+            )?;
+            rows += 1;
+        }
+    }
+
+    Ok(rows)
+}
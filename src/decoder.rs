@@ -0,0 +1,591 @@
+//!.panic
+//! Provides utilities like [``decode_file``] for decoding NEXRAD data.
+//!
+
+use bincode::{DefaultOptions, Options};
+use serde::de::DeserializeOwned;
+use std::collections::{btree_map, BTreeMap};
+use std::fmt::Display;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::mem::size_of;
+use std::path::Path;
+
+use crate::cancel::CancellationToken;
+use crate::decompressor::decompress_file;
+use crate::error::Error;
+use crate::metadata::is_compressed;
+use crate::records::{
+    DataBlockHeader, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+    Message31Header, MessageHeader, RadialData, VolumeData, VolumeHeaderRecord,
+    RADIAL_DATA_BASE_SIZE,
+};
+use crate::sweep::{content_hash, propagate_metadata, Sweep};
+use anyhow::Result;
+
+/// Size of the CTM header preceding each message's header.
+const CTM_HEADER_SIZE: u64 = 12;
+
+/// Options controlling how a data file is decoded.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    sort_azimuths: bool,
+    propagate_metadata: bool,
+    strict: bool,
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl DecodeOptions {
+    /// Create the default decode options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether each elevation scan's radials should be sorted by azimuth once decoding finishes.
+    /// Enabled by default.
+    #[must_use]
+    pub fn with_sort_azimuths(mut self, sort_azimuths: bool) -> Self {
+        self.sort_azimuths = sort_azimuths;
+        self
+    }
+
+    /// Whether each elevation scan's radials will be sorted by azimuth.
+    #[must_use]
+    pub fn sort_azimuths(&self) -> bool {
+        self.sort_azimuths
+    }
+
+    /// Whether the volume, elevation, and radial data blocks should be copied to every radial
+    /// lacking them once decoding finishes, per [`DataFile::propagate_metadata`]. Disabled by
+    /// default.
+    #[must_use]
+    pub fn with_propagate_metadata(mut self, propagate_metadata: bool) -> Self {
+        self.propagate_metadata = propagate_metadata;
+        self
+    }
+
+    /// Whether metadata blocks will be copied to every radial lacking them.
+    #[must_use]
+    pub fn propagate_metadata(&self) -> bool {
+        self.propagate_metadata
+    }
+
+    /// Whether decoding should fail on a radial that cannot be decoded, e.g. one whose data blocks
+    /// are compressed, rather than skip it and record it in the [``DecodeReport``]. Disabled by
+    /// default.
+    #[must_use]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether decoding will fail on a radial that cannot be decoded.
+    #[must_use]
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// A token which, once cancelled, stops decoding before the next message.
+    #[must_use]
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// The token checked for cancellation while decoding, if any.
+    #[must_use]
+    pub fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            sort_azimuths: true,
+            propagate_metadata: false,
+            strict: false,
+            cancellation_token: None,
+        }
+    }
+}
+
+/// Why a radial was skipped while decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The radial's data blocks are compressed with the specified code, 1 for BZIP2 or 2 for zlib,
+    /// which this decoder does not decompress. Reading them as uncompressed would produce corrupt
+    /// moments.
+    UnsupportedCompression(u8),
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedCompression(code) => {
+                write!(f, "unsupported radial compression code {code}")
+            }
+        }
+    }
+}
+
+/// A radial skipped while decoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedRadial {
+    offset: u64,
+    elevation_number: u8,
+    azimuth_number: u16,
+    azimuth: f32,
+    reason: SkipReason,
+}
+
+impl SkippedRadial {
+    /// The radial's message offset in bytes from the start of the decompressed file.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The radial's elevation number.
+    #[must_use]
+    pub fn elevation_number(&self) -> u8 {
+        self.elevation_number
+    }
+
+    /// The radial's azimuth number within its sweep.
+    #[must_use]
+    pub fn azimuth_number(&self) -> u16 {
+        self.azimuth_number
+    }
+
+    /// The radial's azimuth angle in degrees.
+    #[must_use]
+    pub fn azimuth(&self) -> f32 {
+        self.azimuth
+    }
+
+    /// Why the radial was skipped.
+    #[must_use]
+    pub fn reason(&self) -> SkipReason {
+        self.reason
+    }
+}
+
+/// What could not be decoded from a data file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeReport {
+    skipped: Vec<SkippedRadial>,
+}
+
+impl DecodeReport {
+    /// Radials skipped while decoding, in the order they appear in the file.
+    #[must_use]
+    pub fn skipped(&self) -> &[SkippedRadial] {
+        &self.skipped
+    }
+
+    /// Whether every radial was decoded.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+}
+
+/// A decoded NEXRAD WSR-88D data file including sweep data.
+pub struct DataFile {
+    volume_header: VolumeHeaderRecord,
+    elevation_scans: BTreeMap<u8, Vec<Message31>>,
+    decode_report: DecodeReport,
+}
+
+impl DataFile {
+    /// Load a nexrad file from a file path, decoding if necessary
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a valid NEXRAD file.
+    pub fn new(file_path: &Path) -> Result<Self> {
+        let data = std::fs::read(file_path)?;
+
+        if is_compressed(&data) {
+            let decompressed = decompress_file(&data)?;
+            Self::from_vec(decompressed)
+        } else {
+            Self::from_vec(data)
+        }
+    }
+
+    /// Load a nexrad file from byte slice.
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a valid NEXRAD file.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        Self::from_vec(data.to_vec())
+    }
+
+    /// Given an uncompressed data file, decodes it and returns the decoded structure.
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a valid NEXRAD file.
+    pub fn from_vec(data: Vec<u8>) -> Result<Self> {
+        Self::from_vec_with_options(data, &DecodeOptions::default())
+    }
+
+    /// Given a data file, decodes it according to the specified options and returns the decoded
+    /// structure.
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a valid NEXRAD file or decoding was cancelled, or if a
+    /// radial cannot be decoded and either decoding is strict or the radial cannot be skipped.
+    pub fn from_vec_with_options(mut data: Vec<u8>, options: &DecodeOptions) -> Result<Self> {
+        if is_compressed(&data) {
+            data = decompress_file(&data)?;
+        }
+
+        let mut reader = Cursor::new(data.as_slice());
+
+        let file_header: VolumeHeaderRecord = Self::decode_file_header(&mut reader)?;
+        let mut file = Self::from_header(file_header);
+
+        while reader.position() < data.len() as u64 {
+            if let Some(token) = options.cancellation_token() {
+                token.check()?;
+            }
+
+            let message_start = reader.position();
+            let message_header: MessageHeader = Self::deserialize(&mut reader)?;
+
+            if message_header.msg_type() == 31 {
+                let message_end = (message_header.msg_size() != 0).then(|| {
+                    message_start + CTM_HEADER_SIZE + u64::from(message_header.msg_size()) * 2
+                });
+
+                let radial_start = reader.position();
+                let radial_header: Message31Header = Self::deserialize(&mut reader)?;
+                reader.seek(SeekFrom::Start(radial_start))?;
+
+                let compression_code = radial_header.compression_code();
+                if compression_code != 0 {
+                    // Without a message or radial length the next message cannot be found
+                    let radial_len = u64::from(radial_header.radial_len());
+                    let skip_to =
+                        message_end.or((radial_len != 0).then(|| radial_start + radial_len));
+                    let (false, Some(skip_to)) = (options.strict(), skip_to) else {
+                        return Err(Error::UnsupportedRadialCompression(compression_code).into());
+                    };
+
+                    file.decode_report.skipped.push(SkippedRadial {
+                        offset: message_start,
+                        elevation_number: radial_header.elev_num(),
+                        azimuth_number: radial_header.azm_num(),
+                        azimuth: radial_header.azm(),
+                        reason: SkipReason::UnsupportedCompression(compression_code),
+                    });
+                    reader.seek(SeekFrom::Start(skip_to))?;
+                    continue;
+                }
+
+                let message = Self::decode_message_31(&mut reader, None)?;
+                file.elevation_scans_mut()
+                    .entry(message.header().elev_num())
+                    .or_default()
+                    .push(message);
+
+                // Skip any padding following the data blocks to the end of the message
+                if let Some(message_end) = message_end {
+                    reader.seek(SeekFrom::Start(message_end.max(reader.position())))?;
+                }
+            } else {
+                let ff_distance = i64::try_from(2432 - size_of::<MessageHeader>())?;
+                reader.seek(SeekFrom::Current(ff_distance))?;
+            }
+        }
+
+        if options.sort_azimuths() {
+            for radials in file.elevation_scans_mut().values_mut() {
+                sort_by_azimuth(radials);
+            }
+        }
+
+        if options.propagate_metadata() {
+            file.propagate_metadata();
+        }
+
+        Ok(file)
+    }
+
+    /// Create a new data file for the specified header with no sweep data.
+    pub(crate) fn from_header(file_header: VolumeHeaderRecord) -> Self {
+        Self {
+            volume_header: file_header,
+            elevation_scans: BTreeMap::new(),
+            decode_report: DecodeReport::default(),
+        }
+    }
+
+    /// Assembles a data file from a volume header and sweeps, e.g. as produced by a decoder for
+    /// another format, so that they can be used with this crate's products and encoders. Each
+    /// sweep's radials are kept in the order given.
+    ///
+    /// # Errors
+    /// Returns an error if two sweeps have the same elevation number.
+    pub fn from_parts(volume_header: VolumeHeaderRecord, sweeps: Vec<Sweep>) -> Result<Self> {
+        let mut file = Self::from_header(volume_header);
+
+        for sweep in sweeps {
+            let elevation_number = sweep.elevation_number();
+            if file.elevation_scans.contains_key(&elevation_number) {
+                return Err(Error::DuplicateElevation(elevation_number).into());
+            }
+
+            file.elevation_scans
+                .insert(elevation_number, sweep.into_radials());
+        }
+
+        Ok(file)
+    }
+
+    /// The volume/file header information.
+    #[must_use]
+    pub fn volume_header(&self) -> &VolumeHeaderRecord {
+        &self.volume_header
+    }
+
+    /// Scan data grouped by elevation number. Each scan's radials are ordered by azimuth unless
+    /// sorting was disabled with [`DecodeOptions::with_sort_azimuths`].
+    #[must_use]
+    pub fn elevation_scans(&self) -> &BTreeMap<u8, Vec<Message31>> {
+        &self.elevation_scans
+    }
+
+    /// The radials of the scan at a position in elevation number order, e.g. 0 for the lowest
+    /// elevation number. Returns `None` if the file has fewer scans.
+    #[must_use]
+    pub fn elevation_scan_at(&self, index: usize) -> Option<&[Message31]> {
+        self.elevation_scans.values().nth(index).map(Vec::as_slice)
+    }
+
+    /// Scan data grouped by elevation number, ordered the same as [`DataFile::elevation_scans`].
+    #[must_use]
+    pub fn as_elevation_scans(self) -> BTreeMap<u8, Vec<Message31>> {
+        self.elevation_scans
+    }
+
+    /// Consumes the file, returning an iterator over its sweeps in elevation order. Sweeps are
+    /// moved out of the file, so no moment data is cloned.
+    #[must_use]
+    pub fn into_sweeps(self) -> IntoSweeps {
+        IntoSweeps {
+            scans: self.elevation_scans.into_iter(),
+        }
+    }
+
+    /// What could not be decoded from the file, e.g. radials skipped because their data blocks are
+    /// compressed. Files not decoded from data, e.g. assembled from parts, have a complete report.
+    #[must_use]
+    pub fn decode_report(&self) -> &DecodeReport {
+        &self.decode_report
+    }
+
+    /// Scan data grouped by elevation number.
+    pub(crate) fn elevation_scans_mut(&mut self) -> &mut BTreeMap<u8, Vec<Message31>> {
+        &mut self.elevation_scans
+    }
+
+    /// Copies the volume, elevation, and radial data blocks to every radial lacking them, so that
+    /// consumers need not search for the radials that happened to carry them. Within each sweep, a
+    /// radial receives the blocks of the nearest preceding radial carrying them, or of the first
+    /// if none precedes it. Sweeps without volume data receive the volume's first.
+    pub fn propagate_metadata(&mut self) {
+        let volume_data = self
+            .elevation_scans
+            .values()
+            .flatten()
+            .find_map(Message31::volume_data)
+            .cloned();
+        for radials in self.elevation_scans.values_mut() {
+            propagate_metadata(radials, volume_data.as_ref());
+        }
+    }
+
+    /// The content hash of each sweep by elevation number, per [`Sweep::content_hash`].
+    #[must_use]
+    pub fn sweep_hashes(&self) -> BTreeMap<u8, u64> {
+        self.elevation_scans
+            .iter()
+            .map(|(elevation_number, radials)| (*elevation_number, content_hash(radials)))
+            .collect()
+    }
+
+    /// First available header for the specified elevation.
+    #[must_use]
+    pub fn first_volume_data(&self) -> Option<VolumeData> {
+        let header = self
+            .elevation_scans
+            .first_key_value()?
+            .1
+            .first()?
+            .volume_data()?
+            .clone();
+
+        Some(header)
+    }
+
+    pub(crate) fn decode_file_header<R: Read + Seek>(reader: &mut R) -> Result<VolumeHeaderRecord> {
+        Self::deserialize(reader)
+    }
+
+    /// Decodes a message 31 following its message header. If a moment is specified, only that
+    /// moment's data is decoded and other moments are skipped.
+    pub(crate) fn decode_message_31(
+        reader: &mut Cursor<&[u8]>,
+        moment: Option<&DataBlockProduct>,
+    ) -> Result<Message31> {
+        let start_pos = reader.position();
+
+        let message_31_header: Message31Header = Self::deserialize(reader)?;
+        let compression_code = message_31_header.compression_code();
+        if compression_code != 0 {
+            return Err(Error::UnsupportedRadialCompression(compression_code).into());
+        }
+        let mut message = Message31::new(message_31_header);
+
+        let pointers_space = message.header().data_block_count() as usize * size_of::<u32>();
+        let mut pointers_raw = vec![0; pointers_space];
+        reader.read_exact(&mut pointers_raw)?;
+
+        let data_block_pointers = pointers_raw
+            .chunks_exact(size_of::<u32>())
+            .filter_map(|v| Some(<u32>::from_be_bytes(v.try_into().ok()?)))
+            .collect::<Vec<_>>();
+
+        // Blocks may appear in any order, so each is located from the start of the header rather
+        // than the reader's position, and must lie after the pointers within the radial
+        let pointers_end = reader.position();
+        let radial_len = u64::from(message.header().radial_len());
+        let data_end = reader.get_ref().len() as u64;
+
+        // Newer builds may lengthen blocks or append unknown ones, so track the furthest byte
+        // belonging to a known block to expose anything beyond it
+        let mut known_end = pointers_end;
+
+        for pointer in data_block_pointers {
+            let block_start = start_pos + u64::from(pointer);
+            let within_radial = radial_len == 0 || u64::from(pointer) < radial_len;
+            if block_start < pointers_end || !within_radial || block_start + 4 > data_end {
+                return Err(Error::InvalidDataBlockPointer(pointer).into());
+            }
+
+            reader.seek(SeekFrom::Start(block_start))?;
+
+            let data_block: DataBlockHeader = Self::deserialize(reader)?;
+            reader.seek(SeekFrom::Current(-4))?;
+
+            // Skip blocks unknown to this decoder rather than failing the whole radial
+            let Ok(data_block_product) = data_block.data_block_product() else {
+                continue;
+            };
+
+            match data_block_product {
+                DataBlockProduct::VolumeData => {
+                    let data: VolumeData = Self::deserialize(reader)?;
+                    known_end = known_end.max(block_start + u64::from(data.lrtup()));
+                    message.set_volume_data(data);
+                }
+                DataBlockProduct::ElevationData => {
+                    let data: ElevationData = Self::deserialize(reader)?;
+                    known_end = known_end.max(block_start + u64::from(data.lrtup()));
+                    message.set_elevation_data(data);
+                }
+                DataBlockProduct::RadialData => {
+                    let mut data: RadialData = Self::deserialize(reader)?;
+
+                    // Newer builds append fields, which are read rather than left to the
+                    // following block's pointer to skip
+                    let extension_size = data.lrtup().saturating_sub(RADIAL_DATA_BASE_SIZE);
+                    if extension_size > 0 {
+                        if block_start + u64::from(data.lrtup()) > data_end {
+                            return Err(Error::InvalidDataBlockPointer(pointer).into());
+                        }
+                        let mut extension = vec![0; usize::from(extension_size)];
+                        reader.read_exact(&mut extension)?;
+                        data.set_extension(extension)?;
+                    }
+
+                    message.set_radial_data(data);
+                }
+                DataBlockProduct::Reflectivity
+                | DataBlockProduct::Velocity
+                | DataBlockProduct::ClutterFilterProbability
+                | DataBlockProduct::SpectrumWidth
+                | DataBlockProduct::DifferentialReflectivity
+                | DataBlockProduct::DifferentialPhase
+                | DataBlockProduct::CorrelationCoefficient => {
+                    let generic_data: GenericData = Self::deserialize(reader)?;
+
+                    if moment.is_some_and(|moment| *moment != data_block_product) {
+                        let moment_size = i64::try_from(generic_data.moment_size())?;
+                        reader.seek(SeekFrom::Current(moment_size))?;
+                    } else {
+                        let mut moment_data = vec![0; generic_data.moment_size()];
+                        reader.read_exact(&mut moment_data)?;
+
+                        let data = DataMoment::new(data_block_product, generic_data, moment_data);
+                        message.set_data_moment(data);
+                    }
+                }
+            }
+
+            known_end = known_end.max(reader.position());
+        }
+
+        // Preserve any bytes the header's radial length claims beyond the known blocks
+        let radial_end = start_pos + radial_len;
+        if radial_end.min(data_end) > known_end {
+            let mut trailing_bytes =
+                vec![0; usize::try_from(radial_end.min(data_end) - known_end)?];
+            reader.seek(SeekFrom::Start(known_end))?;
+            reader.read_exact(&mut trailing_bytes)?;
+            message.set_trailing_bytes(trailing_bytes);
+        }
+
+        Ok(message)
+    }
+
+    /// Attempts to deserialize some struct from the provided binary reader.
+    pub(crate) fn deserialize<R: Read + Seek, S: DeserializeOwned>(reader: &mut R) -> Result<S> {
+        Ok(DefaultOptions::new()
+            .with_fixint_encoding()
+            .with_big_endian()
+            .deserialize_from(reader.by_ref())?)
+    }
+}
+
+/// An owning iterator over a data file's sweeps, created by [`DataFile::into_sweeps`].
+pub struct IntoSweeps {
+    scans: btree_map::IntoIter<u8, Vec<Message31>>,
+}
+
+impl Iterator for IntoSweeps {
+    type Item = Sweep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (elevation_number, radials) = self.scans.next()?;
+        Some(Sweep::new(elevation_number, radials))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.scans.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoSweeps {}
+
+/// Sorts the radials by their azimuth angle.
+pub(crate) fn sort_by_azimuth(radials: &mut [Message31]) {
+    radials.sort_by(|a, b| {
+        a.header()
+            .azm()
+            .partial_cmp(&b.header().azm())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
@@ -0,0 +1,168 @@
+//!
+//! Provides utilities like [``decompress_file``] for decompressing BZIP2- or zlib-compressed NEXRAD
+//! data.
+//!
+
+use crate::error::Error;
+use crate::metadata::{is_bzip2_compressed, is_compressed, is_zlib_compressed};
+use crate::records::VolumeHeaderRecord;
+use anyhow::Result;
+use std::io::Read;
+
+/// Given a compressed data file, decompresses it and returns a new copy of the decompressed data.
+/// Each record may be compressed with either BZIP2 or zlib, which is detected per record.
+///
+/// # Errors
+/// Will fail if the file is already decompressed or a record's compression is not recognized.
+#[allow(clippy::module_name_repetitions)]
+pub fn decompress_file(data: &[u8]) -> Result<Vec<u8>> {
+    if !is_compressed(data) {
+        return Err(Error::DecompressUnsupportedFile.into());
+    }
+
+    let mut decompressed_buffer = Vec::new();
+
+    // Start the decompressed data by copying the file header, which is not compressed
+    let header_size = std::mem::size_of::<VolumeHeaderRecord>();
+    let (header, records) = data.split_at(header_size);
+    decompressed_buffer.extend_from_slice(header);
+
+    // Append each decompressed record to the decompressed data
+    for record in decompress_records(records) {
+        decompressed_buffer.extend(record?);
+    }
+
+    Ok(decompressed_buffer)
+}
+
+/// Decompresses a data file like [``decompress_file``], but decompresses its records in parallel.
+///
+/// # Errors
+/// Will fail if the file is already decompressed or a record's compression is not recognized.
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn decompress_file_parallel(data: &[u8]) -> Result<Vec<u8>> {
+    use rayon::prelude::*;
+
+    if !is_compressed(data) {
+        return Err(Error::DecompressUnsupportedFile.into());
+    }
+
+    let header_size = std::mem::size_of::<VolumeHeaderRecord>();
+    let (header, records) = data.split_at(header_size);
+
+    let decompressed = split_records(records)?
+        .into_par_iter()
+        .map(decompress_record)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut decompressed_buffer = header.to_vec();
+    for record in decompressed {
+        decompressed_buffer.extend(record);
+    }
+
+    Ok(decompressed_buffer)
+}
+
+/// Given a sequence of compressed records with their size prefixes, e.g. the remainder of a data
+/// file following its volume header or a real-time chunk, returns an iterator which decompresses
+/// each record only as it is reached.
+#[must_use]
+pub fn decompress_records(records: &[u8]) -> RecordDecompressor<'_> {
+    RecordDecompressor { reader: records }
+}
+
+/// Splits a sequence of compressed records into each record's compressed data using their size
+/// prefixes, without decompressing them. The last record's size prefix is conventionally negative.
+///
+/// # Errors
+/// Will fail if a record's size prefix exceeds the remaining data.
+pub fn split_records(records: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut reader = records;
+    let mut split = Vec::new();
+
+    while !reader.is_empty() {
+        let (prefix, remainder) = reader
+            .split_first_chunk::<4>()
+            .ok_or(Error::DecompressUnsupportedRecord)?;
+
+        let size = usize::try_from(i32::from_be_bytes(*prefix).unsigned_abs())?;
+        if size > remainder.len() {
+            return Err(Error::DecompressUnsupportedRecord.into());
+        }
+
+        let (record, remainder) = remainder.split_at(size);
+        split.push(record);
+        reader = remainder;
+    }
+
+    Ok(split)
+}
+
+/// Decompresses a single record's compressed data, excluding its size prefix.
+///
+/// # Errors
+/// Will fail if the record's compression is not recognized or its data is invalid.
+pub fn decompress_record(record: &[u8]) -> Result<Vec<u8>> {
+    Ok(decompress_stream(record)?.0)
+}
+
+/// Decompresses the BZIP2 or zlib stream at the start of the data, returning the decompressed data
+/// and the number of compressed bytes consumed.
+fn decompress_stream(reader: &[u8]) -> Result<(Vec<u8>, u64)> {
+    let mut block_buffer = Vec::new();
+    let consumed = if is_bzip2_compressed(reader) {
+        let mut decoder = bzip2::read::BzDecoder::new(reader);
+        decoder.read_to_end(&mut block_buffer)?;
+        decoder.total_in()
+    } else if is_zlib_compressed(reader) {
+        let mut decoder = flate2::bufread::ZlibDecoder::new(reader);
+        decoder.read_to_end(&mut block_buffer)?;
+        decoder.total_in()
+    } else {
+        return Err(Error::DecompressUnsupportedRecord.into());
+    };
+
+    Ok((block_buffer, consumed))
+}
+
+/// Lazily decompresses a sequence of compressed records, created by [`decompress_records`].
+pub struct RecordDecompressor<'a> {
+    reader: &'a [u8],
+}
+
+impl RecordDecompressor<'_> {
+    fn decompress_next(&mut self) -> Result<Vec<u8>> {
+        // Skip the first 4 bytes of the compressed block, which is the size of the block
+        if self.reader.len() < 4 {
+            return Err(Error::DecompressUnsupportedRecord.into());
+        }
+        let reader = self.reader.split_at(4).1;
+
+        // Read the decompressed block into a buffer, noting how many compressed bytes it consumed
+        let (block_buffer, consumed) = decompress_stream(reader)?;
+
+        // Advance the reader to the next compressed block
+        self.reader = reader.split_at(usize::try_from(consumed)?).1;
+
+        Ok(block_buffer)
+    }
+}
+
+impl Iterator for RecordDecompressor<'_> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.is_empty() {
+            return None;
+        }
+
+        let record = self.decompress_next();
+        if record.is_err() {
+            // Stop after an error since the next record's position is unknown
+            self.reader = &[];
+        }
+
+        Some(record)
+    }
+}
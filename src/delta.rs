@@ -0,0 +1,407 @@
+//!
+//! Provides [``DeltaEncoder``] and [``DeltaDecoder``] for relaying live radials over constrained
+//! links, e.g. marine or satellite, as a compact stream in which most radials carry only the gates
+//! that changed from the previous radial.
+//!
+//! The stream is a sequence of frames, each beginning with a sync marker and ending with a CRC-32:
+//!
+//! | Field | Size |
+//! |---|---|
+//! | Sync marker, `NXDF` | 4 bytes |
+//! | Kind: 0 for a keyframe, 1 for a delta | 1 byte |
+//! | Sequence number | 4 bytes |
+//! | Payload length | 4 bytes |
+//! | Payload | variable |
+//! | CRC-32 of the kind through the payload | 4 bytes |
+//!
+//! A keyframe's payload is the radial encoded as an Archive II message 31. A delta's payload is the
+//! radial's message 31 header followed, for each moment, by runs of changed gates encoded as a
+//! count of unchanged gates to skip, a count of changed gates, then their raw data words. Deltas
+//! apply to the frame with the previous sequence number, so when frames are lost or corrupted the
+//! decoder skips to the next sync marker and discards deltas until the next keyframe, which the
+//! encoder sends at the start of each sweep and periodically within it.
+//!
+
+use std::io::Cursor;
+
+use anyhow::Result;
+use flate2::Crc;
+
+use crate::decoder::DataFile;
+use crate::encode::{encode_message_31, serialize};
+use crate::error::Error;
+use crate::records::{DataMoment, Message31, Message31Header, MessageHeader, Product, RadialData};
+
+/// The marker each frame begins with.
+const SYNC_MARKER: &[u8; 4] = b"NXDF";
+
+/// The size of a frame's marker, kind, sequence number, and payload length.
+const FRAME_HEADER_SIZE: usize = 13;
+
+/// The size of the CRC-32 ending each frame.
+const CRC_SIZE: usize = 4;
+
+/// The longest payload accepted, beyond which a frame header is assumed corrupt.
+const MAX_PAYLOAD_SIZE: usize = 1 << 20;
+
+const KEYFRAME: u8 = 0;
+const DELTA: u8 = 1;
+
+/// Encodes radials into a delta-compressed frame stream.
+pub struct DeltaEncoder {
+    keyframe_interval: usize,
+    sequence: u32,
+    since_keyframe: usize,
+    previous: Option<Message31>,
+}
+
+impl DeltaEncoder {
+    /// Create an encoder which sends a keyframe at least every 30 radials, limiting how much of
+    /// a sweep is lost after a dropped frame to about 15 degrees at super resolution.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most radials sent between keyframes, counting the keyframe.
+    ///
+    /// # Panics
+    /// Panics if the interval is zero.
+    #[must_use]
+    pub fn with_keyframe_interval(mut self, keyframe_interval: usize) -> Self {
+        assert!(keyframe_interval > 0, "keyframe interval must be positive");
+        self.keyframe_interval = keyframe_interval;
+        self
+    }
+
+    /// Encodes the next radial as a frame: a keyframe if it starts a sweep, differs from the
+    /// previous radial in anything but its header and gate values, or is due; otherwise a delta.
+    ///
+    /// # Errors
+    /// Returns an error if the radial is too large to encode.
+    pub fn encode(&mut self, radial: &Message31) -> Result<Vec<u8>> {
+        let delta = match &self.previous {
+            Some(previous)
+                if self.since_keyframe < self.keyframe_interval
+                    && is_compatible(previous, radial)? =>
+            {
+                Some(encode_delta(previous, radial)?)
+            }
+            _ => None,
+        };
+
+        let (kind, payload) = if let Some(payload) = delta {
+            self.since_keyframe += 1;
+            (DELTA, payload)
+        } else {
+            self.since_keyframe = 1;
+            (KEYFRAME, encode_message_31(radial, 0)?)
+        };
+
+        let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len() + CRC_SIZE);
+        frame.extend_from_slice(SYNC_MARKER);
+        frame.push(kind);
+        frame.extend_from_slice(&self.sequence.to_be_bytes());
+        frame.extend_from_slice(&u32::try_from(payload.len())?.to_be_bytes());
+        frame.extend(payload);
+
+        let mut crc = Crc::new();
+        crc.update(&frame[SYNC_MARKER.len()..]);
+        frame.extend_from_slice(&crc.sum().to_be_bytes());
+
+        self.sequence = self.sequence.wrapping_add(1);
+        self.previous = Some(radial.clone());
+
+        Ok(frame)
+    }
+}
+
+impl Default for DeltaEncoder {
+    fn default() -> Self {
+        Self {
+            keyframe_interval: 30,
+            sequence: 0,
+            since_keyframe: 0,
+            previous: None,
+        }
+    }
+}
+
+/// Decodes radials from a delta-compressed frame stream, tolerating lost and corrupted frames.
+#[derive(Default)]
+pub struct DeltaDecoder {
+    buffer: Vec<u8>,
+    previous: Option<(u32, Message31)>,
+    frames_dropped: usize,
+    bytes_skipped: usize,
+}
+
+impl DeltaDecoder {
+    /// Create a decoder awaiting its first keyframe.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the radials of every complete frame in the data received so far, which may be
+    /// received in chunks of any size. Frames which cannot be decoded, such as deltas following a
+    /// lost frame, are dropped.
+    pub fn decode(&mut self, data: &[u8]) -> Vec<Message31> {
+        self.buffer.extend_from_slice(data);
+
+        let mut radials = Vec::new();
+        let mut position = 0;
+        loop {
+            // Resynchronize on the next sync marker
+            let Some(start) = self.buffer[position..]
+                .windows(SYNC_MARKER.len())
+                .position(|window| window == SYNC_MARKER)
+            else {
+                // Keep a partial marker at the end of the buffer
+                let keep = (SYNC_MARKER.len() - 1).min(self.buffer.len() - position);
+                self.bytes_skipped += self.buffer.len() - position - keep;
+                position = self.buffer.len() - keep;
+                break;
+            };
+            self.bytes_skipped += start;
+            position += start;
+
+            let frame = &self.buffer[position..];
+            if frame.len() < FRAME_HEADER_SIZE {
+                break;
+            }
+
+            let payload_size = u32::from_be_bytes([frame[9], frame[10], frame[11], frame[12]]);
+            let payload_size = usize::try_from(payload_size).unwrap_or(usize::MAX);
+            if payload_size > MAX_PAYLOAD_SIZE {
+                self.bytes_skipped += 1;
+                position += 1;
+                continue;
+            }
+
+            let frame_size = FRAME_HEADER_SIZE + payload_size + CRC_SIZE;
+            if frame.len() < frame_size {
+                break;
+            }
+
+            let mut crc = Crc::new();
+            crc.update(&frame[SYNC_MARKER.len()..frame_size - CRC_SIZE]);
+            if crc.sum().to_be_bytes() != frame[frame_size - CRC_SIZE..frame_size] {
+                self.bytes_skipped += 1;
+                position += 1;
+                continue;
+            }
+
+            let kind = frame[4];
+            let sequence = u32::from_be_bytes([frame[5], frame[6], frame[7], frame[8]]);
+            let payload = &frame[FRAME_HEADER_SIZE..frame_size - CRC_SIZE];
+
+            let radial = match (kind, &self.previous) {
+                (KEYFRAME, _) => decode_keyframe(payload).ok(),
+                (DELTA, Some((previous_sequence, previous)))
+                    if *previous_sequence == sequence.wrapping_sub(1) =>
+                {
+                    decode_delta(previous, payload).ok()
+                }
+                _ => None,
+            };
+
+            if let Some(radial) = radial {
+                self.previous = Some((sequence, radial.clone()));
+                radials.push(radial);
+            } else {
+                self.previous = None;
+                self.frames_dropped += 1;
+            }
+
+            position += frame_size;
+        }
+
+        self.buffer.drain(..position);
+        radials
+    }
+
+    /// The number of intact frames dropped because they could not be decoded, typically deltas
+    /// following a lost frame.
+    #[must_use]
+    pub fn frames_dropped(&self) -> usize {
+        self.frames_dropped
+    }
+
+    /// The number of bytes skipped while searching for a frame, e.g. corrupted frames.
+    #[must_use]
+    pub fn bytes_skipped(&self) -> usize {
+        self.bytes_skipped
+    }
+}
+
+/// Whether a radial can be sent as a delta from the previous radial: both are from the same sweep
+/// and have the same data blocks, moment layouts, and trailing bytes.
+fn is_compatible(previous: &Message31, radial: &Message31) -> Result<bool> {
+    if previous.header().elev_num() != radial.header().elev_num()
+        || previous.trailing_bytes() != radial.trailing_bytes()
+    {
+        return Ok(false);
+    }
+
+    if previous.volume_data().map(serialize).transpose()?
+        != radial.volume_data().map(serialize).transpose()?
+        || previous.elevation_data().map(serialize).transpose()?
+            != radial.elevation_data().map(serialize).transpose()?
+        || previous.radial_data().map(serialize).transpose()?
+            != radial.radial_data().map(serialize).transpose()?
+        || previous.radial_data().map(RadialData::extension)
+            != radial.radial_data().map(RadialData::extension)
+    {
+        return Ok(false);
+    }
+
+    for product in Product::ALL {
+        let layout = |radial: &Message31| -> Result<Option<(Vec<u8>, usize)>> {
+            radial
+                .get_data_moment(&product.into())
+                .map(|moment| Ok((serialize(moment.data())?, moment.moment_data().len())))
+                .transpose()
+        };
+
+        if layout(previous)? != layout(radial)? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Encodes a radial's header and the runs of gates which changed from the previous radial.
+fn encode_delta(previous: &Message31, radial: &Message31) -> Result<Vec<u8>> {
+    let mut payload = serialize(radial.header())?;
+
+    for product in Product::ALL {
+        let (Some(base), Some(moment)) = (
+            previous.get_data_moment(&product.into()),
+            radial.get_data_moment(&product.into()),
+        ) else {
+            continue;
+        };
+
+        let word_bytes = moment.data().word_bytes();
+        let base_words: Vec<&[u8]> = base.moment_data().chunks(word_bytes).collect();
+        let words: Vec<&[u8]> = moment.moment_data().chunks(word_bytes).collect();
+
+        let mut runs = Vec::new();
+        let mut index = 0;
+        while index < words.len() {
+            let start = index;
+            while index < words.len() && words[index] == base_words[index] {
+                index += 1;
+            }
+            let skip = index - start;
+
+            let changed_start = index;
+            while index < words.len() && words[index] != base_words[index] {
+                index += 1;
+            }
+
+            if index > changed_start {
+                runs.push((skip, changed_start..index));
+            }
+        }
+
+        write_varint(&mut payload, runs.len());
+        for (skip, changed) in runs {
+            write_varint(&mut payload, skip);
+            write_varint(&mut payload, changed.len());
+            for word in &words[changed] {
+                payload.extend_from_slice(word);
+            }
+        }
+    }
+
+    Ok(payload)
+}
+
+/// Decodes a keyframe's message 31.
+fn decode_keyframe(payload: &[u8]) -> Result<Message31> {
+    let mut reader = Cursor::new(payload);
+    let _: MessageHeader = DataFile::deserialize(&mut reader)?;
+    DataFile::decode_message_31(&mut reader, None)
+}
+
+/// Applies a delta to the previous radial.
+fn decode_delta(previous: &Message31, payload: &[u8]) -> Result<Message31> {
+    let mut reader = Cursor::new(payload);
+    let header: Message31Header = DataFile::deserialize(&mut reader)?;
+
+    let mut radial = Message31::new(header);
+    if let Some(volume_data) = previous.volume_data() {
+        radial.set_volume_data(volume_data.clone());
+    }
+    if let Some(elevation_data) = previous.elevation_data() {
+        radial.set_elevation_data(elevation_data.clone());
+    }
+    if let Some(radial_data) = previous.radial_data() {
+        radial.set_radial_data(radial_data.clone());
+    }
+    radial.set_trailing_bytes(previous.trailing_bytes().to_vec());
+
+    let mut data = &payload[usize::try_from(reader.position())?..];
+    for product in Product::ALL {
+        let Some(base) = previous.get_data_moment(&product.into()) else {
+            continue;
+        };
+
+        let word_bytes = base.data().word_bytes();
+        let mut moment_data = base.moment_data().to_vec();
+        let mut index = 0;
+        for _ in 0..read_varint(&mut data)? {
+            index += read_varint(&mut data)?;
+            let changed = read_varint(&mut data)? * word_bytes;
+
+            let start = index * word_bytes;
+            let (words, rest) = data
+                .split_at_checked(changed)
+                .ok_or(Error::InvalidDeltaFrame)?;
+            moment_data
+                .get_mut(start..start + changed)
+                .ok_or(Error::InvalidDeltaFrame)?
+                .copy_from_slice(words);
+
+            data = rest;
+            index += changed / word_bytes;
+        }
+
+        radial.set_data_moment(DataMoment::new(
+            base.product().clone(),
+            base.data().clone(),
+            moment_data,
+        ));
+    }
+
+    Ok(radial)
+}
+
+/// Writes an unsigned LEB128 variable-length integer.
+#[allow(clippy::cast_possible_truncation)]
+fn write_varint(data: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Reads an unsigned LEB128 variable-length integer, advancing past it.
+fn read_varint(data: &mut &[u8]) -> Result<usize> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (byte, rest) = data.split_first().ok_or(Error::InvalidDeltaFrame)?;
+        *data = rest;
+
+        value |= usize::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(Error::InvalidDeltaFrame.into())
+}
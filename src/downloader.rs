@@ -0,0 +1,718 @@
+//!
+//! Downloads NEXRAD level-II data from an AWS S3 bucket populated by NOAA, and recent volumes
+//! from the real-time chunk bucket. Volumes may also be retrieved from Google Cloud's copy of the
+//! archive, mirrors, local caches, and NCEI archive orders through [``FailoverDownloader``].
+//!
+
+use std::io::{ErrorKind, Read, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::{config::Region, types::Object, Client, Config};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::backfill::{ArchiveSource, Backfill, ChunkMetadata, ChunkSource};
+use crate::error::Error;
+use crate::metadata::FileMetadata;
+use anyhow::Result;
+
+const REGION: &str = "us-east-1";
+const BUCKET: &str = "noaa-nexrad-level2";
+const CHUNK_BUCKET: &str = "unidata-nexrad-level2-chunks";
+const GCP_ENDPOINT: &str = "https://storage.googleapis.com";
+const GCP_BUCKET: &str = "gcp-public-data-nexrad-l2";
+
+/// The size of a tar archive's headers and the blocks its members are padded to.
+const TAR_BLOCK: u64 = 512;
+
+/// List data files for the specified site and date. This effectively returns an index of data files
+/// which can then be individually downloaded.
+///
+/// # Errors
+/// Will error if the list of files cannot be retrieved.
+pub async fn list_files(site: &str, date: &NaiveDate) -> Result<Vec<FileMetadata>> {
+    list_bucket_files(&get_client(), BUCKET, site, date).await
+}
+
+/// Download a data file specified by its metadata. Returns the downloaded file's encoded contents
+/// which may then need to be decompressed and decoded.
+///
+/// # Errors
+/// Will error if the file cannot be retrieved.
+#[allow(clippy::module_name_repetitions)]
+pub async fn download_file(meta: &FileMetadata) -> Result<Vec<u8>> {
+    download_bucket_file(&get_client(), BUCKET, meta).await
+}
+
+/// The archive bucket, as a source for a [``Backfill``].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AwsArchive;
+
+impl ArchiveSource for AwsArchive {
+    async fn list_volumes(&self, site: &str, date: NaiveDate) -> Result<Vec<FileMetadata>> {
+        list_files(site, &date).await
+    }
+
+    async fn fetch_volume(&self, meta: &FileMetadata) -> Result<Vec<u8>> {
+        download_file(meta).await
+    }
+}
+
+/// The real-time chunk bucket, as a source for a [``Backfill``].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AwsChunks;
+
+impl ChunkSource for AwsChunks {
+    async fn list_chunks(&self, site: &str) -> Result<Vec<ChunkMetadata>> {
+        let client = get_client();
+        let prefix = format!("{site}/");
+
+        // The bucket holds a site's recent volumes, which may span several pages of objects
+        let mut chunks = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let response = client
+                .list_objects_v2()
+                .bucket(CHUNK_BUCKET)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+
+            chunks.extend(
+                response
+                    .contents()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|object| ChunkMetadata::from_key(object.key()?)),
+            );
+
+            match response.next_continuation_token() {
+                Some(token) if response.is_truncated() => {
+                    continuation_token = Some(token.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    async fn fetch_chunk(&self, chunk: &ChunkMetadata) -> Result<Vec<u8>> {
+        download_object(&get_client(), CHUNK_BUCKET, &chunk.key()).await
+    }
+}
+
+/// Lists a site's volumes from the specified duration ago until now, from the archive bucket and,
+/// for volumes not yet archived, the real-time chunk bucket.
+///
+/// # Errors
+/// Will error if either bucket's listing cannot be retrieved.
+pub async fn backfill(site: &str, window: Duration) -> Result<Backfill<AwsArchive, AwsChunks>> {
+    let now = Utc::now().naive_utc();
+    Backfill::new(AwsArchive, AwsChunks, site, now - window, now).await
+}
+
+/// A source of data files, tried in turn by [``FailoverDownloader``].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeSource {
+    /// The archive bucket on AWS.
+    Aws,
+    /// Google Cloud's public copy of the archive, read through Cloud Storage's S3-compatible
+    /// endpoint. It holds each site's volumes in hourly tar archives, e.g.
+    /// `2023/04/06/KDMX/NWS_NEXRAD_NXL2DP_KDMX_20230406000000_20230406005959.tar`, whose members
+    /// are read individually rather than downloading whole archives.
+    Gcp,
+    /// An S3-compatible bucket mirroring the archive's layout, e.g. a private copy or another
+    /// provider's S3 interoperability endpoint. Requests are unsigned.
+    Mirror { endpoint: String, bucket: String },
+    /// A directory mirroring the archive's layout, e.g. `2023/04/06/KDMX/KDMX20230406_000215_V06`
+    /// beneath the directory, such as a cache of previously downloaded files.
+    LocalCache(PathBuf),
+    /// A directory of the hourly tar archives delivered by an NCEI archive order, e.g.
+    /// `NWS_NEXRAD_NXL2DP_KDMX_20230406000000_20230406005959.tar`. NCEI provides older volumes by
+    /// order rather than for direct download, so an order's archives are retrieved into the
+    /// directory beforehand.
+    Ncei(PathBuf),
+}
+
+impl VolumeSource {
+    /// Lists the source's data files for a site and date. A source without any, e.g. one whose
+    /// mirror is incomplete, lists none.
+    async fn list_files(&self, site: &str, date: &NaiveDate) -> Result<Vec<FileMetadata>> {
+        match self {
+            Self::Aws => list_files(site, date).await,
+            Self::Mirror { endpoint, bucket } => {
+                list_bucket_files(&get_mirror_client(endpoint), bucket, site, date).await
+            }
+            Self::LocalCache(root) => {
+                let directory = root.join(format!("{}/{}", date.format("%Y/%m/%d"), site));
+                let mut entries = match fs::read_dir(directory).await {
+                    Ok(entries) => entries,
+                    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(error) => return Err(error.into()),
+                };
+
+                let mut metas = Vec::new();
+                while let Some(entry) = entries.next_entry().await? {
+                    if entry.file_type().await?.is_file() {
+                        let identifier = entry.file_name().to_string_lossy().to_string();
+                        metas.push(FileMetadata::new(site.to_string(), *date, identifier));
+                    }
+                }
+                metas.sort_by(|a, b| a.identifier().cmp(b.identifier()));
+
+                Ok(metas)
+            }
+            Self::Gcp | Self::Ncei(_) => {
+                let mut metas = Vec::new();
+                for location in self.archives(site, date).await? {
+                    for member in location.open().await?.members().await? {
+                        let identifier = member.identifier().to_string();
+                        metas.push(FileMetadata::new(site.to_string(), *date, identifier));
+                    }
+                }
+                metas.sort_by(|a, b| a.identifier().cmp(b.identifier()));
+
+                Ok(metas)
+            }
+        }
+    }
+
+    /// Retrieves a data file's encoded contents from the source.
+    async fn download_file(&self, meta: &FileMetadata) -> Result<Vec<u8>> {
+        match self {
+            Self::Aws => download_file(meta).await,
+            Self::Mirror { endpoint, bucket } => {
+                download_bucket_file(&get_mirror_client(endpoint), bucket, meta).await
+            }
+            Self::LocalCache(root) => Ok(fs::read(root.join(archive_key(meta))).await?),
+            Self::Gcp | Self::Ncei(_) => {
+                let not_found = || Error::VolumeNotFound(meta.identifier().clone());
+                let time = meta.date_time().ok_or_else(not_found)?;
+
+                // Volumes are archived by the hour in which they began
+                let locations = self.archives(meta.site(), meta.date()).await?;
+                let location = locations
+                    .iter()
+                    .find(|location| {
+                        archive_span(location.name())
+                            .is_some_and(|(_, start, end)| (start..=end).contains(&time))
+                    })
+                    .ok_or_else(not_found)?;
+
+                let mut archive = location.open().await?;
+                let member = archive
+                    .members()
+                    .await?
+                    .into_iter()
+                    .find(|member| member.identifier() == meta.identifier())
+                    .ok_or_else(not_found)?;
+
+                archive.read_member(&member).await
+            }
+        }
+    }
+
+    /// The hourly tar archives holding a site's volumes from a date, for the sources which
+    /// archive volumes by the hour.
+    async fn archives(&self, site: &str, date: &NaiveDate) -> Result<Vec<ArchiveLocation>> {
+        let matches = |name: &str| {
+            archive_span(name).is_some_and(|(archive_site, start, _)| {
+                archive_site == site && start.date() == *date
+            })
+        };
+
+        match self {
+            Self::Gcp => {
+                let client = get_mirror_client(GCP_ENDPOINT);
+                let prefix = format!("{}/{}/", date.format("%Y/%m/%d"), site);
+                let objects = list_objects(&client, GCP_BUCKET, &prefix)
+                    .await?
+                    .unwrap_or_default();
+
+                Ok(objects
+                    .iter()
+                    .filter_map(Object::key)
+                    .filter(|key| matches(key))
+                    .map(|key| ArchiveLocation::Object(key.to_string()))
+                    .collect())
+            }
+            Self::Ncei(root) => {
+                let mut entries = fs::read_dir(root).await?;
+
+                let mut locations = Vec::new();
+                while let Some(entry) = entries.next_entry().await? {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if matches(&name) && entry.file_type().await?.is_file() {
+                        locations.push(ArchiveLocation::File(entry.path()));
+                    }
+                }
+                locations.sort_by(|a, b| a.name().cmp(b.name()));
+
+                Ok(locations)
+            }
+            Self::Aws | Self::Mirror { .. } | Self::LocalCache(_) => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Where an hourly tar archive of volumes is kept.
+enum ArchiveLocation {
+    /// An object in Google Cloud's copy of the archive.
+    Object(String),
+    /// A file on disk.
+    File(PathBuf),
+}
+
+impl ArchiveLocation {
+    /// The archive's key or path.
+    fn name(&self) -> &str {
+        match self {
+            Self::Object(key) => key,
+            Self::File(path) => path.to_str().unwrap_or_default(),
+        }
+    }
+
+    /// Opens the archive for reading its members.
+    async fn open(&self) -> Result<TarArchive> {
+        Ok(match self {
+            Self::Object(key) => TarArchive::Object {
+                client: get_mirror_client(GCP_ENDPOINT),
+                key: key.clone(),
+            },
+            Self::File(path) => TarArchive::File(fs::File::open(path).await?),
+        })
+    }
+}
+
+/// A tar archive of volumes, read in ranges so that only the headers and the members needed are
+/// retrieved.
+enum TarArchive {
+    /// An object in Google Cloud's copy of the archive.
+    Object { client: Client, key: String },
+    /// A file on disk.
+    File(fs::File),
+}
+
+/// A member of a tar archive: a volume, possibly gzip compressed.
+struct TarMember {
+    name: String,
+    offset: u64,
+    size: u64,
+}
+
+impl TarMember {
+    /// The volume's identifier: its file name without any directories or `.gz` extension.
+    fn identifier(&self) -> &str {
+        let name = self.name.rsplit('/').next().unwrap_or_default();
+        name.strip_suffix(".gz").unwrap_or(name)
+    }
+}
+
+impl TarArchive {
+    /// The archive's regular files, read from their headers.
+    async fn members(&mut self) -> Result<Vec<TarMember>> {
+        let mut members = Vec::new();
+        let mut offset = 0;
+        loop {
+            // Archives end with zeroed blocks, though a truncated archive simply ends
+            let header = self.read_at(offset, TAR_BLOCK).await?;
+            if (header.len() as u64) < TAR_BLOCK || header.iter().all(|byte| *byte == 0) {
+                break;
+            }
+
+            let size = std::str::from_utf8(&header[124..136])
+                .ok()
+                .and_then(|size| u64::from_str_radix(size.trim_matches(['\0', ' ']), 8).ok())
+                .ok_or(Error::InvalidTarArchive("invalid member size"))?;
+
+            // Long names are split between the name and, in ustar archives, a prefix
+            let field = |range: std::ops::Range<usize>| {
+                let bytes = &header[range];
+                let end = bytes
+                    .iter()
+                    .position(|byte| *byte == 0)
+                    .unwrap_or(bytes.len());
+                String::from_utf8_lossy(&bytes[..end]).into_owned()
+            };
+            let (prefix, name) = (field(345..500), field(0..100));
+            let name = if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                format!("{prefix}/{name}")
+            } else {
+                name
+            };
+
+            if matches!(header[156], b'0' | 0) {
+                members.push(TarMember {
+                    name,
+                    offset: offset + TAR_BLOCK,
+                    size,
+                });
+            }
+            offset += TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+        }
+
+        Ok(members)
+    }
+
+    /// Reads a member's volume, decompressing it if it is gzip compressed.
+    async fn read_member(&mut self, member: &TarMember) -> Result<Vec<u8>> {
+        let data = self.read_at(member.offset, member.size).await?;
+        if (data.len() as u64) < member.size {
+            return Err(Error::InvalidTarArchive("truncated member").into());
+        }
+
+        if data.starts_with(&[0x1f, 0x8b]) {
+            let mut volume = Vec::new();
+            GzDecoder::new(data.as_slice()).read_to_end(&mut volume)?;
+            return Ok(volume);
+        }
+
+        Ok(data)
+    }
+
+    /// Reads up to the specified number of bytes from an offset, fewer at the archive's end.
+    async fn read_at(&mut self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        match self {
+            Self::Object { client, key } => {
+                let response = client
+                    .get_object()
+                    .bucket(GCP_BUCKET)
+                    .key(key.as_str())
+                    .range(format!("bytes={offset}-{}", offset + len - 1))
+                    .send()
+                    .await?;
+                Ok(response.body.collect().await?.to_vec())
+            }
+            Self::File(file) => {
+                file.seek(SeekFrom::Start(offset)).await?;
+                let mut data = Vec::new();
+                (&mut *file).take(len).read_to_end(&mut data).await?;
+                Ok(data)
+            }
+        }
+    }
+}
+
+/// The site and the first and last times of the hour covered by an hourly tar archive, from its
+/// name, e.g. `NWS_NEXRAD_NXL2DP_KDMX_20230406000000_20230406005959.tar`.
+fn archive_span(name: &str) -> Option<(&str, NaiveDateTime, NaiveDateTime)> {
+    let stem = name.rsplit(['/', '\\']).next()?.strip_suffix(".tar")?;
+    let mut parts = stem.rsplitn(4, '_');
+    let end = NaiveDateTime::parse_from_str(parts.next()?, "%Y%m%d%H%M%S").ok()?;
+    let start = NaiveDateTime::parse_from_str(parts.next()?, "%Y%m%d%H%M%S").ok()?;
+    let site = parts.next()?;
+
+    Some((site, start, end))
+}
+
+/// A source's record of requests made to it by a [``FailoverDownloader``].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceHealth {
+    successes: usize,
+    misses: usize,
+    failures: usize,
+    consecutive_failures: usize,
+    last_error: Option<String>,
+}
+
+impl SourceHealth {
+    /// The number of requests the source answered.
+    #[must_use]
+    pub fn successes(&self) -> usize {
+        self.successes
+    }
+
+    /// The number of requests for which the source had no data files, i.e. empty listings and
+    /// data files it does not hold. These are not failures.
+    #[must_use]
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// The number of requests that failed.
+    #[must_use]
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    /// The number of requests that have failed since the source last answered one.
+    #[must_use]
+    pub fn consecutive_failures(&self) -> usize {
+        self.consecutive_failures
+    }
+
+    /// The most recent failure's error.
+    #[must_use]
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+/// Lists and downloads data files from an ordered list of [``VolumeSource``]s, failing over to
+/// the next source when one errors or is missing the requested data. Sources which have failed
+/// repeatedly are tried only after the healthy ones, until they next succeed.
+#[derive(Debug)]
+pub struct FailoverDownloader {
+    sources: Vec<VolumeSource>,
+    health: Mutex<Vec<SourceHealth>>,
+    max_consecutive_failures: usize,
+}
+
+impl FailoverDownloader {
+    /// Create a downloader trying the sources in order. A source is deprioritized after 3
+    /// consecutive failures.
+    #[must_use]
+    pub fn new(sources: Vec<VolumeSource>) -> Self {
+        let health = Mutex::new(vec![SourceHealth::default(); sources.len()]);
+        Self {
+            sources,
+            health,
+            max_consecutive_failures: 3,
+        }
+    }
+
+    /// The number of consecutive failures after which a source is deprioritized.
+    #[must_use]
+    pub fn with_max_consecutive_failures(mut self, max_consecutive_failures: usize) -> Self {
+        self.max_consecutive_failures = max_consecutive_failures.max(1);
+        self
+    }
+
+    /// The sources in their configured order.
+    #[must_use]
+    pub fn sources(&self) -> &[VolumeSource] {
+        &self.sources
+    }
+
+    /// Each source's health, in the sources' configured order.
+    ///
+    /// # Panics
+    /// Panics if a thread panicked while recording a source's health.
+    #[must_use]
+    pub fn health(&self) -> Vec<SourceHealth> {
+        self.health.lock().expect("health is not poisoned").clone()
+    }
+
+    /// List data files for the specified site and date from the first source that has any.
+    /// Returns no data files if every source that answered had none.
+    ///
+    /// # Errors
+    /// Will error if no source answered, with the last source's error.
+    pub async fn list_files(&self, site: &str, date: &NaiveDate) -> Result<Vec<FileMetadata>> {
+        let mut answered = false;
+        let mut last_error = Error::NoVolumeSources.into();
+        for index in self.attempt_order() {
+            match self.sources[index].list_files(site, date).await {
+                Ok(metas) if metas.is_empty() => {
+                    answered = true;
+                    self.record(index, |health| health.misses += 1);
+                }
+                Ok(metas) => {
+                    self.record_success(index);
+                    return Ok(metas);
+                }
+                Err(error) => {
+                    self.record_failure(index, &error);
+                    last_error = error;
+                }
+            }
+        }
+
+        if answered {
+            Ok(Vec::new())
+        } else {
+            Err(last_error)
+        }
+    }
+
+    /// Download a data file specified by its metadata from the first source that provides it.
+    ///
+    /// # Errors
+    /// Will error if no source provided the file, with the last source's error.
+    pub async fn download_file(&self, meta: &FileMetadata) -> Result<Vec<u8>> {
+        let mut last_error = Error::NoVolumeSources.into();
+        for index in self.attempt_order() {
+            match self.sources[index].download_file(meta).await {
+                Ok(data) => {
+                    self.record_success(index);
+                    return Ok(data);
+                }
+                Err(error) if is_not_found(&error) => {
+                    self.record(index, |health| health.misses += 1);
+                    last_error = error;
+                }
+                Err(error) => {
+                    self.record_failure(index, &error);
+                    last_error = error;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// The indices of the sources in the order they are tried: healthy sources, then those with
+    /// too many consecutive failures, each in their configured order.
+    fn attempt_order(&self) -> Vec<usize> {
+        let health = self.health();
+        let (mut healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..self.sources.len())
+            .partition(|index| health[*index].consecutive_failures < self.max_consecutive_failures);
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    /// Updates a source's health.
+    fn record(&self, index: usize, update: impl FnOnce(&mut SourceHealth)) {
+        if let Ok(mut health) = self.health.lock() {
+            update(&mut health[index]);
+        }
+    }
+
+    /// Records a source answering a request.
+    fn record_success(&self, index: usize) {
+        self.record(index, |health| {
+            health.successes += 1;
+            health.consecutive_failures = 0;
+        });
+    }
+
+    /// Records a source failing a request.
+    fn record_failure(&self, index: usize, error: &anyhow::Error) {
+        self.record(index, |health| {
+            health.failures += 1;
+            health.consecutive_failures += 1;
+            health.last_error = Some(error.to_string());
+        });
+    }
+}
+
+impl ArchiveSource for FailoverDownloader {
+    async fn list_volumes(&self, site: &str, date: NaiveDate) -> Result<Vec<FileMetadata>> {
+        self.list_files(site, &date).await
+    }
+
+    async fn fetch_volume(&self, meta: &FileMetadata) -> Result<Vec<u8>> {
+        self.download_file(meta).await
+    }
+}
+
+/// Lists data files for the specified site and date from a bucket laid out like the archive.
+async fn list_bucket_files(
+    client: &Client,
+    bucket: &str,
+    site: &str,
+    date: &NaiveDate,
+) -> Result<Vec<FileMetadata>> {
+    // Query S3 for objects matching the prefix (i.e. files for the specified site and date)
+    let prefix = format!("{}/{}", date.format("%Y/%m/%d"), site);
+    let objects = list_objects(client, bucket, &prefix)
+        .await?
+        .unwrap_or_default();
+
+    // Pull the returned objects' keys and parse them into metadata
+    let metas = objects
+        .iter()
+        .filter_map(|object| {
+            let key = object.key()?;
+
+            // E.g. 2023/04/06/KDMX/KDMX20230406_000215_V06
+            //      date_string:    "2023_04_06"
+            //      site:           "KDMX"
+            //      identifier:     "KDMX20230406_000215_V06"
+
+            let parts: Vec<&str> = key.split('/').collect();
+
+            let date_string = parts[0..=2].join("/");
+            let date = NaiveDate::parse_from_str(&date_string, "%Y/%m/%d").ok()?;
+
+            let site = parts[3];
+            let identifier = parts[4..].join("");
+
+            Some(FileMetadata::new(site.to_string(), date, identifier))
+        })
+        .collect();
+
+    Ok(metas)
+}
+
+/// Downloads a data file specified by its metadata from a bucket laid out like the archive.
+async fn download_bucket_file(
+    client: &Client,
+    bucket: &str,
+    meta: &FileMetadata,
+) -> Result<Vec<u8>> {
+    // Reconstruct the S3 object key from the file's metadata
+    download_object(client, bucket, &archive_key(meta)).await
+}
+
+/// Whether an error is a source reporting that it does not hold a data file, rather than failing.
+fn is_not_found(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<std::io::Error>() {
+        return error.kind() == ErrorKind::NotFound;
+    }
+    if let Some(SdkError::ServiceError(context)) = error.downcast_ref::<SdkError<GetObjectError>>()
+    {
+        return context.err().is_no_such_key();
+    }
+
+    matches!(error.downcast_ref(), Some(Error::VolumeNotFound(_)))
+}
+
+/// The key of a data file in the archive's layout, e.g. `2023/04/06/KDMX/KDMX20230406_000215_V06`.
+fn archive_key(meta: &FileMetadata) -> String {
+    let formatted_date = meta.date().format("%Y/%m/%d");
+    format!("{}/{}/{}", formatted_date, meta.site(), meta.identifier())
+}
+
+/// Downloads an object from S3 and returns only its contents. This will only work for
+/// unauthenticated requests (requests are unsigned).
+async fn download_object(client: &Client, bucket: &str, key: &str) -> Result<Vec<u8>> {
+    let operation = client.get_object().bucket(bucket).key(key);
+
+    let response = operation.send().await?;
+    let bytes = response.body.collect().await?;
+
+    Ok(bytes.to_vec())
+}
+
+/// Lists objects from a S3 bucket with the specified prefix. This will only work for
+/// unauthenticated requests (requests are unsigned).
+async fn list_objects(client: &Client, bucket: &str, prefix: &str) -> Result<Option<Vec<Object>>> {
+    let operation = client.list_objects_v2().bucket(bucket).prefix(prefix);
+
+    let response = operation.send().await?;
+    Ok(response
+        .contents()
+        .map(<[aws_sdk_s3::types::Object]>::to_vec))
+}
+
+/// Creates a new S3 client for an S3-compatible endpoint, addressing buckets by path.
+fn get_mirror_client(endpoint: &str) -> Client {
+    Client::from_conf(
+        Config::builder()
+            .region(Region::from_static(REGION))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .build(),
+    )
+}
+
+/// Creates a new S3 client for a predetermined region.
+fn get_client() -> Client {
+    Client::from_conf(
+        Config::builder()
+            .region(Region::from_static(REGION))
+            .build(),
+    )
+}
@@ -0,0 +1,169 @@
+//!
+//! Provides utilities like [``encode_file``] for encoding decoded data back into NEXRAD Archive II
+//! data, e.g. to write modified or synthetic volumes.
+//!
+
+use std::io::Write;
+
+use anyhow::Result;
+use bincode::{DefaultOptions, Options};
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+use serde::Serialize;
+
+use crate::decoder::DataFile;
+use crate::records::{DataBlockProduct, Message31, MessageHeader};
+
+/// Number of radials per compressed record, matching what the RDA produces.
+const RADIALS_PER_RECORD: usize = 120;
+
+/// Message type for digital radar data generic format messages.
+const MESSAGE_31: u8 = 31;
+
+/// Size of the CTM header preceding each message's header.
+const CTM_HEADER_SIZE: usize = 12;
+
+/// Data blocks in the order they are encoded within each message 31.
+const BLOCK_ORDER: [DataBlockProduct; 10] = [
+    DataBlockProduct::VolumeData,
+    DataBlockProduct::ElevationData,
+    DataBlockProduct::RadialData,
+    DataBlockProduct::Reflectivity,
+    DataBlockProduct::Velocity,
+    DataBlockProduct::SpectrumWidth,
+    DataBlockProduct::DifferentialReflectivity,
+    DataBlockProduct::DifferentialPhase,
+    DataBlockProduct::CorrelationCoefficient,
+    DataBlockProduct::ClutterFilterProbability,
+];
+
+/// Encodes a data file into uncompressed Archive II data: the volume header followed by a message
+/// 31 for each radial in elevation order. The result may be decoded with [`DataFile::from_vec`].
+///
+/// # Errors
+/// Returns an error if a radial is too large to encode.
+pub fn encode_file(file: &DataFile) -> Result<Vec<u8>> {
+    let mut data = serialize(file.volume_header())?;
+    for message in encode_messages(file)? {
+        data.extend(message);
+    }
+
+    Ok(data)
+}
+
+/// Encodes a data file into BZIP2-compressed Archive II data like that distributed by NOAA: the
+/// volume header followed by size-prefixed compressed records of up to 120 radials each.
+///
+/// # Errors
+/// Returns an error if a radial is too large to encode or compression fails.
+pub fn encode_compressed_file(file: &DataFile) -> Result<Vec<u8>> {
+    let mut data = serialize(file.volume_header())?;
+
+    let messages = encode_messages(file)?;
+    let record_count = messages.chunks(RADIALS_PER_RECORD).len();
+
+    for (index, record) in messages.chunks(RADIALS_PER_RECORD).enumerate() {
+        let mut encoder = BzEncoder::new(Vec::new(), Compression::default());
+        for message in record {
+            encoder.write_all(message)?;
+        }
+        let compressed = encoder.finish()?;
+
+        // The last record's size is conventionally negated to mark the end of the volume
+        let mut size = i32::try_from(compressed.len())?;
+        if index + 1 == record_count {
+            size = -size;
+        }
+
+        data.extend_from_slice(&size.to_be_bytes());
+        data.extend(compressed);
+    }
+
+    Ok(data)
+}
+
+/// Encodes each radial as a message 31 including its CTM and message headers.
+fn encode_messages(file: &DataFile) -> Result<Vec<Vec<u8>>> {
+    let mut messages = Vec::new();
+
+    for radials in file.elevation_scans().values() {
+        for radial in radials {
+            let sequence = u16::try_from(messages.len() % 0x8000)?;
+            messages.push(encode_message_31(radial, sequence)?);
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Encodes a single radial as a message 31 with the specified message sequence number.
+pub(crate) fn encode_message_31(radial: &Message31, sequence: u16) -> Result<Vec<u8>> {
+    let mut blocks = Vec::new();
+    for product in &BLOCK_ORDER {
+        let block = match product {
+            DataBlockProduct::VolumeData => radial.volume_data().map(serialize),
+            DataBlockProduct::ElevationData => radial.elevation_data().map(serialize),
+            DataBlockProduct::RadialData => radial.radial_data().map(|data| {
+                let mut block = serialize(data)?;
+                block.extend_from_slice(data.extension());
+                Ok(block)
+            }),
+            _ => radial.get_data_moment(product).map(|moment| {
+                let mut block = serialize(moment.data())?;
+                block.extend_from_slice(moment.moment_data());
+                Ok(block)
+            }),
+        };
+
+        if let Some(block) = block {
+            blocks.push(block?);
+        }
+    }
+
+    // The header is followed by a pointer to each block, relative to the start of the header
+    let mut header = radial.header().clone();
+    let header_size = serialize(&header)?.len();
+    let pointers_size = blocks.len() * size_of::<u32>();
+    let blocks_size = blocks.iter().map(Vec::len).sum::<usize>();
+    let radial_len = header_size + pointers_size + blocks_size + radial.trailing_bytes().len();
+    header.set_layout(u16::try_from(blocks.len())?, u16::try_from(radial_len)?);
+
+    let mut body = serialize(&header)?;
+    let mut pointer = header_size + pointers_size;
+    for block in &blocks {
+        body.extend_from_slice(&u32::try_from(pointer)?.to_be_bytes());
+        pointer += block.len();
+    }
+    for block in blocks {
+        body.extend(block);
+    }
+    body.extend_from_slice(radial.trailing_bytes());
+
+    // Messages are sized in halfwords, so pad the body to an even length
+    if body.len() % 2 != 0 {
+        body.push(0);
+    }
+
+    // The message size in halfwords counts the message header but not the CTM header
+    let message_size = size_of::<MessageHeader>() - CTM_HEADER_SIZE + body.len();
+    let message_header = MessageHeader::new(
+        u16::try_from(message_size / 2)?,
+        MESSAGE_31,
+        sequence,
+        radial.header().ray_date(),
+        radial.header().ray_time(),
+    );
+
+    let mut message = serialize(&message_header)?;
+    message.extend(body);
+
+    Ok(message)
+}
+
+/// Serializes a structure with the same encoding it is decoded with.
+pub(crate) fn serialize<S: Serialize>(value: &S) -> Result<Vec<u8>> {
+    Ok(DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_big_endian()
+        .serialize(value)?)
+}
@@ -1,6 +1,7 @@
 //!
 //! Contains the Error types for NEXRAD specific operations.
 //!
+use chrono::NaiveDateTime;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,6 +9,114 @@ pub enum Error {
     #[error("cannot decompress uncompressed data")]
     DecompressUnsupportedFile,
 
+    #[error("unrecognized compression for data file record")]
+    DecompressUnsupportedRecord,
+
+    #[error("decompressing a record panicked")]
+    DecompressionPanicked,
+
+    #[error("message size {0} is smaller than its header")]
+    InvalidMessageSize(u16),
+
     #[error("unhandled product type encountered")]
     UnhandledProduct,
+
+    #[error("no radials were found")]
+    MissingRadials,
+
+    #[error("computation was cancelled")]
+    Cancelled,
+
+    #[error("volumes are from different radar sites")]
+    MismatchedSites,
+
+    #[error("volumes are not adjacent in time")]
+    VolumesNotAdjacent,
+
+    #[error("time is outside the volume series")]
+    TimeOutsideSeries,
+
+    #[error("grid dimensions do not match")]
+    GridMismatch,
+
+    #[error("gate {0} is outside the data moment")]
+    GateOutOfRange(usize),
+
+    #[error("data block pointer {0} is outside its radial")]
+    InvalidDataBlockPointer(u32),
+
+    #[error("data block extension of {0} bytes exceeds the largest block size")]
+    DataBlockTooLarge(usize),
+
+    #[error("elevation number {0} appears in more than one sweep")]
+    DuplicateElevation(u8),
+
+    #[error("ODIM object {0} is not a polar volume or scan")]
+    UnsupportedOdimObject(String),
+
+    #[error("ODIM attribute {0} is missing or invalid")]
+    MissingOdimAttribute(String),
+
+    #[error("ODIM dataset {0} has raw value {1}, beyond the largest representable")]
+    UnrepresentableOdimValue(String, u16),
+
+    #[error("invalid Sigmet RAW file: {0}")]
+    InvalidSigmetFile(&'static str),
+
+    #[error("invalid Universal Format file: {0}")]
+    InvalidUfFile(&'static str),
+
+    #[error("volume has no valid start time")]
+    MissingVolumeTime,
+
+    #[error("time {0} is outside the Archive II date range")]
+    UnrepresentableTime(NaiveDateTime),
+
+    #[error("volume has no site location")]
+    MissingSiteLocation,
+
+    #[error("BUFR message exceeds the maximum length")]
+    BufrMessageTooLarge,
+
+    #[error("delta frame does not apply to the previous radial")]
+    InvalidDeltaFrame,
+
+    #[error("palette line {0} is malformed")]
+    InvalidPalette(usize),
+
+    #[error("palette has no colors")]
+    EmptyPalette,
+
+    #[error("invalid echo climatology: {0}")]
+    InvalidClimatology(&'static str),
+
+    #[error("invalid render option: {0}")]
+    InvalidRenderOption(&'static str),
+
+    #[error("radial compression code {0} is not supported")]
+    UnsupportedRadialCompression(u8),
+
+    #[error("no volume sources are configured")]
+    NoVolumeSources,
+
+    #[error("volume {0} was not found in the source")]
+    VolumeNotFound(String),
+
+    #[error("invalid tar archive: {0}")]
+    InvalidTarArchive(&'static str),
+
+    #[error("no recent volumes were found for {0}")]
+    NoRecentVolume(String),
+
+    #[error("invalid expression at character {0}: {1}")]
+    InvalidExpression(usize, &'static str),
+
+    #[error("event archive schema version {0} is newer than supported")]
+    UnsupportedArchiveSchema(u32),
+
+    #[error("volume {0} is not in the event archive")]
+    MissingArchivedVolume(i64),
+
+    #[error("database has tables but is not an event archive")]
+    UnrecognizedArchive,
 }
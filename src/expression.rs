@@ -0,0 +1,601 @@
+//!
+//! Provides [``Expression``] for computing a field from a per-gate combination of moments written
+//! as text, e.g. `REF > 35 && RHO < 0.9`, so masks and derived fields can be prototyped from
+//! configuration without writing Rust.
+//!
+//! Expressions combine moments, named as [``Product``] parses them (`REF`, `VEL`, `SW`, `ZDR`,
+//! `PHI`, `RHO`, and `CFP`, in any case), and numbers with, from loosest to tightest binding:
+//!
+//! - `||`, `&&`: logical or and and
+//! - `<`, `<=`, `>`, `>=`, `==`, `!=`: comparisons
+//! - `+`, `-`, then `*`, `/`: arithmetic
+//! - `-`, `!`: negation and logical not
+//! - `abs(x)`, `min(x, y)`, `max(x, y)`, and parentheses
+//!
+//! Comparisons and logical operators produce 1 for true and 0 for false, and any nonzero value is
+//! true. A gate missing any moment the expression uses has no value. Parentheses, function calls,
+//! unary operators, and chained binary operators may nest at most 64 deep.
+//!
+//! [``AlertRule``] evaluates an expression over a volume's gates as an alert, which triggers when
+//! enough gates satisfy it.
+//!
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::calibration::value_at_range;
+use crate::composite::{grid_layer, VolumeLayer};
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::grid::{Grid, GridSpec};
+use crate::records::{DataBlockProduct, Message31, Product};
+
+/// The deepest that parentheses, function calls, and operators may nest, so that parsing and
+/// evaluating an expression's tree cannot overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// A parsed per-gate expression.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Expression {
+    source: String,
+    root: Node,
+    products: Vec<Product>,
+}
+
+impl Expression {
+    /// Parses an expression.
+    ///
+    /// # Errors
+    /// Returns an error naming the character at which the expression is malformed, or uses an
+    /// unknown moment or function.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            end: source.len(),
+            depth: 0,
+        };
+        let root = parser.parse_or()?;
+        if let Some((position, _)) = parser.tokens.get(parser.position) {
+            return Err(Error::InvalidExpression(*position, "unexpected token"));
+        }
+
+        let mut products = Vec::new();
+        root.collect_products(&mut products);
+
+        Ok(Self {
+            source: source.to_string(),
+            root,
+            products,
+        })
+    }
+
+    /// The expression's text, as it was parsed.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The moments the expression uses, in the order they first appear.
+    #[must_use]
+    pub fn products(&self) -> &[Product] {
+        &self.products
+    }
+
+    /// Evaluates the expression with each moment's value provided by a closure, returning `None`
+    /// if the closure does not provide a moment the expression uses.
+    pub fn evaluate<F: FnMut(Product) -> Option<f32>>(&self, mut values: F) -> Option<f32> {
+        self.root.evaluate(&mut values)
+    }
+
+    /// Evaluates the expression at each of a radial's gates, laid out like the gates of the first
+    /// moment the expression uses, or reflectivity if it uses none. Other moments are sampled at
+    /// the nearest gate by range. Returns `None` if the radial lacks that moment.
+    #[must_use]
+    pub fn evaluate_radial(&self, radial: &Message31) -> Option<Vec<Option<f32>>> {
+        let reference = self
+            .products
+            .first()
+            .copied()
+            .unwrap_or(Product::Reflectivity);
+        let reference = radial.get_data_moment(&DataBlockProduct::from(reference))?;
+
+        let values = (0..reference.gate_count())
+            .map(|index| {
+                let range = reference.data().gate_range_m(index);
+                self.evaluate(|product| {
+                    value_at_range(radial.get_data_moment(&product.into())?, range)
+                })
+            })
+            .collect();
+
+        Some(values)
+    }
+
+    /// Evaluates the expression at each of a sweep's gates, with a row for each radial and a
+    /// column for each gate, laid out as by [``Expression::evaluate_radial``]. Radials lacking the
+    /// moments or with fewer gates are `None` beyond their values.
+    #[must_use]
+    pub fn evaluate_sweep(&self, radials: &[Message31]) -> Grid<Option<f32>> {
+        let fields: Vec<_> = radials
+            .iter()
+            .map(|radial| self.evaluate_radial(radial).unwrap_or_default())
+            .collect();
+        let columns = fields.iter().map(Vec::len).max().unwrap_or(0);
+
+        let values = fields
+            .into_iter()
+            .flat_map(|mut field| {
+                field.resize(columns, None);
+                field
+            })
+            .collect();
+
+        Grid::new(columns, radials.len(), values)
+    }
+
+    /// Evaluates the expression at each cell of a grid, from each moment it uses gridded from the
+    /// specified part of a volume.
+    #[must_use]
+    pub fn evaluate_grid(
+        &self,
+        file: &DataFile,
+        layer: VolumeLayer,
+        spec: &GridSpec,
+    ) -> Grid<Option<f32>> {
+        let grids: Vec<_> = self
+            .products
+            .iter()
+            .map(|product| (*product, grid_layer(file, *product, layer, spec)))
+            .collect();
+
+        let values = (0..spec.columns() * spec.rows())
+            .map(|index| {
+                self.evaluate(|product| {
+                    let (_, grid) = grids.iter().find(|(gridded, _)| *gridded == product)?;
+                    grid.values()[index]
+                })
+            })
+            .collect();
+
+        Grid::new(spec.columns(), spec.rows(), values)
+    }
+}
+
+/// A named alert which triggers when at least a number of a volume's gates satisfy an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    name: String,
+    expression: Expression,
+    min_gates: usize,
+}
+
+impl AlertRule {
+    /// Create an alert which triggers when any gate satisfies the expression.
+    #[must_use]
+    pub fn new(name: &str, expression: Expression) -> Self {
+        Self {
+            name: name.to_string(),
+            expression,
+            min_gates: 1,
+        }
+    }
+
+    /// The least number of gates which must satisfy the expression for the alert to trigger.
+    ///
+    /// # Panics
+    /// Panics if the number is zero.
+    #[must_use]
+    pub fn with_min_gates(mut self, min_gates: usize) -> Self {
+        assert!(min_gates > 0, "minimum gates must be positive");
+        self.min_gates = min_gates;
+        self
+    }
+
+    /// The alert's name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The expression gates must satisfy.
+    #[must_use]
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// The least number of gates which must satisfy the expression for the alert to trigger.
+    #[must_use]
+    pub fn min_gates(&self) -> usize {
+        self.min_gates
+    }
+
+    /// Evaluates the alert over each gate of each of a volume's sweeps, laid out as by
+    /// [``Expression::evaluate_radial``]. A gate satisfies the expression if its value is nonzero.
+    #[must_use]
+    pub fn evaluate(&self, file: &DataFile) -> AlertEvaluation {
+        let matching_gates = file
+            .elevation_scans()
+            .values()
+            .flatten()
+            .filter_map(|radial| self.expression.evaluate_radial(radial))
+            .flatten()
+            .filter(|value| value.is_some_and(|value| value != 0.0))
+            .count();
+
+        AlertEvaluation {
+            matching_gates,
+            triggered: matching_gates >= self.min_gates,
+        }
+    }
+}
+
+/// The outcome of evaluating an [``AlertRule``] over a volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertEvaluation {
+    matching_gates: usize,
+    triggered: bool,
+}
+
+impl AlertEvaluation {
+    /// The number of gates which satisfied the alert's expression.
+    #[must_use]
+    pub fn matching_gates(&self) -> usize {
+        self.matching_gates
+    }
+
+    /// Whether enough gates satisfied the expression for the alert to trigger.
+    #[must_use]
+    pub fn triggered(&self) -> bool {
+        self.triggered
+    }
+}
+
+impl FromStr for Expression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+/// A node of a parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f32),
+    Moment(Product),
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Abs(Box<Node>),
+    Min(Box<Node>, Box<Node>),
+    Max(Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn evaluate<F: FnMut(Product) -> Option<f32>>(&self, values: &mut F) -> Option<f32> {
+        Some(match self {
+            Self::Number(value) => *value,
+            Self::Moment(product) => values(*product)?,
+            Self::Negate(operand) => -operand.evaluate(values)?,
+            Self::Not(operand) => truth(operand.evaluate(values)? == 0.0),
+            Self::Binary(operator, left, right) => {
+                operator.apply(left.evaluate(values)?, right.evaluate(values)?)
+            }
+            Self::Abs(operand) => operand.evaluate(values)?.abs(),
+            Self::Min(left, right) => left.evaluate(values)?.min(right.evaluate(values)?),
+            Self::Max(left, right) => left.evaluate(values)?.max(right.evaluate(values)?),
+        })
+    }
+
+    fn collect_products(&self, products: &mut Vec<Product>) {
+        match self {
+            Self::Number(_) => {}
+            Self::Moment(product) => {
+                if !products.contains(product) {
+                    products.push(*product);
+                }
+            }
+            Self::Negate(operand) | Self::Not(operand) | Self::Abs(operand) => {
+                operand.collect_products(products);
+            }
+            Self::Binary(_, left, right) | Self::Min(left, right) | Self::Max(left, right) => {
+                left.collect_products(products);
+                right.collect_products(products);
+            }
+        }
+    }
+}
+
+/// A binary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Or,
+    And,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl Operator {
+    #[allow(clippy::float_cmp)]
+    fn apply(self, left: f32, right: f32) -> f32 {
+        match self {
+            Self::Or => truth(left != 0.0 || right != 0.0),
+            Self::And => truth(left != 0.0 && right != 0.0),
+            Self::Less => truth(left < right),
+            Self::LessEqual => truth(left <= right),
+            Self::Greater => truth(left > right),
+            Self::GreaterEqual => truth(left >= right),
+            Self::Equal => truth(left == right),
+            Self::NotEqual => truth(left != right),
+            Self::Add => left + right,
+            Self::Subtract => left - right,
+            Self::Multiply => left * right,
+            Self::Divide => left / right,
+        }
+    }
+}
+
+/// The value of a condition.
+fn truth(condition: bool) -> f32 {
+    if condition {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// A token of an expression's text.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Identifier(String),
+    Operator(Operator),
+    Minus,
+    Not,
+    Open,
+    Close,
+    Comma,
+}
+
+/// Splits an expression's text into tokens, each with the position of its first character.
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        let next = chars.peek().map(|(_, next)| *next);
+        let token = match (c, next) {
+            (c, _) if c.is_whitespace() => continue,
+            (c, _) if c.is_ascii_digit() || c == '.' => {
+                let mut end = position + c.len_utf8();
+                while let Some((index, c)) = chars.peek().copied() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                let number = source[position..end]
+                    .parse()
+                    .map_err(|_| Error::InvalidExpression(position, "malformed number"))?;
+                Token::Number(number)
+            }
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let mut end = position + c.len_utf8();
+                while let Some((index, c)) = chars.peek().copied() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                Token::Identifier(source[position..end].to_string())
+            }
+            ('|', Some('|')) | ('&', Some('&')) | ('<' | '>' | '=' | '!', Some('=')) => {
+                chars.next();
+                Token::Operator(match c {
+                    '|' => Operator::Or,
+                    '&' => Operator::And,
+                    '<' => Operator::LessEqual,
+                    '>' => Operator::GreaterEqual,
+                    '=' => Operator::Equal,
+                    _ => Operator::NotEqual,
+                })
+            }
+            ('<', _) => Token::Operator(Operator::Less),
+            ('>', _) => Token::Operator(Operator::Greater),
+            ('+', _) => Token::Operator(Operator::Add),
+            ('*', _) => Token::Operator(Operator::Multiply),
+            ('/', _) => Token::Operator(Operator::Divide),
+            ('-', _) => Token::Minus,
+            ('!', _) => Token::Not,
+            ('(', _) => Token::Open,
+            (')', _) => Token::Close,
+            (',', _) => Token::Comma,
+            _ => return Err(Error::InvalidExpression(position, "unexpected character")),
+        };
+        tokens.push((position, token));
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser over an expression's tokens.
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    position: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// The position of the next token, or the end of the text.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn expect(&mut self, expected: &Token, message: &'static str) -> Result<(), Error> {
+        if self.peek() != Some(expected) {
+            return Err(Error::InvalidExpression(self.offset(), message));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Enters a level of nesting, failing if it is deeper than the greatest allowed.
+    fn descend(&mut self) -> Result<(), Error> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Error::InvalidExpression(self.offset(), "nested too deeply"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Parses a nested operand, e.g. within parentheses.
+    fn parse_nested(&mut self, parse: fn(&mut Self) -> Result<Node, Error>) -> Result<Node, Error> {
+        self.descend()?;
+        let node = parse(self)?;
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    /// Parses a left-associative chain of the specified operators' operands. Each operator nests
+    /// the chain before it a level deeper.
+    fn parse_binary(
+        &mut self,
+        operators: &[Operator],
+        operand: fn(&mut Self) -> Result<Node, Error>,
+    ) -> Result<Node, Error> {
+        let depth = self.depth;
+        let mut left = operand(self)?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Operator(operator)) if operators.contains(operator) => *operator,
+                Some(Token::Minus) if operators.contains(&Operator::Subtract) => Operator::Subtract,
+                _ => {
+                    self.depth = depth;
+                    return Ok(left);
+                }
+            };
+            self.position += 1;
+            self.descend()?;
+            left = Node::Binary(operator, Box::new(left), Box::new(operand(self)?));
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Node, Error> {
+        self.parse_binary(&[Operator::Or], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, Error> {
+        self.parse_binary(&[Operator::And], Self::parse_comparison)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Node, Error> {
+        self.parse_binary(
+            &[
+                Operator::Less,
+                Operator::LessEqual,
+                Operator::Greater,
+                Operator::GreaterEqual,
+                Operator::Equal,
+                Operator::NotEqual,
+            ],
+            Self::parse_additive,
+        )
+    }
+
+    fn parse_additive(&mut self) -> Result<Node, Error> {
+        self.parse_binary(
+            &[Operator::Add, Operator::Subtract],
+            Self::parse_multiplicative,
+        )
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Node, Error> {
+        self.parse_binary(&[Operator::Multiply, Operator::Divide], Self::parse_unary)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, Error> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.position += 1;
+                Ok(Node::Negate(Box::new(
+                    self.parse_nested(Self::parse_unary)?,
+                )))
+            }
+            Some(Token::Not) => {
+                self.position += 1;
+                Ok(Node::Not(Box::new(self.parse_nested(Self::parse_unary)?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, Error> {
+        let offset = self.offset();
+        let Some(token) = self.peek().cloned() else {
+            return Err(Error::InvalidExpression(offset, "expected a value"));
+        };
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Ok(Node::Number(value)),
+            Token::Open => {
+                let node = self.parse_nested(Self::parse_or)?;
+                self.expect(&Token::Close, "expected ')'")?;
+                Ok(node)
+            }
+            Token::Identifier(name) if self.peek() == Some(&Token::Open) => {
+                self.position += 1;
+                let first = Box::new(self.parse_nested(Self::parse_or)?);
+                let node = match name.to_lowercase().as_str() {
+                    "abs" => Node::Abs(first),
+                    "min" | "max" => {
+                        self.expect(&Token::Comma, "expected ','")?;
+                        let second = Box::new(self.parse_nested(Self::parse_or)?);
+                        if name.eq_ignore_ascii_case("min") {
+                            Node::Min(first, second)
+                        } else {
+                            Node::Max(first, second)
+                        }
+                    }
+                    _ => return Err(Error::InvalidExpression(offset, "unknown function")),
+                };
+                self.expect(&Token::Close, "expected ')'")?;
+                Ok(node)
+            }
+            Token::Identifier(name) => name
+                .parse()
+                .map(Node::Moment)
+                .map_err(|_| Error::InvalidExpression(offset, "unknown moment")),
+            _ => Err(Error::InvalidExpression(offset, "expected a value")),
+        }
+    }
+}
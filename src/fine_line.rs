@@ -0,0 +1,501 @@
+//!
+//! Provides [``detect_fine_lines``] for detecting thin lines of enhanced reflectivity with
+//! convergent radial velocity in a volume's lowest tilt, such as gust fronts and other boundaries,
+//! and [``FineLineTracker``] for estimating their movement across volumes.
+//!
+
+use std::f32::consts::PI;
+
+use chrono::NaiveDateTime;
+
+use crate::decoder::DataFile;
+use crate::records::{DataMoment, Message31, Product};
+use crate::sweep::SweepCapabilities;
+
+/// Options controlling which features are detected as fine lines.
+#[derive(Debug, Clone)]
+pub struct FineLineOptions {
+    min_reflectivity: f32,
+    max_reflectivity: f32,
+    min_prominence: f32,
+    flank_distance: f32,
+    min_convergence: f32,
+    min_radials: usize,
+    max_range_jump: f32,
+}
+
+impl FineLineOptions {
+    /// Create the default options, tuned for gust fronts: 5 to 35 dBZ lines at least 5 dB above
+    /// their surroundings 1.5 km to either side, a velocity difference of at least 4 m/s across
+    /// the line, and lines spanning at least 8 radials.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The range of reflectivity in dBZ considered for a line's gates.
+    #[must_use]
+    pub fn with_reflectivity_range(mut self, min: f32, max: f32) -> Self {
+        self.min_reflectivity = min;
+        self.max_reflectivity = max;
+        self
+    }
+
+    /// How many dB a line's reflectivity must exceed its surroundings by.
+    #[must_use]
+    pub fn with_min_prominence(mut self, prominence: f32) -> Self {
+        self.min_prominence = prominence;
+        self
+    }
+
+    /// The distance in meters to either side of a line at which its surroundings are sampled.
+    #[must_use]
+    pub fn with_flank_distance(mut self, distance: f32) -> Self {
+        self.flank_distance = distance;
+        self
+    }
+
+    /// The radial velocity decrease in meters per second required across a line.
+    #[must_use]
+    pub fn with_min_convergence(mut self, convergence: f32) -> Self {
+        self.min_convergence = convergence;
+        self
+    }
+
+    /// The number of consecutive radials a line must span.
+    #[must_use]
+    pub fn with_min_radials(mut self, radials: usize) -> Self {
+        self.min_radials = radials.max(2);
+        self
+    }
+
+    /// The largest change in range in meters between a line's points on adjacent radials.
+    #[must_use]
+    pub fn with_max_range_jump(mut self, distance: f32) -> Self {
+        self.max_range_jump = distance;
+        self
+    }
+}
+
+impl Default for FineLineOptions {
+    fn default() -> Self {
+        Self {
+            min_reflectivity: 5.0,
+            max_reflectivity: 35.0,
+            min_prominence: 5.0,
+            flank_distance: 1500.0,
+            min_convergence: 4.0,
+            min_radials: 8,
+            max_range_jump: 1000.0,
+        }
+    }
+}
+
+/// A detected fine line, as a polyline of points in meters east and north of the radar.
+#[derive(Debug, Clone)]
+pub struct FineLine {
+    points: Vec<(f32, f32)>,
+    mean_reflectivity: f32,
+    mean_convergence: f32,
+}
+
+impl FineLine {
+    /// The line's vertices in meters east and north of the radar, ordered by azimuth.
+    #[must_use]
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// The mean reflectivity in dBZ along the line.
+    #[must_use]
+    pub fn mean_reflectivity(&self) -> f32 {
+        self.mean_reflectivity
+    }
+
+    /// The mean radial velocity decrease in meters per second across the line.
+    #[must_use]
+    pub fn mean_convergence(&self) -> f32 {
+        self.mean_convergence
+    }
+
+    /// The mean of the line's vertices in meters east and north of the radar.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn centroid(&self) -> (f32, f32) {
+        let count = self.points.len().max(1) as f32;
+        let (x, y) = self
+            .points
+            .iter()
+            .fold((0.0, 0.0), |(x, y), point| (x + point.0, y + point.1));
+
+        (x / count, y / count)
+    }
+
+    /// The line as a line string in meters east and north of the radar.
+    #[must_use]
+    #[cfg(feature = "geo")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "geo")))]
+    pub fn to_line_string(&self) -> geo_types::LineString<f32> {
+        self.points.iter().copied().collect()
+    }
+
+    /// The line as a line string of longitude and latitude in degrees, given the radar's location.
+    #[must_use]
+    #[cfg(feature = "geo")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "geo")))]
+    pub fn to_geographic_line_string(&self, lat: f32, long: f32) -> geo_types::LineString<f32> {
+        self.points
+            .iter()
+            .map(|(x, y)| {
+                let azimuth = x.atan2(*y).to_degrees().rem_euclid(360.0);
+                let (point_lat, point_long) =
+                    crate::geometry::destination(lat, long, azimuth, x.hypot(*y));
+                (point_long, point_lat)
+            })
+            .collect()
+    }
+
+    /// The line's length in meters along its vertices.
+    #[must_use]
+    pub fn length_m(&self) -> f32 {
+        self.points
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+            .sum()
+    }
+}
+
+/// The fine lines detected in a volume.
+#[derive(Debug, Clone)]
+pub struct FineLineDetection {
+    time: Option<NaiveDateTime>,
+    elevation: f32,
+    lines: Vec<FineLine>,
+}
+
+impl FineLineDetection {
+    /// When the tilt searched began, if known.
+    #[must_use]
+    pub fn time(&self) -> Option<NaiveDateTime> {
+        self.time
+    }
+
+    /// The elevation angle in degrees of the tilt searched.
+    #[must_use]
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+
+    /// The detected lines.
+    #[must_use]
+    pub fn lines(&self) -> &[FineLine] {
+        &self.lines
+    }
+}
+
+/// Detects fine lines in the volume's lowest tilt with both reflectivity and velocity. Returns
+/// `None` if no tilt has both.
+#[must_use]
+pub fn detect_fine_lines(file: &DataFile, options: &FineLineOptions) -> Option<FineLineDetection> {
+    let radials = file.elevation_scans().values().find(|radials| {
+        let capabilities = SweepCapabilities::from_radials(radials);
+        capabilities.has(Product::Reflectivity) && capabilities.is_doppler()
+    })?;
+
+    let first = radials.first()?.header();
+    Some(FineLineDetection {
+        time: first.date_time(),
+        elevation: first.elev(),
+        lines: detect_fine_lines_in_sweep(radials, options),
+    })
+}
+
+/// Detects fine lines in a sweep's radials, which must be ordered by azimuth. Lines are found where
+/// a gate's reflectivity peaks above its surroundings along the radial while radial velocity
+/// decreases across it, so boundaries oriented across the beam are detected most reliably.
+#[must_use]
+pub fn detect_fine_lines_in_sweep(
+    radials: &[Message31],
+    options: &FineLineOptions,
+) -> Vec<FineLine> {
+    let candidates: Vec<Vec<Candidate>> = radials
+        .iter()
+        .map(|radial| radial_candidates(radial, options))
+        .collect();
+
+    // Chain candidates on adjacent radials into lines, tracking each open chain's last range
+    let mut chains: Vec<Vec<(usize, usize)>> = Vec::new();
+    let mut open: Vec<(usize, f32)> = Vec::new();
+    for (radial_index, radial_candidates) in candidates.iter().enumerate() {
+        let mut next_open = Vec::new();
+        for (candidate_index, candidate) in radial_candidates.iter().enumerate() {
+            let previous = open
+                .iter()
+                .position(|(_, range)| (range - candidate.range).abs() <= options.max_range_jump);
+
+            let chain_index = if let Some(position) = previous {
+                let (chain_index, _) = open.swap_remove(position);
+                chains[chain_index].push((radial_index, candidate_index));
+                chain_index
+            } else {
+                chains.push(vec![(radial_index, candidate_index)]);
+                chains.len() - 1
+            };
+            next_open.push((chain_index, candidate.range));
+        }
+        open = next_open;
+    }
+
+    // Join a line crossing north, which ends on the last radial and resumes on the first
+    let range_of = |(radial, candidate): (usize, usize)| candidates[radial][candidate].range;
+    let ending = chains.iter().position(|chain| {
+        chain
+            .last()
+            .is_some_and(|(radial, _)| *radial + 1 == radials.len())
+    });
+    let starting = chains
+        .iter()
+        .position(|chain| chain.first().is_some_and(|(radial, _)| *radial == 0));
+    if let (Some(ending), Some(starting)) = (ending, starting) {
+        let end = chains[ending].last().copied();
+        let start = chains[starting].first().copied();
+        if let (Some(end), Some(start)) = (end, start) {
+            if ending != starting
+                && (range_of(end) - range_of(start)).abs() <= options.max_range_jump
+            {
+                let resumed = chains.remove(starting);
+                let ending = if starting < ending {
+                    ending - 1
+                } else {
+                    ending
+                };
+                chains[ending].extend(resumed);
+            }
+        }
+    }
+
+    chains
+        .into_iter()
+        .filter(|chain| chain.len() >= options.min_radials)
+        .map(|chain| {
+            let points = chain
+                .iter()
+                .map(|(radial, candidate)| {
+                    let azimuth = radials[*radial].header().azm() * PI / 180.0;
+                    let range = candidates[*radial][*candidate].range;
+                    (range * azimuth.sin(), range * azimuth.cos())
+                })
+                .collect();
+
+            #[allow(clippy::cast_precision_loss)]
+            let count = chain.len() as f32;
+            let (reflectivity, convergence) = chain.iter().fold(
+                (0.0, 0.0),
+                |(reflectivity, convergence), (radial, candidate)| {
+                    let candidate = &candidates[*radial][*candidate];
+                    (
+                        reflectivity + candidate.reflectivity,
+                        convergence + candidate.convergence,
+                    )
+                },
+            );
+
+            FineLine {
+                points,
+                mean_reflectivity: reflectivity / count,
+                mean_convergence: convergence / count,
+            }
+        })
+        .collect()
+}
+
+/// A gate along a radial which may be part of a fine line.
+struct Candidate {
+    range: f32,
+    reflectivity: f32,
+    convergence: f32,
+}
+
+/// Finds the gates along a radial where reflectivity peaks above its surroundings and radial
+/// velocity decreases across the peak.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn radial_candidates(radial: &Message31, options: &FineLineOptions) -> Vec<Candidate> {
+    let (Some(reflectivity), Some(velocity)) = (radial.reflectivity_data(), radial.velocity_data())
+    else {
+        return Vec::new();
+    };
+
+    let values: Vec<f32> = reflectivity
+        .values()
+        .map(|value| value.value().unwrap_or(f32::NEG_INFINITY))
+        .collect();
+
+    let interval = f32::from(reflectivity.data().data_moment_range_sample_interval()).max(1.0);
+    let flank = (options.flank_distance / interval).round().max(1.0) as usize;
+
+    let mut candidates = Vec::new();
+    for gate in flank..values.len().saturating_sub(flank) {
+        let dbz = values[gate];
+        if dbz < options.min_reflectivity || dbz > options.max_reflectivity {
+            continue;
+        }
+        if values[gate - 1] > dbz || values[gate + 1] >= dbz {
+            continue;
+        }
+
+        // Clear air around a line is often below threshold, so floor missing surroundings
+        let floor = options.min_reflectivity - options.min_prominence;
+        let surroundings = values[gate - flank].max(values[gate + flank]).max(floor);
+        if dbz - surroundings < options.min_prominence {
+            continue;
+        }
+
+        let range = reflectivity.data().gate_range_m(gate);
+        let (Some(near), Some(far)) = (
+            velocity_at(velocity, range - options.flank_distance),
+            velocity_at(velocity, range + options.flank_distance),
+        ) else {
+            continue;
+        };
+
+        let convergence = near - far;
+        if convergence < options.min_convergence {
+            continue;
+        }
+
+        candidates.push(Candidate {
+            range,
+            reflectivity: dbz,
+            convergence,
+        });
+    }
+
+    candidates
+}
+
+/// The velocity of the gate nearest the specified range, if it has a valid value.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn velocity_at(velocity: &DataMoment, range: f32) -> Option<f32> {
+    let data = velocity.data();
+    let interval = f32::from(data.data_moment_range_sample_interval()).max(1.0);
+    let gate = ((range - f32::from(data.data_moment_range())) / interval).round();
+    if gate < 0.0 {
+        return None;
+    }
+
+    velocity.value(gate as usize)?.value()
+}
+
+/// A fine line tracked across volumes.
+#[derive(Debug, Clone)]
+pub struct TrackedFineLine {
+    id: u64,
+    line: FineLine,
+    motion: Option<(f32, f32)>,
+}
+
+impl TrackedFineLine {
+    /// An identifier which persists as the line is matched across volumes.
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The line's geometry in the most recent volume.
+    #[must_use]
+    pub fn line(&self) -> &FineLine {
+        &self.line
+    }
+
+    /// The line's estimated motion in meters per second toward the east and north, from the
+    /// displacement of its centroid since the previous volume. `None` for newly detected lines.
+    #[must_use]
+    pub fn motion(&self) -> Option<(f32, f32)> {
+        self.motion
+    }
+
+    /// The line's estimated speed in meters per second, if known.
+    #[must_use]
+    pub fn speed(&self) -> Option<f32> {
+        self.motion.map(|(u, v)| u.hypot(v))
+    }
+}
+
+/// Matches fine lines across consecutive volumes to estimate their movement.
+#[derive(Debug, Clone)]
+pub struct FineLineTracker {
+    max_distance: f32,
+    previous: Option<(NaiveDateTime, Vec<TrackedFineLine>)>,
+    next_id: u64,
+}
+
+impl FineLineTracker {
+    /// Create a new tracker which matches lines whose centroids moved at most `max_distance`
+    /// meters between volumes.
+    #[must_use]
+    pub fn new(max_distance: f32) -> Self {
+        Self {
+            max_distance,
+            previous: None,
+            next_id: 0,
+        }
+    }
+
+    /// Matches a volume's lines to those of the previous volume, each to the nearest unmatched
+    /// previous line, returning them with persistent identifiers and motion estimates. Volumes must
+    /// be provided in time order.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn update(&mut self, time: NaiveDateTime, lines: Vec<FineLine>) -> Vec<TrackedFineLine> {
+        let (seconds, mut previous) = match self.previous.take() {
+            Some((previous_time, previous)) if previous_time < time => (
+                (time - previous_time).num_milliseconds() as f32 / 1000.0,
+                previous,
+            ),
+            _ => (0.0, Vec::new()),
+        };
+
+        let tracked: Vec<TrackedFineLine> = lines
+            .into_iter()
+            .map(|line| {
+                let (x, y) = line.centroid();
+                let nearest = previous
+                    .iter()
+                    .enumerate()
+                    .map(|(index, tracked)| {
+                        let (previous_x, previous_y) = tracked.line.centroid();
+                        (index, (x - previous_x).hypot(y - previous_y))
+                    })
+                    .filter(|(_, distance)| *distance <= self.max_distance)
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+
+                if let Some((index, _)) = nearest {
+                    let matched = previous.swap_remove(index);
+                    let (previous_x, previous_y) = matched.line.centroid();
+                    return TrackedFineLine {
+                        id: matched.id,
+                        line,
+                        motion: Some(((x - previous_x) / seconds, (y - previous_y) / seconds)),
+                    };
+                }
+
+                self.next_id += 1;
+                TrackedFineLine {
+                    id: self.next_id,
+                    line,
+                    motion: None,
+                }
+            })
+            .collect();
+
+        self.previous = Some((time, tracked.clone()));
+        tracked
+    }
+}
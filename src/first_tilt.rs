@@ -0,0 +1,224 @@
+//!
+//! Provides utilities like [``decode_first_tilt``] for decoding only the lowest tilt's reflectivity
+//! from an archive or real-time chunk stream as quickly as possible, e.g. for alerting. Records are
+//! decompressed in parallel batches, records following the lowest tilt's batch are never
+//! decompressed, and other moments are skipped.
+//!
+
+use std::io::Cursor;
+use std::mem::size_of;
+use std::num::NonZeroUsize;
+use std::thread;
+
+use anyhow::Result;
+
+use crate::decoder::{sort_by_azimuth, DataFile};
+use crate::decompressor::{decompress_record, split_records};
+use crate::error::Error;
+use crate::metadata::{is_bzip2_compressed, is_zlib_compressed};
+use crate::records::{DataBlockProduct, Message31, MessageHeader, VolumeHeaderRecord};
+use crate::sweep::Sweep;
+
+/// Size of a message segment other than message 31, including its 12-byte CTM header.
+const FIXED_MESSAGE_SIZE: usize = 2432;
+
+/// Size of the CTM header preceding each message's header.
+const CTM_HEADER_SIZE: usize = 12;
+
+/// Radial status indicating the end of an elevation.
+const END_OF_ELEVATION: u8 = 2;
+
+/// Radial status indicating the end of the volume.
+const END_OF_VOLUME: u8 = 4;
+
+/// Decodes the lowest tilt's reflectivity from a compressed or uncompressed archive, or from the
+/// concatenated chunks of a real-time volume. Decoding stops as soon as the tilt is complete. The
+/// returned sweep's radials contain only reflectivity and any metadata blocks.
+///
+/// # Errors
+/// Returns an error if the data is not valid or contains no radials.
+pub fn decode_first_tilt(data: &[u8]) -> Result<Sweep> {
+    let mut decoder = FirstTiltDecoder::new();
+    match decoder.push(data)? {
+        Some(sweep) => Ok(sweep),
+        None => decoder.finish(),
+    }
+}
+
+/// Incrementally decodes the lowest tilt's reflectivity as an archive's chunks arrive, returning
+/// the sweep as soon as the tilt is complete.
+#[derive(Default)]
+pub struct FirstTiltDecoder {
+    compressed: Option<bool>,
+    pending: Vec<u8>,
+    elevation_number: Option<u8>,
+    radials: Vec<Message31>,
+    complete: bool,
+}
+
+impl FirstTiltDecoder {
+    /// Create a new decoder awaiting the volume's first chunk.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the lowest tilt has been completely decoded.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Decodes the next chunk of the volume. The first chunk may begin with the volume header and
+    /// determines whether the volume is compressed. Compressed chunks must consist of whole
+    /// records, while uncompressed data may be split anywhere. Returns the lowest tilt's sweep once
+    /// it is complete, after which further chunks are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the chunk is not valid.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Option<Sweep>> {
+        if self.complete {
+            return Ok(None);
+        }
+
+        let mut chunk = chunk;
+        if chunk.starts_with(b"AR2") {
+            chunk = chunk
+                .get(size_of::<VolumeHeaderRecord>()..)
+                .ok_or(Error::MissingRadials)?;
+        }
+
+        let compressed = *self.compressed.get_or_insert_with(|| {
+            chunk.len() > 4 && (is_bzip2_compressed(&chunk[4..]) || is_zlib_compressed(&chunk[4..]))
+        });
+
+        if compressed {
+            let records = split_records(chunk)?;
+            let batch_size = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+            for batch in records.chunks(batch_size) {
+                for record in decompress_batch(batch) {
+                    self.pending.extend(record?);
+                    self.decode_pending()?;
+
+                    if self.complete {
+                        return Ok(Some(self.take_sweep()));
+                    }
+                }
+            }
+        } else {
+            self.pending.extend_from_slice(chunk);
+            self.decode_pending()?;
+
+            if self.complete {
+                return Ok(Some(self.take_sweep()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns whatever portion of the lowest tilt has been decoded, e.g. if the stream ended
+    /// before the tilt was complete.
+    ///
+    /// # Errors
+    /// Returns an error if no radials were decoded.
+    pub fn finish(mut self) -> Result<Sweep> {
+        if self.radials.is_empty() {
+            return Err(Error::MissingRadials.into());
+        }
+
+        Ok(self.take_sweep())
+    }
+
+    /// Decodes each whole message in the pending buffer until the lowest tilt is complete.
+    fn decode_pending(&mut self) -> Result<()> {
+        let header_size = size_of::<MessageHeader>();
+
+        let mut offset = 0;
+        while !self.complete && self.pending.len() - offset >= header_size {
+            let mut reader = Cursor::new(&self.pending[offset..]);
+            let message_header: MessageHeader = DataFile::deserialize(&mut reader)?;
+
+            let message_size = if message_header.msg_type() == 31 {
+                CTM_HEADER_SIZE + usize::from(message_header.msg_size()) * 2
+            } else {
+                FIXED_MESSAGE_SIZE
+            };
+
+            // A message shorter than its headers would otherwise be sliced backwards
+            if message_size < header_size {
+                return Err(Error::InvalidMessageSize(message_header.msg_size()).into());
+            }
+
+            if self.pending.len() - offset < message_size {
+                break;
+            }
+
+            if message_header.msg_type() == 31 {
+                let message_data = &self.pending[offset + header_size..offset + message_size];
+                let message = DataFile::decode_message_31(
+                    &mut Cursor::new(message_data),
+                    Some(&DataBlockProduct::Reflectivity),
+                )?;
+
+                self.accept(message);
+            }
+
+            offset += message_size;
+        }
+
+        self.pending.drain(..offset);
+
+        Ok(())
+    }
+
+    /// Adds a radial to the lowest tilt, or marks the tilt complete if it belongs to a later tilt.
+    fn accept(&mut self, message: Message31) {
+        let elevation_number = message.header().elev_num();
+        if *self.elevation_number.get_or_insert(elevation_number) != elevation_number {
+            self.complete = true;
+            return;
+        }
+
+        let radial_status = message.header().radial_status();
+        self.radials.push(message);
+
+        if radial_status == END_OF_ELEVATION || radial_status == END_OF_VOLUME {
+            self.complete = true;
+        }
+    }
+
+    fn take_sweep(&mut self) -> Sweep {
+        self.complete = true;
+        self.pending = Vec::new();
+
+        let mut radials = std::mem::take(&mut self.radials);
+        sort_by_azimuth(&mut radials);
+
+        Sweep::new(self.elevation_number.unwrap_or_default(), radials)
+    }
+}
+
+/// Decompresses a batch of records, in parallel if there are several.
+fn decompress_batch(batch: &[&[u8]]) -> Vec<Result<Vec<u8>>> {
+    if let [record] = batch {
+        return vec![decompress_record(record)];
+    }
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = batch
+            .iter()
+            .map(|record| scope.spawn(|| decompress_record(record)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(Error::DecompressionPanicked.into()))
+            })
+            .collect()
+    })
+}
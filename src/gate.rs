@@ -0,0 +1,92 @@
+//!
+//! Scaled gate values and utilities like [``GateIterator``] for walking a radial's gates.
+//!
+
+use crate::records::{DataMoment, Message31, Product};
+
+/// A single gate's value, scaled from its raw data word.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GateValue {
+    /// The signal at this gate was below the SNR threshold.
+    BelowThreshold,
+    /// The signal at this gate is range folded (overlaid).
+    RangeFolded,
+    /// The gate's value in the product's physical units, e.g. dBZ for reflectivity.
+    Value(f32),
+}
+
+impl GateValue {
+    /// Scales a raw data word into a gate value. Raw values 0 and 1 are reserved to indicate below
+    /// threshold and range folded gates, respectively. A scale of zero indicates the raw value is
+    /// not scaled.
+    #[must_use]
+    pub fn from_raw(raw: u16, scale: f32, offset: f32) -> Self {
+        match raw {
+            0 => Self::BelowThreshold,
+            1 => Self::RangeFolded,
+            _ if scale == 0.0 => Self::Value(f32::from(raw)),
+            _ => Self::Value((f32::from(raw) - offset) / scale),
+        }
+    }
+
+    /// The gate's scaled value, if it has one.
+    #[must_use]
+    pub fn value(&self) -> Option<f32> {
+        match self {
+            Self::Value(value) => Some(*value),
+            Self::BelowThreshold | Self::RangeFolded => None,
+        }
+    }
+}
+
+/// Iterates over a radial's gates, yielding `(range_m, azimuth_deg, elevation_deg, value)` for
+/// each gate. Created by [`GateIterator::from_radial`].
+pub struct GateIterator<'a> {
+    moment: &'a DataMoment,
+    azimuth: f32,
+    elevation: f32,
+    index: usize,
+}
+
+impl<'a> GateIterator<'a> {
+    /// Iterates over the specified product's gates of a radial, with the radial's azimuth and
+    /// elevation. Returns `None` if the radial does not contain the product.
+    #[must_use]
+    pub fn from_radial(radial: &'a Message31, product: Product) -> Option<Self> {
+        let moment = radial.get_data_moment(&product.into())?;
+        Some(Self::new(
+            moment,
+            radial.header().azm(),
+            radial.header().elev(),
+        ))
+    }
+
+    pub(crate) fn new(moment: &'a DataMoment, azimuth: f32, elevation: f32) -> Self {
+        Self {
+            moment,
+            azimuth,
+            elevation,
+            index: 0,
+        }
+    }
+}
+
+impl Iterator for GateIterator<'_> {
+    type Item = (f32, f32, f32, GateValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.moment.value(self.index)?;
+        let range = self.moment.data().gate_range_m(self.index);
+
+        self.index += 1;
+
+        Some((range, self.azimuth, self.elevation, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.moment.gate_count().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for GateIterator<'_> {}
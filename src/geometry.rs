@@ -0,0 +1,114 @@
+//!
+//! Provides utilities like [``beam_height_m``] for locating radar gates in space.
+//!
+
+/// Mean radius of the earth in meters.
+pub const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Effective earth radius multiplier for standard atmospheric refraction (the "4/3 earth" model).
+pub const EFFECTIVE_RADIUS_FACTOR: f64 = 4.0 / 3.0;
+
+/// Height of the beam's center in meters above mean sea level at the specified slant range in
+/// meters and elevation angle in degrees, for an antenna at the specified altitude in meters above
+/// mean sea level. Assumes standard refraction.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn beam_height_m(range: f32, elevation: f32, antenna_altitude: f32) -> f32 {
+    let range = f64::from(range);
+    let elevation = f64::from(elevation).to_radians();
+    let effective_radius = EARTH_RADIUS_M * EFFECTIVE_RADIUS_FACTOR;
+
+    let height = (range.powi(2)
+        + effective_radius.powi(2)
+        + 2.0 * range * effective_radius * elevation.sin())
+    .sqrt()
+        - effective_radius;
+
+    (height + f64::from(antenna_altitude)) as f32
+}
+
+/// Latitude and longitude in degrees of the point at the specified azimuth in degrees clockwise
+/// from north and ground distance in meters from an origin, along a great circle.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn destination(lat: f32, long: f32, azimuth: f32, distance: f32) -> (f32, f32) {
+    let lat = f64::from(lat).to_radians();
+    let long = f64::from(long).to_radians();
+    let azimuth = f64::from(azimuth).to_radians();
+    let angular_distance = f64::from(distance) / EARTH_RADIUS_M;
+
+    let destination_lat = (lat.sin() * angular_distance.cos()
+        + lat.cos() * angular_distance.sin() * azimuth.cos())
+    .asin();
+    let destination_long = long
+        + (azimuth.sin() * angular_distance.sin() * lat.cos())
+            .atan2(angular_distance.cos() - lat.sin() * destination_lat.sin());
+
+    (
+        destination_lat.to_degrees() as f32,
+        destination_long.to_degrees() as f32,
+    )
+}
+
+/// Ground distance in meters along the earth's surface to the point beneath the beam's center at
+/// the specified slant range in meters and elevation angle in degrees. Assumes standard
+/// refraction.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn ground_range_m(range: f32, elevation: f32) -> f32 {
+    let range = f64::from(range);
+    let elevation = f64::from(elevation).to_radians();
+    let effective_radius = EARTH_RADIUS_M * EFFECTIVE_RADIUS_FACTOR;
+
+    let height = (range.powi(2)
+        + effective_radius.powi(2)
+        + 2.0 * range * effective_radius * elevation.sin())
+    .sqrt();
+
+    (effective_radius * (range * elevation.cos() / height).asin()) as f32
+}
+
+/// Slant range in meters and elevation angle in degrees at which a beam reaches the point at the
+/// specified ground distance in meters and height in meters above the antenna, the inverse of
+/// [`ground_range_m`] and [`beam_height_m`]. Assumes standard refraction.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn slant_range_and_elevation(ground_range: f32, height: f32) -> (f32, f32) {
+    let effective_radius = EARTH_RADIUS_M * EFFECTIVE_RADIUS_FACTOR;
+    let angle = f64::from(ground_range) / effective_radius;
+    let radius = effective_radius + f64::from(height);
+
+    let range = (effective_radius.powi(2) + radius.powi(2)
+        - 2.0 * effective_radius * radius * angle.cos())
+    .sqrt();
+    let elevation = if range > 0.0 {
+        ((radius * angle.cos() - effective_radius) / range)
+            .clamp(-1.0, 1.0)
+            .asin()
+    } else {
+        0.0
+    };
+
+    (range as f32, elevation.to_degrees() as f32)
+}
+
+/// Great circle distance in meters and initial azimuth in degrees clockwise from north from an
+/// origin to a destination, the inverse of [`destination`].
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn distance_and_azimuth(lat: f32, long: f32, to_lat: f32, to_long: f32) -> (f32, f32) {
+    let lat = f64::from(lat).to_radians();
+    let to_lat = f64::from(to_lat).to_radians();
+    let delta_long = (f64::from(to_long) - f64::from(long)).to_radians();
+
+    let haversine = ((to_lat - lat) / 2.0).sin().powi(2)
+        + lat.cos() * to_lat.cos() * (delta_long / 2.0).sin().powi(2);
+    let distance = 2.0 * EARTH_RADIUS_M * haversine.sqrt().asin();
+    let azimuth = (delta_long.sin() * to_lat.cos())
+        .atan2(lat.cos() * to_lat.sin() - lat.sin() * to_lat.cos() * delta_long.cos());
+
+    (
+        distance as f32,
+        azimuth.to_degrees().rem_euclid(360.0) as f32,
+    )
+}
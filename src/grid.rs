@@ -0,0 +1,367 @@
+//!
+//! Struct definitions for [``Grid``], a regular two-dimensional field of values such as a gridded
+//! radar product, and utilities like [``grid_sweep``] for sampling radar data onto grids.
+//!
+
+use anyhow::Result;
+
+use crate::cancel::CancellationToken;
+use crate::decoder::DataFile;
+use crate::gate::GateValue;
+use crate::records::{Message31, Product};
+
+/// A regular grid of values stored in row-major order, with row 0 first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    columns: usize,
+    rows: usize,
+    values: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Create a new grid from values in row-major order.
+    ///
+    /// # Panics
+    /// Panics if the number of values does not match the grid's dimensions.
+    #[must_use]
+    pub fn new(columns: usize, rows: usize, values: Vec<T>) -> Self {
+        assert_eq!(
+            values.len(),
+            columns * rows,
+            "value count must match grid dimensions"
+        );
+
+        Self {
+            columns,
+            rows,
+            values,
+        }
+    }
+
+    /// Create a new grid with every cell set to the specified value.
+    #[must_use]
+    pub fn filled(columns: usize, rows: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::new(columns, rows, vec![value; columns * rows])
+    }
+
+    /// Number of columns in the grid.
+    #[must_use]
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Number of rows in the grid.
+    #[must_use]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The value at the specified column and row, if within the grid.
+    #[must_use]
+    pub fn get(&self, column: usize, row: usize) -> Option<&T> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        self.values.get(row * self.columns + column)
+    }
+
+    /// A mutable reference to the value at the specified column and row, if within the grid.
+    pub fn get_mut(&mut self, column: usize, row: usize) -> Option<&mut T> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        self.values.get_mut(row * self.columns + column)
+    }
+
+    /// The grid's values in row-major order.
+    #[must_use]
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Consumes the grid, returning its values in row-major order.
+    #[must_use]
+    pub fn into_values(self) -> Vec<T> {
+        self.values
+    }
+
+    /// Copies the grid into a two-dimensional array indexed by row then column.
+    #[must_use]
+    #[cfg(feature = "ndarray")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn to_array(&self) -> ndarray::Array2<T>
+    where
+        T: Clone,
+    {
+        ndarray::Array2::from_shape_fn((self.rows, self.columns), |(row, column)| {
+            self.values[row * self.columns + column].clone()
+        })
+    }
+
+    /// Create a grid of the same dimensions by applying a function to each value.
+    #[must_use]
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            columns: self.columns,
+            rows: self.rows,
+            values: self.values.iter().map(f).collect(),
+        }
+    }
+}
+
+impl Grid<Option<f32>> {
+    /// Encodes the grid as raw little-endian 32-bit floats in row-major order, with NaN for cells
+    /// without a value, as read by most raster tools given the grid's dimensions.
+    #[must_use]
+    pub fn to_f32_le_bytes(&self) -> Vec<u8> {
+        self.values
+            .iter()
+            .flat_map(|value| value.unwrap_or(f32::NAN).to_le_bytes())
+            .collect()
+    }
+}
+
+/// The layout of a regular grid centered on a radar, in meters east and north of the radar. Row 0
+/// is the northernmost row and column 0 the westernmost column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridSpec {
+    columns: usize,
+    rows: usize,
+    cell_size: f32,
+}
+
+impl GridSpec {
+    /// Create a new grid layout with the specified dimensions and square cells `cell_size` meters
+    /// wide, centered on the radar.
+    ///
+    /// # Panics
+    /// Panics if the cell size is not positive.
+    #[must_use]
+    pub fn new(columns: usize, rows: usize, cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+
+        Self {
+            columns,
+            rows,
+            cell_size,
+        }
+    }
+
+    /// Number of columns in the grid.
+    #[must_use]
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Number of rows in the grid.
+    #[must_use]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The width of each cell in meters.
+    #[must_use]
+    pub fn cell_size_m(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// The center of the specified cell in meters east and north of the radar.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn cell_center(&self, column: usize, row: usize) -> (f32, f32) {
+        let x = (column as f32 + 0.5 - self.columns as f32 / 2.0) * self.cell_size;
+        let y = (self.rows as f32 / 2.0 - row as f32 - 0.5) * self.cell_size;
+        (x, y)
+    }
+
+    /// Create a grid of this layout by evaluating a function at each cell's center.
+    #[must_use]
+    pub fn generate<T, F: FnMut(f32, f32) -> T>(&self, mut f: F) -> Grid<T> {
+        let values = (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let (x, y) = self.cell_center(column, row);
+                f(x, y)
+            })
+            .collect();
+
+        Grid::new(self.columns, self.rows, values)
+    }
+
+    /// Evaluates a function at every cell's center like [`GridSpec::generate`], checking the token
+    /// for cancellation before each row.
+    ///
+    /// # Errors
+    /// Returns an error if generation was cancelled.
+    pub fn generate_cancellable<T, F: FnMut(f32, f32) -> T>(
+        &self,
+        token: &CancellationToken,
+        mut f: F,
+    ) -> Result<Grid<T>> {
+        let mut values = Vec::with_capacity(self.columns * self.rows);
+        for row in 0..self.rows {
+            token.check()?;
+            for column in 0..self.columns {
+                let (x, y) = self.cell_center(column, row);
+                values.push(f(x, y));
+            }
+        }
+
+        Ok(Grid::new(self.columns, self.rows, values))
+    }
+}
+
+/// Samples a product from a sweep's radials onto a grid, taking each cell's value from the gate
+/// nearest its center. Cells beyond the sweep's range, more than a degree from any radial, or at
+/// gates without a value are `None`.
+#[must_use]
+pub fn grid_sweep(radials: &[Message31], product: Product, spec: &GridSpec) -> Grid<Option<f32>> {
+    grid_sweep_gates(radials, product, spec).map(|gate| gate.and_then(|gate| gate.value()))
+}
+
+/// Samples a product from a sweep's radials onto a grid like [``grid_sweep``], checking the token
+/// for cancellation before each row.
+///
+/// # Errors
+/// Returns an error if gridding was cancelled.
+pub fn grid_sweep_cancellable(
+    radials: &[Message31],
+    product: Product,
+    spec: &GridSpec,
+    token: &CancellationToken,
+) -> Result<Grid<Option<f32>>> {
+    let gates = grid_sweep_gates_cancellable(radials, product, spec, token)?;
+    Ok(gates.map(|gate| gate.and_then(|gate| gate.value())))
+}
+
+/// Samples a product from a sweep's radials onto a grid like [``grid_sweep``], but retains each
+/// cell's gate value so below threshold and range folded gates can be distinguished. Cells beyond
+/// the sweep's range or more than a degree from any radial are `None`.
+#[must_use]
+pub fn grid_sweep_gates(
+    radials: &[Message31],
+    product: Product,
+    spec: &GridSpec,
+) -> Grid<Option<GateValue>> {
+    spec.generate(gate_sampler(radials, product))
+}
+
+/// Samples a product from a sweep's radials onto a grid like [``grid_sweep_gates``], checking the
+/// token for cancellation before each row.
+///
+/// # Errors
+/// Returns an error if gridding was cancelled.
+pub fn grid_sweep_gates_cancellable(
+    radials: &[Message31],
+    product: Product,
+    spec: &GridSpec,
+    token: &CancellationToken,
+) -> Result<Grid<Option<GateValue>>> {
+    spec.generate_cancellable(token, gate_sampler(radials, product))
+}
+
+/// Samples a product from every sweep of a volume onto a grid, taking each cell's largest value
+/// across sweeps, e.g. composite reflectivity.
+#[must_use]
+pub fn grid_composite(file: &DataFile, product: Product, spec: &GridSpec) -> Grid<Option<f32>> {
+    let mut composite = Grid::filled(spec.columns, spec.rows, None);
+    for radials in file.elevation_scans().values() {
+        composite_max(&mut composite, grid_sweep(radials, product, spec));
+    }
+
+    composite
+}
+
+/// Samples a product from every sweep of a volume onto a grid like [``grid_composite``], checking
+/// the token for cancellation before each row of each sweep.
+///
+/// # Errors
+/// Returns an error if gridding was cancelled.
+pub fn grid_composite_cancellable(
+    file: &DataFile,
+    product: Product,
+    spec: &GridSpec,
+    token: &CancellationToken,
+) -> Result<Grid<Option<f32>>> {
+    let mut composite = Grid::filled(spec.columns, spec.rows, None);
+    for radials in file.elevation_scans().values() {
+        let sweep = grid_sweep_cancellable(radials, product, spec, token)?;
+        composite_max(&mut composite, sweep);
+    }
+
+    Ok(composite)
+}
+
+/// The value of the gate nearest each point of a sweep, for generating grids.
+fn gate_sampler(
+    radials: &[Message31],
+    product: Product,
+) -> impl Fn(f32, f32) -> Option<GateValue> + '_ {
+    let mut azimuths: Vec<(f32, &Message31)> = radials
+        .iter()
+        .filter(|radial| radial.get_data_moment(&product.into()).is_some())
+        .map(|radial| (radial.header().azm(), radial))
+        .collect();
+    azimuths.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    move |x, y| {
+        let azimuth = x.atan2(y).to_degrees().rem_euclid(360.0);
+        let range = x.hypot(y);
+
+        let radial = nearest_radial(&azimuths, azimuth)?;
+        let elevation = radial.header().elev().to_radians();
+        sample_radial(radial, product, range / elevation.cos())
+    }
+}
+
+/// Takes the larger of a composite's and a sweep's values in each cell.
+fn composite_max(composite: &mut Grid<Option<f32>>, sweep: Grid<Option<f32>>) {
+    for (cell, value) in composite.values.iter_mut().zip(sweep.values) {
+        *cell = match (*cell, value) {
+            (Some(current), Some(value)) => Some(f32::max(current, value)),
+            (current, value) => current.or(value),
+        };
+    }
+}
+
+/// The radial nearest the specified azimuth from radials sorted by azimuth, if within a degree.
+fn nearest_radial<'a>(azimuths: &[(f32, &'a Message31)], azimuth: f32) -> Option<&'a Message31> {
+    let index = azimuths.partition_point(|(radial_azimuth, _)| *radial_azimuth < azimuth);
+
+    // The nearest radial is on either side of the insertion point, wrapping around north
+    let candidates = [
+        azimuths.get(index % azimuths.len().max(1)),
+        azimuths.get((index + azimuths.len()).saturating_sub(1) % azimuths.len().max(1)),
+    ];
+
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|(radial_azimuth, radial)| {
+            let difference = (radial_azimuth - azimuth).rem_euclid(360.0);
+            (difference.min(360.0 - difference), *radial)
+        })
+        .filter(|(difference, _)| *difference <= 1.0)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, radial)| radial)
+}
+
+/// The gate nearest the specified slant range in meters, if within the radial.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn sample_radial(radial: &Message31, product: Product, range: f32) -> Option<GateValue> {
+    let moment = radial.get_data_moment(&product.into())?;
+    let data = moment.data();
+
+    let interval = f32::from(data.data_moment_range_sample_interval()).max(1.0);
+    let gate = ((range - f32::from(data.data_moment_range())) / interval).round();
+    if gate < 0.0 {
+        return None;
+    }
+
+    moment.value(gate as usize)
+}
@@ -0,0 +1,93 @@
+//!
+//! The high-level layer: ergonomic types for working with decoded volumes as sweeps, gates,
+//! gridded fields, and derived products, along with downloading, format conversion, and batch
+//! processing. This layer evolves with the crate's API, so it may change between minor versions
+//! before 1.0; the ICD structs it is built on are available with a stronger stability promise in
+//! [``crate::raw``].
+//!
+
+pub use crate::backfill::{ArchiveSource, Backfill, ChunkKind, ChunkMetadata, ChunkSource};
+pub use crate::batch::{
+    render_batch, BatchImage, BatchJob, BatchReport, DirectoryLoader, RenderManifest, VolumeLoader,
+};
+pub use crate::blockage::{Blockage, Dem, NoBlockage, TerrainBlockage, WSR88D_BEAMWIDTH};
+pub use crate::bufr::{
+    encode_radial_wind_bufr, encode_radial_wind_bufr_with_blockage, BufrOptions,
+};
+pub use crate::calibration::{estimate_reflectivity_bias, CalibrationEstimate, CalibrationOptions};
+pub use crate::cancel::CancellationToken;
+pub use crate::cfradial::encode_cfradial;
+pub use crate::climatology::EchoClimatology;
+pub use crate::composite::{
+    Combination, InterpolationOptions, VolumeLayer, VolumePair, VolumeSeries,
+};
+pub use crate::csv::{encode_csv, write_csv, CsvOptions};
+pub use crate::decoder::{
+    DataFile, DecodeOptions, DecodeReport, IntoSweeps, SkipReason, SkippedRadial,
+};
+pub use crate::delta::{DeltaDecoder, DeltaEncoder};
+pub use crate::encode::{encode_compressed_file, encode_file};
+pub use crate::expression::{AlertEvaluation, AlertRule, Expression};
+pub use crate::fine_line::{
+    detect_fine_lines, detect_fine_lines_in_sweep, FineLine, FineLineDetection, FineLineOptions,
+    FineLineTracker, TrackedFineLine,
+};
+pub use crate::first_tilt::{decode_first_tilt, FirstTiltDecoder};
+pub use crate::gate::{GateIterator, GateValue};
+pub use crate::geometry::{
+    beam_height_m, destination, distance_and_azimuth, ground_range_m, slant_range_and_elevation,
+    EARTH_RADIUS_M, EFFECTIVE_RADIUS_FACTOR,
+};
+pub use crate::grid::{
+    grid_composite, grid_composite_cancellable, grid_sweep, grid_sweep_cancellable,
+    grid_sweep_gates, grid_sweep_gates_cancellable, Grid, GridSpec,
+};
+pub use crate::hybrid_scan::{HybridScan, HybridScanOptions};
+pub use crate::metadata::FileMetadata;
+pub use crate::odim::{read_odim_volume, OdimAttribute, OdimSource, OdimTree};
+pub use crate::partition::{
+    identify_cells, partition_grid, partition_sweep, ConvectiveCell, PartitionOptions, RainType,
+    SteinerOptions,
+};
+pub use crate::phase::{PhaseOptions, ProcessedPhase};
+pub use crate::pipeline::{
+    ExportFormat, FieldConfig, GridConfig, Pipeline, ProcessingConfig, ProductGridConfig, QcStep,
+};
+pub use crate::precip_type::{
+    surface_precipitation_types, HydrometeorClass, MeltingLayer, PrecipitationTypeInput,
+    SurfacePrecipitationType,
+};
+pub use crate::pyramid::{Aggregation, PolarPyramid, PyramidLevel, PyramidOptions};
+pub use crate::qpe::{
+    combine_rain_rates, rain_rate_grid, rain_rate_sweep, rain_rate_sweep_weighted, QpeOptions,
+    ZrPreset, ZrRelation, ZrSelection,
+};
+pub use crate::quality::{QualityOptions, RadialQuality, SweepQuality};
+pub use crate::radar_pair::{match_sweeps, PolarCoordinate, RadarLocation, RadarPair, SweepMatch};
+pub use crate::records::Product;
+pub use crate::registration::{
+    check_registration, check_series, RegistrationAnomaly, RegistrationCheck, RegistrationOptions,
+};
+pub use crate::render::{
+    render_all_elevations, render_sweep, render_sweep_cancellable, ColorMap, ElevationImage,
+    ElevationImages, Image, Palette, RenderOptions, Rgba,
+};
+pub use crate::sigmet::{read_sigmet_file, read_sigmet_raw};
+pub use crate::simulate::{SimulatedSite, SimulatedVolume, Simulator, SimulatorConfig};
+pub use crate::superob::{
+    superob_sweep, superob_sweep_weighted, Averaging, Superob, SuperobOptions,
+};
+pub use crate::sweep::{Sweep, SweepCapabilities};
+pub use crate::uf::{encode_uf, read_uf, read_uf_file};
+pub use crate::verification::{verify_against, ContingencyTable, FieldSource};
+
+#[cfg(feature = "download")]
+#[cfg_attr(docsrs, doc(cfg(feature = "download")))]
+pub use crate::downloader::{
+    backfill, download_file, list_files, AwsArchive, AwsChunks, FailoverDownloader, SourceHealth,
+    VolumeSource,
+};
+
+#[cfg(feature = "sqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+pub use crate::sqlite::{EventArchive, SCHEMA_VERSION};
@@ -0,0 +1,325 @@
+//!
+//! Provides [``HybridScan``] for combining a volume's sweeps into a single near-surface
+//! reflectivity field by selecting the lowest sufficiently unblocked tilt in each polar bin, the
+//! standard input for quantitative precipitation estimation (QPE).
+//!
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::blockage::Blockage;
+use crate::cancel::CancellationToken;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::gate::{GateIterator, GateValue};
+use crate::records::{Message31, Product};
+use crate::sweep::SweepCapabilities;
+
+/// Options controlling how a hybrid scan is constructed.
+#[derive(Debug, Clone)]
+pub struct HybridScanOptions {
+    azimuth_resolution: f32,
+    range_resolution: f32,
+    max_range: f32,
+    max_blockage: f32,
+}
+
+impl HybridScanOptions {
+    /// Create the default options: one degree by one kilometer bins out to 230 km, using tilts
+    /// which are less than half blocked.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The size of each bin in degrees of azimuth and meters of range.
+    ///
+    /// # Panics
+    /// Panics if either resolution is not positive.
+    #[must_use]
+    pub fn with_resolution(mut self, azimuth_resolution: f32, range_resolution: f32) -> Self {
+        assert!(
+            azimuth_resolution > 0.0,
+            "azimuth resolution must be positive"
+        );
+        assert!(range_resolution > 0.0, "range resolution must be positive");
+
+        self.azimuth_resolution = azimuth_resolution;
+        self.range_resolution = range_resolution;
+        self
+    }
+
+    /// The range in meters out to which bins are constructed.
+    #[must_use]
+    pub fn with_max_range(mut self, max_range: f32) -> Self {
+        self.max_range = max_range;
+        self
+    }
+
+    /// The largest blocked fraction of the beam, from 0 to 1, for which a tilt may be selected.
+    #[must_use]
+    pub fn with_max_blockage(mut self, max_blockage: f32) -> Self {
+        self.max_blockage = max_blockage;
+        self
+    }
+
+    /// The size of each bin in degrees of azimuth.
+    #[must_use]
+    pub fn azimuth_resolution(&self) -> f32 {
+        self.azimuth_resolution
+    }
+
+    /// The size of each bin in meters of range.
+    #[must_use]
+    pub fn range_resolution(&self) -> f32 {
+        self.range_resolution
+    }
+
+    /// The range in meters out to which bins are constructed.
+    #[must_use]
+    pub fn max_range(&self) -> f32 {
+        self.max_range
+    }
+
+    /// The largest blocked fraction of the beam for which a tilt may be selected.
+    #[must_use]
+    pub fn max_blockage(&self) -> f32 {
+        self.max_blockage
+    }
+}
+
+impl Default for HybridScanOptions {
+    fn default() -> Self {
+        Self {
+            azimuth_resolution: 1.0,
+            range_resolution: 1000.0,
+            max_range: 230_000.0,
+            max_blockage: 0.5,
+        }
+    }
+}
+
+/// Near-surface reflectivity in polar bins, each taken from the lowest tilt whose beam is
+/// sufficiently unblocked at that bin. Bins are ordered by azimuth bin then range bin.
+#[derive(Debug, Clone)]
+pub struct HybridScan {
+    azimuth_resolution: f32,
+    range_resolution: f32,
+    range_bins: usize,
+    reflectivity: Vec<Option<f32>>,
+    elevations: Vec<Option<f32>>,
+    blockage: Vec<f32>,
+}
+
+impl HybridScan {
+    /// Constructs a hybrid scan from a volume's reflectivity. Sweeps are ranked by their mean
+    /// elevation angle; where several sweeps share an angle, the first is used. Each bin's
+    /// reflectivity is the largest of the selected tilt's gates within it.
+    ///
+    /// # Errors
+    /// Returns an error if the volume has no reflectivity data.
+    pub fn new<B: Blockage + ?Sized>(
+        file: &DataFile,
+        blockage: &B,
+        options: &HybridScanOptions,
+    ) -> Result<Self> {
+        Self::build(file, blockage, options, None)
+    }
+
+    /// Constructs a hybrid scan like [`HybridScan::new`], checking the token for cancellation
+    /// before each azimuth bin is selected and each tilt is sampled.
+    ///
+    /// # Errors
+    /// Returns an error if the volume has no reflectivity data or construction was cancelled.
+    pub fn new_cancellable<B: Blockage + ?Sized>(
+        file: &DataFile,
+        blockage: &B,
+        options: &HybridScanOptions,
+        token: &CancellationToken,
+    ) -> Result<Self> {
+        Self::build(file, blockage, options, Some(token))
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn build<B: Blockage + ?Sized>(
+        file: &DataFile,
+        blockage: &B,
+        options: &HybridScanOptions,
+        token: Option<&CancellationToken>,
+    ) -> Result<Self> {
+        let tilts = reflectivity_tilts(file);
+        if tilts.is_empty() {
+            return Err(Error::MissingRadials.into());
+        }
+
+        let azimuth_bins = (360.0 / options.azimuth_resolution).ceil() as usize;
+        let range_bins = (options.max_range / options.range_resolution).ceil() as usize;
+        let bin_count = azimuth_bins * range_bins;
+
+        // Select the lowest tilt in each bin whose beam is sufficiently unblocked
+        let mut selected = vec![None; bin_count];
+        let mut blockage_fractions = vec![1.0; bin_count];
+        for azimuth_bin in 0..azimuth_bins {
+            if let Some(token) = token {
+                token.check()?;
+            }
+
+            let azimuth = (azimuth_bin as f32 + 0.5) * options.azimuth_resolution;
+            for range_bin in 0..range_bins {
+                let range = (range_bin as f32 + 0.5) * options.range_resolution;
+                let bin = azimuth_bin * range_bins + range_bin;
+
+                for (tilt_index, (elevation, _)) in tilts.iter().enumerate() {
+                    let fraction = blockage.blocked_fraction(*elevation, azimuth, range);
+                    if fraction <= options.max_blockage {
+                        selected[bin] = Some(tilt_index);
+                        blockage_fractions[bin] = fraction;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut scan = Self {
+            azimuth_resolution: options.azimuth_resolution,
+            range_resolution: options.range_resolution,
+            range_bins,
+            reflectivity: vec![None; bin_count],
+            elevations: selected
+                .iter()
+                .map(|tilt| tilt.map(|index| tilts[index].0))
+                .collect(),
+            blockage: blockage_fractions,
+        };
+
+        // Fill each bin from its selected tilt's gates
+        for (tilt_index, (_, radials)) in tilts.iter().enumerate() {
+            if let Some(token) = token {
+                token.check()?;
+            }
+
+            for radial in *radials {
+                let Some(gates) = GateIterator::from_radial(radial, Product::Reflectivity) else {
+                    continue;
+                };
+
+                for (range, azimuth, _, value) in gates {
+                    let Some(bin) = scan.bin_index(azimuth, range) else {
+                        continue;
+                    };
+                    if selected[bin] != Some(tilt_index) {
+                        continue;
+                    }
+
+                    if let GateValue::Value(dbz) = value {
+                        let current = &mut scan.reflectivity[bin];
+                        *current = Some(current.map_or(dbz, |current| current.max(dbz)));
+                    }
+                }
+            }
+        }
+
+        Ok(scan)
+    }
+
+    /// The size of each bin in degrees of azimuth.
+    #[must_use]
+    pub fn azimuth_resolution(&self) -> f32 {
+        self.azimuth_resolution
+    }
+
+    /// The size of each bin in meters of range.
+    #[must_use]
+    pub fn range_resolution(&self) -> f32 {
+        self.range_resolution
+    }
+
+    /// Number of azimuth bins.
+    #[must_use]
+    pub fn azimuth_bins(&self) -> usize {
+        self.elevations.len() / self.range_bins.max(1)
+    }
+
+    /// Number of range bins along each azimuth.
+    #[must_use]
+    pub fn range_bins(&self) -> usize {
+        self.range_bins
+    }
+
+    /// Each bin's reflectivity in dBZ. Bins without echo, or where every tilt is blocked, are
+    /// `None`.
+    #[must_use]
+    pub fn reflectivity(&self) -> &[Option<f32>] {
+        &self.reflectivity
+    }
+
+    /// Each bin's selected elevation angle in degrees, or `None` if every tilt is blocked.
+    #[must_use]
+    pub fn elevations(&self) -> &[Option<f32>] {
+        &self.elevations
+    }
+
+    /// The blocked fraction of the beam at each bin for its selected tilt, or 1 where every tilt
+    /// is blocked.
+    #[must_use]
+    pub fn blockage(&self) -> &[f32] {
+        &self.blockage
+    }
+
+    /// The reflectivity in dBZ of the bin containing the specified azimuth in degrees and range in
+    /// meters.
+    #[must_use]
+    pub fn value(&self, azimuth: f32, range: f32) -> Option<f32> {
+        self.reflectivity[self.bin_index(azimuth, range)?]
+    }
+
+    /// The selected elevation angle in degrees of the bin containing the specified azimuth in
+    /// degrees and range in meters.
+    #[must_use]
+    pub fn elevation(&self, azimuth: f32, range: f32) -> Option<f32> {
+        self.elevations[self.bin_index(azimuth, range)?]
+    }
+
+    /// The index of the bin containing the specified azimuth and range, if within range.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn bin_index(&self, azimuth: f32, range: f32) -> Option<usize> {
+        if range < 0.0 {
+            return None;
+        }
+
+        let range_bin = (range / self.range_resolution) as usize;
+        if range_bin >= self.range_bins {
+            return None;
+        }
+
+        let azimuth_bin =
+            (azimuth.rem_euclid(360.0) / self.azimuth_resolution) as usize % self.azimuth_bins();
+
+        Some(azimuth_bin * self.range_bins + range_bin)
+    }
+}
+
+/// The volume's sweeps with reflectivity, ordered by mean elevation angle with one sweep per angle.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn reflectivity_tilts(file: &DataFile) -> Vec<(f32, &Vec<Message31>)> {
+    let mut tilts = BTreeMap::new();
+
+    for radials in file.elevation_scans().values() {
+        if !SweepCapabilities::from_radials(radials).has(Product::Reflectivity) {
+            continue;
+        }
+
+        let mean_elevation =
+            radials.iter().map(|r| r.header().elev()).sum::<f32>() / radials.len() as f32;
+        let tenths = (mean_elevation * 10.0).round() as i32;
+
+        tilts.entry(tenths).or_insert((mean_elevation, radials));
+    }
+
+    tilts.into_values().collect()
+}
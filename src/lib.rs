@@ -1,22 +1,130 @@
 #![forbid(unsafe_code)]
 #![warn(clippy::pedantic)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
 
 //! # NEXRAD
 //!
 //! Download and decode functions for NEXRAD radar data.
 //!
-pub mod decode;
-pub mod decompress;
+//! The crate is organized in two layers with separate stability promises:
+//!
+//! - [``raw``] contains structs mirroring the Archive II ICD exactly and the functions which
+//!   decompress them, which change only when the format does. Raw types depend on nothing from the
+//!   high-level layer.
+//! - [``high_level``] contains ergonomic types such as [``Sweep``], [``high_level::Grid``], and
+//!   derived products, which may evolve between minor versions.
+//!
+//! Every type and function is reached through one of the two layers; the modules implementing
+//! them are private, and the pre-0.1 module paths remain only as deprecated aliases. The most
+//! commonly used high-level types are also exported at the crate root, [``error``] is shared by
+//! both layers, and [``cookbook``] collects recipes built on the high-level layer.
+//!
+//! ## Optional features
+//!
+//! - `download` (default): downloading data files from the NOAA archive on AWS.
+//! - `rayon`: parallel record decompression.
+//! - `ndarray`: grids and sweeps as two-dimensional arrays.
+//! - `geo`: fine lines as `geo-types` line strings.
+//! - `image`: rendered images as `image` buffers.
+//! - `sqlite`: an event archive of volume summaries and detections in `SQLite`.
+//!
+mod backfill;
+mod batch;
+mod blockage;
+mod bufr;
+mod calibration;
+mod cancel;
+mod cfradial;
+mod climatology;
+mod composite;
+pub mod cookbook;
+mod csv;
+mod decoder;
+mod decompressor;
+mod delta;
+mod encode;
 pub mod error;
-pub mod file_metadata;
-pub mod model;
+mod expression;
+mod fine_line;
+mod first_tilt;
+mod gate;
+mod geometry;
+mod grid;
+pub mod high_level;
+mod hybrid_scan;
+mod metadata;
+mod odim;
+mod partition;
+mod phase;
+mod pipeline;
+mod precip_type;
+mod pyramid;
+mod qpe;
+mod quality;
+mod radar_pair;
+pub mod raw;
+mod records;
+mod registration;
+mod render;
+mod sigmet;
+mod simulate;
+mod superob;
+mod sweep;
+mod uf;
+mod verification;
 
 // Expose more useful things
-pub use decode::DataFile;
-pub use model::Product;
+pub use decoder::{DataFile, DecodeOptions};
+pub use gate::GateValue;
+pub use records::Product;
+pub use sweep::{Sweep, SweepCapabilities};
+
+/// Deprecated alias for [``DataFile``], which moved to the crate root and [``high_level``].
+#[deprecated(
+    since = "0.1.0",
+    note = "use `nexrad::DataFile` or `nexrad::high_level` instead"
+)]
+pub mod decode {
+    pub use crate::decoder::DataFile;
+}
 
+/// Deprecated alias for the decompression functions, which moved to [``raw``].
+#[deprecated(since = "0.1.0", note = "use `nexrad::raw` instead")]
+pub mod decompress {
+    pub use crate::decompressor::decompress_file;
+}
+
+/// Deprecated alias for file metadata, which moved to [``raw``] and [``high_level``].
+#[deprecated(
+    since = "0.1.0",
+    note = "use `nexrad::raw` or `nexrad::high_level` instead"
+)]
+pub mod file_metadata {
+    pub use crate::metadata::{is_compressed, FileMetadata};
+}
+
+/// Deprecated alias for the Archive II structs, which moved to [``raw``].
+#[deprecated(since = "0.1.0", note = "use `nexrad::raw` instead")]
+pub mod model {
+    pub use crate::records::{
+        DataBlockHeader, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+        Message31Header, MessageHeader, Product, RadialData, VolumeData, VolumeHeaderRecord,
+    };
+}
+
+/// Deprecated alias for the download functions, which moved to [``high_level``].
 #[cfg(feature = "download")]
-pub mod download;
+#[cfg_attr(docsrs, doc(cfg(feature = "download")))]
+#[deprecated(since = "0.1.0", note = "use `nexrad::high_level` instead")]
+pub mod download {
+    pub use crate::downloader::{download_file, list_files};
+}
+
+#[cfg(feature = "download")]
+mod downloader;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
 
 #[cfg(test)]
 mod test;
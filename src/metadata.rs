@@ -0,0 +1,76 @@
+//!
+//! Struct definitions and utilities for NEXRAD Level II data files.
+//!
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Metadata describing a NEXRAD WSR-88D radar data file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetadata {
+    site: String,
+    date: NaiveDate,
+    identifier: String,
+}
+
+impl FileMetadata {
+    /// Create new file metadata.
+    #[must_use]
+    pub fn new(site: String, date: NaiveDate, identifier: String) -> Self {
+        Self {
+            site,
+            date,
+            identifier,
+        }
+    }
+
+    /// The radar site this file was produced at, e.g. KDMX.
+    #[must_use]
+    pub fn site(&self) -> &String {
+        &self.site
+    }
+
+    /// The date this file's data was collected on.
+    #[must_use]
+    pub fn date(&self) -> &NaiveDate {
+        &self.date
+    }
+
+    /// The unique identifier for this file for its site on the date.
+    #[must_use]
+    pub fn identifier(&self) -> &String {
+        &self.identifier
+    }
+
+    /// The time this file's volume began, parsed from its identifier, e.g.
+    /// `KDMX20230406_000215_V06`. Returns `None` for metadata-only `_MDM` files and identifiers not
+    /// in that form.
+    #[must_use]
+    pub fn date_time(&self) -> Option<NaiveDateTime> {
+        if self.identifier.ends_with("_MDM") {
+            return None;
+        }
+
+        let time = self.identifier.get(self.site.len()..self.site.len() + 15)?;
+        NaiveDateTime::parse_from_str(time, "%Y%m%d_%H%M%S").ok()
+    }
+}
+
+/// Determines whether the provided NEXRAD data file is compressed.
+#[must_use]
+pub fn is_compressed(data: &[u8]) -> bool {
+    data.len() >= 30 && (is_bzip2_compressed(&data[28..]) || is_zlib_compressed(&data[28..]))
+}
+
+/// Determines whether the provided record data begins with a BZIP2 stream.
+#[must_use]
+pub fn is_bzip2_compressed(record: &[u8]) -> bool {
+    record.starts_with(b"BZh")
+}
+
+/// Determines whether the provided record data begins with a zlib stream header.
+#[must_use]
+pub fn is_zlib_compressed(record: &[u8]) -> bool {
+    record.len() >= 2
+        && record[0] & 0x0F == 8
+        && (u16::from(record[0]) << 8 | u16::from(record[1])) % 31 == 0
+}
@@ -0,0 +1,449 @@
+//!
+//! Provides [``read_odim_volume``] for importing `ODIM_H5` polar volumes, the format exchanged by
+//! European (OPERA) radar networks, into a [``DataFile``] so this crate's derived products and
+//! renderers can be used on them unchanged.
+//!
+//! This crate does not link an HDF5 library. Instead, files are accessed through the
+//! [``OdimSource``] trait, which can be implemented over any HDF5 binding, or populated into an
+//! [``OdimTree``] in memory.
+//!
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::records::{
+    to_archive_date_time, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+    Message31Header, RadialData, VolumeData, VolumeHeaderRecord,
+};
+use crate::sweep::Sweep;
+
+/// The value of an ODIM attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdimAttribute {
+    /// An integer attribute, e.g. `nbins`.
+    Integer(i64),
+    /// A real attribute, e.g. `elangle`.
+    Real(f64),
+    /// A string attribute, e.g. `quantity`.
+    Text(String),
+    /// An array of reals, e.g. the `startazA` ray start azimuths.
+    RealArray(Vec<f64>),
+}
+
+impl OdimAttribute {
+    /// The attribute as a real number, if numeric.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            Self::Integer(value) => Some(*value as f64),
+            Self::Real(value) => Some(*value),
+            Self::Text(_) | Self::RealArray(_) => None,
+        }
+    }
+
+    /// The attribute as text, if a string.
+    #[must_use]
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(value) => Some(value),
+            Self::Integer(_) | Self::Real(_) | Self::RealArray(_) => None,
+        }
+    }
+}
+
+/// Access to the groups of an `ODIM_H5` file, e.g. backed by an HDF5 library.
+pub trait OdimSource {
+    /// The named attribute of a group, e.g. group `dataset1/where` and name `elangle`, or `None`
+    /// if absent. The root group is the empty string.
+    ///
+    /// # Errors
+    /// Returns an error if the attribute exists but cannot be read.
+    fn attribute(&self, group: &str, name: &str) -> Result<Option<OdimAttribute>>;
+
+    /// The raw values of a two-dimensional dataset, e.g. `dataset1/data1/data`, with one row per
+    /// ray and one column per bin, or `None` if absent.
+    ///
+    /// # Errors
+    /// Returns an error if the dataset exists but cannot be read.
+    fn dataset(&self, path: &str) -> Result<Option<Grid<u16>>>;
+}
+
+/// An `ODIM_H5` file's attributes and datasets held in memory.
+#[derive(Debug, Clone, Default)]
+pub struct OdimTree {
+    attributes: BTreeMap<(String, String), OdimAttribute>,
+    datasets: BTreeMap<String, Grid<u16>>,
+}
+
+impl OdimTree {
+    /// Create a new empty tree.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the named attribute of a group.
+    pub fn set_attribute(&mut self, group: &str, name: &str, value: OdimAttribute) {
+        self.attributes
+            .insert((group.to_string(), name.to_string()), value);
+    }
+
+    /// Sets the dataset at a path.
+    pub fn set_dataset(&mut self, path: &str, values: Grid<u16>) {
+        self.datasets.insert(path.to_string(), values);
+    }
+}
+
+impl OdimSource for OdimTree {
+    fn attribute(&self, group: &str, name: &str) -> Result<Option<OdimAttribute>> {
+        Ok(self
+            .attributes
+            .get(&(group.to_string(), name.to_string()))
+            .cloned())
+    }
+
+    fn dataset(&self, path: &str) -> Result<Option<Grid<u16>>> {
+        Ok(self.datasets.get(path).cloned())
+    }
+}
+
+/// Imports an `ODIM_H5` polar volume (`PVOL`) or single scan (`SCAN`). Each `datasetN` group
+/// becomes a sweep with elevation number N, and each of its `dataM` groups with a quantity this
+/// crate models becomes a moment: `DBZH`/`TH` reflectivity, `VRADH` velocity, `WRADH` spectrum
+/// width, `ZDR`, `PHIDP`, and `RHOHV`. Other quantities are skipped. Ray azimuths are taken from
+/// `startazA`/`stopazA` if present, otherwise rays are assumed evenly spaced from north.
+///
+/// Values keep their full precision: raw values are stored as 16-bit words scaled by each
+/// quantity's `gain` and `offset`, with `nodata` and `undetect` values mapped to below threshold.
+/// Since words 0 and 1 are reserved, raw values up to 65533 are representable.
+///
+/// # Errors
+/// Returns an error if the object is not a polar volume or scan, a required attribute is missing
+/// or out of range, a dataset's dimensions disagree with its attributes, or a dataset holds a raw
+/// value above 65533 other than its `nodata` or `undetect` value.
+#[allow(clippy::cast_possible_truncation)]
+pub fn read_odim_volume<S: OdimSource + ?Sized>(source: &S) -> Result<DataFile> {
+    let object = text(source, "what", "object")?;
+    if object != "PVOL" && object != "SCAN" {
+        return Err(Error::UnsupportedOdimObject(object).into());
+    }
+
+    let radar_id = radar_id(&text(source, "what", "source")?);
+    let volume_time = date_time(source, "what", "date", "time")?;
+    let height = i16::try_from(real(source, "where", "height")?.round() as i64)
+        .map_err(|_| Error::MissingOdimAttribute("where/height".to_string()))?;
+    let volume_data = VolumeData::new(
+        real(source, "where", "lat")? as f32,
+        real(source, "where", "lon")? as f32,
+        height,
+        0,
+        0,
+    );
+
+    let mut sweeps = Vec::new();
+    for dataset in 1.. {
+        let group = format!("dataset{dataset}");
+        if source
+            .attribute(&format!("{group}/where"), "elangle")?
+            .is_none()
+        {
+            break;
+        }
+
+        let elevation_number = u8::try_from(dataset)?;
+        sweeps.push(read_sweep(
+            source,
+            &group,
+            elevation_number,
+            radar_id,
+            &volume_data,
+        )?);
+    }
+
+    let (date, millis) = to_archive_date_time(volume_time)
+        .ok_or_else(|| Error::MissingOdimAttribute("what/date".to_string()))?;
+    let header = VolumeHeaderRecord::new(*b"ODIM_H5.PVOL", date.into(), millis, radar_id);
+
+    DataFile::from_parts(header, sweeps)
+}
+
+/// Reads a `datasetN` group as a sweep.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn read_sweep<S: OdimSource + ?Sized>(
+    source: &S,
+    group: &str,
+    elevation_number: u8,
+    radar_id: [u8; 4],
+    volume_data: &VolumeData,
+) -> Result<Sweep> {
+    let location = format!("{group}/where");
+    let elevation = real(source, &location, "elangle")? as f32;
+    let rays = integer(source, &location, "nrays")?;
+    let bins = u16::try_from(integer(source, &location, "nbins")?)?;
+    let first_ray = integer(source, &location, "a1gate").unwrap_or(0);
+    let range_start = real(source, &location, "rstart").unwrap_or(0.0) * 1000.0;
+    let range_scale = real(source, &location, "rscale")?;
+
+    let start = date_time(source, &format!("{group}/what"), "startdate", "starttime")?;
+    let end = date_time(source, &format!("{group}/what"), "enddate", "endtime").unwrap_or(start);
+
+    let how = format!("{group}/how");
+    let azimuths = ray_azimuths(source, &how, rays)?;
+    let nyquist_velocity = source
+        .attribute(&how, "NI")?
+        .and_then(|value| value.as_real())
+        .unwrap_or(0.0);
+
+    let mut moments = Vec::new();
+    for data in 1.. {
+        let data_group = format!("{group}/data{data}");
+        let Some(quantity) = source.attribute(&format!("{data_group}/what"), "quantity")? else {
+            break;
+        };
+        let Some(product) = quantity.as_text().and_then(quantity_product) else {
+            continue;
+        };
+
+        let values = source
+            .dataset(&format!("{data_group}/data"))?
+            .ok_or_else(|| Error::MissingOdimAttribute(format!("{data_group}/data")))?;
+        if values.rows() != rays || values.columns() != usize::from(bins) {
+            return Err(Error::GridMismatch.into());
+        }
+
+        let scaling = read_moment_scaling(source, &data_group)?;
+        check_representable(&data_group, &values, scaling)?;
+        moments.push((product, scaling, values));
+    }
+
+    let ray_duration = (end - start) / i32::try_from(rays.max(1))?;
+    let azimuth_resolution = if rays > 360 { 1 } else { 2 };
+
+    let mut radials = Vec::with_capacity(rays);
+    for index in 0..rays {
+        // Rays are stored by azimuth, but collection began at the a1gate ray
+        let ray = (first_ray + index) % rays;
+        let (ray_date, ray_millis) =
+            to_archive_date_time(start + ray_duration * i32::try_from(index)?)
+                .ok_or_else(|| Error::MissingOdimAttribute(format!("{group}/what/startdate")))?;
+
+        let mut radial = Message31::new(Message31Header::new(
+            radar_id,
+            ray_millis,
+            ray_date,
+            u16::try_from(index + 1)?,
+            azimuths[ray],
+            azimuth_resolution,
+            radial_status(index, rays),
+            elevation_number,
+            elevation,
+        ));
+
+        radial.set_volume_data(volume_data.clone());
+        radial.set_elevation_data(ElevationData::new([0, 0], 0.0));
+        radial.set_radial_data(RadialData::new(0, (nyquist_velocity * 100.0) as u16));
+
+        for (product, (gain, offset, nodata, undetect), values) in &moments {
+            let data = GenericData::new(
+                product,
+                bins,
+                (range_start + range_scale / 2.0) as u16,
+                range_scale as u16,
+                16,
+                (1.0 / gain) as f32,
+                (2.0 - offset / gain) as f32,
+            );
+
+            // Shift raw values past the reserved below threshold and range folded words
+            let mut words = Vec::with_capacity(usize::from(bins) * 2);
+            for bin in 0..usize::from(bins) {
+                let raw = values.get(bin, ray).copied().unwrap_or_default();
+                let word = if Some(raw) == *nodata || Some(raw) == *undetect {
+                    0
+                } else {
+                    raw + 2
+                };
+                words.extend_from_slice(&word.to_be_bytes());
+            }
+
+            radial.set_data_moment(DataMoment::new(product.clone(), data, words));
+        }
+
+        radials.push(radial);
+    }
+
+    Ok(Sweep::new(elevation_number, radials))
+}
+
+/// Each ray's azimuth in degrees, from the midpoint of its start and stop azimuths if the sweep
+/// has them, otherwise spaced evenly around the sweep.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn ray_azimuths<S: OdimSource + ?Sized>(source: &S, how: &str, rays: usize) -> Result<Vec<f32>> {
+    Ok(
+        match (
+            source.attribute(how, "startazA")?,
+            source.attribute(how, "stopazA")?,
+        ) {
+            (Some(OdimAttribute::RealArray(starts)), Some(OdimAttribute::RealArray(stops)))
+                if starts.len() == rays && stops.len() == rays =>
+            {
+                starts
+                    .iter()
+                    .zip(stops)
+                    .map(|(start, stop)| {
+                        // Rays crossing north stop at a smaller azimuth than they start
+                        let stop = if stop < *start { stop + 360.0 } else { stop };
+                        (f64::midpoint(*start, stop) % 360.0) as f32
+                    })
+                    .collect()
+            }
+            _ => (0..rays)
+                .map(|ray| ((ray as f64 + 0.5) * 360.0 / rays as f64) as f32)
+                .collect(),
+        },
+    )
+}
+
+/// Checks that a data group's raw values fit in a word after the reserved below threshold and
+/// range folded words, other than its `nodata` and `undetect` values which map to below threshold.
+fn check_representable(
+    data_group: &str,
+    values: &Grid<u16>,
+    (_, _, nodata, undetect): (f64, f64, Option<u16>, Option<u16>),
+) -> Result<()> {
+    let unrepresentable = values
+        .values()
+        .iter()
+        .copied()
+        .find(|raw| *raw > u16::MAX - 2 && Some(*raw) != nodata && Some(*raw) != undetect);
+
+    match unrepresentable {
+        Some(raw) => Err(Error::UnrepresentableOdimValue(format!("{data_group}/data"), raw).into()),
+        None => Ok(()),
+    }
+}
+
+/// A data group's gain, offset, and raw `nodata` and `undetect` values.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn read_moment_scaling<S: OdimSource + ?Sized>(
+    source: &S,
+    data_group: &str,
+) -> Result<(f64, f64, Option<u16>, Option<u16>)> {
+    let what = format!("{data_group}/what");
+
+    let gain = real(source, &what, "gain")?;
+    if gain <= 0.0 {
+        return Err(Error::MissingOdimAttribute(format!("{what}/gain")).into());
+    }
+
+    let special = |name: &str| -> Result<Option<u16>> {
+        Ok(source
+            .attribute(&what, name)?
+            .and_then(|value| value.as_real())
+            .filter(|value| (0.0..=f64::from(u16::MAX)).contains(value))
+            .map(|value| value as u16))
+    };
+
+    Ok((
+        gain,
+        real(source, &what, "offset")?,
+        special("nodata")?,
+        special("undetect")?,
+    ))
+}
+
+/// The product for an ODIM quantity, if modeled by this crate.
+fn quantity_product(quantity: &str) -> Option<DataBlockProduct> {
+    match quantity {
+        "DBZH" | "DBZ" | "TH" => Some(DataBlockProduct::Reflectivity),
+        "VRADH" | "VRAD" => Some(DataBlockProduct::Velocity),
+        "WRADH" | "WRAD" => Some(DataBlockProduct::SpectrumWidth),
+        "ZDR" => Some(DataBlockProduct::DifferentialReflectivity),
+        "PHIDP" => Some(DataBlockProduct::DifferentialPhase),
+        "RHOHV" => Some(DataBlockProduct::CorrelationCoefficient),
+        _ => None,
+    }
+}
+
+/// The radial status of a ray: 0 for the first in a sweep, 2 for the last, and 1 otherwise.
+fn radial_status(index: usize, rays: usize) -> u8 {
+    match index {
+        0 => 0,
+        _ if index + 1 == rays => 2,
+        _ => 1,
+    }
+}
+
+/// A four-character radar identifier from an ODIM `source` attribute, preferring the OPERA node
+/// (`NOD`) or radar (`RAD`) identifiers.
+fn radar_id(source: &str) -> [u8; 4] {
+    let fields: BTreeMap<&str, &str> = source
+        .split(',')
+        .filter_map(|field| field.split_once(':'))
+        .collect();
+
+    let identifier = fields
+        .get("NOD")
+        .or_else(|| fields.get("RAD"))
+        .or_else(|| fields.values().next())
+        .copied()
+        .unwrap_or_default();
+
+    let mut id = *b"    ";
+    for (target, character) in id.iter_mut().zip(identifier.bytes()) {
+        *target = character.to_ascii_uppercase();
+    }
+
+    id
+}
+
+/// A date and time from `YYYYMMDD` and `HHMMSS` attributes.
+fn date_time<S: OdimSource + ?Sized>(
+    source: &S,
+    group: &str,
+    date: &str,
+    time: &str,
+) -> Result<NaiveDateTime> {
+    let invalid = || Error::MissingOdimAttribute(format!("{group}/{date}"));
+
+    let date =
+        NaiveDate::parse_from_str(&text(source, group, date)?, "%Y%m%d").map_err(|_| invalid())?;
+    let time =
+        NaiveTime::parse_from_str(&text(source, group, time)?, "%H%M%S").map_err(|_| invalid())?;
+
+    Ok(date.and_time(time))
+}
+
+/// A required string attribute.
+fn text<S: OdimSource + ?Sized>(source: &S, group: &str, name: &str) -> Result<String> {
+    source
+        .attribute(group, name)?
+        .and_then(|value| value.as_text().map(str::to_string))
+        .ok_or_else(|| Error::MissingOdimAttribute(format!("{group}/{name}")).into())
+}
+
+/// A required numeric attribute.
+fn real<S: OdimSource + ?Sized>(source: &S, group: &str, name: &str) -> Result<f64> {
+    source
+        .attribute(group, name)?
+        .and_then(|value| value.as_real())
+        .ok_or_else(|| Error::MissingOdimAttribute(format!("{group}/{name}")).into())
+}
+
+/// A required non-negative integer attribute.
+fn integer<S: OdimSource + ?Sized>(source: &S, group: &str, name: &str) -> Result<usize> {
+    match source.attribute(group, name)? {
+        Some(OdimAttribute::Integer(value)) => Ok(usize::try_from(value)?),
+        _ => Err(Error::MissingOdimAttribute(format!("{group}/{name}")).into()),
+    }
+}
@@ -0,0 +1,469 @@
+//!
+//! Provides [``partition_sweep``] for classifying a sweep's precipitation as convective or
+//! stratiform from the texture and peakedness of its reflectivity along each radial, and
+//! [``partition_grid``] for the Steiner et al. (1995) classification of gridded low-level
+//! reflectivity, e.g. to select the Z-R relation each gate's rain rate is estimated with, and
+//! [``identify_cells``] for the convective cells of that classification.
+//!
+
+use crate::grid::{Grid, GridSpec};
+use crate::records::{DataMoment, Message31};
+
+/// The kind of precipitation at a location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RainType {
+    /// Widespread precipitation with weak vertical motion, such as the trailing region of a
+    /// squall line.
+    Stratiform,
+    /// Precipitation from cells with strong updrafts, concentrated and intense.
+    Convective,
+}
+
+impl RainType {
+    /// The type's code in categorical exports: 1 for stratiform and 2 for convective, leaving 0
+    /// for locations without precipitation.
+    #[must_use]
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Stratiform => 1,
+            Self::Convective => 2,
+        }
+    }
+
+    /// The codes of a classification's cells in row-major order, 0 for cells without
+    /// precipitation.
+    #[must_use]
+    pub fn codes(rain_types: &Grid<Option<Self>>) -> Vec<u8> {
+        rain_types
+            .values()
+            .iter()
+            .map(|rain_type| rain_type.map_or(0, Self::code))
+            .collect()
+    }
+}
+
+/// Options controlling how gates are classified.
+#[derive(Debug, Clone)]
+pub struct PartitionOptions {
+    window: f32,
+    min_reflectivity: f32,
+    convective_reflectivity: f32,
+    min_peakedness: f32,
+    min_texture: f32,
+}
+
+impl PartitionOptions {
+    /// Create the default options: precipitation of at least 10 dBZ, convective if at least 40
+    /// dBZ or both at least 4 dB above the background within 11 km along the radial and varying by
+    /// at least 3 dB from gate to gate.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The length in meters of the window along each radial, centered on a gate, over which its
+    /// background reflectivity and texture are computed.
+    ///
+    /// # Panics
+    /// Panics if the window is not positive.
+    #[must_use]
+    pub fn with_window(mut self, window: f32) -> Self {
+        assert!(window > 0.0, "window must be positive");
+        self.window = window;
+        self
+    }
+
+    /// The least reflectivity in dBZ considered precipitation. Weaker gates are unclassified.
+    #[must_use]
+    pub fn with_min_reflectivity(mut self, min_reflectivity: f32) -> Self {
+        self.min_reflectivity = min_reflectivity;
+        self
+    }
+
+    /// The reflectivity in dBZ at and above which a gate is convective regardless of its
+    /// surroundings.
+    #[must_use]
+    pub fn with_convective_reflectivity(mut self, convective_reflectivity: f32) -> Self {
+        self.convective_reflectivity = convective_reflectivity;
+        self
+    }
+
+    /// The least peakedness, the reflectivity in dB above the background's mean, and the least
+    /// texture, the root mean square difference in dB between adjacent gates within the window,
+    /// of a convective gate weaker than the convective reflectivity.
+    #[must_use]
+    pub fn with_min_peakedness_and_texture(mut self, peakedness: f32, texture: f32) -> Self {
+        self.min_peakedness = peakedness;
+        self.min_texture = texture;
+        self
+    }
+}
+
+impl Default for PartitionOptions {
+    fn default() -> Self {
+        Self {
+            window: 11_000.0,
+            min_reflectivity: 10.0,
+            convective_reflectivity: 40.0,
+            min_peakedness: 4.0,
+            min_texture: 3.0,
+        }
+    }
+}
+
+/// Classifies each of a sweep's reflectivity gates as convective or stratiform, with a row for
+/// each radial and a column for each gate. Convective cells stand out from their background and
+/// vary sharply from gate to gate, while stratiform precipitation is smooth. Gates weaker than the
+/// least precipitation, without a value, or beyond a radial's gates are `None`.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn partition_sweep(
+    radials: &[Message31],
+    options: &PartitionOptions,
+) -> Grid<Option<RainType>> {
+    let columns = radials
+        .iter()
+        .filter_map(Message31::reflectivity_data)
+        .map(DataMoment::gate_count)
+        .max()
+        .unwrap_or(0);
+
+    let mut values = Vec::with_capacity(radials.len() * columns);
+    for radial in radials {
+        let Some(moment) = radial.reflectivity_data() else {
+            values.extend(std::iter::repeat_n(None, columns));
+            continue;
+        };
+
+        let reflectivity: Vec<Option<f32>> = (0..moment.gate_count())
+            .map(|index| moment.value(index).and_then(|value| value.value()))
+            .collect();
+        let interval = f32::from(moment.data().data_moment_range_sample_interval());
+        let half_window = if interval > 0.0 {
+            (options.window / interval / 2.0).round() as usize
+        } else {
+            0
+        };
+
+        values.extend((0..columns).map(|index| {
+            let value = reflectivity.get(index).copied().flatten()?;
+            if value < options.min_reflectivity {
+                return None;
+            }
+            if value >= options.convective_reflectivity {
+                return Some(RainType::Convective);
+            }
+
+            let window = &reflectivity[index.saturating_sub(half_window)
+                ..(index + half_window + 1).min(reflectivity.len())];
+            let is_convective = peakedness(value, window) >= options.min_peakedness
+                && texture(window).is_some_and(|texture| texture >= options.min_texture);
+
+            Some(if is_convective {
+                RainType::Convective
+            } else {
+                RainType::Stratiform
+            })
+        }));
+    }
+
+    Grid::new(columns, radials.len(), values)
+}
+
+/// The reflectivity in dB above the mean of a window's reflectivity, averaged as linear
+/// reflectivity factor. Gates without a value contribute none.
+#[allow(clippy::cast_precision_loss)]
+fn peakedness(value: f32, window: &[Option<f32>]) -> f32 {
+    let (sum, count) = window
+        .iter()
+        .flatten()
+        .fold((0.0, 0), |(sum, count), value| {
+            (sum + 10f32.powf(value / 10.0), count + 1)
+        });
+
+    value - 10.0 * (sum / count as f32).log10()
+}
+
+/// The root mean square difference in dB between a window's adjacent gates with values, or `None`
+/// if no adjacent gates both have values.
+#[allow(clippy::cast_precision_loss)]
+fn texture(window: &[Option<f32>]) -> Option<f32> {
+    let (sum, count) = window
+        .windows(2)
+        .filter_map(|pair| Some((pair[1]? - pair[0]?).powi(2)))
+        .fold((0.0, 0), |(sum, count), square| (sum + square, count + 1));
+
+    (count > 0).then(|| (sum / count as f32).sqrt())
+}
+
+/// Options controlling the Steiner classification of gridded reflectivity.
+#[derive(Debug, Clone)]
+pub struct SteinerOptions {
+    background_radius: f32,
+    min_reflectivity: f32,
+    convective_reflectivity: f32,
+}
+
+impl SteinerOptions {
+    /// Create the default options: precipitation of at least 10 dBZ, a background within 11 km,
+    /// and convective centers of at least 40 dBZ.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The radius in meters of the circle around each cell over which its background reflectivity
+    /// is averaged.
+    ///
+    /// # Panics
+    /// Panics if the radius is not positive.
+    #[must_use]
+    pub fn with_background_radius(mut self, background_radius: f32) -> Self {
+        assert!(
+            background_radius > 0.0,
+            "background radius must be positive"
+        );
+        self.background_radius = background_radius;
+        self
+    }
+
+    /// The least reflectivity in dBZ considered precipitation. Weaker cells are unclassified and
+    /// excluded from backgrounds.
+    #[must_use]
+    pub fn with_min_reflectivity(mut self, min_reflectivity: f32) -> Self {
+        self.min_reflectivity = min_reflectivity;
+        self
+    }
+
+    /// The reflectivity in dBZ at and above which a cell is a convective center regardless of its
+    /// background.
+    #[must_use]
+    pub fn with_convective_reflectivity(mut self, convective_reflectivity: f32) -> Self {
+        self.convective_reflectivity = convective_reflectivity;
+        self
+    }
+}
+
+impl Default for SteinerOptions {
+    fn default() -> Self {
+        Self {
+            background_radius: 11_000.0,
+            min_reflectivity: 10.0,
+            convective_reflectivity: 40.0,
+        }
+    }
+}
+
+/// Classifies each cell of gridded low-level reflectivity in dBZ, with the specified cell size in
+/// meters, as convective or stratiform following Steiner et al. (1995). A cell is a convective
+/// center if it reaches the convective reflectivity or exceeds its background, the mean
+/// reflectivity factor within the background radius, by a margin decreasing from 10 dB for weak
+/// backgrounds to none above 42.4 dBZ. Cells within a radius of each center growing from 1 to 5 km
+/// with its background are convective too, and the remaining precipitation is stratiform. Cells
+/// weaker than the least precipitation or without a value are `None`.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn partition_grid(
+    reflectivity: &Grid<Option<f32>>,
+    cell_size: f32,
+    options: &SteinerOptions,
+) -> Grid<Option<RainType>> {
+    let (columns, rows) = (reflectivity.columns(), reflectivity.rows());
+    let echo = |column: usize, row: usize| {
+        reflectivity
+            .get(column, row)
+            .copied()
+            .flatten()
+            .filter(|value| *value >= options.min_reflectivity)
+    };
+
+    let mut rain_types = reflectivity.map(|value| {
+        value
+            .filter(|value| *value >= options.min_reflectivity)
+            .map(|_| RainType::Stratiform)
+    });
+    if cell_size <= 0.0 {
+        return rain_types;
+    }
+
+    let background_cells = (options.background_radius / cell_size).floor() as usize;
+    for row in 0..rows {
+        for column in 0..columns {
+            let Some(value) = echo(column, row) else {
+                continue;
+            };
+
+            let (mut sum, mut count) = (0.0, 0.0);
+            for (neighbor_column, neighbor_row) in
+                within(column, row, background_cells, columns, rows)
+            {
+                if let Some(neighbor) = echo(neighbor_column, neighbor_row) {
+                    sum += 10f32.powf(neighbor / 10.0);
+                    count += 1.0;
+                }
+            }
+            let background = 10.0 * (sum / count).log10();
+
+            let is_center = value >= options.convective_reflectivity
+                || value - background > convective_margin(background);
+            if !is_center {
+                continue;
+            }
+
+            let radius_cells = (convective_radius(background) / cell_size).floor() as usize;
+            for (neighbor_column, neighbor_row) in within(column, row, radius_cells, columns, rows)
+            {
+                if echo(neighbor_column, neighbor_row).is_some() {
+                    if let Some(cell) = rain_types.get_mut(neighbor_column, neighbor_row) {
+                        *cell = Some(RainType::Convective);
+                    }
+                }
+            }
+        }
+    }
+
+    rain_types
+}
+
+/// A convective cell: a connected region of convective grid cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvectiveCell {
+    centroid: (f32, f32),
+    area: f32,
+    max_reflectivity: f32,
+    grid_cells: usize,
+}
+
+impl ConvectiveCell {
+    /// The mean location of the cell's grid cells in meters east and north of the radar.
+    #[must_use]
+    pub fn centroid(&self) -> (f32, f32) {
+        self.centroid
+    }
+
+    /// The cell's area in square meters.
+    #[must_use]
+    pub fn area_m2(&self) -> f32 {
+        self.area
+    }
+
+    /// The cell's greatest reflectivity in dBZ.
+    #[must_use]
+    pub fn max_reflectivity(&self) -> f32 {
+        self.max_reflectivity
+    }
+
+    /// The number of grid cells making up the cell.
+    #[must_use]
+    pub fn grid_cells(&self) -> usize {
+        self.grid_cells
+    }
+}
+
+/// Identifies the convective cells of gridded low-level reflectivity in dBZ laid out as
+/// specified, the regions of grid cells [``partition_grid``] classifies as convective which
+/// touch along an edge or at a corner. Cells are ordered from the most to the least intense.
+/// Reflectivity of other dimensions than the layout's has no cells.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn identify_cells(
+    reflectivity: &Grid<Option<f32>>,
+    spec: &GridSpec,
+    options: &SteinerOptions,
+) -> Vec<ConvectiveCell> {
+    let (columns, rows) = (spec.columns(), spec.rows());
+    if reflectivity.columns() != columns || reflectivity.rows() != rows {
+        return Vec::new();
+    }
+
+    let rain_types = partition_grid(reflectivity, spec.cell_size_m(), options);
+    let convective = |column: usize, row: usize| {
+        rain_types.get(column, row) == Some(&Some(RainType::Convective))
+    };
+
+    let mut visited = vec![false; columns * rows];
+    let mut cells = Vec::new();
+    for start in 0..columns * rows {
+        if visited[start] || !convective(start % columns, start / columns) {
+            continue;
+        }
+
+        visited[start] = true;
+        let mut pending = vec![start];
+        let (mut sum_x, mut sum_y, mut count) = (0.0, 0.0, 0);
+        let mut max_reflectivity = f32::MIN;
+        while let Some(index) = pending.pop() {
+            let (column, row) = (index % columns, index / columns);
+            let (x, y) = spec.cell_center(column, row);
+            sum_x += x;
+            sum_y += y;
+            count += 1;
+            if let Some(Some(value)) = reflectivity.get(column, row) {
+                max_reflectivity = max_reflectivity.max(*value);
+            }
+
+            for neighbor_row in row.saturating_sub(1)..(row + 2).min(rows) {
+                for neighbor_column in column.saturating_sub(1)..(column + 2).min(columns) {
+                    let neighbor = neighbor_row * columns + neighbor_column;
+                    if !visited[neighbor] && convective(neighbor_column, neighbor_row) {
+                        visited[neighbor] = true;
+                        pending.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        cells.push(ConvectiveCell {
+            centroid: (sum_x / count as f32, sum_y / count as f32),
+            area: count as f32 * spec.cell_size_m() * spec.cell_size_m(),
+            max_reflectivity,
+            grid_cells: count,
+        });
+    }
+
+    cells.sort_by(|a, b| b.max_reflectivity.total_cmp(&a.max_reflectivity));
+    cells
+}
+
+/// The cells of a grid within a radius in cells of a cell, including the cell itself.
+fn within(
+    column: usize,
+    row: usize,
+    radius: usize,
+    columns: usize,
+    rows: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let column_range = column.saturating_sub(radius)..(column + radius + 1).min(columns);
+    let row_range = row.saturating_sub(radius)..(row + radius + 1).min(rows);
+
+    row_range
+        .flat_map(move |other_row| column_range.clone().map(move |other| (other, other_row)))
+        .filter(move |(other_column, other_row)| {
+            let (dx, dy) = (column.abs_diff(*other_column), row.abs_diff(*other_row));
+            dx * dx + dy * dy <= radius * radius
+        })
+}
+
+/// The margin in dB by which a cell must exceed its background reflectivity in dBZ to be a
+/// convective center.
+fn convective_margin(background: f32) -> f32 {
+    if background < 0.0 {
+        10.0
+    } else if background < 42.43 {
+        10.0 - background * background / 180.0
+    } else {
+        0.0
+    }
+}
+
+/// The radius in meters around a convective center with a background reflectivity in dBZ within
+/// which precipitation is also convective.
+fn convective_radius(background: f32) -> f32 {
+    match background {
+        background if background < 20.0 => 1000.0,
+        background if background < 25.0 => 2000.0,
+        background if background < 30.0 => 3000.0,
+        background if background < 35.0 => 4000.0,
+        _ => 5000.0,
+    }
+}
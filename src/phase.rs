@@ -0,0 +1,185 @@
+//!
+//! Provides [``ProcessedPhase``] for producing differential phase (PHIDP) with the radar's system
+//! phase offset removed and the specific differential phase (KDP) derived from it.
+//!
+
+use crate::gate::GateIterator;
+use crate::records::{Message31, Product};
+
+/// Default number of gates over which KDP is estimated, about 2.25 km at 250 m gate spacing.
+const DEFAULT_KDP_WINDOW: usize = 9;
+
+/// Options controlling how differential phase is processed.
+#[derive(Debug, Clone)]
+pub struct PhaseOptions {
+    system_phase_offset: Option<f32>,
+    kdp_window: usize,
+}
+
+impl PhaseOptions {
+    /// Create the default phase processing options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the system phase offset in degrees removed from each gate's differential phase.
+    /// By default the offset is the volume's
+    /// [`crate::records::VolumeData::initial_system_differential_phase`].
+    #[must_use]
+    pub fn with_system_phase_offset(mut self, offset: f32) -> Self {
+        self.system_phase_offset = Some(offset);
+        self
+    }
+
+    /// The overriding system phase offset in degrees, if any.
+    #[must_use]
+    pub fn system_phase_offset(&self) -> Option<f32> {
+        self.system_phase_offset
+    }
+
+    /// The number of gates over which KDP is estimated. Longer windows are less noisy but smooth
+    /// out small features. Values below three gates are raised to three.
+    #[must_use]
+    pub fn with_kdp_window(mut self, gates: usize) -> Self {
+        self.kdp_window = gates.max(3);
+        self
+    }
+
+    /// The number of gates over which KDP is estimated.
+    #[must_use]
+    pub fn kdp_window(&self) -> usize {
+        self.kdp_window
+    }
+}
+
+impl Default for PhaseOptions {
+    fn default() -> Self {
+        Self {
+            system_phase_offset: None,
+            kdp_window: DEFAULT_KDP_WINDOW,
+        }
+    }
+}
+
+/// A radial's processed differential phase and specific differential phase, indexed by gate.
+/// Gates without a valid differential phase measurement are `None`.
+#[derive(Debug, Clone)]
+pub struct ProcessedPhase {
+    system_phase_offset: f32,
+    gate_ranges: Vec<f32>,
+    phidp: Vec<Option<f32>>,
+    kdp: Vec<Option<f32>>,
+}
+
+impl ProcessedPhase {
+    /// Processes a radial's differential phase. Returns `None` if the radial has no differential
+    /// phase moment, or if no offset override was specified and the radial has no volume data.
+    #[must_use]
+    pub fn from_radial(radial: &Message31, options: &PhaseOptions) -> Option<Self> {
+        let system_phase_offset = match options.system_phase_offset() {
+            Some(offset) => offset,
+            None => radial.volume_data()?.initial_system_differential_phase(),
+        };
+
+        let gates = GateIterator::from_radial(radial, Product::DifferentialPhase)?;
+        let mut gate_ranges = Vec::with_capacity(gates.len());
+        let mut phidp = Vec::with_capacity(gates.len());
+        for (range, _, _, value) in gates {
+            gate_ranges.push(range);
+            phidp.push(
+                value
+                    .value()
+                    .map(|phase| remove_offset(phase, system_phase_offset)),
+            );
+        }
+
+        let kdp = estimate_kdp(&gate_ranges, &phidp, options.kdp_window());
+
+        Some(Self {
+            system_phase_offset,
+            gate_ranges,
+            phidp,
+            kdp,
+        })
+    }
+
+    /// The system phase offset in degrees which was removed.
+    #[must_use]
+    pub fn system_phase_offset(&self) -> f32 {
+        self.system_phase_offset
+    }
+
+    /// The range to each gate's center in meters.
+    #[must_use]
+    pub fn gate_ranges(&self) -> &[f32] {
+        &self.gate_ranges
+    }
+
+    /// Each gate's differential phase in degrees, relative to the system phase offset.
+    #[must_use]
+    pub fn phidp(&self) -> &[Option<f32>] {
+        &self.phidp
+    }
+
+    /// Each gate's specific differential phase in degrees per kilometer.
+    #[must_use]
+    pub fn kdp(&self) -> &[Option<f32>] {
+        &self.kdp
+    }
+}
+
+/// Removes the system offset from a measured phase. Phases measured slightly below the offset due
+/// to noise would otherwise wrap to nearly 360 degrees, so differences below -180 degrees are
+/// unwrapped.
+fn remove_offset(phase: f32, offset: f32) -> f32 {
+    let corrected = phase - offset;
+    if corrected < -180.0 {
+        corrected + 360.0
+    } else {
+        corrected
+    }
+}
+
+/// Estimates KDP at each gate as half the least-squares slope of PHIDP over range within a window
+/// centered on the gate. Gates whose window is less than half populated are `None`.
+#[allow(clippy::cast_precision_loss)]
+fn estimate_kdp(ranges: &[f32], phidp: &[Option<f32>], window: usize) -> Vec<Option<f32>> {
+    let half_window = window / 2;
+
+    (0..phidp.len())
+        .map(|gate| {
+            phidp[gate]?;
+
+            let start = gate.saturating_sub(half_window);
+            let end = (gate + half_window + 1).min(phidp.len());
+
+            let samples: Vec<(f32, f32)> = (start..end)
+                .filter_map(|index| Some((ranges[index] / 1000.0, phidp[index]?)))
+                .collect();
+            if samples.len() * 2 < window {
+                return None;
+            }
+
+            let count = samples.len() as f32;
+            let mean_range = samples.iter().map(|(range, _)| range).sum::<f32>() / count;
+            let mean_phase = samples.iter().map(|(_, phase)| phase).sum::<f32>() / count;
+
+            let (covariance, variance) =
+                samples
+                    .iter()
+                    .fold((0.0, 0.0), |(covariance, variance), (range, phase)| {
+                        let range_delta = range - mean_range;
+                        (
+                            covariance + range_delta * (phase - mean_phase),
+                            variance + range_delta * range_delta,
+                        )
+                    });
+            if variance == 0.0 {
+                return None;
+            }
+
+            Some(covariance / variance / 2.0)
+        })
+        .collect()
+}
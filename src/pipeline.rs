@@ -0,0 +1,610 @@
+//!
+//! Provides [``Pipeline``] for processing volumes as described by a [``ProcessingConfig``]: which
+//! volumes are processed and which products are rendered, as a [``RenderManifest``], which quality
+//! control steps are applied, which products and fields are gridded, and which formats volumes are
+//! exported to. Configurations are deserializable with serde, so deployments may change behavior by
+//! editing a YAML or TOML file rather than recompiling; the `pipeline` example runs a configuration
+//! file over a directory of volumes.
+//!
+//! ```toml
+//! exports = ["archive2_compressed", { radial_wind_bufr = { options = { originating_centre = 7 } } }]
+//! rain_type = { columns = 400, rows = 400, cell_size = 1000.0 }
+//!
+//! [manifest]
+//! sites = ["KDMX"]
+//! times = ["2023-04-06T00:02:15"]
+//! products = ["reflectivity"]
+//! output_template = "products/{site}/{time}_{product}.png"
+//! options = { size = 1024, palette = "palettes/reflectivity.pal" }
+//!
+//! [[qc]]
+//! step = "censor"
+//! product = "reflectivity"
+//! by = "correlationcoefficient"
+//! min = 0.8
+//!
+//! [[qc]]
+//! step = "threshold"
+//! product = "reflectivity"
+//! min = 5.0
+//!
+//! [[grids]]
+//! product = "reflectivity"
+//! grid = { columns = 400, rows = 400, cell_size = 1000.0, layer = "composite" }
+//!
+//! [[fields]]
+//! name = "hail_mask"
+//! expression = "REF > 50 && RHO < 0.95"
+//! grid = { columns = 400, rows = 400, cell_size = 1000.0 }
+//! ```
+//!
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::batch::{for_each_volume, product_name, render_image, RenderManifest, VolumeLoader};
+use crate::bufr::{encode_radial_wind_bufr, BufrOptions};
+use crate::calibration::{estimate_reflectivity_bias, CalibrationOptions};
+use crate::cancel::CancellationToken;
+use crate::cfradial::encode_cfradial;
+use crate::composite::{grid_layer, VolumeLayer};
+use crate::csv::{encode_csv, CsvOptions};
+use crate::decoder::DataFile;
+use crate::encode::{encode_compressed_file, encode_file};
+use crate::error::Error;
+use crate::expression::Expression;
+use crate::gate::GateValue;
+use crate::grid::GridSpec;
+use crate::partition::{partition_grid, RainType, SteinerOptions};
+use crate::records::{DataMoment, Message31, Product};
+use crate::sweep::SweepCapabilities;
+use crate::uf::encode_uf;
+
+/// What a [``Pipeline``] does with each volume.
+///
+/// Every output is written to the manifest's output template with `{product}` replaced by the
+/// output's name and the template's extension replaced by the output's, e.g. `ref` and `png` for
+/// rendered reflectivity, `ref_grid` and `f32` for gridded reflectivity, or the format's name for
+/// exports.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessingConfig {
+    manifest: RenderManifest,
+    #[serde(default)]
+    qc: Vec<QcStep>,
+    #[serde(default)]
+    grids: Vec<ProductGridConfig>,
+    #[serde(default)]
+    fields: Vec<FieldConfig>,
+    #[serde(default)]
+    rain_type: Option<GridConfig>,
+    #[serde(default)]
+    exports: Vec<ExportFormat>,
+}
+
+impl ProcessingConfig {
+    /// The volumes processed, the products rendered from each and how, and where outputs are
+    /// written.
+    #[must_use]
+    pub fn manifest(&self) -> &RenderManifest {
+        &self.manifest
+    }
+
+    /// A token which, once cancelled, stops [``Pipeline::run_manifest``] before its next volume.
+    #[must_use]
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.manifest = self.manifest.with_cancellation_token(token);
+        self
+    }
+
+    /// The quality control steps applied to each volume, in order, before any output.
+    #[must_use]
+    pub fn qc(&self) -> &[QcStep] {
+        &self.qc
+    }
+
+    /// The products gridded from each volume.
+    #[must_use]
+    pub fn grids(&self) -> &[ProductGridConfig] {
+        &self.grids
+    }
+
+    /// The fields computed from expressions over each volume's moments.
+    #[must_use]
+    pub fn fields(&self) -> &[FieldConfig] {
+        &self.fields
+    }
+
+    /// How reflectivity is gridded to be classified as convective or stratiform, if it is. The
+    /// classification is written as a grid of bytes, 0 without precipitation, 1 for stratiform,
+    /// and 2 for convective, named `rain_type`.
+    #[must_use]
+    pub fn rain_type(&self) -> Option<&GridConfig> {
+        self.rain_type.as_ref()
+    }
+
+    /// The formats each volume is exported to.
+    #[must_use]
+    pub fn exports(&self) -> &[ExportFormat] {
+        &self.exports
+    }
+}
+
+/// A quality control step applied to a volume. Censored gates are marked below threshold.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case", deny_unknown_fields)]
+pub enum QcStep {
+    /// Copies the volume, elevation, and radial data blocks to every radial lacking them.
+    PropagateMetadata,
+    /// Censors a product's gates with values below a minimum or above a maximum.
+    Threshold {
+        product: Product,
+        #[serde(default)]
+        min: Option<f32>,
+        #[serde(default)]
+        max: Option<f32>,
+    },
+    /// Censors a product's gates where another product, at the nearest gate in range of the same
+    /// radial, is below a minimum or above a maximum, e.g. reflectivity where the correlation
+    /// coefficient is below 0.8 to remove non-meteorological echo. Gates where the other product
+    /// has no value are kept.
+    Censor {
+        product: Product,
+        by: Product,
+        #[serde(default)]
+        min: Option<f32>,
+        #[serde(default)]
+        max: Option<f32>,
+    },
+    /// Censors runs of fewer than `min_gates` consecutive gates with values along each radial,
+    /// removing isolated speckles of a product.
+    Despeckle { product: Product, min_gates: usize },
+    /// Censors every product's gates nearer than `min` or farther than `max` meters from the radar.
+    RangeLimit {
+        #[serde(default)]
+        min: Option<f32>,
+        #[serde(default)]
+        max: Option<f32>,
+    },
+    /// Adds an offset to each of a product's values, e.g. to correct a known calibration bias.
+    Offset { product: Product, offset: f32 },
+    /// Subtracts the reflectivity bias estimated by [``estimate_reflectivity_bias``] with the
+    /// default options. Volumes whose bias cannot be estimated are unchanged.
+    CalibrateReflectivity,
+}
+
+/// A product gridded from each volume.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProductGridConfig {
+    product: Product,
+    grid: GridConfig,
+}
+
+impl ProductGridConfig {
+    /// The product.
+    #[must_use]
+    pub fn product(&self) -> Product {
+        self.product
+    }
+
+    /// How the product is gridded.
+    #[must_use]
+    pub fn grid(&self) -> &GridConfig {
+        &self.grid
+    }
+}
+
+/// A field computed from an [``Expression``] over a volume's moments and written as a grid, in
+/// the same layout as gridded products.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldConfig {
+    name: String,
+    expression: Expression,
+    grid: GridConfig,
+}
+
+impl FieldConfig {
+    /// The field's name in output paths.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The expression the field is computed from.
+    #[must_use]
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// How the field is gridded.
+    #[must_use]
+    pub fn grid(&self) -> &GridConfig {
+        &self.grid
+    }
+}
+
+/// How a product is gridded. Grids are written as rows from north to south of little-endian
+/// 32-bit floats, with NaN for cells without a value.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GridConfig {
+    columns: usize,
+    rows: usize,
+    cell_size: f32,
+    #[serde(default = "default_layer")]
+    layer: VolumeLayer,
+}
+
+impl GridConfig {
+    /// The grid's dimensions and cell size.
+    #[must_use]
+    pub fn spec(&self) -> GridSpec {
+        GridSpec::new(self.columns, self.rows, self.cell_size)
+    }
+
+    /// Which part of the volume is gridded, by default its lowest tilt.
+    #[must_use]
+    pub fn layer(&self) -> VolumeLayer {
+        self.layer
+    }
+}
+
+fn default_layer() -> VolumeLayer {
+    VolumeLayer::LowestTilt
+}
+
+/// A format volumes are exported to. Formats with options are tables of their options, e.g.
+/// `{ csv = { options = { products = ["reflectivity", "velocity"] } } }`, whose unspecified
+/// options take their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExportFormat {
+    /// Uncompressed Archive II.
+    Archive2,
+    /// Archive II with bzip2-compressed records.
+    Archive2Compressed,
+    /// Universal Format.
+    Uf,
+    /// BUFR radial velocity superobservations.
+    RadialWindBufr {
+        #[serde(default)]
+        options: BufrOptions,
+    },
+    /// Long-format CSV of gates.
+    Csv {
+        #[serde(default)]
+        options: CsvOptions,
+    },
+    /// CF/Radial `netCDF`.
+    CfRadial,
+}
+
+impl ExportFormat {
+    /// The format's name in output paths.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Archive2 => "archive2",
+            Self::Archive2Compressed => "archive2_compressed",
+            Self::Uf => "uf",
+            Self::RadialWindBufr { .. } => "radial_wind_bufr",
+            Self::Csv { .. } => "csv",
+            Self::CfRadial => "cf_radial",
+        }
+    }
+
+    /// The extension of the format's files.
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Archive2 | Self::Archive2Compressed => "ar2v",
+            Self::Uf => "uf",
+            Self::RadialWindBufr { .. } => "bufr",
+            Self::Csv { .. } => "csv",
+            Self::CfRadial => "nc",
+        }
+    }
+
+    /// Encodes a volume in the format.
+    fn encode(&self, file: &DataFile) -> Result<Vec<u8>> {
+        match self {
+            Self::Archive2 => encode_file(file),
+            Self::Archive2Compressed => encode_compressed_file(file),
+            Self::Uf => encode_uf(file),
+            Self::RadialWindBufr { options } => encode_radial_wind_bufr(file, options),
+            Self::Csv { options } => encode_csv(file, options),
+            Self::CfRadial => encode_cfradial(file),
+        }
+    }
+}
+
+/// Processes volumes as described by a [``ProcessingConfig``].
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    config: ProcessingConfig,
+}
+
+impl Pipeline {
+    /// Create a pipeline for a configuration.
+    #[must_use]
+    pub fn new(config: ProcessingConfig) -> Self {
+        Self { config }
+    }
+
+    /// The pipeline's configuration.
+    #[must_use]
+    pub fn config(&self) -> &ProcessingConfig {
+        &self.config
+    }
+
+    /// Applies the configured quality control steps to a volume.
+    pub fn apply_qc(&self, file: &mut DataFile) {
+        for step in &self.config.qc {
+            match step {
+                QcStep::PropagateMetadata => file.propagate_metadata(),
+                QcStep::Threshold { product, min, max } => censor(file, *product, |_, moment| {
+                    gates_where(moment, |value| !within(value, *min, *max))
+                }),
+                QcStep::Censor {
+                    product,
+                    by,
+                    min,
+                    max,
+                } => censor(file, *product, |radial, moment| {
+                    let Some(other) = radial.get_data_moment(&(*by).into()) else {
+                        return Vec::new();
+                    };
+                    (0..moment.gate_count())
+                        .filter(|index| {
+                            value_at_range(other, moment.data().gate_range_m(*index))
+                                .is_some_and(|value| !within(value, *min, *max))
+                        })
+                        .collect()
+                }),
+                QcStep::Despeckle { product, min_gates } => {
+                    censor(file, *product, |_, moment| speckles(moment, *min_gates));
+                }
+                QcStep::RangeLimit { min, max } => {
+                    for product in Product::ALL {
+                        censor(file, product, |_, moment| {
+                            (0..moment.gate_count())
+                                .filter(|index| {
+                                    !within(moment.data().gate_range_m(*index), *min, *max)
+                                })
+                                .collect()
+                        });
+                    }
+                }
+                QcStep::Offset { product, offset } => offset_values(file, *product, *offset),
+                QcStep::CalibrateReflectivity => {
+                    if let Some(estimate) =
+                        estimate_reflectivity_bias(file, &CalibrationOptions::new())
+                    {
+                        offset_values(file, Product::Reflectivity, -estimate.bias());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Applies quality control to a volume and writes its configured products and exports,
+    /// returning the paths written. Products absent from the volume are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the volume has no valid start time, or an output cannot be generated or
+    /// written.
+    pub fn run(&self, mut file: DataFile) -> Result<Vec<PathBuf>> {
+        self.apply_qc(&mut file);
+
+        let time = file
+            .volume_header()
+            .date_time()
+            .ok_or(Error::MissingVolumeTime)?;
+        let site = String::from_utf8_lossy(file.volume_header().radar_id())
+            .trim()
+            .to_string();
+        let manifest = &self.config.manifest;
+        let output_path = |name: &str, extension: &str| {
+            manifest
+                .named_output_path(&site, time, name)
+                .with_extension(extension)
+        };
+        let has_product = |product: Product| {
+            file.elevation_scans()
+                .values()
+                .any(|radials| SweepCapabilities::from_radials(radials).has(product))
+        };
+
+        let mut written = Vec::new();
+        for product in manifest.products() {
+            if has_product(*product) {
+                let path = output_path(&product_name(*product), "png");
+                render_image(manifest, &file, *product, &path)?;
+                written.push(path);
+            }
+        }
+
+        for grid in &self.config.grids {
+            if has_product(grid.product) {
+                let data = grid_layer(&file, grid.product, grid.grid.layer, &grid.grid.spec());
+                written.push(write_output(
+                    &output_path(&format!("{}_grid", product_name(grid.product)), "f32"),
+                    &data.to_f32_le_bytes(),
+                )?);
+            }
+        }
+
+        for field in &self.config.fields {
+            let grid = field
+                .expression
+                .evaluate_grid(&file, field.grid.layer, &field.grid.spec());
+            written.push(write_output(
+                &output_path(&field.name, "f32"),
+                &grid.to_f32_le_bytes(),
+            )?);
+        }
+
+        if let Some(grid) = &self.config.rain_type {
+            let spec = grid.spec();
+            let reflectivity = grid_layer(&file, Product::Reflectivity, grid.layer, &spec);
+            let rain_types =
+                partition_grid(&reflectivity, spec.cell_size_m(), &SteinerOptions::new());
+            written.push(write_output(
+                &output_path("rain_type", "u8"),
+                &RainType::codes(&rain_types),
+            )?);
+        }
+
+        for format in &self.config.exports {
+            written.push(write_output(
+                &output_path(format.name(), format.extension()),
+                &format.encode(&file)?,
+            )?);
+        }
+
+        Ok(written)
+    }
+
+    /// Runs the pipeline over each of the manifest's sites and times on its threads, loading each
+    /// volume from the loader and returning the paths written. Volumes the loader cannot find are
+    /// skipped.
+    ///
+    /// # Errors
+    /// Returns the first error loading or processing a volume, or an error if the pipeline was
+    /// cancelled.
+    pub fn run_manifest<L: VolumeLoader + Sync + ?Sized>(
+        &self,
+        loader: &L,
+    ) -> Result<Vec<PathBuf>> {
+        let written = Mutex::new(Vec::new());
+        let failure = Mutex::new(None);
+
+        for_each_volume(&self.config.manifest, |site, time| {
+            let result = loader
+                .load(site, time)
+                .and_then(|file| file.map(|file| self.run(file)).transpose());
+            match result {
+                Ok(paths) => written
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .extend(paths.into_iter().flatten()),
+                Err(error) => {
+                    failure
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .get_or_insert(error);
+                }
+            }
+        })?;
+
+        if let Some(error) = failure.into_inner().unwrap_or_else(PoisonError::into_inner) {
+            return Err(error);
+        }
+
+        Ok(written.into_inner().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// Whether a value is within optional bounds.
+fn within(value: f32, min: Option<f32>, max: Option<f32>) -> bool {
+    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+/// The indices of a moment's gates whose values satisfy a predicate.
+fn gates_where(moment: &DataMoment, predicate: impl Fn(f32) -> bool) -> Vec<usize> {
+    moment
+        .values()
+        .enumerate()
+        .filter(|(_, value)| value.value().is_some_and(&predicate))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// The value of a moment's gate nearest to a range, if the range is within its gates.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn value_at_range(moment: &DataMoment, range: f32) -> Option<f32> {
+    let first = moment.data().gate_range_m(0);
+    let interval = f32::from(moment.data().data_moment_range_sample_interval());
+    let index = ((range - first) / interval).round();
+    if index.is_nan() || index < 0.0 {
+        return None;
+    }
+
+    moment.value(index as usize)?.value()
+}
+
+/// The indices of gates within runs of fewer than `min_gates` consecutive gates with values.
+fn speckles(moment: &DataMoment, min_gates: usize) -> Vec<usize> {
+    let mut speckles = Vec::new();
+    let mut run = Vec::new();
+    for (index, value) in moment.values().enumerate() {
+        if value.value().is_some() {
+            run.push(index);
+        } else {
+            if run.len() < min_gates {
+                speckles.append(&mut run);
+            }
+            run.clear();
+        }
+    }
+    if run.len() < min_gates {
+        speckles.append(&mut run);
+    }
+
+    speckles
+}
+
+/// Censors the gates of a product in each radial chosen by `gates`.
+fn censor<F: Fn(&Message31, &DataMoment) -> Vec<usize>>(
+    file: &mut DataFile,
+    product: Product,
+    gates: F,
+) {
+    for radial in file.elevation_scans_mut().values_mut().flatten() {
+        let Some(moment) = radial.get_data_moment(&product.into()) else {
+            continue;
+        };
+        let censored = gates(radial, moment);
+
+        if let Some(moment) = radial.data_moment_mut(&product.into()) {
+            for index in censored {
+                // The index is within the moment's gates, so this cannot fail
+                let _ = moment.set_value(index, GateValue::BelowThreshold);
+            }
+        }
+    }
+}
+
+/// Adds an offset to each of a product's values.
+fn offset_values(file: &mut DataFile, product: Product, offset: f32) {
+    for radial in file.elevation_scans_mut().values_mut().flatten() {
+        let Some(moment) = radial.data_moment_mut(&product.into()) else {
+            continue;
+        };
+
+        for index in 0..moment.gate_count() {
+            if let Some(value) = moment.value(index).and_then(|value| value.value()) {
+                // The index is within the moment's gates, so this cannot fail
+                let _ = moment.set_value(index, GateValue::Value(value + offset));
+            }
+        }
+    }
+}
+
+/// Writes an output via a temporary file which is renamed once complete, returning its path.
+fn write_output(path: &Path, data: &[u8]) -> Result<PathBuf> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, data)?;
+    fs::rename(&partial, path)?;
+
+    Ok(path.to_path_buf())
+}
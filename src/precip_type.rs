@@ -0,0 +1,266 @@
+//!
+//! Provides [``surface_precipitation_types``] for estimating the type of precipitation reaching the
+//! surface (rain, snow, mix, ice pellets, or freezing rain) from hydrometeor classification (HCA)
+//! output near the surface and the melting layer's height, e.g. for road-weather applications.
+//!
+
+use crate::grid::Grid;
+
+/// A hydrometeor class as produced by the WSR-88D hydrometeor classification algorithm (HCA).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HydrometeorClass {
+    /// Insects and birds.
+    Biological,
+    /// Ground clutter or anomalous propagation.
+    GroundClutter,
+    /// Ice crystals, typically observed high above the melting layer.
+    IceCrystals,
+    /// Dry aggregated snow.
+    DrySnow,
+    /// Wet, partially melted snow, typically observed within the melting layer.
+    WetSnow,
+    /// Light to moderate rain.
+    LightModerateRain,
+    /// Heavy rain.
+    HeavyRain,
+    /// Rain with few but large drops, e.g. at the leading edge of convection.
+    BigDrops,
+    /// Graupel, snow rimed by supercooled drops.
+    Graupel,
+    /// Hail mixed with rain.
+    HailRain,
+    /// Hail larger than an inch in diameter.
+    LargeHail,
+    /// Hail larger than two inches in diameter.
+    GiantHail,
+    /// Echo the algorithm could not classify.
+    Unknown,
+    /// No echo above the noise.
+    NoEcho,
+    /// Echo obscured by range folding.
+    RangeFolded,
+}
+
+impl HydrometeorClass {
+    /// The class for a Level III HCA product data level, if recognized.
+    #[must_use]
+    pub fn from_level3_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0 => Self::NoEcho,
+            10 => Self::Biological,
+            20 => Self::GroundClutter,
+            30 => Self::IceCrystals,
+            40 => Self::DrySnow,
+            50 => Self::WetSnow,
+            60 => Self::LightModerateRain,
+            70 => Self::HeavyRain,
+            80 => Self::BigDrops,
+            90 => Self::Graupel,
+            100 => Self::HailRain,
+            110 => Self::LargeHail,
+            120 => Self::GiantHail,
+            140 => Self::Unknown,
+            150 => Self::RangeFolded,
+            _ => return None,
+        })
+    }
+
+    /// Whether the class describes falling precipitation rather than non-meteorological or
+    /// missing echo.
+    #[must_use]
+    pub fn is_precipitation(&self) -> bool {
+        !matches!(
+            self,
+            Self::Biological
+                | Self::GroundClutter
+                | Self::Unknown
+                | Self::NoEcho
+                | Self::RangeFolded
+        )
+    }
+
+    /// Whether the class describes liquid precipitation.
+    #[must_use]
+    pub fn is_liquid(&self) -> bool {
+        matches!(
+            self,
+            Self::LightModerateRain | Self::HeavyRain | Self::BigDrops | Self::HailRain
+        )
+    }
+}
+
+/// The estimated type of precipitation reaching the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfacePrecipitationType {
+    /// No precipitation, or echo which is not precipitation.
+    None,
+    /// Liquid drops reaching a surface above freezing.
+    Rain,
+    /// Frozen precipitation which has not melted.
+    Snow,
+    /// Partially melted snow, such as wet snow or rain and snow together.
+    Mix,
+    /// Partially melted particles which refroze before reaching the surface, i.e. sleet.
+    IcePellets,
+    /// Liquid drops which freeze on contact with a subfreezing surface.
+    FreezingRain,
+}
+
+/// The melting layer's bounds in meters above mean sea level, above which precipitation is frozen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeltingLayer {
+    bottom_m: f32,
+    top_m: f32,
+    subfreezing_surface: bool,
+}
+
+impl MeltingLayer {
+    /// Create a new melting layer with the specified bottom and top heights in meters above mean
+    /// sea level.
+    #[must_use]
+    pub fn new(bottom_m: f32, top_m: f32) -> Self {
+        Self {
+            bottom_m: bottom_m.min(top_m),
+            top_m: top_m.max(bottom_m),
+            subfreezing_surface: false,
+        }
+    }
+
+    /// Whether air beneath the melting layer is below freezing, i.e. the melting layer is an
+    /// elevated warm layer above a refreezing layer, as in freezing rain and ice pellet events.
+    #[must_use]
+    pub fn with_subfreezing_surface(mut self, subfreezing_surface: bool) -> Self {
+        self.subfreezing_surface = subfreezing_surface;
+        self
+    }
+
+    /// Height of the melting layer's bottom in meters above mean sea level.
+    #[must_use]
+    pub fn bottom_m(&self) -> f32 {
+        self.bottom_m
+    }
+
+    /// Height of the melting layer's top in meters above mean sea level.
+    #[must_use]
+    pub fn top_m(&self) -> f32 {
+        self.top_m
+    }
+
+    /// Whether air beneath the melting layer is below freezing.
+    #[must_use]
+    pub fn subfreezing_surface(&self) -> bool {
+        self.subfreezing_surface
+    }
+}
+
+/// The inputs for estimating one grid cell's surface precipitation type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrecipitationTypeInput {
+    class: HydrometeorClass,
+    observation_height_m: f32,
+    ground_height_m: f32,
+    melting_layer: MeltingLayer,
+    surface_temperature: Option<f32>,
+}
+
+impl PrecipitationTypeInput {
+    /// Create the inputs for a cell from its lowest observed hydrometeor class, the height in
+    /// meters above mean sea level at which it was observed, the ground's height in meters above
+    /// mean sea level, and the melting layer above the cell.
+    #[must_use]
+    pub fn new(
+        class: HydrometeorClass,
+        observation_height_m: f32,
+        ground_height_m: f32,
+        melting_layer: MeltingLayer,
+    ) -> Self {
+        Self {
+            class,
+            observation_height_m,
+            ground_height_m,
+            melting_layer,
+            surface_temperature: None,
+        }
+    }
+
+    /// The surface air temperature in degrees Celsius, if observed. A surface at or below freezing
+    /// refreezes precipitation which melted aloft, as if the melting layer had a subfreezing
+    /// surface.
+    #[must_use]
+    pub fn with_surface_temperature(mut self, surface_temperature: f32) -> Self {
+        self.surface_temperature = Some(surface_temperature);
+        self
+    }
+
+    /// The surface air temperature in degrees Celsius, if observed.
+    #[must_use]
+    pub fn surface_temperature(&self) -> Option<f32> {
+        self.surface_temperature
+    }
+
+    /// Estimates the type of precipitation reaching the surface.
+    ///
+    /// - If the melting layer is at or below the surface, liquid drops are supercooled and freeze
+    ///   on contact while frozen hydrometeors remain snow.
+    /// - If the surface lies within the melting layer, frozen hydrometeors arrive partially melted
+    ///   as a mix, and liquid drops as rain or, over a subfreezing surface, freezing rain.
+    /// - If the melting layer is aloft over a subfreezing surface, particles observed still
+    ///   partially melted, as wet snow or as ice below the layer, refreeze into ice pellets, and
+    ///   fully melted drops into freezing rain.
+    /// - If the melting layer is aloft over a surface above freezing, wet snow observed below the
+    ///   layer survived it and arrives as a mix, and other precipitation melts into rain.
+    #[must_use]
+    pub fn classify(&self) -> SurfacePrecipitationType {
+        if !self.class.is_precipitation() {
+            return SurfacePrecipitationType::None;
+        }
+
+        let layer = &self.melting_layer;
+        let is_liquid = self.class.is_liquid();
+        let refreezes = layer.subfreezing_surface
+            || self
+                .surface_temperature
+                .is_some_and(|temperature| temperature <= 0.0);
+
+        if layer.top_m <= self.ground_height_m {
+            // The whole column is below freezing, so liquid drops are supercooled
+            return if is_liquid {
+                SurfacePrecipitationType::FreezingRain
+            } else {
+                SurfacePrecipitationType::Snow
+            };
+        }
+
+        if layer.bottom_m <= self.ground_height_m {
+            return match (is_liquid, refreezes) {
+                (false, _) => SurfacePrecipitationType::Mix,
+                (true, false) => SurfacePrecipitationType::Rain,
+                (true, true) => SurfacePrecipitationType::FreezingRain,
+            };
+        }
+
+        let is_wet_snow = self.class == HydrometeorClass::WetSnow;
+        let below_layer = self.observation_height_m < layer.bottom_m;
+        if refreezes {
+            return if is_wet_snow || (below_layer && !is_liquid) {
+                SurfacePrecipitationType::IcePellets
+            } else {
+                SurfacePrecipitationType::FreezingRain
+            };
+        }
+
+        if is_wet_snow && below_layer {
+            SurfacePrecipitationType::Mix
+        } else {
+            SurfacePrecipitationType::Rain
+        }
+    }
+}
+
+/// Estimates the surface precipitation type of each grid cell.
+#[must_use]
+pub fn surface_precipitation_types(
+    inputs: &Grid<PrecipitationTypeInput>,
+) -> Grid<SurfacePrecipitationType> {
+    inputs.map(PrecipitationTypeInput::classify)
+}
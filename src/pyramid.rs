@@ -0,0 +1,261 @@
+//!
+//! Provides [``PolarPyramid``] for aggregating a sweep into coarser levels of detail in polar
+//! space, so interactive viewers can fetch coarse data when zoomed out and full resolution when
+//! zoomed in.
+//!
+
+use std::f32::consts::PI;
+
+use crate::grid::Grid;
+use crate::records::{Message31, Product};
+
+/// How the gates within each aggregated cell are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    /// The largest value, which preserves small intense features such as hail cores.
+    Maximum,
+    /// The mean value. Reflectivity is averaged as linear reflectivity factor, since a mean in dBZ
+    /// understates the power of mixed strong and weak echo.
+    Mean,
+}
+
+/// Options controlling which levels a pyramid has and how gates are aggregated into them.
+#[derive(Debug, Clone)]
+pub struct PyramidOptions {
+    factors: Vec<usize>,
+    aggregation: Aggregation,
+}
+
+impl PyramidOptions {
+    /// Create the default options: levels aggregating 2, 4, and 8 radials and gates by their
+    /// maximum.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The aggregation factors of the levels beyond full resolution, each the number of radials
+    /// and of gates combined into a cell.
+    ///
+    /// # Panics
+    /// Panics if any factor is zero.
+    #[must_use]
+    pub fn with_factors(mut self, mut factors: Vec<usize>) -> Self {
+        assert!(
+            factors.iter().all(|factor| *factor > 0),
+            "factors must be positive"
+        );
+        factors.sort_unstable();
+        factors.dedup();
+        self.factors = factors;
+        self
+    }
+
+    /// How the gates within each aggregated cell are combined.
+    #[must_use]
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+}
+
+impl Default for PyramidOptions {
+    fn default() -> Self {
+        Self {
+            factors: vec![2, 4, 8],
+            aggregation: Aggregation::Maximum,
+        }
+    }
+}
+
+/// A single level of detail: a row of cells for each group of radials, with a column for each
+/// group of gates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PyramidLevel {
+    factor: usize,
+    azimuths: Vec<f32>,
+    first_gate_range: f32,
+    gate_interval: f32,
+    values: Grid<Option<f32>>,
+}
+
+impl PyramidLevel {
+    /// The number of radials and of gates combined into each cell, 1 at full resolution.
+    #[must_use]
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// The center azimuth in degrees of each row.
+    #[must_use]
+    pub fn azimuths(&self) -> &[f32] {
+        &self.azimuths
+    }
+
+    /// The range in meters to the center of the first column.
+    #[must_use]
+    pub fn first_gate_range(&self) -> f32 {
+        self.first_gate_range
+    }
+
+    /// The range in meters between columns.
+    #[must_use]
+    pub fn gate_interval(&self) -> f32 {
+        self.gate_interval
+    }
+
+    /// The aggregated values, with a row for each group of radials and a column for each group of
+    /// gates. Cells with no gate values are `None`.
+    #[must_use]
+    pub fn values(&self) -> &Grid<Option<f32>> {
+        &self.values
+    }
+}
+
+/// A sweep's product at full resolution and at successively coarser levels of detail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolarPyramid {
+    levels: Vec<PyramidLevel>,
+}
+
+impl PolarPyramid {
+    /// Builds a pyramid of a sweep's product. Each level aggregates blocks of adjacent radials and
+    /// gates from full resolution, so levels are independent of one another. Radials are ordered
+    /// by azimuth first, so blocks cover adjacent azimuths whichever radial the sweep began with.
+    /// Returns `None` if no radial has the product.
+    #[must_use]
+    pub fn from_radials(
+        radials: &[Message31],
+        product: Product,
+        options: &PyramidOptions,
+    ) -> Option<Self> {
+        let mut radials: Vec<&Message31> = radials.iter().collect();
+        radials.sort_by(|a, b| a.header().azm().total_cmp(&b.header().azm()));
+
+        let moments: Vec<_> = radials
+            .iter()
+            .map(|radial| radial.get_data_moment(&product.into()))
+            .collect();
+        let first = moments.iter().flatten().next()?;
+        let gates = moments
+            .iter()
+            .flatten()
+            .map(|moment| moment.gate_count())
+            .max()?;
+
+        let full = Grid::new(
+            gates,
+            radials.len(),
+            moments
+                .iter()
+                .flat_map(|moment| {
+                    (0..gates).map(move |gate| {
+                        moment
+                            .and_then(|moment| moment.value(gate))
+                            .and_then(|value| value.value())
+                    })
+                })
+                .collect(),
+        );
+        let azimuths: Vec<f32> = radials.iter().map(|radial| radial.header().azm()).collect();
+
+        let first_gate_range = first.data().gate_range_m(0);
+        let gate_interval = f32::from(first.data().data_moment_range_sample_interval());
+
+        let levels = [1]
+            .into_iter()
+            .chain(options.factors.iter().copied().filter(|factor| *factor > 1))
+            .map(|factor| {
+                aggregate(
+                    &full,
+                    &azimuths,
+                    factor,
+                    first_gate_range,
+                    gate_interval,
+                    options.aggregation,
+                    product == Product::Reflectivity,
+                )
+            })
+            .collect();
+
+        Some(Self { levels })
+    }
+
+    /// The levels from full resolution to coarsest.
+    #[must_use]
+    pub fn levels(&self) -> &[PyramidLevel] {
+        &self.levels
+    }
+
+    /// The coarsest level whose cells are no longer in range than the specified size in meters,
+    /// e.g. the extent of a screen pixel, or full resolution if every level's cells are longer.
+    #[must_use]
+    pub fn level_for_resolution(&self, cell_size: f32) -> &PyramidLevel {
+        self.levels
+            .iter()
+            .rev()
+            .find(|level| level.gate_interval <= cell_size)
+            .unwrap_or(&self.levels[0])
+    }
+}
+
+/// Aggregates blocks of a full resolution sweep's radials and gates into a level, averaging values
+/// in dB as linear powers if `logarithmic`.
+#[allow(clippy::cast_precision_loss)]
+fn aggregate(
+    full: &Grid<Option<f32>>,
+    azimuths: &[f32],
+    factor: usize,
+    first_gate_range: f32,
+    gate_interval: f32,
+    aggregation: Aggregation,
+    logarithmic: bool,
+) -> PyramidLevel {
+    let rows = full.rows().div_ceil(factor);
+    let columns = full.columns().div_ceil(factor);
+
+    let mut values = Vec::with_capacity(rows * columns);
+    for row in 0..rows {
+        for column in 0..columns {
+            let block = (row * factor..((row + 1) * factor).min(full.rows())).flat_map(|radial| {
+                (column * factor..((column + 1) * factor).min(full.columns()))
+                    .filter_map(move |gate| full.get(gate, radial).copied().flatten())
+            });
+
+            values.push(match aggregation {
+                Aggregation::Maximum => block.reduce(f32::max),
+                Aggregation::Mean if logarithmic => {
+                    let (sum, count) = block.fold((0.0, 0), |(sum, count), value| {
+                        (sum + 10f32.powf(value / 10.0), count + 1)
+                    });
+                    (count > 0).then(|| 10.0 * (sum / count as f32).log10())
+                }
+                Aggregation::Mean => {
+                    let (sum, count) =
+                        block.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+                    (count > 0).then(|| sum / count as f32)
+                }
+            });
+        }
+    }
+
+    // Each row is centered on the circular mean of its radials' azimuths
+    let row_azimuths = azimuths
+        .chunks(factor)
+        .map(|azimuths| {
+            let (sin, cos) = azimuths.iter().fold((0.0, 0.0), |(sin, cos), azimuth| {
+                let radians = azimuth * PI / 180.0;
+                (sin + radians.sin(), cos + radians.cos())
+            });
+            f32::atan2(sin, cos).to_degrees().rem_euclid(360.0)
+        })
+        .collect();
+
+    PyramidLevel {
+        factor,
+        azimuths: row_azimuths,
+        first_gate_range: first_gate_range + (factor - 1) as f32 / 2.0 * gate_interval,
+        gate_interval: gate_interval * factor as f32,
+        values: Grid::new(columns, rows, values),
+    }
+}
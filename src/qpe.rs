@@ -0,0 +1,349 @@
+//!
+//! Provides [``rain_rate_sweep``] and [``rain_rate_grid``] for estimating rain rates from a
+//! sweep's or gridded reflectivity with `Z = aR^b` relations, either a single relation for every
+//! gate or relations selected by classifying each gate's precipitation as convective or
+//! stratiform, and [``ZrPreset``]s of the relations commonly used for climatological regimes.
+//! [``rain_rate_sweep_weighted``] pairs each rate with its gate's quality index so that
+//! [``combine_rain_rates``] can merge estimates, e.g. of several radars, weighted by quality.
+//!
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::grid::Grid;
+use crate::partition::{
+    partition_grid, partition_sweep, PartitionOptions, RainType, SteinerOptions,
+};
+use crate::quality::SweepQuality;
+use crate::records::{DataMoment, Message31};
+
+/// A relation `Z = aR^b` between reflectivity factor `Z` in mm⁶/m³ and rain rate `R` in mm/h.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZrRelation {
+    a: f32,
+    b: f32,
+}
+
+impl ZrRelation {
+    /// Create a relation with the specified coefficient and exponent.
+    ///
+    /// # Panics
+    /// Panics if either is not positive.
+    #[must_use]
+    pub fn new(a: f32, b: f32) -> Self {
+        assert!(
+            a > 0.0 && b > 0.0,
+            "coefficient and exponent must be positive"
+        );
+        Self { a, b }
+    }
+
+    /// The coefficient `a`.
+    #[must_use]
+    pub fn a(&self) -> f32 {
+        self.a
+    }
+
+    /// The exponent `b`.
+    #[must_use]
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+
+    /// The rain rate in mm/h for a reflectivity in dBZ.
+    #[must_use]
+    pub fn rain_rate(&self, reflectivity: f32) -> f32 {
+        (10f32.powf(reflectivity / 10.0) / self.a).powf(1.0 / self.b)
+    }
+
+    /// The reflectivity in dBZ for a rain rate in mm/h.
+    #[must_use]
+    pub fn reflectivity(&self, rain_rate: f32) -> f32 {
+        10.0 * (self.a * rain_rate.powf(self.b)).log10()
+    }
+}
+
+/// Z-R relations for climatological precipitation regimes. Presets deserialize from their
+/// snake case names, e.g. `cool_season`, so they may be chosen in configuration files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZrPreset {
+    /// `Z = 300R^1.4`, the WSR-88D's default relation for deep convection.
+    Convective,
+    /// `Z = 200R^1.6`, the Marshall-Palmer relation for stratiform rain.
+    Stratiform,
+    /// `Z = 250R^1.2`, the Rosenfeld relation for tropical rain rich in small drops.
+    Tropical,
+    /// `Z = 130R^2.0`, the relation for cool-season stratiform rain east of the continental divide.
+    CoolSeason,
+}
+
+impl ZrPreset {
+    /// The preset's relation.
+    #[must_use]
+    pub fn relation(self) -> ZrRelation {
+        match self {
+            Self::Convective => ZrRelation::new(300.0, 1.4),
+            Self::Stratiform => ZrRelation::new(200.0, 1.6),
+            Self::Tropical => ZrRelation::new(250.0, 1.2),
+            Self::CoolSeason => ZrRelation::new(130.0, 2.0),
+        }
+    }
+}
+
+impl From<ZrPreset> for ZrRelation {
+    fn from(preset: ZrPreset) -> Self {
+        preset.relation()
+    }
+}
+
+/// How the Z-R relation for each gate is selected.
+#[derive(Debug, Clone)]
+pub enum ZrSelection {
+    /// One relation for every gate, e.g. a preset chosen for the run's regime.
+    Fixed(ZrRelation),
+    /// Relations selected by classifying each gate's precipitation, with [``partition_sweep``] for
+    /// sweeps and [``partition_grid``] for gridded reflectivity.
+    Partitioned {
+        convective: ZrRelation,
+        stratiform: ZrRelation,
+        partition: PartitionOptions,
+        steiner: SteinerOptions,
+    },
+}
+
+impl ZrSelection {
+    /// Selects the convective and stratiform presets by partitioning with the default options.
+    #[must_use]
+    pub fn partitioned() -> Self {
+        Self::Partitioned {
+            convective: ZrPreset::Convective.relation(),
+            stratiform: ZrPreset::Stratiform.relation(),
+            partition: PartitionOptions::new(),
+            steiner: SteinerOptions::new(),
+        }
+    }
+}
+
+/// Options controlling how rain rates are estimated.
+#[derive(Debug, Clone)]
+pub struct QpeOptions {
+    selection: ZrSelection,
+    max_reflectivity: f32,
+    min_quality: f32,
+}
+
+impl QpeOptions {
+    /// Create the default options: the convective preset for every gate, with reflectivity capped
+    /// at 53 dBZ, weighting gates of any positive quality.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How the Z-R relation for each gate is selected.
+    #[must_use]
+    pub fn with_selection(mut self, selection: ZrSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// The reflectivity in dBZ above which gates are capped, as stronger echo is usually hail
+    /// whose rain rate a Z-R relation overestimates.
+    #[must_use]
+    pub fn with_max_reflectivity(mut self, max_reflectivity: f32) -> Self {
+        self.max_reflectivity = max_reflectivity;
+        self
+    }
+
+    /// The quality index below which gates are excluded from weighted estimates, e.g. to drop
+    /// gates too blocked or attenuated to contribute. Gates with an index of zero are always
+    /// excluded.
+    #[must_use]
+    pub fn with_min_quality(mut self, min_quality: f32) -> Self {
+        self.min_quality = min_quality;
+        self
+    }
+
+    /// How the Z-R relation for each gate is selected.
+    #[must_use]
+    pub fn selection(&self) -> &ZrSelection {
+        &self.selection
+    }
+
+    /// The reflectivity in dBZ above which gates are capped.
+    #[must_use]
+    pub fn max_reflectivity(&self) -> f32 {
+        self.max_reflectivity
+    }
+
+    /// The quality index below which gates are excluded from weighted estimates.
+    #[must_use]
+    pub fn min_quality(&self) -> f32 {
+        self.min_quality
+    }
+}
+
+impl Default for QpeOptions {
+    fn default() -> Self {
+        Self {
+            selection: ZrSelection::Fixed(ZrPreset::Convective.relation()),
+            max_reflectivity: 53.0,
+            min_quality: 0.0,
+        }
+    }
+}
+
+/// Estimates the rain rate in mm/h of each of a sweep's reflectivity gates, with a row for each
+/// radial and a column for each gate. Gates without a value, beyond a radial's gates, or, when
+/// relations are selected by partitioning, not classified as precipitation are `None`.
+#[must_use]
+pub fn rain_rate_sweep(radials: &[Message31], options: &QpeOptions) -> Grid<Option<f32>> {
+    let reflectivity = reflectivity_grid(radials);
+    let rain_types = match &options.selection {
+        ZrSelection::Fixed(_) => None,
+        ZrSelection::Partitioned { partition, .. } => Some(partition_sweep(radials, partition)),
+    };
+
+    rain_rates(&reflectivity, rain_types.as_ref(), options)
+}
+
+/// Estimates the rain rate in mm/h of each of a sweep's reflectivity gates like
+/// [``rain_rate_sweep``], paired with the gate's quality index as its weight. Gates without a
+/// rate, without a quality index, or with an index of zero or below the options' minimum are
+/// `None`.
+#[must_use]
+pub fn rain_rate_sweep_weighted(
+    radials: &[Message31],
+    quality: &SweepQuality,
+    options: &QpeOptions,
+) -> Grid<Option<(f32, f32)>> {
+    let rates = rain_rate_sweep(radials, options);
+    let columns = rates.columns();
+
+    let values = rates
+        .values()
+        .iter()
+        .enumerate()
+        .map(|(index, rate)| {
+            let weight = quality
+                .radials()
+                .get(index / columns)?
+                .as_ref()?
+                .values()
+                .get(index % columns)
+                .copied()
+                .flatten()
+                .filter(|weight| *weight > 0.0 && *weight >= options.min_quality)?;
+            Some(((*rate)?, weight))
+        })
+        .collect();
+
+    Grid::new(columns, rates.rows(), values)
+}
+
+/// Combines co-located rain rate estimates paired with their weights, e.g. from
+/// [``rain_rate_sweep_weighted``] or several radars' estimates on a common grid, into each cell's
+/// weighted mean rate. Cells without an estimate are `None`.
+///
+/// # Errors
+/// Returns an error if the estimates' dimensions differ.
+pub fn combine_rain_rates(estimates: &[Grid<Option<(f32, f32)>>]) -> Result<Grid<Option<f32>>> {
+    let Some(first) = estimates.first() else {
+        return Ok(Grid::new(0, 0, Vec::new()));
+    };
+    if estimates
+        .iter()
+        .any(|grid| grid.columns() != first.columns() || grid.rows() != first.rows())
+    {
+        return Err(Error::GridMismatch.into());
+    }
+
+    let values = (0..first.values().len())
+        .map(|index| {
+            let (total, weight) = estimates
+                .iter()
+                .filter_map(|grid| grid.values()[index])
+                .fold((0.0, 0.0), |(total, weights), (rate, weight)| {
+                    (total + rate * weight, weights + weight)
+                });
+            (weight > 0.0).then(|| total / weight)
+        })
+        .collect();
+
+    Ok(Grid::new(first.columns(), first.rows(), values))
+}
+
+/// Estimates the rain rate in mm/h of each cell of gridded low-level reflectivity in dBZ, e.g.
+/// from [``grid_sweep``](crate::grid::grid_sweep), with the specified cell size in meters. Cells
+/// without a value or, when relations are selected by partitioning, not classified as
+/// precipitation are `None`.
+#[must_use]
+pub fn rain_rate_grid(
+    reflectivity: &Grid<Option<f32>>,
+    cell_size: f32,
+    options: &QpeOptions,
+) -> Grid<Option<f32>> {
+    let rain_types = match &options.selection {
+        ZrSelection::Fixed(_) => None,
+        ZrSelection::Partitioned { steiner, .. } => {
+            Some(partition_grid(reflectivity, cell_size, steiner))
+        }
+    };
+
+    rain_rates(reflectivity, rain_types.as_ref(), options)
+}
+
+/// Applies the selected relations to reflectivity, classified if the relations are partitioned.
+fn rain_rates(
+    reflectivity: &Grid<Option<f32>>,
+    rain_types: Option<&Grid<Option<RainType>>>,
+    options: &QpeOptions,
+) -> Grid<Option<f32>> {
+    let values = reflectivity
+        .values()
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let relation = match &options.selection {
+                ZrSelection::Fixed(relation) => relation,
+                ZrSelection::Partitioned {
+                    convective,
+                    stratiform,
+                    ..
+                } => match rain_types?.values()[index]? {
+                    RainType::Convective => convective,
+                    RainType::Stratiform => stratiform,
+                },
+            };
+            Some(relation.rain_rate((*value)?.min(options.max_reflectivity)))
+        })
+        .collect();
+
+    Grid::new(reflectivity.columns(), reflectivity.rows(), values)
+}
+
+/// A sweep's reflectivity in dBZ, with a row for each radial and a column for each gate.
+fn reflectivity_grid(radials: &[Message31]) -> Grid<Option<f32>> {
+    let columns = radials
+        .iter()
+        .filter_map(Message31::reflectivity_data)
+        .map(DataMoment::gate_count)
+        .max()
+        .unwrap_or(0);
+
+    let values = radials
+        .iter()
+        .flat_map(|radial| {
+            let moment = radial.reflectivity_data();
+            (0..columns).map(move |index| {
+                moment
+                    .and_then(|moment| moment.value(index))
+                    .and_then(|value| value.value())
+            })
+        })
+        .collect();
+
+    Grid::new(columns, radials.len(), values)
+}
@@ -0,0 +1,279 @@
+//!
+//! Provides [``SweepQuality``] for estimating a quality index from 0 to 1 for each reflectivity
+//! gate of a sweep, so that precipitation estimates and data assimilation can weight gates by how
+//! far they can be trusted. The index combines the signal-to-noise ratio, correlation coefficient,
+//! clutter filter power removed, beam blockage, and attenuation along the path to the gate.
+//!
+
+use crate::blockage::Blockage;
+use crate::calibration::value_at_range;
+use crate::phase::{PhaseOptions, ProcessedPhase};
+use crate::records::{DataMoment, Message31};
+
+/// Options controlling how each factor of the quality index is derived.
+#[derive(Debug, Clone)]
+pub struct QualityOptions {
+    noise_reflectivity: f32,
+    snr_range: (f32, f32),
+    correlation_range: (f32, f32),
+    clutter_range: (f32, f32),
+    attenuation_coefficient: f32,
+    max_attenuation: f32,
+    phase: PhaseOptions,
+}
+
+impl QualityOptions {
+    /// Create the default options: a noise level of -41.5 dBZ at 1 km, signal-to-noise ratios from
+    /// 0 to 10 dB, correlation coefficients from 0.8 to 0.95, clutter filter power removed from 5
+    /// to 25 dB, and 0.04 dB of attenuation per degree of differential phase up to 5 dB.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The reflectivity in dBZ of the receiver's noise at a range of 1 km, from which each gate's
+    /// signal-to-noise ratio is estimated. The WSR-88D's is about -7.5 dBZ at 50 km.
+    #[must_use]
+    pub fn with_noise_reflectivity(mut self, noise_reflectivity: f32) -> Self {
+        self.noise_reflectivity = noise_reflectivity;
+        self
+    }
+
+    /// The signal-to-noise ratios in dB at and below which a gate's signal factor is 0, and at and
+    /// above which it is 1. The factor increases linearly between them.
+    #[must_use]
+    pub fn with_snr_range(mut self, min_snr: f32, max_snr: f32) -> Self {
+        self.snr_range = (min_snr, max_snr);
+        self
+    }
+
+    /// The correlation coefficients at and below which a gate's correlation factor is 0, and at
+    /// and above which it is 1, separating non-meteorological echo from hydrometeors.
+    #[must_use]
+    pub fn with_correlation_range(mut self, min_correlation: f32, max_correlation: f32) -> Self {
+        self.correlation_range = (min_correlation, max_correlation);
+        self
+    }
+
+    /// The clutter filter power removed in dB at and below which a gate's clutter factor is 1, and
+    /// at and above which it is 0. The factor decreases linearly between them, as clutter makes up
+    /// more of the power received and the echo left after filtering is less trustworthy.
+    #[must_use]
+    pub fn with_clutter_range(mut self, min_removed: f32, max_removed: f32) -> Self {
+        self.clutter_range = (min_removed, max_removed);
+        self
+    }
+
+    /// The two-way attenuation in dB per degree of differential phase accumulated along the path,
+    /// and the attenuation in dB at which a gate's attenuation factor reaches 0.
+    #[must_use]
+    pub fn with_attenuation(mut self, coefficient: f32, max_attenuation: f32) -> Self {
+        self.attenuation_coefficient = coefficient;
+        self.max_attenuation = max_attenuation;
+        self
+    }
+
+    /// How differential phase is processed for the attenuation factor.
+    #[must_use]
+    pub fn with_phase_options(mut self, phase: PhaseOptions) -> Self {
+        self.phase = phase;
+        self
+    }
+}
+
+impl Default for QualityOptions {
+    fn default() -> Self {
+        Self {
+            noise_reflectivity: -41.5,
+            snr_range: (0.0, 10.0),
+            correlation_range: (0.8, 0.95),
+            clutter_range: (5.0, 25.0),
+            attenuation_coefficient: 0.04,
+            max_attenuation: 5.0,
+            phase: PhaseOptions::new(),
+        }
+    }
+}
+
+/// The quality index of each of a radial's reflectivity gates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialQuality {
+    first_gate_range: f32,
+    gate_interval: f32,
+    values: Vec<Option<f32>>,
+}
+
+impl RadialQuality {
+    /// The range in meters to the center of the first gate.
+    #[must_use]
+    pub fn first_gate_range(&self) -> f32 {
+        self.first_gate_range
+    }
+
+    /// The range in meters between gates.
+    #[must_use]
+    pub fn gate_interval(&self) -> f32 {
+        self.gate_interval
+    }
+
+    /// Each reflectivity gate's quality index from 0 to 1. Gates without a reflectivity value are
+    /// `None`.
+    #[must_use]
+    pub fn values(&self) -> &[Option<f32>] {
+        &self.values
+    }
+
+    /// The quality index of the gate nearest the specified range in meters, e.g. to weight another
+    /// moment's gates.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn at_range(&self, range: f32) -> Option<f32> {
+        if self.gate_interval <= 0.0 || range < self.first_gate_range - self.gate_interval / 2.0 {
+            return None;
+        }
+
+        let index = ((range - self.first_gate_range) / self.gate_interval).round() as usize;
+        self.values.get(index).copied().flatten()
+    }
+}
+
+/// A sweep's quality index, with an entry for each of its radials in order. Each gate's index is
+/// the product of the factors derived from its inputs, each from 0 to 1:
+///
+/// - signal: the signal-to-noise ratio estimated from reflectivity and range
+/// - correlation: the correlation coefficient, low for clutter, biota, and hail
+/// - clutter: decreasing from 1 to 0 with the clutter filter power removed in dB. The ICD encodes
+///   CFP with an offset of 8 and a scale of 1, so its codes for gates where the filter was not
+///   applied, or only the point clutter filter was, decode below 0 dB and are not penalized
+/// - blockage: one less the fraction of the beam blocked by terrain
+/// - attenuation: one less the attenuation estimated from differential phase, relative to the
+///   greatest tolerated
+///
+/// A factor whose input is missing, e.g. correlation for a legacy radial without dual-polarization
+/// moments, is omitted rather than penalizing the gate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepQuality {
+    radials: Vec<Option<RadialQuality>>,
+}
+
+impl SweepQuality {
+    /// Derives the quality index of each of a sweep's reflectivity gates, using the specified beam
+    /// blockage.
+    #[must_use]
+    pub fn from_radials<B: Blockage + ?Sized>(
+        radials: &[Message31],
+        blockage: &B,
+        options: &QualityOptions,
+    ) -> Self {
+        Self {
+            radials: radials
+                .iter()
+                .map(|radial| radial_quality(radial, blockage, options))
+                .collect(),
+        }
+    }
+
+    /// The quality index of each radial, in the sweep's order. Radials without reflectivity are
+    /// `None`.
+    #[must_use]
+    pub fn radials(&self) -> &[Option<RadialQuality>] {
+        &self.radials
+    }
+
+    /// The quality index of the specified radial's gate nearest the specified range in meters.
+    #[must_use]
+    pub fn at_range(&self, radial: usize, range: f32) -> Option<f32> {
+        self.radials.get(radial)?.as_ref()?.at_range(range)
+    }
+}
+
+/// Derives the quality index of each of a radial's reflectivity gates.
+fn radial_quality<B: Blockage + ?Sized>(
+    radial: &Message31,
+    blockage: &B,
+    options: &QualityOptions,
+) -> Option<RadialQuality> {
+    let reflectivity = radial.reflectivity_data()?;
+    let correlation = radial.rho_data();
+    let clutter = radial.cfp_data();
+    let attenuation = path_attenuation(radial, options);
+
+    let elevation = radial.header().elev();
+    let azimuth = radial.header().azm();
+    let first_gate_range = reflectivity.data().gate_range_m(0);
+    let gate_interval = f32::from(reflectivity.data().data_moment_range_sample_interval());
+
+    let values = (0..reflectivity.gate_count())
+        .map(|index| {
+            let value = reflectivity.value(index)?.value()?;
+            let range = reflectivity.data().gate_range_m(index);
+
+            let noise = options.noise_reflectivity + 20.0 * (range / 1000.0).max(0.001).log10();
+            let mut quality = ramp(value - noise, options.snr_range);
+
+            if let Some(correlation) = moment_at_range(correlation, range) {
+                quality *= ramp(correlation, options.correlation_range);
+            }
+            if let Some(removed) = moment_at_range(clutter, range) {
+                quality *= 1.0 - ramp(removed, options.clutter_range);
+            }
+            quality *= 1.0
+                - blockage
+                    .blocked_fraction(elevation, azimuth, range)
+                    .clamp(0.0, 1.0);
+            if let Some(attenuation) = attenuation.as_ref().and_then(|a| a.at_range(range)) {
+                quality *= 1.0 - (attenuation / options.max_attenuation).clamp(0.0, 1.0);
+            }
+
+            Some(quality)
+        })
+        .collect();
+
+    Some(RadialQuality {
+        first_gate_range,
+        gate_interval,
+        values,
+    })
+}
+
+/// The value of a moment's gate nearest a range, if the moment is present.
+fn moment_at_range(moment: Option<&DataMoment>, range: f32) -> Option<f32> {
+    value_at_range(moment?, range)
+}
+
+/// The attenuation in dB accumulated along a radial to each of its differential phase gates,
+/// from the greatest differential phase measured at or before the gate so that noise does not
+/// reduce it. Returns `None` if the radial's differential phase cannot be processed.
+fn path_attenuation(radial: &Message31, options: &QualityOptions) -> Option<RadialQuality> {
+    let phase = ProcessedPhase::from_radial(radial, &options.phase)?;
+    let ranges = phase.gate_ranges();
+    let first_gate_range = *ranges.first()?;
+    let gate_interval = ranges
+        .get(1)
+        .map_or(0.0, |second| second - first_gate_range);
+
+    let mut accumulated: f32 = 0.0;
+    let values = phase
+        .phidp()
+        .iter()
+        .map(|phidp| {
+            accumulated = accumulated.max(phidp.unwrap_or(0.0));
+            Some(accumulated * options.attenuation_coefficient)
+        })
+        .collect();
+
+    Some(RadialQuality {
+        first_gate_range,
+        gate_interval,
+        values,
+    })
+}
+
+/// A factor rising linearly from 0 at the range's start to 1 at its end.
+fn ramp(value: f32, (start, end): (f32, f32)) -> f32 {
+    if end <= start {
+        return if value >= end { 1.0 } else { 0.0 };
+    }
+
+    ((value - start) / (end - start)).clamp(0.0, 1.0)
+}
@@ -0,0 +1,278 @@
+//!
+//! Provides [``RadarPair``] for expressing one radar's gates in another radar's polar coordinates,
+//! for cross-validating overlapping sites and preparing dual-Doppler analyses, and
+//! [``match_sweeps``] for pairing their sweeps in time.
+//!
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::decoder::DataFile;
+use crate::gate::{GateIterator, GateValue};
+use crate::geometry::{
+    beam_height_m, destination, distance_and_azimuth, ground_range_m, slant_range_and_elevation,
+};
+use crate::records::{Message31, Product, VolumeData};
+
+/// The location of a radar's antenna.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadarLocation {
+    lat: f32,
+    long: f32,
+    altitude: f32,
+}
+
+impl RadarLocation {
+    /// Create a location from a latitude and longitude in degrees and the antenna's altitude in
+    /// meters above mean sea level.
+    #[must_use]
+    pub fn new(lat: f32, long: f32, altitude: f32) -> Self {
+        Self {
+            lat,
+            long,
+            altitude,
+        }
+    }
+
+    /// The location of the radar whose volume data block this is.
+    #[must_use]
+    pub fn from_volume_data(volume_data: &VolumeData) -> Self {
+        Self::new(
+            volume_data.lat(),
+            volume_data.long(),
+            volume_data.antenna_altitude_m(),
+        )
+    }
+
+    /// Latitude in degrees.
+    #[must_use]
+    pub fn lat(&self) -> f32 {
+        self.lat
+    }
+
+    /// Longitude in degrees.
+    #[must_use]
+    pub fn long(&self) -> f32 {
+        self.long
+    }
+
+    /// Altitude of the antenna in meters above mean sea level.
+    #[must_use]
+    pub fn altitude(&self) -> f32 {
+        self.altitude
+    }
+}
+
+/// A point in a radar's polar coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PolarCoordinate {
+    range: f32,
+    azimuth: f32,
+    elevation: f32,
+}
+
+impl PolarCoordinate {
+    /// Create a coordinate from a slant range in meters, an azimuth in degrees clockwise from
+    /// north, and an elevation angle in degrees.
+    #[must_use]
+    pub fn new(range: f32, azimuth: f32, elevation: f32) -> Self {
+        Self {
+            range,
+            azimuth,
+            elevation,
+        }
+    }
+
+    /// Slant range from the antenna in meters.
+    #[must_use]
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+
+    /// Azimuth in degrees clockwise from north.
+    #[must_use]
+    pub fn azimuth(&self) -> f32 {
+        self.azimuth
+    }
+
+    /// Elevation angle in degrees.
+    #[must_use]
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+}
+
+/// Two radars, for expressing points observed by the source radar in the target radar's polar
+/// coordinates. Beams are assumed to propagate with standard refraction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadarPair {
+    source: RadarLocation,
+    target: RadarLocation,
+}
+
+impl RadarPair {
+    /// Create a pair transforming the source radar's coordinates to the target radar's.
+    #[must_use]
+    pub fn new(source: RadarLocation, target: RadarLocation) -> Self {
+        Self { source, target }
+    }
+
+    /// Create a pair from the radars' volumes, located by their first volume data blocks. Returns
+    /// `None` if either volume has no volume data.
+    #[must_use]
+    pub fn from_volumes(source: &DataFile, target: &DataFile) -> Option<Self> {
+        let locate = |file: &DataFile| {
+            file.elevation_scans()
+                .values()
+                .flatten()
+                .find_map(Message31::volume_data)
+                .map(RadarLocation::from_volume_data)
+        };
+
+        Some(Self::new(locate(source)?, locate(target)?))
+    }
+
+    /// The radar whose coordinates are transformed.
+    #[must_use]
+    pub fn source(&self) -> &RadarLocation {
+        &self.source
+    }
+
+    /// The radar whose coordinates are transformed to.
+    #[must_use]
+    pub fn target(&self) -> &RadarLocation {
+        &self.target
+    }
+
+    /// The pair transforming the target radar's coordinates to the source radar's.
+    #[must_use]
+    pub fn reversed(&self) -> Self {
+        Self::new(self.target, self.source)
+    }
+
+    /// The great circle distance between the radars in meters and the azimuth of the target from
+    /// the source in degrees.
+    #[must_use]
+    pub fn baseline(&self) -> (f32, f32) {
+        distance_and_azimuth(
+            self.source.lat,
+            self.source.long,
+            self.target.lat,
+            self.target.long,
+        )
+    }
+
+    /// Expresses a point in the source radar's coordinates in the target radar's. The point is
+    /// located by its height and the position beneath it, and the target's range and elevation are
+    /// those of the refracted beam reaching it.
+    #[must_use]
+    pub fn to_target(&self, coordinate: &PolarCoordinate) -> PolarCoordinate {
+        let height = beam_height_m(coordinate.range, coordinate.elevation, self.source.altitude);
+        let ground_range = ground_range_m(coordinate.range, coordinate.elevation);
+        let (lat, long) = destination(
+            self.source.lat,
+            self.source.long,
+            coordinate.azimuth,
+            ground_range,
+        );
+
+        let (target_ground_range, azimuth) =
+            distance_and_azimuth(self.target.lat, self.target.long, lat, long);
+        let (range, elevation) =
+            slant_range_and_elevation(target_ground_range, height - self.target.altitude);
+
+        PolarCoordinate::new(range, azimuth, elevation)
+    }
+
+    /// Expresses each of a source radial's gates of a product in the target radar's coordinates,
+    /// with its value. Returns `None` if the radial does not contain the product.
+    #[must_use]
+    pub fn transform_gates<'a>(
+        &'a self,
+        radial: &'a Message31,
+        product: Product,
+    ) -> Option<impl Iterator<Item = (PolarCoordinate, GateValue)> + 'a> {
+        let gates = GateIterator::from_radial(radial, product)?;
+        Some(gates.map(|(range, azimuth, elevation, value)| {
+            (
+                self.to_target(&PolarCoordinate::new(range, azimuth, elevation)),
+                value,
+            )
+        }))
+    }
+}
+
+/// A source radar's sweep paired with the target radar's sweep collected nearest in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepMatch {
+    source_elevation_number: u8,
+    target_elevation_number: u8,
+    source_time: NaiveDateTime,
+    target_time: NaiveDateTime,
+}
+
+impl SweepMatch {
+    /// The source sweep's elevation number.
+    #[must_use]
+    pub fn source_elevation_number(&self) -> u8 {
+        self.source_elevation_number
+    }
+
+    /// The target sweep's elevation number.
+    #[must_use]
+    pub fn target_elevation_number(&self) -> u8 {
+        self.target_elevation_number
+    }
+
+    /// When the source sweep's first radial was collected.
+    #[must_use]
+    pub fn source_time(&self) -> NaiveDateTime {
+        self.source_time
+    }
+
+    /// When the target sweep's first radial was collected.
+    #[must_use]
+    pub fn target_time(&self) -> NaiveDateTime {
+        self.target_time
+    }
+
+    /// How long after the source sweep the target sweep began, negative if it began before.
+    #[must_use]
+    pub fn time_difference(&self) -> Duration {
+        self.target_time - self.source_time
+    }
+}
+
+/// Pairs each of the source volume's sweeps with the target volume's sweep that began nearest in
+/// time, in the source's elevation order. Sweeps without a valid radial time are omitted.
+#[must_use]
+pub fn match_sweeps(source: &DataFile, target: &DataFile) -> Vec<SweepMatch> {
+    let target_times: Vec<(u8, NaiveDateTime)> = sweep_times(target).collect();
+
+    sweep_times(source)
+        .filter_map(|(source_elevation_number, source_time)| {
+            let (target_elevation_number, target_time) = target_times
+                .iter()
+                .min_by_key(|(_, time)| (*time - source_time).abs())?;
+
+            Some(SweepMatch {
+                source_elevation_number,
+                target_elevation_number: *target_elevation_number,
+                source_time,
+                target_time: *target_time,
+            })
+        })
+        .collect()
+}
+
+/// The elevation number and start time of each of a volume's sweeps with a valid radial time.
+fn sweep_times(file: &DataFile) -> impl Iterator<Item = (u8, NaiveDateTime)> + '_ {
+    file.elevation_scans()
+        .iter()
+        .filter_map(|(elevation_number, radials)| {
+            let start = radials
+                .iter()
+                .filter_map(|radial| radial.header().date_time())
+                .min()?;
+            Some((*elevation_number, start))
+        })
+}
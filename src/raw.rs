@@ -0,0 +1,24 @@
+//!
+//! The raw layer: structs mirroring the Archive II interface control document (ICD) exactly, and
+//! the functions which detect compression and decompress them. These change only when the ICD
+//! does, so downstream crates working at the message level may depend on this module without being
+//! affected by changes to the high-level layer in [``crate::high_level``]; nothing here refers to
+//! a high-level type.
+//!
+//! Field names, layouts, and units here follow the ICD rather than this crate's conventions, e.g.
+//! [``Message31Header::azm``] is the azimuth angle as encoded. Prefer the high-level layer unless
+//! exact message contents are needed.
+//!
+
+#[cfg(feature = "rayon")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub use crate::decompressor::decompress_file_parallel;
+pub use crate::decompressor::{
+    decompress_file, decompress_record, decompress_records, split_records, RecordDecompressor,
+};
+pub use crate::gate::GateValue;
+pub use crate::metadata::{is_bzip2_compressed, is_compressed, is_zlib_compressed};
+pub use crate::records::{
+    DataBlockHeader, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+    Message31Header, MessageHeader, Product, RadialData, VolumeData, VolumeHeaderRecord,
+};
@@ -0,0 +1,1387 @@
+//!
+//! Struct definitions for decoded NEXRAD Level II data structures.
+//!
+
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
+
+use anyhow::Result;
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::gate::{GateIterator, GateValue};
+
+/// NEXRAD data volume/file header.
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VolumeHeaderRecord {
+    filename: [u8; 12],
+    file_date: u32,
+    file_time: u32,
+    radar_id: [u8; 4],
+}
+
+impl VolumeHeaderRecord {
+    /// Create a new volume header from its filename, e.g. `AR2V0006.001`, its date as days since
+    /// 1 January 1970 counting that day as 1, its time as milliseconds past midnight, and the radar
+    /// site's identifier.
+    #[must_use]
+    pub fn new(filename: [u8; 12], file_date: u32, file_time: u32, radar_id: [u8; 4]) -> Self {
+        Self {
+            filename,
+            file_date,
+            file_time,
+            radar_id,
+        }
+    }
+
+    /// Filename of the archive.
+    #[must_use]
+    pub fn filename(&self) -> &[u8; 12] {
+        &self.filename
+    }
+
+    /// Modified Julian date of the file.
+    #[must_use]
+    pub fn file_date(&self) -> u32 {
+        self.file_date
+    }
+
+    /// Milliseconds of day since midnight of the file.
+    #[must_use]
+    pub fn file_time(&self) -> u32 {
+        self.file_time
+    }
+
+    /// Date and time of the file in UTC, combining [`VolumeHeaderRecord::file_date`] and
+    /// [`VolumeHeaderRecord::file_time`]. Returns `None` if they are out of range.
+    #[must_use]
+    pub fn date_time(&self) -> Option<NaiveDateTime> {
+        archive_date_time(self.file_date, self.file_time)
+    }
+
+    /// ICAO radar identifier in ASCII.
+    #[must_use]
+    pub fn radar_id(&self) -> &[u8; 4] {
+        &self.radar_id
+    }
+}
+
+/// Converts an Archive II date, in days since 1/1/1970 counting from 1, and time, in milliseconds
+/// past midnight, to a date and time in UTC.
+fn archive_date_time(date: u32, time: u32) -> Option<NaiveDateTime> {
+    let date = NaiveDate::from_ymd_opt(1970, 1, 1)?
+        .checked_add_days(Days::new(u64::from(date.checked_sub(1)?)))?;
+    let time = NaiveTime::from_num_seconds_from_midnight_opt(time / 1000, time % 1000 * 1_000_000)?;
+
+    Some(date.and_time(time))
+}
+
+/// Converts a date and time in UTC to the Archive II convention of days since 1/1/1970 (counting
+/// from 1) and milliseconds past midnight. Returns `None` if the date is before 1970 or after the
+/// last day a halfword can count, in 2149.
+pub(crate) fn to_archive_date_time(time: NaiveDateTime) -> Option<(u16, u32)> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    let days = u16::try_from((time.date() - epoch).num_days() + 1).ok()?;
+    let millis = time.num_seconds_from_midnight() * 1000 + time.nanosecond() / 1_000_000;
+
+    Some((days, millis))
+}
+
+/// A NEXRAD volume message header indicating its type and size to be decoded.
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MessageHeader {
+    rpg: [u8; 12],
+    msg_size: u16,
+    channel: u8,
+    msg_type: u8,
+    id_seq: u16,
+    msg_date: u16,
+    msg_time: u32,
+    num_segs: u16,
+    seg_num: u16,
+}
+
+impl MessageHeader {
+    /// Create a new single-segment message header.
+    pub(crate) fn new(
+        msg_size: u16,
+        msg_type: u8,
+        id_seq: u16,
+        msg_date: u16,
+        msg_time: u32,
+    ) -> Self {
+        Self {
+            rpg: [0; 12],
+            msg_size,
+            channel: 0,
+            msg_type,
+            id_seq,
+            msg_date,
+            msg_time,
+            num_segs: 1,
+            seg_num: 1,
+        }
+    }
+
+    /// 12 bytes inserted by RPG Communications Mgr. Ignored.
+    #[must_use]
+    pub fn rpg(&self) -> &[u8; 12] {
+        &self.rpg
+    }
+
+    /// Message size for this segment, in halfwords.
+    #[must_use]
+    pub fn msg_size(&self) -> u16 {
+        self.msg_size
+    }
+
+    /// RDA Redundant Channel
+    #[must_use]
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Message type. For example, 31.
+    #[must_use]
+    pub fn msg_type(&self) -> u8 {
+        self.msg_type
+    }
+
+    /// Msg seq num = 0 to 7FFF, then roll over to 0.
+    #[must_use]
+    pub fn id_seq(&self) -> u16 {
+        self.id_seq
+    }
+
+    /// Modified Julian date from 1/1/70.
+    #[must_use]
+    pub fn msg_date(&self) -> u16 {
+        self.msg_date
+    }
+
+    /// Packet generation time in ms past midnight.
+    #[must_use]
+    pub fn msg_time(&self) -> u32 {
+        self.msg_time
+    }
+
+    /// Number of segments for this message.
+    #[must_use]
+    pub fn num_segs(&self) -> u16 {
+        self.num_segs
+    }
+
+    /// Number of this segment.
+    #[must_use]
+    pub fn seg_num(&self) -> u16 {
+        self.seg_num
+    }
+}
+
+/// Structured data for message type 31.
+#[derive(Clone)]
+pub struct Message31 {
+    header: Message31Header,
+    volume_data: Option<VolumeData>,
+    elevation_data: Option<ElevationData>,
+    radial_data: Option<RadialData>,
+    reflectivity_data: Option<DataMoment>,
+    velocity_data: Option<DataMoment>,
+    sw_data: Option<DataMoment>,
+    zdr_data: Option<DataMoment>,
+    phi_data: Option<DataMoment>,
+    rho_data: Option<DataMoment>,
+    cfp_data: Option<DataMoment>,
+    trailing_bytes: Vec<u8>,
+}
+
+impl Message31 {
+    /// Create a new message 31 structure with just the header to start. Data blocks are added with
+    /// the setters, and the header's layout is set when the radial is encoded.
+    #[must_use]
+    pub fn new(header: Message31Header) -> Self {
+        Self {
+            header,
+            volume_data: None,
+            elevation_data: None,
+            radial_data: None,
+            reflectivity_data: None,
+            velocity_data: None,
+            sw_data: None,
+            zdr_data: None,
+            phi_data: None,
+            rho_data: None,
+            cfp_data: None,
+            trailing_bytes: Vec::new(),
+        }
+    }
+
+    /// The message 31 header.
+    #[must_use]
+    pub fn header(&self) -> &Message31Header {
+        &self.header
+    }
+
+    /// The message 31 header, for modification.
+    pub fn header_mut(&mut self) -> &mut Message31Header {
+        &mut self.header
+    }
+
+    /// The volume data block.
+    #[must_use]
+    pub fn volume_data(&self) -> Option<&VolumeData> {
+        self.volume_data.as_ref()
+    }
+
+    /// The elevation data block.
+    #[must_use]
+    pub fn elevation_data(&self) -> Option<&ElevationData> {
+        self.elevation_data.as_ref()
+    }
+
+    /// The radial data block.
+    #[must_use]
+    pub fn radial_data(&self) -> Option<&RadialData> {
+        self.radial_data.as_ref()
+    }
+
+    /// The reflectivity data block.
+    #[must_use]
+    pub fn reflectivity_data(&self) -> Option<&DataMoment> {
+        self.reflectivity_data.as_ref()
+    }
+
+    /// The velocity data block.
+    #[must_use]
+    pub fn velocity_data(&self) -> Option<&DataMoment> {
+        self.velocity_data.as_ref()
+    }
+
+    /// The spectrum width data block.
+    #[must_use]
+    pub fn sw_data(&self) -> Option<&DataMoment> {
+        self.sw_data.as_ref()
+    }
+
+    /// The differential reflectivity data block.
+    #[must_use]
+    pub fn zdr_data(&self) -> Option<&DataMoment> {
+        self.zdr_data.as_ref()
+    }
+
+    /// The differential phase data block.
+    #[must_use]
+    pub fn phi_data(&self) -> Option<&DataMoment> {
+        self.phi_data.as_ref()
+    }
+
+    /// The correlation coefficient data block.
+    #[must_use]
+    pub fn rho_data(&self) -> Option<&DataMoment> {
+        self.rho_data.as_ref()
+    }
+
+    /// The clutter filter power data block.
+    #[must_use]
+    pub fn cfp_data(&self) -> Option<&DataMoment> {
+        self.cfp_data.as_ref()
+    }
+
+    #[must_use]
+    pub fn get_data_moment(&self, product: &DataBlockProduct) -> Option<&DataMoment> {
+        match product {
+            DataBlockProduct::Reflectivity => self.reflectivity_data(),
+            DataBlockProduct::Velocity => self.velocity_data(),
+            DataBlockProduct::SpectrumWidth => self.sw_data(),
+            DataBlockProduct::DifferentialReflectivity => self.zdr_data(),
+            DataBlockProduct::DifferentialPhase => self.phi_data(),
+            DataBlockProduct::CorrelationCoefficient => self.rho_data(),
+            DataBlockProduct::ClutterFilterProbability => self.cfp_data(),
+            DataBlockProduct::VolumeData
+            | DataBlockProduct::ElevationData
+            | DataBlockProduct::RadialData => None,
+        }
+    }
+
+    /// Iterates over the specified product's gates, yielding each gate's range in meters, the
+    /// radial's azimuth and elevation in degrees, and the gate's scaled value. Returns `None` if
+    /// this radial does not contain the product.
+    #[must_use]
+    pub fn iter_gates(&self, product: Product) -> Option<GateIterator<'_>> {
+        GateIterator::from_radial(self, product)
+    }
+
+    /// Bytes within the radial's length, per [`Message31Header::radial_len`], following its known
+    /// data blocks. Newer builds may append fields or data blocks this decoder does not recognize;
+    /// they are exposed here rather than misinterpreted. Empty for most radials.
+    #[must_use]
+    pub fn trailing_bytes(&self) -> &[u8] {
+        &self.trailing_bytes
+    }
+
+    /// The specified product's data block, for modification.
+    pub fn data_moment_mut(&mut self, product: &DataBlockProduct) -> Option<&mut DataMoment> {
+        match product {
+            DataBlockProduct::Reflectivity => self.reflectivity_data.as_mut(),
+            DataBlockProduct::Velocity => self.velocity_data.as_mut(),
+            DataBlockProduct::SpectrumWidth => self.sw_data.as_mut(),
+            DataBlockProduct::DifferentialReflectivity => self.zdr_data.as_mut(),
+            DataBlockProduct::DifferentialPhase => self.phi_data.as_mut(),
+            DataBlockProduct::CorrelationCoefficient => self.rho_data.as_mut(),
+            DataBlockProduct::ClutterFilterProbability => self.cfp_data.as_mut(),
+            DataBlockProduct::VolumeData
+            | DataBlockProduct::ElevationData
+            | DataBlockProduct::RadialData => None,
+        }
+    }
+
+    /// Removes the specified product's data block, returning it if the radial had one.
+    pub fn remove_data_moment(&mut self, product: &DataBlockProduct) -> Option<DataMoment> {
+        match product {
+            DataBlockProduct::Reflectivity => self.reflectivity_data.take(),
+            DataBlockProduct::Velocity => self.velocity_data.take(),
+            DataBlockProduct::SpectrumWidth => self.sw_data.take(),
+            DataBlockProduct::DifferentialReflectivity => self.zdr_data.take(),
+            DataBlockProduct::DifferentialPhase => self.phi_data.take(),
+            DataBlockProduct::CorrelationCoefficient => self.rho_data.take(),
+            DataBlockProduct::ClutterFilterProbability => self.cfp_data.take(),
+            DataBlockProduct::VolumeData
+            | DataBlockProduct::ElevationData
+            | DataBlockProduct::RadialData => None,
+        }
+    }
+
+    /// Add a data moment, replacing any existing data block of its product.
+    #[must_use]
+    pub fn with_data_moment(mut self, data_moment: DataMoment) -> Self {
+        self.set_data_moment(data_moment);
+        self
+    }
+
+    /// Add the volume data block.
+    #[must_use]
+    pub fn with_volume_data(mut self, volume_data: VolumeData) -> Self {
+        self.set_volume_data(volume_data);
+        self
+    }
+
+    /// Add the elevation data block.
+    #[must_use]
+    pub fn with_elevation_data(mut self, elevation_data: ElevationData) -> Self {
+        self.set_elevation_data(elevation_data);
+        self
+    }
+
+    /// Add the radial data block.
+    #[must_use]
+    pub fn with_radial_data(mut self, radial_data: RadialData) -> Self {
+        self.set_radial_data(radial_data);
+        self
+    }
+
+    /// Set data based on `DataMoment`, replacing any existing data block of its product.
+    pub fn set_data_moment(&mut self, data_moment: DataMoment) {
+        match data_moment.product {
+            DataBlockProduct::Reflectivity => self.reflectivity_data = Some(data_moment),
+            DataBlockProduct::Velocity => self.velocity_data = Some(data_moment),
+            DataBlockProduct::SpectrumWidth => self.sw_data = Some(data_moment),
+            DataBlockProduct::DifferentialReflectivity => self.zdr_data = Some(data_moment),
+            DataBlockProduct::DifferentialPhase => self.phi_data = Some(data_moment),
+            DataBlockProduct::CorrelationCoefficient => self.rho_data = Some(data_moment),
+            DataBlockProduct::ClutterFilterProbability => self.cfp_data = Some(data_moment),
+            DataBlockProduct::VolumeData
+            | DataBlockProduct::ElevationData
+            | DataBlockProduct::RadialData => {}
+        }
+    }
+
+    /// Set the volume data block.
+    pub fn set_volume_data(&mut self, volume_data: VolumeData) {
+        self.volume_data = Some(volume_data);
+    }
+
+    /// Set the elevation data block.
+    pub fn set_elevation_data(&mut self, elevation_data: ElevationData) {
+        self.elevation_data = Some(elevation_data);
+    }
+
+    /// Set the radial data block.
+    pub fn set_radial_data(&mut self, radial_data: RadialData) {
+        self.radial_data = Some(radial_data);
+    }
+
+    /// Set the bytes following the radial's known data blocks.
+    pub fn set_trailing_bytes(&mut self, trailing_bytes: Vec<u8>) {
+        self.trailing_bytes = trailing_bytes;
+    }
+}
+
+/// Header for message type 31.
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Message31Header {
+    radar_id: [u8; 4],
+    ray_time: u32,
+    ray_date: u16,
+    azm_num: u16,
+    azm: f32,
+    compression_code: u8,
+    spare: u8,
+    radial_len: u16,
+    azm_res: u8,
+    radial_status: u8,
+    elev_num: u8,
+    sector_cut_num: u8,
+    elev: f32,
+    radial_spot_blanking: u8,
+    azm_indexing_mode: u8,
+    data_block_count: u16,
+}
+
+impl Message31Header {
+    /// Create a new uncompressed message 31 header with no data blocks. The layout is set when the
+    /// message is encoded.
+    #[must_use]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        radar_id: [u8; 4],
+        ray_time: u32,
+        ray_date: u16,
+        azm_num: u16,
+        azm: f32,
+        azm_res: u8,
+        radial_status: u8,
+        elev_num: u8,
+        elev: f32,
+    ) -> Self {
+        Self {
+            radar_id,
+            ray_time,
+            ray_date,
+            azm_num,
+            azm,
+            compression_code: 0,
+            spare: 0,
+            radial_len: 0,
+            azm_res,
+            radial_status,
+            elev_num,
+            sector_cut_num: 1,
+            elev,
+            radial_spot_blanking: 0,
+            azm_indexing_mode: 0,
+            data_block_count: 0,
+        }
+    }
+
+    /// Set the number of data blocks and the radial's length in bytes.
+    pub(crate) fn set_layout(&mut self, data_block_count: u16, radial_len: u16) {
+        self.data_block_count = data_block_count;
+        self.radial_len = radial_len;
+    }
+
+    /// Set the azimuth angle in degrees.
+    pub fn set_azm(&mut self, azm: f32) {
+        self.azm = azm;
+    }
+
+    /// Set the elevation angle in degrees.
+    pub fn set_elev(&mut self, elev: f32) {
+        self.elev = elev;
+    }
+
+    /// Set the radial status, e.g. the start or end of an elevation or volume.
+    pub fn set_radial_status(&mut self, radial_status: u8) {
+        self.radial_status = radial_status;
+    }
+
+    /// Set the data collection date and time, as days since 1 January 1970 and milliseconds past
+    /// midnight GMT.
+    pub fn set_ray_date_time(&mut self, ray_date: u16, ray_time: u32) {
+        self.ray_date = ray_date;
+        self.ray_time = ray_time;
+    }
+
+    /// Radar site identifier.
+    #[must_use]
+    pub fn radar_id(&self) -> &[u8; 4] {
+        &self.radar_id
+    }
+
+    /// Data collection time in milliseconds past midnight GMT.
+    #[must_use]
+    pub fn ray_time(&self) -> u32 {
+        self.ray_time
+    }
+
+    /// Julian date - 2440586.5 (1/01/1970).
+    #[must_use]
+    pub fn ray_date(&self) -> u16 {
+        self.ray_date
+    }
+
+    /// Data collection date and time in UTC, combining [`Message31Header::ray_date`] and
+    /// [`Message31Header::ray_time`]. Returns `None` if they are out of range.
+    #[must_use]
+    pub fn date_time(&self) -> Option<NaiveDateTime> {
+        archive_date_time(self.ray_date.into(), self.ray_time)
+    }
+
+    /// Radial number within elevation scan.
+    #[must_use]
+    pub fn azm_num(&self) -> u16 {
+        self.azm_num
+    }
+
+    /// Azimuth angle in degrees (0 to 359.956055).
+    #[must_use]
+    pub fn azm(&self) -> f32 {
+        self.azm
+    }
+
+    /// 0 = uncompressed, 1 = BZIP2, 2 = zlib.
+    #[must_use]
+    pub fn compression_code(&self) -> u8 {
+        self.compression_code
+    }
+
+    /// For word alignment.
+    #[must_use]
+    pub fn spare(&self) -> u8 {
+        self.spare
+    }
+
+    /// Radial length in bytes, including data header block.
+    #[must_use]
+    pub fn radial_len(&self) -> u16 {
+        self.radial_len
+    }
+
+    /// Azimuthal resolution.
+    #[must_use]
+    pub fn azm_res(&self) -> u8 {
+        self.azm_res
+    }
+
+    /// Radial status.
+    #[must_use]
+    pub fn radial_status(&self) -> u8 {
+        self.radial_status
+    }
+
+    /// Elevation number.
+    #[must_use]
+    pub fn elev_num(&self) -> u8 {
+        self.elev_num
+    }
+
+    /// Sector cut number.
+    #[must_use]
+    pub fn sector_cut_num(&self) -> u8 {
+        self.sector_cut_num
+    }
+
+    /// Elevation angle in degrees (-7.0 to 70.0).
+    #[must_use]
+    pub fn elev(&self) -> f32 {
+        self.elev
+    }
+
+    /// Radial spot blanking.
+    #[must_use]
+    pub fn radial_spot_blanking(&self) -> u8 {
+        self.radial_spot_blanking
+    }
+
+    /// Azimuth indexing mode.
+    #[must_use]
+    pub fn azm_indexing_mode(&self) -> u8 {
+        self.azm_indexing_mode
+    }
+
+    /// Data block count.
+    #[must_use]
+    pub fn data_block_count(&self) -> u16 {
+        self.data_block_count
+    }
+}
+
+/// Introduces a data block containing data, such as VEL, REF, etc.
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DataBlockHeader {
+    data_block_type: [u8; 1],
+    data_name: [u8; 3],
+}
+
+impl DataBlockHeader {
+    /// Create a new data block header for the specified product.
+    pub(crate) fn new(product: &DataBlockProduct) -> Self {
+        let data_block_type = match product {
+            DataBlockProduct::VolumeData
+            | DataBlockProduct::ElevationData
+            | DataBlockProduct::RadialData => *b"R",
+            _ => *b"D",
+        };
+
+        Self {
+            data_block_type,
+            data_name: *product.data_name(),
+        }
+    }
+
+    #[must_use]
+    pub fn data_block_type(&self) -> &[u8; 1] {
+        &self.data_block_type
+    }
+
+    /// Data name, e.g. "REF", "VEL", etc.
+    #[must_use]
+    pub fn data_name(&self) -> &[u8; 3] {
+        &self.data_name
+    }
+
+    /// Data block header name
+    ///
+    /// # Errors
+    /// Will error if the data block product is not recognized.
+    pub fn data_block_product(&self) -> Result<DataBlockProduct> {
+        Ok(DataBlockProduct::from_str(
+            String::from_utf8_lossy(self.data_name()).as_ref(),
+        )?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataBlockProduct {
+    Reflectivity,
+    Velocity,
+    SpectrumWidth,
+    DifferentialReflectivity,
+    DifferentialPhase,
+    CorrelationCoefficient,
+    ClutterFilterProbability,
+
+    VolumeData,
+    ElevationData,
+    RadialData,
+}
+
+impl DataBlockProduct {
+    /// The data block's name as it appears in the data block header, e.g. "REF".
+    #[must_use]
+    pub fn data_name(&self) -> &'static [u8; 3] {
+        match self {
+            Self::Reflectivity => b"REF",
+            Self::Velocity => b"VEL",
+            Self::SpectrumWidth => b"SW ",
+            Self::DifferentialReflectivity => b"ZDR",
+            Self::DifferentialPhase => b"PHI",
+            Self::CorrelationCoefficient => b"RHO",
+            Self::ClutterFilterProbability => b"CFP",
+            Self::VolumeData => b"VOL",
+            Self::ElevationData => b"ELV",
+            Self::RadialData => b"RAD",
+        }
+    }
+}
+
+impl FromStr for DataBlockProduct {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "REF" => Ok(Self::Reflectivity),
+            "VEL" => Ok(Self::Velocity),
+            "SW " => Ok(Self::SpectrumWidth),
+            "ZDR" => Ok(Self::DifferentialReflectivity),
+            "PHI" => Ok(Self::DifferentialPhase),
+            "RHO" => Ok(Self::CorrelationCoefficient),
+            "CFP" => Ok(Self::ClutterFilterProbability),
+            "VOL" => Ok(Self::VolumeData),
+            "RAD" => Ok(Self::RadialData),
+            "ELV" => Ok(Self::ElevationData),
+            _ => Err(Error::UnhandledProduct),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Product {
+    Reflectivity,
+    Velocity,
+    SpectrumWidth,
+    DifferentialReflectivity,
+    DifferentialPhase,
+    CorrelationCoefficient,
+    ClutterFilterProbability,
+}
+
+impl Product {
+    /// Every product, in the order their data blocks are conventionally encoded.
+    pub const ALL: [Product; 7] = [
+        Product::Reflectivity,
+        Product::Velocity,
+        Product::SpectrumWidth,
+        Product::DifferentialReflectivity,
+        Product::DifferentialPhase,
+        Product::CorrelationCoefficient,
+        Product::ClutterFilterProbability,
+    ];
+}
+
+impl FromStr for Product {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ref" | "reflectivity" => Ok(Self::Reflectivity),
+            "vel" | "velocity" => Ok(Self::Velocity),
+            "sw" => Ok(Self::SpectrumWidth),
+            "zdr" => Ok(Self::DifferentialReflectivity),
+            "phi" => Ok(Self::DifferentialPhase),
+            "rho" => Ok(Self::CorrelationCoefficient),
+            "cfp" => Ok(Self::ClutterFilterProbability),
+
+            _ => Err(Error::UnhandledProduct),
+        }
+    }
+}
+
+// To string
+impl Display for Product {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Product::Reflectivity => write!(f, "Reflectivity"),
+            Product::Velocity => write!(f, "Velocity"),
+            Product::SpectrumWidth => write!(f, "Spectrum Width"),
+            Product::DifferentialReflectivity => write!(f, "Differential Reflectivity"),
+            Product::DifferentialPhase => write!(f, "Differential Phase"),
+            Product::CorrelationCoefficient => write!(f, "Correlation Coefficient"),
+            Product::ClutterFilterProbability => write!(f, "Clutter Filter Probability"),
+        }
+    }
+}
+
+impl From<Product> for DataBlockProduct {
+    fn from(product: Product) -> Self {
+        match product {
+            Product::Reflectivity => Self::Reflectivity,
+            Product::Velocity => Self::Velocity,
+            Product::SpectrumWidth => Self::SpectrumWidth,
+            Product::DifferentialReflectivity => Self::DifferentialReflectivity,
+            Product::DifferentialPhase => Self::DifferentialPhase,
+            Product::CorrelationCoefficient => Self::CorrelationCoefficient,
+            Product::ClutterFilterProbability => Self::ClutterFilterProbability,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VolumeData {
+    data_block_header: DataBlockHeader,
+    lrtup: u16,
+    version_major: u8,
+    version_minor: u8,
+    lat: f32,
+    long: f32,
+    site_height: i16,
+    feedhorn_height: i16,
+    calibration_constant: f32,
+    shvtx_power_hor: f32,
+    shvtx_power_ver: f32,
+    system_differential_reflectivity: f32,
+    initial_system_differential_phase: f32,
+    volume_coverage_pattern_number: u16,
+    processing_status: u16,
+}
+
+impl VolumeData {
+    /// Create a new volume data block for a site, with zeroed calibration values.
+    #[must_use]
+    pub fn new(
+        lat: f32,
+        long: f32,
+        site_height: i16,
+        feedhorn_height: i16,
+        volume_coverage_pattern_number: u16,
+    ) -> Self {
+        Self {
+            data_block_header: DataBlockHeader::new(&DataBlockProduct::VolumeData),
+            lrtup: 44,
+            version_major: 1,
+            version_minor: 0,
+            lat,
+            long,
+            site_height,
+            feedhorn_height,
+            calibration_constant: 0.0,
+            shvtx_power_hor: 0.0,
+            shvtx_power_ver: 0.0,
+            system_differential_reflectivity: 0.0,
+            initial_system_differential_phase: 0.0,
+            volume_coverage_pattern_number,
+            processing_status: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn data_block_header(&self) -> &DataBlockHeader {
+        &self.data_block_header
+    }
+
+    #[must_use]
+    pub fn lrtup(&self) -> u16 {
+        self.lrtup
+    }
+
+    #[must_use]
+    pub fn version_major(&self) -> u8 {
+        self.version_major
+    }
+
+    #[must_use]
+    pub fn version_minor(&self) -> u8 {
+        self.version_minor
+    }
+
+    #[must_use]
+    pub fn lat(&self) -> f32 {
+        self.lat
+    }
+
+    #[must_use]
+    pub fn long(&self) -> f32 {
+        self.long
+    }
+
+    /// Height of the site's base in meters above mean sea level. Negative for sites below sea
+    /// level.
+    #[must_use]
+    pub fn site_height(&self) -> i16 {
+        self.site_height
+    }
+
+    /// Height of the feedhorn in meters above the site's base (ground level).
+    #[must_use]
+    pub fn feedhorn_height(&self) -> i16 {
+        self.feedhorn_height
+    }
+
+    /// Altitude of the antenna's feedhorn in meters above mean sea level, combining the site and
+    /// feedhorn heights. This is the reference used for all beam height computations.
+    #[must_use]
+    pub fn antenna_altitude_m(&self) -> f32 {
+        f32::from(self.site_height) + f32::from(self.feedhorn_height)
+    }
+
+    #[must_use]
+    pub fn calibration_constant(&self) -> f32 {
+        self.calibration_constant
+    }
+
+    #[must_use]
+    pub fn shvtx_power_hor(&self) -> f32 {
+        self.shvtx_power_hor
+    }
+
+    #[must_use]
+    pub fn shvtx_power_ver(&self) -> f32 {
+        self.shvtx_power_ver
+    }
+
+    #[must_use]
+    pub fn system_differential_reflectivity(&self) -> f32 {
+        self.system_differential_reflectivity
+    }
+
+    #[must_use]
+    pub fn initial_system_differential_phase(&self) -> f32 {
+        self.initial_system_differential_phase
+    }
+
+    #[must_use]
+    pub fn volume_coverage_pattern_number(&self) -> u16 {
+        self.volume_coverage_pattern_number
+    }
+
+    /// Whether the volume coverage pattern is a clear-air mode pattern (31, 32, or 35), which scans
+    /// fewer, slower tilts for sensitivity and may lack Doppler moments on some tilts.
+    #[must_use]
+    pub fn is_clear_air(&self) -> bool {
+        matches!(self.volume_coverage_pattern_number, 31 | 32 | 35)
+    }
+
+    #[must_use]
+    pub fn processing_status(&self) -> u16 {
+        self.processing_status
+    }
+}
+
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ElevationData {
+    data_block_header: DataBlockHeader,
+    lrtup: u16,
+    atmos: [u8; 2],
+    calib_const: f32,
+}
+
+impl ElevationData {
+    /// Create a new elevation data block.
+    #[must_use]
+    pub fn new(atmos: [u8; 2], calib_const: f32) -> Self {
+        Self {
+            data_block_header: DataBlockHeader::new(&DataBlockProduct::ElevationData),
+            lrtup: 12,
+            atmos,
+            calib_const,
+        }
+    }
+
+    #[must_use]
+    pub fn data_block_header(&self) -> &DataBlockHeader {
+        &self.data_block_header
+    }
+
+    /// Size of data block in bytes
+    #[must_use]
+    pub fn lrtup(&self) -> u16 {
+        self.lrtup
+    }
+
+    /// Atmospheric Attenuation Factor
+    #[must_use]
+    pub fn atmos(&self) -> &[u8; 2] {
+        &self.atmos
+    }
+
+    /// Scaling constant used by the Signal Processor for this elevation to calculate reflectivity
+    #[must_use]
+    pub fn calib_const(&self) -> f32 {
+        self.calib_const
+    }
+}
+
+/// Size in bytes of the radial data block's fields common to every build. Newer builds lengthen
+/// the block, with the extension's size indicated by its `lrtup`.
+pub(crate) const RADIAL_DATA_BASE_SIZE: u16 = 28;
+
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RadialData {
+    data_block_header: DataBlockHeader,
+    lrtup: u16,
+    unambiguous_range: u16,
+    noise_level_horz: f32,
+    noise_level_vert: f32,
+    nyquist_velocity: u16,
+    radial_flags: u16,
+    calib_const_horz_chan: f32,
+    calib_const_vert_chan: f32,
+    #[serde(skip)]
+    extension: Vec<u8>,
+}
+
+impl RadialData {
+    /// Create a new radial data block with zeroed noise and calibration values.
+    #[must_use]
+    pub fn new(unambiguous_range: u16, nyquist_velocity: u16) -> Self {
+        Self {
+            data_block_header: DataBlockHeader::new(&DataBlockProduct::RadialData),
+            lrtup: RADIAL_DATA_BASE_SIZE,
+            unambiguous_range,
+            noise_level_horz: 0.0,
+            noise_level_vert: 0.0,
+            nyquist_velocity,
+            radial_flags: 0,
+            calib_const_horz_chan: 0.0,
+            calib_const_vert_chan: 0.0,
+            extension: Vec::new(),
+        }
+    }
+
+    /// Extends the block with a Build 19 ZDR bias estimate in dB, as with [``zdr_bias_estimate``].
+    ///
+    /// [``zdr_bias_estimate``]: RadialData::zdr_bias_estimate
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn with_zdr_bias_estimate(mut self, zdr_bias_estimate: f32) -> Self {
+        // A block without room for the estimate is given Build 19's, the estimate and a spare
+        // halfword
+        if self.extension.len() < 2 {
+            self.extension = vec![0; 4];
+            self.lrtup = RADIAL_DATA_BASE_SIZE + 4;
+        }
+
+        let scaled = (zdr_bias_estimate * 1000.0).round() as i16;
+        self.extension[..2].copy_from_slice(&scaled.to_be_bytes());
+        self
+    }
+
+    #[must_use]
+    pub fn data_block_header(&self) -> &DataBlockHeader {
+        &self.data_block_header
+    }
+
+    /// Size of data block in bytes
+    #[must_use]
+    pub fn lrtup(&self) -> u16 {
+        self.lrtup
+    }
+
+    /// Unambiguous Range, Interval Size
+    #[must_use]
+    pub fn unambiguous_range(&self) -> u16 {
+        self.unambiguous_range
+    }
+
+    #[must_use]
+    pub fn noise_level_horz(&self) -> f32 {
+        self.noise_level_horz
+    }
+
+    #[must_use]
+    pub fn noise_level_vert(&self) -> f32 {
+        self.noise_level_vert
+    }
+
+    #[must_use]
+    pub fn nyquist_velocity(&self) -> u16 {
+        self.nyquist_velocity
+    }
+
+    #[must_use]
+    pub fn radial_flags(&self) -> u16 {
+        self.radial_flags
+    }
+
+    #[must_use]
+    pub fn calib_const_horz_chan(&self) -> f32 {
+        self.calib_const_horz_chan
+    }
+
+    #[must_use]
+    pub fn calib_const_vert_chan(&self) -> f32 {
+        self.calib_const_vert_chan
+    }
+
+    /// The weighted mean of the system's ZDR bias in dB, estimated from Bragg scatter and light
+    /// rain, which dual-polarization builds since Build 19 append to the block. `None` for blocks
+    /// from earlier builds.
+    #[must_use]
+    pub fn zdr_bias_estimate(&self) -> Option<f32> {
+        let scaled = i16::from_be_bytes(self.extension.get(..2)?.try_into().ok()?);
+        Some(f32::from(scaled) / 1000.0)
+    }
+
+    /// The block's bytes beyond the fields common to every build, including those exposed by
+    /// accessors such as [``zdr_bias_estimate``] and any spare or unrecognized fields. Empty for
+    /// blocks from earlier builds.
+    ///
+    /// [``zdr_bias_estimate``]: RadialData::zdr_bias_estimate
+    #[must_use]
+    pub fn extension(&self) -> &[u8] {
+        &self.extension
+    }
+
+    /// Set the block's bytes beyond the fields common to every build, updating its size.
+    ///
+    /// # Errors
+    /// Returns an error if the extended block would exceed the largest representable size.
+    pub fn set_extension(&mut self, extension: Vec<u8>) -> Result<()> {
+        self.lrtup = u16::try_from(extension.len())
+            .ok()
+            .and_then(|len| RADIAL_DATA_BASE_SIZE.checked_add(len))
+            .ok_or(Error::DataBlockTooLarge(extension.len()))?;
+        self.extension = extension;
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct DataMoment {
+    product: DataBlockProduct,
+    data: GenericData,
+    moment_data: Vec<u8>,
+}
+
+impl DataMoment {
+    /// Create a new data moment from its generic data block and raw data words, one byte per gate
+    /// for 8-bit words or two big-endian bytes per gate for 16-bit words.
+    #[must_use]
+    pub fn new(product: DataBlockProduct, data: GenericData, moment_data: Vec<u8>) -> Self {
+        Self {
+            product,
+            data,
+            moment_data,
+        }
+    }
+
+    /// The product this moment contains.
+    #[must_use]
+    pub fn product(&self) -> &DataBlockProduct {
+        &self.product
+    }
+
+    #[must_use]
+    pub fn data(&self) -> &GenericData {
+        &self.data
+    }
+
+    #[must_use]
+    pub fn moment_data(&self) -> &[u8] {
+        &self.moment_data
+    }
+
+    /// Number of gates present in the moment data.
+    #[must_use]
+    pub fn gate_count(&self) -> usize {
+        match self.data.data_word_size() {
+            8 | 16 => (self.moment_data.len() / self.data.word_bytes())
+                .min(self.data.number_data_moment_gates() as usize),
+            _ => 0,
+        }
+    }
+
+    /// The raw data word for the gate at the specified index, supporting 8- and 16-bit words.
+    #[must_use]
+    pub fn raw_value(&self, index: usize) -> Option<u16> {
+        if index >= self.gate_count() {
+            return None;
+        }
+
+        match self.data.data_word_size() {
+            8 => Some(u16::from(self.moment_data[index])),
+            16 => Some(u16::from_be_bytes([
+                self.moment_data[index * 2],
+                self.moment_data[index * 2 + 1],
+            ])),
+            _ => None,
+        }
+    }
+
+    /// The scaled value for the gate at the specified index.
+    #[must_use]
+    pub fn value(&self, index: usize) -> Option<GateValue> {
+        let raw = self.raw_value(index)?;
+        Some(GateValue::from_raw(
+            raw,
+            self.data.scale(),
+            self.data.offset(),
+        ))
+    }
+
+    /// Iterates over the scaled values for each gate.
+    pub fn values(&self) -> impl Iterator<Item = GateValue> + '_ {
+        (0..self.gate_count()).filter_map(|index| self.value(index))
+    }
+
+    /// Set the raw data word for the gate at the specified index.
+    ///
+    /// # Errors
+    /// Returns an error if the index is beyond the moment's gates or the word does not fit the
+    /// moment's word size.
+    pub fn set_raw_value(&mut self, index: usize, raw: u16) -> Result<()> {
+        if index >= self.gate_count() {
+            return Err(Error::GateOutOfRange(index).into());
+        }
+
+        match self.data.data_word_size() {
+            8 => self.moment_data[index] = u8::try_from(raw)?,
+            _ => self.moment_data[index * 2..index * 2 + 2].copy_from_slice(&raw.to_be_bytes()),
+        }
+        Ok(())
+    }
+
+    /// Set the gate at the specified index to a value, scaled to a raw data word. Values are
+    /// rounded to the nearest word and clamped to the range of words the moment can represent.
+    ///
+    /// # Errors
+    /// Returns an error if the index is beyond the moment's gates.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn set_value(&mut self, index: usize, value: GateValue) -> Result<()> {
+        let maximum = if self.data.data_word_size() == 8 {
+            f32::from(u8::MAX)
+        } else {
+            f32::from(u16::MAX)
+        };
+
+        let raw = match value {
+            GateValue::BelowThreshold => 0,
+            GateValue::RangeFolded => 1,
+            GateValue::Value(value) if self.data.scale() == 0.0 => {
+                value.round().clamp(2.0, maximum) as u16
+            }
+            GateValue::Value(value) => (value * self.data.scale() + self.data.offset())
+                .round()
+                .clamp(2.0, maximum) as u16,
+        };
+
+        self.set_raw_value(index, raw)
+    }
+}
+
+#[repr(C)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenericData {
+    data_block_type: [u8; 1],
+    data_name: [u8; 3],
+    reserved: u32,
+    number_data_moment_gates: u16,
+    data_moment_range: u16,
+    data_moment_range_sample_interval: u16,
+    tover: u16,
+    snr_threshold: u16,
+    control_flags: u8,
+    data_word_size: u8,
+    scale: f32,
+    offset: f32,
+}
+
+impl GenericData {
+    /// Create a new generic data block header for a moment, with no overlay or SNR thresholds and
+    /// no control flags.
+    #[must_use]
+    pub fn new(
+        product: &DataBlockProduct,
+        number_data_moment_gates: u16,
+        data_moment_range: u16,
+        data_moment_range_sample_interval: u16,
+        data_word_size: u8,
+        scale: f32,
+        offset: f32,
+    ) -> Self {
+        Self {
+            data_block_type: *b"D",
+            data_name: *product.data_name(),
+            reserved: 0,
+            number_data_moment_gates,
+            data_moment_range,
+            data_moment_range_sample_interval,
+            tover: 0,
+            snr_threshold: 0,
+            control_flags: 0,
+            data_word_size,
+            scale,
+            offset,
+        }
+    }
+
+    /// Set the overlay threshold, per [`GenericData::tover`].
+    #[must_use]
+    pub fn with_tover(mut self, tover: u16) -> Self {
+        self.tover = tover;
+        self
+    }
+
+    /// Set the SNR threshold for valid data, per [`GenericData::snr_threshold`].
+    #[must_use]
+    pub fn with_snr_threshold(mut self, snr_threshold: u16) -> Self {
+        self.snr_threshold = snr_threshold;
+        self
+    }
+
+    /// Set the control flags, per [`GenericData::control_flags`].
+    #[must_use]
+    pub fn with_control_flags(mut self, control_flags: u8) -> Self {
+        self.control_flags = control_flags;
+        self
+    }
+
+    #[must_use]
+    pub fn data_block_type(&self) -> &[u8; 1] {
+        &self.data_block_type
+    }
+
+    #[must_use]
+    pub fn data_name(&self) -> &[u8; 3] {
+        &self.data_name
+    }
+
+    #[must_use]
+    pub fn reserved(&self) -> u32 {
+        self.reserved
+    }
+
+    /// Number of data moment gates for current radial
+    #[must_use]
+    pub fn number_data_moment_gates(&self) -> u16 {
+        self.number_data_moment_gates
+    }
+
+    /// Range to center of first range gate
+    #[must_use]
+    pub fn data_moment_range(&self) -> u16 {
+        self.data_moment_range
+    }
+
+    /// Size of data moment sample interval
+    #[must_use]
+    pub fn data_moment_range_sample_interval(&self) -> u16 {
+        self.data_moment_range_sample_interval
+    }
+
+    /// Threshold parameter which specifies the minimum difference in echo power between two
+    /// resolution gates for them not to be labeled "overlayed"
+    #[must_use]
+    pub fn tover(&self) -> u16 {
+        self.tover
+    }
+
+    /// SNR threshold for valid data
+    #[must_use]
+    pub fn snr_threshold(&self) -> u16 {
+        self.snr_threshold
+    }
+
+    /// Indicates special control features
+    #[must_use]
+    pub fn control_flags(&self) -> u8 {
+        self.control_flags
+    }
+
+    /// Number of bits (DWS) used for storing data for each Data Moment gate
+    #[must_use]
+    pub fn data_word_size(&self) -> u8 {
+        self.data_word_size
+    }
+
+    /// Scale value used to convert Data Moments from integer to floating point data
+    #[must_use]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Offset value used to convert Data Moments from integer to floating point data
+    #[must_use]
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    #[must_use]
+    pub fn moment_size(&self) -> usize {
+        self.number_data_moment_gates as usize * self.data_word_size as usize / 8
+    }
+
+    /// Range to the center of the gate at the specified index, in meters.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn gate_range_m(&self, index: usize) -> f32 {
+        f32::from(self.data_moment_range)
+            + index as f32 * f32::from(self.data_moment_range_sample_interval)
+    }
+
+    /// Number of bytes used to store each gate's data word.
+    pub(crate) fn word_bytes(&self) -> usize {
+        (self.data_word_size as usize / 8).max(1)
+    }
+}
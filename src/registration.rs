@@ -0,0 +1,409 @@
+//!
+//! Provides [``check_registration``] for diagnosing a radar's georegistration from consecutive
+//! volumes. Echoes move between volumes by translation with the wind, so a rotation of the
+//! low-level reflectivity field about the radar suggests the antenna's azimuth encoder has
+//! shifted, and motion too fast for the volumes' reported times suggests a clock error. Applied
+//! across an archive, e.g. with [``check_series``], this locates when a site's problem began.
+//!
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime};
+
+use crate::composite::{estimate_motion, grid_layer, VolumeLayer, VolumePair, VolumeSeries};
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::grid::{Grid, GridSpec};
+use crate::records::Product;
+
+/// The most times rotation and translation are alternately re-estimated.
+const MAX_ITERATIONS: usize = 4;
+
+/// Options controlling how consecutive volumes are compared.
+#[derive(Debug, Clone)]
+pub struct RegistrationOptions {
+    spec: GridSpec,
+    min_reflectivity: f32,
+    max_displacement: f32,
+    max_rotation: f32,
+    max_gap: Duration,
+    max_azimuth_bias: f32,
+    max_speed: f32,
+    min_correlation: f32,
+}
+
+impl RegistrationOptions {
+    /// Create the default options: reflectivity from 10 dBZ gridded over 240 km at 2 km, searched
+    /// for displacements up to 30 km and rotations up to 10°, between volumes at most 15 minutes
+    /// apart. Pairs are anomalous if their fields, correlated at 0.5 or better, are rotated by at
+    /// least 1° or moved faster than 40 m/s.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The grid low-level reflectivity is compared on, centered on the radar.
+    #[must_use]
+    pub fn with_spec(mut self, spec: GridSpec) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    /// The reflectivity in dBZ below which echo is considered absent. Weaker gates are raised to
+    /// it so that noise does not affect the comparison.
+    #[must_use]
+    pub fn with_min_reflectivity(mut self, min_reflectivity: f32) -> Self {
+        self.min_reflectivity = min_reflectivity;
+        self
+    }
+
+    /// The greatest displacement in meters and rotation in degrees searched for.
+    #[must_use]
+    pub fn with_search(mut self, max_displacement: f32, max_rotation: f32) -> Self {
+        self.max_displacement = max_displacement;
+        self.max_rotation = max_rotation;
+        self
+    }
+
+    /// The greatest time between volumes compared by [``check_series``].
+    #[must_use]
+    pub fn with_max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// The rotation in degrees and the speed in meters per second at and above which a pair is
+    /// anomalous.
+    #[must_use]
+    pub fn with_thresholds(mut self, max_azimuth_bias: f32, max_speed: f32) -> Self {
+        self.max_azimuth_bias = max_azimuth_bias;
+        self.max_speed = max_speed;
+        self
+    }
+
+    /// The least correlation between the aligned fields for a pair to be judged at all, as
+    /// estimates from fields with little echo in common are unreliable.
+    #[must_use]
+    pub fn with_min_correlation(mut self, min_correlation: f32) -> Self {
+        self.min_correlation = min_correlation;
+        self
+    }
+}
+
+impl Default for RegistrationOptions {
+    fn default() -> Self {
+        Self {
+            spec: GridSpec::new(120, 120, 2000.0),
+            min_reflectivity: 10.0,
+            max_displacement: 30_000.0,
+            max_rotation: 10.0,
+            max_gap: Duration::minutes(15),
+            max_azimuth_bias: 1.0,
+            max_speed: 40.0,
+            min_correlation: 0.5,
+        }
+    }
+}
+
+/// An apparent problem with a volume's georegistration relative to the previous volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationAnomaly {
+    /// The field rotated about the radar, suggesting the azimuth encoder shifted.
+    AzimuthShift,
+    /// The field moved implausibly fast for the volumes' times, suggesting a clock error.
+    ImplausibleMotion,
+}
+
+/// The comparison of a volume's low-level reflectivity with the previous volume's.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistrationCheck {
+    earlier_time: NaiveDateTime,
+    later_time: NaiveDateTime,
+    azimuth_bias: f32,
+    displacement: (f32, f32),
+    correlation: f32,
+    anomalies: Vec<RegistrationAnomaly>,
+}
+
+impl RegistrationCheck {
+    /// The start time of the earlier volume.
+    #[must_use]
+    pub fn earlier_time(&self) -> NaiveDateTime {
+        self.earlier_time
+    }
+
+    /// The start time of the later volume.
+    #[must_use]
+    pub fn later_time(&self) -> NaiveDateTime {
+        self.later_time
+    }
+
+    /// The estimated azimuth bias of the later volume relative to the earlier, in degrees
+    /// clockwise: the rotation about the radar best aligning the earlier field with the later.
+    #[must_use]
+    pub fn azimuth_bias(&self) -> f32 {
+        self.azimuth_bias
+    }
+
+    /// How far echoes appear to have moved between the volumes, in meters east and north.
+    #[must_use]
+    pub fn displacement(&self) -> (f32, f32) {
+        self.displacement
+    }
+
+    /// The speed in meters per second echoes appear to have moved at, given the volumes' times.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn apparent_speed(&self) -> f32 {
+        let seconds = (self.later_time - self.earlier_time).num_milliseconds() as f32 / 1000.0;
+        self.displacement.0.hypot(self.displacement.1) / seconds.max(f32::EPSILON)
+    }
+
+    /// The correlation between the later field and the earlier field once aligned, from -1 to 1.
+    #[must_use]
+    pub fn correlation(&self) -> f32 {
+        self.correlation
+    }
+
+    /// The anomalies found, if the fields were correlated well enough to judge.
+    #[must_use]
+    pub fn anomalies(&self) -> &[RegistrationAnomaly] {
+        &self.anomalies
+    }
+
+    /// Whether any anomaly was found.
+    #[must_use]
+    pub fn is_anomalous(&self) -> bool {
+        !self.anomalies.is_empty()
+    }
+}
+
+/// Compares the low-level reflectivity of two consecutive volumes from the same radar, estimating
+/// the later volume's azimuth bias and the echoes' apparent motion relative to the earlier.
+///
+/// # Errors
+/// Returns an error if the volumes are from different radars, or if their times are unknown or
+/// out of order.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+pub fn check_registration(
+    earlier: &DataFile,
+    later: &DataFile,
+    options: &RegistrationOptions,
+) -> Result<RegistrationCheck> {
+    VolumePair::new(earlier, later, Duration::MAX)?;
+    let (Some(earlier_time), Some(later_time)) = (
+        earlier.volume_header().date_time(),
+        later.volume_header().date_time(),
+    ) else {
+        return Err(Error::MissingVolumeTime.into());
+    };
+
+    let floor = |value: &Option<f32>| value.map(|value| value.max(options.min_reflectivity));
+    let low_level = |file| {
+        grid_layer(
+            file,
+            Product::Reflectivity,
+            VolumeLayer::LowestTilt,
+            &options.spec,
+        )
+        .map(floor)
+    };
+    let (earlier, later) = (low_level(earlier), low_level(later));
+
+    // Alignment starts both from the echoes' motion and from none, as a rotation large enough to
+    // be mistaken for motion is otherwise only partly recovered
+    let cell_size = options.spec.cell_size_m();
+    let max_shift = (options.max_displacement / cell_size).ceil() as isize;
+    let motion = estimate_motion(&earlier, &later, max_shift);
+    let (azimuth_bias, shift, _, correlation) = [motion, (0, 0)]
+        .into_iter()
+        .map(|shift| align(&earlier, &later, &options.spec, shift, max_shift, options))
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .unwrap_or((0.0, motion, f32::INFINITY, 0.0));
+    let displacement = (shift.0 as f32 * cell_size, -shift.1 as f32 * cell_size);
+
+    let mut check = RegistrationCheck {
+        earlier_time,
+        later_time,
+        azimuth_bias,
+        displacement,
+        correlation,
+        anomalies: Vec::new(),
+    };
+    if correlation >= options.min_correlation {
+        if azimuth_bias.abs() >= options.max_azimuth_bias {
+            check.anomalies.push(RegistrationAnomaly::AzimuthShift);
+        }
+        if check.apparent_speed() >= options.max_speed {
+            check.anomalies.push(RegistrationAnomaly::ImplausibleMotion);
+        }
+    }
+
+    Ok(check)
+}
+
+/// Compares each pair of consecutive volumes in a series no more than the maximum gap apart, in
+/// order. Pairs which cannot be compared are omitted.
+#[must_use]
+pub fn check_series(
+    series: &VolumeSeries,
+    options: &RegistrationOptions,
+) -> Vec<RegistrationCheck> {
+    let volumes: Vec<_> = series.volumes().collect();
+
+    volumes
+        .windows(2)
+        .filter(|pair| VolumePair::new(pair[0], pair[1], options.max_gap).is_ok())
+        .filter_map(|pair| check_registration(pair[0], pair[1], options).ok())
+        .collect()
+}
+
+/// Aligns the earlier field with the later from a starting shift, estimating rotation and
+/// translation alternately as each biases the other's estimate when echoes are concentrated away
+/// from the radar. Returns the rotation, the shift, and the aligned fields' mean absolute
+/// difference and correlation.
+fn align(
+    earlier: &Grid<Option<f32>>,
+    later: &Grid<Option<f32>>,
+    spec: &GridSpec,
+    mut shift: (isize, isize),
+    max_shift: isize,
+    options: &RegistrationOptions,
+) -> (f32, (isize, isize), f32, f32) {
+    let mut rotation = 0.0;
+    for _ in 0..MAX_ITERATIONS {
+        let next_rotation = best_rotation(earlier, later, spec, shift, options.max_rotation);
+        let next_shift = estimate_motion(&rotate(earlier, spec, next_rotation), later, max_shift);
+
+        let converged = next_shift == shift && (next_rotation - rotation).abs() < 0.25;
+        (rotation, shift) = (next_rotation, next_shift);
+        if converged {
+            break;
+        }
+    }
+
+    let (difference, correlation) = compare(&rotate(earlier, spec, rotation), later, shift);
+    (rotation, shift, difference, correlation)
+}
+
+/// The rotation in degrees clockwise, within the greatest searched for, which best aligns the
+/// earlier field with the later given a shift. Rotations are tried in half-degree steps and
+/// refined by fitting a parabola to the best's neighbors.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn best_rotation(
+    earlier: &Grid<Option<f32>>,
+    later: &Grid<Option<f32>>,
+    spec: &GridSpec,
+    shift: (isize, isize),
+    max_rotation: f32,
+) -> f32 {
+    let steps = (max_rotation * 2.0).round() as i32;
+    let scores: Vec<(f32, f32)> = (-steps..=steps)
+        .map(|step| {
+            let rotation = step as f32 / 2.0;
+            let (difference, _) = compare(&rotate(earlier, spec, rotation), later, shift);
+            (rotation, difference)
+        })
+        .collect();
+
+    // Smaller rotations win ties
+    let Some(best) = scores
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.1.total_cmp(&b.1).then(a.0.abs().total_cmp(&b.0.abs())))
+        .map(|(index, _)| index)
+    else {
+        return 0.0;
+    };
+
+    let (rotation, score) = scores[best];
+    let (Some(before), Some(after)) = (
+        best.checked_sub(1).and_then(|index| scores.get(index)),
+        scores.get(best + 1),
+    ) else {
+        return rotation;
+    };
+    let curvature = before.1 - 2.0 * score + after.1;
+    if curvature > 0.0 && curvature.is_finite() {
+        rotation + 0.25 * (before.1 - after.1) / curvature
+    } else {
+        rotation
+    }
+}
+
+/// A field rotated clockwise about the grid's center by `rotation` degrees, sampling the nearest
+/// cell.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn rotate(grid: &Grid<Option<f32>>, spec: &GridSpec, rotation: f32) -> Grid<Option<f32>> {
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let cell_size = spec.cell_size_m();
+    let (first_x, first_y) = spec.cell_center(0, 0);
+
+    spec.generate(|x, y| {
+        // Echo at an azimuth came from the azimuth `rotation` degrees counterclockwise of it
+        let (x, y) = (x * cos - y * sin, y * cos + x * sin);
+        let column = ((x - first_x) / cell_size).round();
+        let row = ((first_y - y) / cell_size).round();
+        if column < 0.0 || row < 0.0 {
+            return None;
+        }
+        grid.get(column as usize, row as usize).copied().flatten()
+    })
+}
+
+/// Compares the later field with the earlier shifted by `shift` cells east and south, returning
+/// the mean absolute difference and the correlation over cells where both have values.
+#[allow(clippy::cast_precision_loss, clippy::similar_names)]
+fn compare(
+    earlier: &Grid<Option<f32>>,
+    later: &Grid<Option<f32>>,
+    shift: (isize, isize),
+) -> (f32, f32) {
+    let mut pairs = Vec::new();
+    for row in 0..later.rows() {
+        for column in 0..later.columns() {
+            let (Some(source_column), Some(source_row)) = (
+                column.checked_add_signed(-shift.0),
+                row.checked_add_signed(-shift.1),
+            ) else {
+                continue;
+            };
+            if let (Some(Some(earlier)), Some(Some(later))) = (
+                earlier.get(source_column, source_row),
+                later.get(column, row),
+            ) {
+                pairs.push((*earlier, *later));
+            }
+        }
+    }
+
+    if pairs.is_empty() {
+        return (f32::INFINITY, 0.0);
+    }
+
+    let count = pairs.len() as f32;
+    let difference = pairs.iter().map(|(a, b)| (a - b).abs()).sum::<f32>() / count;
+    let mean_a = pairs.iter().map(|(a, _)| a).sum::<f32>() / count;
+    let mean_b = pairs.iter().map(|(_, b)| b).sum::<f32>() / count;
+    let (covariance, variance_a, variance_b) = pairs.iter().fold(
+        (0.0, 0.0, 0.0),
+        |(covariance, variance_a, variance_b), (a, b)| {
+            let (da, db) = (a - mean_a, b - mean_b);
+            (
+                covariance + da * db,
+                variance_a + da * da,
+                variance_b + db * db,
+            )
+        },
+    );
+    let correlation = if variance_a > 0.0 && variance_b > 0.0 {
+        covariance / (variance_a * variance_b).sqrt()
+    } else {
+        0.0
+    };
+
+    (difference, correlation)
+}
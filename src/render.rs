@@ -0,0 +1,744 @@
+//!
+//! Provides [``ColorMap``] for coloring product values when rendering, [``Palette``] for loading
+//! color tables in the `GR2Analyst` `.pal` format widely shared by the radar community, and
+//! utilities like [``render_all_elevations``] for drawing sweeps into PNG images.
+//!
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::cancel::CancellationToken;
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::gate::GateValue;
+use crate::grid::{grid_sweep_gates, grid_sweep_gates_cancellable, Grid, GridSpec};
+use crate::records::{Message31, Product};
+use crate::sweep::SweepCapabilities;
+
+/// The NWS reflectivity colors in 5 dBZ steps from 5 to 75 dBZ.
+const REFLECTIVITY_COLORS: [(f32, Rgba); 15] = [
+    (5.0, [4, 233, 231, 255]),
+    (10.0, [1, 159, 244, 255]),
+    (15.0, [3, 0, 244, 255]),
+    (20.0, [2, 253, 2, 255]),
+    (25.0, [1, 197, 1, 255]),
+    (30.0, [0, 142, 0, 255]),
+    (35.0, [253, 248, 2, 255]),
+    (40.0, [229, 188, 0, 255]),
+    (45.0, [253, 149, 0, 255]),
+    (50.0, [253, 0, 0, 255]),
+    (55.0, [212, 0, 0, 255]),
+    (60.0, [188, 0, 0, 255]),
+    (65.0, [248, 0, 253, 255]),
+    (70.0, [152, 84, 198, 255]),
+    (75.0, [253, 253, 253, 255]),
+];
+
+/// Inbound velocities in green and outbound in red, in m/s.
+const VELOCITY_COLORS: [(f32, Rgba); 5] = [
+    (-40.0, [0, 255, 0, 255]),
+    (-10.0, [0, 110, 0, 255]),
+    (0.0, [70, 70, 70, 255]),
+    (10.0, [110, 0, 0, 255]),
+    (40.0, [255, 0, 0, 255]),
+];
+
+/// A perceptually ordered ramp from dark blue through green to yellow, spanning zero to one.
+const RAMP_COLORS: [(f32, Rgba); 5] = [
+    (0.0, [68, 1, 84, 255]),
+    (0.25, [59, 82, 139, 255]),
+    (0.5, [33, 145, 140, 255]),
+    (0.75, [94, 201, 98, 255]),
+    (1.0, [253, 231, 37, 255]),
+];
+
+/// The color of range folded gates in the default palettes.
+const RANGE_FOLDED_COLOR: Rgba = [119, 0, 158, 255];
+
+/// A color's red, green, blue, and alpha components.
+pub type Rgba = [u8; 4];
+
+/// Maps product values to colors.
+pub trait ColorMap {
+    /// The color for a value in the product's units, or `None` if it should be left transparent.
+    fn color(&self, value: f32) -> Option<Rgba>;
+
+    /// The color for range folded gates, or `None` if they should be left transparent.
+    fn range_folded_color(&self) -> Option<Rgba> {
+        None
+    }
+
+    /// The color for a gate. Gates below threshold are left transparent.
+    fn gate_color(&self, gate: GateValue) -> Option<Rgba> {
+        match gate {
+            GateValue::Value(value) => self.color(value),
+            GateValue::RangeFolded => self.range_folded_color(),
+            GateValue::BelowThreshold => None,
+        }
+    }
+}
+
+impl<F: Fn(f32) -> Option<Rgba>> ColorMap for F {
+    fn color(&self, value: f32) -> Option<Rgba> {
+        self(value)
+    }
+}
+
+/// A color table parsed from the `GR2Analyst` `.pal` format. Each `Color` line starts a breakpoint
+/// whose color blends towards either its own second color or the next breakpoint's color, while
+/// `SolidColor` breakpoints are not blended. Values below the first breakpoint are transparent and
+/// values above the last take its color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    product: Option<String>,
+    units: Option<String>,
+    scale: f32,
+    offset: f32,
+    breakpoints: Vec<Breakpoint>,
+    range_folded: Option<Rgba>,
+}
+
+/// A palette value at which a color begins.
+#[derive(Debug, Clone, PartialEq)]
+struct Breakpoint {
+    value: f32,
+    color: Rgba,
+    end_color: Option<Rgba>,
+    solid: bool,
+}
+
+impl Palette {
+    /// Parses a palette from the contents of a `.pal` file. Keys are case-insensitive, comments
+    /// begin with `;`, and unrecognized keys such as `Step` are ignored.
+    ///
+    /// # Errors
+    /// Returns an error if a recognized line is malformed or the palette has no colors.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut palette = Self {
+            product: None,
+            units: None,
+            scale: 1.0,
+            offset: 0.0,
+            breakpoints: Vec::new(),
+            range_folded: None,
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            let invalid = || Error::InvalidPalette(index + 1);
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "product" => palette.product = Some(value.to_string()),
+                "units" => palette.units = Some(value.to_string()),
+                "scale" => palette.scale = value.parse().map_err(|_| invalid())?,
+                "offset" => palette.offset = value.parse().map_err(|_| invalid())?,
+                "rf" => {
+                    let fields = parse_numbers(value).ok_or_else(invalid)?;
+                    let colors = parse_colors(&fields, fields.len() == 4).ok_or_else(invalid)?;
+                    palette.range_folded = colors.first().copied();
+                }
+                key @ ("color" | "color4" | "solidcolor" | "solidcolor4") => {
+                    let fields = parse_numbers(value).ok_or_else(invalid)?;
+                    let (value, components) = fields.split_first().ok_or_else(invalid)?;
+                    let colors =
+                        parse_colors(components, key.ends_with('4')).ok_or_else(invalid)?;
+
+                    let solid = key.starts_with("solid");
+                    if solid && colors.len() != 1 {
+                        return Err(invalid().into());
+                    }
+
+                    palette.breakpoints.push(Breakpoint {
+                        value: *value,
+                        color: colors[0],
+                        end_color: colors.get(1).copied(),
+                        solid,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if palette.breakpoints.is_empty() {
+            return Err(Error::EmptyPalette.into());
+        }
+
+        palette
+            .breakpoints
+            .sort_by(|a, b| a.value.total_cmp(&b.value));
+
+        Ok(palette)
+    }
+
+    /// A default palette for the specified product: the NWS reflectivity colors, a green-to-red
+    /// velocity scale, or a blended ramp spanning each other product's typical values.
+    #[must_use]
+    pub fn for_product(product: Product) -> Self {
+        // Ramp breakpoints are scaled from fractions onto the product's typical values
+        let (units, colors, solid, start, span): (&str, &[(f32, Rgba)], bool, f32, f32) =
+            match product {
+                Product::Reflectivity => ("dBZ", &REFLECTIVITY_COLORS, true, 0.0, 1.0),
+                Product::Velocity => ("m/s", &VELOCITY_COLORS, false, 0.0, 1.0),
+                Product::SpectrumWidth => ("m/s", &RAMP_COLORS, false, 0.0, 15.0),
+                Product::DifferentialReflectivity => ("dB", &RAMP_COLORS, false, -2.0, 8.0),
+                Product::DifferentialPhase => ("deg", &RAMP_COLORS, false, 0.0, 360.0),
+                Product::CorrelationCoefficient => ("", &RAMP_COLORS, false, 0.2, 0.85),
+                Product::ClutterFilterProbability => ("", &RAMP_COLORS, false, 0.0, 100.0),
+            };
+
+        Self {
+            product: None,
+            units: Some(units.to_string()).filter(|units| !units.is_empty()),
+            scale: 1.0,
+            offset: 0.0,
+            breakpoints: colors
+                .iter()
+                .map(|(value, color)| Breakpoint {
+                    value: start + value * span,
+                    color: *color,
+                    end_color: None,
+                    solid,
+                })
+                .collect(),
+            range_folded: Some(RANGE_FOLDED_COLOR),
+        }
+    }
+
+    /// Loads a palette from a `.pal` file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a valid palette.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The product the palette is intended for, e.g. `BR` for base reflectivity.
+    #[must_use]
+    pub fn product(&self) -> Option<&str> {
+        self.product.as_deref()
+    }
+
+    /// The units of the palette's values, e.g. `dBZ`.
+    #[must_use]
+    pub fn units(&self) -> Option<&str> {
+        self.units.as_deref()
+    }
+
+    /// The factor applied to product values before lookup, e.g. to convert m/s to knots.
+    #[must_use]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The amount added to product values after scaling, before lookup.
+    #[must_use]
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+}
+
+impl ColorMap for Palette {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::float_cmp
+    )]
+    fn color(&self, value: f32) -> Option<Rgba> {
+        let value = value * self.scale + self.offset;
+
+        let index = self
+            .breakpoints
+            .partition_point(|breakpoint| breakpoint.value <= value)
+            .checked_sub(1)?;
+        let breakpoint = &self.breakpoints[index];
+        if breakpoint.solid {
+            return Some(breakpoint.color);
+        }
+
+        let Some(next) = self.breakpoints.get(index + 1) else {
+            return Some(breakpoint.color);
+        };
+        if next.value == breakpoint.value {
+            return Some(breakpoint.color);
+        }
+
+        let end_color = breakpoint.end_color.unwrap_or(next.color);
+        let fraction = (value - breakpoint.value) / (next.value - breakpoint.value);
+
+        let mut color = breakpoint.color;
+        for (component, end) in color.iter_mut().zip(end_color) {
+            let start = f32::from(*component);
+            *component = (start + (f32::from(end) - start) * fraction).round() as u8;
+        }
+
+        Some(color)
+    }
+
+    fn range_folded_color(&self) -> Option<Rgba> {
+        self.range_folded
+    }
+}
+
+/// Parses a line's whitespace-separated numbers.
+fn parse_numbers(text: &str) -> Option<Vec<f32>> {
+    text.split_whitespace()
+        .map(|field| field.parse().ok())
+        .collect()
+}
+
+/// Groups color components into one or two colors, with alpha if specified or opaque otherwise.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_colors(components: &[f32], alpha: bool) -> Option<Vec<Rgba>> {
+    let width = if alpha { 4 } else { 3 };
+    if components.is_empty()
+        || !components.len().is_multiple_of(width)
+        || components.len() > 2 * width
+    {
+        return None;
+    }
+
+    components
+        .chunks(width)
+        .map(|chunk| {
+            if chunk
+                .iter()
+                .any(|component| !(0.0..=255.0).contains(component))
+            {
+                return None;
+            }
+
+            let mut color = [0, 0, 0, 255];
+            for (target, component) in color.iter_mut().zip(chunk) {
+                *target = component.round() as u8;
+            }
+
+            Some(color)
+        })
+        .collect()
+}
+
+/// An image of RGBA pixels stored in row-major order, with row 0 at the top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Rgba>,
+}
+
+impl Image {
+    /// Create a new image with every pixel set to the specified color.
+    #[must_use]
+    pub fn filled(width: u32, height: u32, color: Rgba) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; width as usize * height as usize],
+        }
+    }
+
+    /// Width of the image in pixels.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image in pixels.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The color of the specified pixel, if within the image.
+    #[must_use]
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Rgba> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        self.pixels
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+    }
+
+    /// The image's pixels in row-major order.
+    #[must_use]
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    /// Encodes the image as a PNG.
+    ///
+    /// # Errors
+    /// Returns an error if the image cannot be encoded.
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut png = Vec::new();
+
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_flattened())?;
+        writer.finish()?;
+
+        Ok(png)
+    }
+
+    /// Encodes the image as a PNG and writes it to the specified path.
+    ///
+    /// # Errors
+    /// Returns an error if the image cannot be encoded or written.
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_png()?)?;
+        Ok(())
+    }
+
+    /// Copies the image into an [``image::RgbaImage``] for further processing or encoding to other
+    /// formats.
+    #[must_use]
+    #[cfg(feature = "image")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+    pub fn to_rgba_image(&self) -> image::RgbaImage {
+        image::RgbaImage::from_fn(self.width, self.height, |x, y| {
+            image::Rgba(self.pixel(x, y).unwrap_or_default())
+        })
+    }
+
+    /// Copies another image into this one with its top-left corner at the specified pixel,
+    /// clipping it to this image's bounds.
+    fn draw(&mut self, image: &Image, left: u32, top: u32) {
+        for y in 0..image.height.min(self.height.saturating_sub(top)) {
+            for x in 0..image.width.min(self.width.saturating_sub(left)) {
+                let target = (top + y) as usize * self.width as usize + (left + x) as usize;
+                self.pixels[target] = image.pixels[y as usize * image.width as usize + x as usize];
+            }
+        }
+    }
+}
+
+/// Options controlling how sweeps are rendered.
+///
+/// Options are deserializable with serde from their settings, `size`, `max_range`, `background`
+/// as `[r, g, b, a]`, and `palette` as the path of a `.pal` file which is loaded when deserialized.
+/// Unspecified settings take their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RenderSettings")]
+pub struct RenderOptions {
+    size: u32,
+    max_range: f32,
+    palette: Option<Palette>,
+    background: Rgba,
+}
+
+impl RenderOptions {
+    /// Create the default options: 512 pixel square images spanning 230 km from the radar on a
+    /// transparent background, colored with the product's default palette.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The width and height of each rendered sweep in pixels.
+    ///
+    /// # Panics
+    /// Panics if the size is zero.
+    #[must_use]
+    pub fn with_size(mut self, size: u32) -> Self {
+        assert!(size > 0, "size must be positive");
+        self.size = size;
+        self
+    }
+
+    /// The range in meters from the radar to the edges of each rendered sweep.
+    ///
+    /// # Panics
+    /// Panics if the range is not positive.
+    #[must_use]
+    pub fn with_max_range(mut self, max_range: f32) -> Self {
+        assert!(max_range > 0.0, "max range must be positive");
+        self.max_range = max_range;
+        self
+    }
+
+    /// The palette used to color gates, in place of the product's default palette.
+    #[must_use]
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = Some(palette);
+        self
+    }
+
+    /// The color of pixels without a colored gate.
+    #[must_use]
+    pub fn with_background(mut self, background: Rgba) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// The width and height of each rendered sweep in pixels.
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The range in meters from the radar to the edges of each rendered sweep.
+    #[must_use]
+    pub fn max_range(&self) -> f32 {
+        self.max_range
+    }
+
+    /// The palette used to color gates, if not the product's default.
+    #[must_use]
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    /// The color of pixels without a colored gate.
+    #[must_use]
+    pub fn background(&self) -> Rgba {
+        self.background
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            size: 512,
+            max_range: 230_000.0,
+            palette: None,
+            background: [0, 0, 0, 0],
+        }
+    }
+}
+
+/// The deserialized settings of [``RenderOptions``].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderSettings {
+    #[serde(default)]
+    size: Option<u32>,
+    #[serde(default)]
+    max_range: Option<f32>,
+    #[serde(default)]
+    palette: Option<PathBuf>,
+    #[serde(default)]
+    background: Option<Rgba>,
+}
+
+impl TryFrom<RenderSettings> for RenderOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(settings: RenderSettings) -> Result<Self> {
+        let mut options = Self::new();
+        if let Some(size) = settings.size {
+            if size == 0 {
+                return Err(Error::InvalidRenderOption("size must be positive").into());
+            }
+            options.size = size;
+        }
+        if let Some(max_range) = settings.max_range {
+            if max_range.is_nan() || max_range <= 0.0 {
+                return Err(Error::InvalidRenderOption("max range must be positive").into());
+            }
+            options.max_range = max_range;
+        }
+        if let Some(palette) = settings.palette {
+            options.palette = Some(Palette::from_file(palette)?);
+        }
+        if let Some(background) = settings.background {
+            options.background = background;
+        }
+
+        Ok(options)
+    }
+}
+
+/// Renders a product from a sweep's radials as seen from above, with the radar at the center of
+/// the image and north at the top. The options' palette is ignored in favor of `color_map`.
+#[must_use]
+pub fn render_sweep<C: ColorMap + ?Sized>(
+    radials: &[Message31],
+    product: Product,
+    color_map: &C,
+    options: &RenderOptions,
+) -> Image {
+    let gates = grid_sweep_gates(radials, product, &render_spec(options));
+    color_gates(&gates, color_map, options)
+}
+
+/// Renders a product from a sweep's radials like [``render_sweep``], checking the token for
+/// cancellation before each row of the image.
+///
+/// # Errors
+/// Returns an error if rendering was cancelled.
+pub fn render_sweep_cancellable<C: ColorMap + ?Sized>(
+    radials: &[Message31],
+    product: Product,
+    color_map: &C,
+    options: &RenderOptions,
+    token: &CancellationToken,
+) -> Result<Image> {
+    let gates = grid_sweep_gates_cancellable(radials, product, &render_spec(options), token)?;
+    Ok(color_gates(&gates, color_map, options))
+}
+
+/// The grid of an image's pixels, centered on the radar.
+#[allow(clippy::cast_precision_loss)]
+fn render_spec(options: &RenderOptions) -> GridSpec {
+    GridSpec::new(
+        options.size as usize,
+        options.size as usize,
+        2.0 * options.max_range / options.size as f32,
+    )
+}
+
+/// Colors gridded gates as an image's pixels.
+fn color_gates<C: ColorMap + ?Sized>(
+    gates: &Grid<Option<GateValue>>,
+    color_map: &C,
+    options: &RenderOptions,
+) -> Image {
+    Image {
+        width: options.size,
+        height: options.size,
+        pixels: gates
+            .values()
+            .iter()
+            .map(|gate| {
+                gate.and_then(|gate| color_map.gate_color(gate))
+                    .unwrap_or(options.background)
+            })
+            .collect(),
+    }
+}
+
+/// A rendered sweep and the elevation it was collected at.
+#[derive(Debug, Clone)]
+pub struct ElevationImage {
+    elevation_number: u8,
+    elevation: f32,
+    image: Image,
+}
+
+impl ElevationImage {
+    /// The sweep's elevation number within its volume.
+    #[must_use]
+    pub fn elevation_number(&self) -> u8 {
+        self.elevation_number
+    }
+
+    /// The sweep's mean elevation angle in degrees.
+    #[must_use]
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+
+    /// The rendered sweep.
+    #[must_use]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+}
+
+/// Every sweep of a volume containing a product, rendered individually in elevation number order.
+#[derive(Debug, Clone)]
+pub struct ElevationImages {
+    frames: Vec<ElevationImage>,
+}
+
+impl ElevationImages {
+    /// The rendered sweeps, one frame per elevation.
+    #[must_use]
+    pub fn frames(&self) -> &[ElevationImage] {
+        &self.frames
+    }
+
+    /// Consumes the renders, returning their frames.
+    #[must_use]
+    pub fn into_frames(self) -> Vec<ElevationImage> {
+        self.frames
+    }
+
+    /// Tiles the frames into a single contact sheet image with the specified number of columns,
+    /// left to right then top to bottom, leaving unused tiles transparent.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn contact_sheet(&self, columns: usize) -> Image {
+        let columns = columns.clamp(1, self.frames.len().max(1));
+        let rows = self.frames.len().div_ceil(columns);
+
+        let (width, height) = self
+            .frames
+            .first()
+            .map_or((0, 0), |frame| (frame.image.width, frame.image.height));
+
+        let mut sheet = Image::filled(width * columns as u32, height * rows as u32, [0, 0, 0, 0]);
+        for (index, frame) in self.frames.iter().enumerate() {
+            let column = (index % columns) as u32;
+            let row = (index / columns) as u32;
+            sheet.draw(&frame.image, column * width, row * height);
+        }
+
+        sheet
+    }
+
+    /// Writes each frame to the specified directory as a PNG named by its elevation number, e.g.
+    /// `elevation_01.png`, returning the paths written.
+    ///
+    /// # Errors
+    /// Returns an error if a frame cannot be encoded or written.
+    pub fn write_frames<P: AsRef<Path>>(&self, directory: P) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::with_capacity(self.frames.len());
+
+        for frame in &self.frames {
+            let path = directory
+                .as_ref()
+                .join(format!("elevation_{:02}.png", frame.elevation_number));
+            frame.image.write_png(&path)?;
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+}
+
+/// Renders every sweep of a volume containing the product, e.g. to review a volume's full
+/// vertical structure as a set of frames or a single contact sheet.
+///
+/// # Errors
+/// Returns an error if no sweep contains the product.
+#[allow(clippy::cast_precision_loss)]
+pub fn render_all_elevations(
+    file: &DataFile,
+    product: Product,
+    options: &RenderOptions,
+) -> Result<ElevationImages> {
+    let palette = options
+        .palette
+        .clone()
+        .unwrap_or_else(|| Palette::for_product(product));
+
+    let frames: Vec<ElevationImage> = file
+        .elevation_scans()
+        .iter()
+        .filter(|(_, radials)| SweepCapabilities::from_radials(radials).has(product))
+        .map(|(elevation_number, radials)| ElevationImage {
+            elevation_number: *elevation_number,
+            elevation: radials.iter().map(|r| r.header().elev()).sum::<f32>()
+                / radials.len() as f32,
+            image: render_sweep(radials, product, &palette, options),
+        })
+        .collect();
+
+    if frames.is_empty() {
+        return Err(Error::MissingRadials.into());
+    }
+
+    Ok(ElevationImages { frames })
+}
@@ -0,0 +1,524 @@
+//!
+//! Provides [``read_sigmet_raw``] for importing Sigmet/IRIS RAW product files, produced by many
+//! private and international C-band radars, into a [``DataFile``] so they can be processed with
+//! the same products and renderers as NEXRAD volumes.
+//!
+//! A RAW file is a sequence of 6144-byte little-endian records: a product header, an ingest header
+//! describing the site and task, then data records holding each sweep's run-length compressed rays.
+//!
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::records::{
+    to_archive_date_time, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+    Message31Header, RadialData, VolumeData, VolumeHeaderRecord,
+};
+use crate::sweep::Sweep;
+
+/// The size of each record in a RAW file.
+const RECORD_SIZE: usize = 6144;
+
+/// The size of the header at the start of each data record.
+const RECORD_HEADER_SIZE: usize = 12;
+
+/// The size of each ingest data header at the start of a sweep's first record.
+const INGEST_DATA_HEADER_SIZE: usize = 76;
+
+/// Structure identifiers of the headers a RAW file begins with.
+const PRODUCT_HEADER_ID: i16 = 27;
+const INGEST_HEADER_ID: i16 = 23;
+const INGEST_DATA_HEADER_ID: i16 = 24;
+
+/// Offsets within the ingest header of the fields read from it.
+const SITE_NAME_OFFSET: usize = 162;
+const LATITUDE_OFFSET: usize = 180;
+const LONGITUDE_OFFSET: usize = 184;
+const GROUND_HEIGHT_OFFSET: usize = 188;
+const RADAR_HEIGHT_OFFSET: usize = 190;
+const VOLUME_TIME_OFFSET: usize = 100;
+const DATA_MASK_OFFSET: usize = 628;
+const PRF_OFFSET: usize = 760;
+const MULTI_PRF_OFFSET: usize = 768;
+const FIRST_BIN_RANGE_OFFSET: usize = 1264;
+const OUTPUT_BIN_STEP_OFFSET: usize = 1280;
+const WAVELENGTH_OFFSET: usize = 1744;
+
+/// A moment's encoding in 16-bit words, as a scale and offset, chosen to retain the resolution of
+/// both 1- and 2-byte Sigmet data.
+const MOMENT_ENCODINGS: [(DataBlockProduct, f32, f32); 6] = [
+    (DataBlockProduct::Reflectivity, 100.0, 32768.0),
+    (DataBlockProduct::Velocity, 100.0, 32768.0),
+    (DataBlockProduct::SpectrumWidth, 100.0, 2.0),
+    (DataBlockProduct::DifferentialReflectivity, 100.0, 32768.0),
+    (DataBlockProduct::DifferentialPhase, 100.0, 2.0),
+    (DataBlockProduct::CorrelationCoefficient, 60000.0, 2.0),
+];
+
+/// Loads a Sigmet RAW product file from a file path.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is not a valid RAW file.
+pub fn read_sigmet_file(path: &Path) -> Result<DataFile> {
+    read_sigmet_raw(&std::fs::read(path)?)
+}
+
+/// Imports a Sigmet RAW product file. Each sweep becomes a sweep of the data file, and each data
+/// type this crate models becomes a moment: reflectivity (`DBZ`, or `DBT` if uncorrected
+/// reflectivity is all that was recorded), velocity, spectrum width, `ZDR`, `PHIDP`, and `RHOHV`,
+/// in either their 1- or 2-byte forms. Other data types are skipped. Rays are kept in the order
+/// they were recorded and rays which were not recorded are omitted.
+///
+/// # Errors
+/// Returns an error if the data is not a RAW file or its records are truncated or inconsistent.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn read_sigmet_raw(data: &[u8]) -> Result<DataFile> {
+    if data.len() < 2 * RECORD_SIZE || read_i16(data, 0)? != PRODUCT_HEADER_ID {
+        return Err(Error::InvalidSigmetFile("missing product header").into());
+    }
+
+    let ingest = &data[RECORD_SIZE..2 * RECORD_SIZE];
+    if read_i16(ingest, 0)? != INGEST_HEADER_ID {
+        return Err(Error::InvalidSigmetFile("missing ingest header").into());
+    }
+
+    let site = read_site(ingest)?;
+    let types = read_data_types(ingest)?;
+
+    // Group each sweep's data records, which are numbered from 1
+    let mut sweep_records: BTreeMap<i16, Vec<u8>> = BTreeMap::new();
+    for record in data[2 * RECORD_SIZE..].chunks(RECORD_SIZE) {
+        if record.len() < RECORD_HEADER_SIZE {
+            break;
+        }
+
+        let sweep_number = read_i16(record, 2)?;
+        if sweep_number < 1 {
+            continue;
+        }
+
+        sweep_records
+            .entry(sweep_number)
+            .or_default()
+            .extend_from_slice(&record[RECORD_HEADER_SIZE..]);
+    }
+
+    let mut sweeps = Vec::with_capacity(sweep_records.len());
+    for (sweep_number, stream) in sweep_records {
+        let elevation_number = u8::try_from(sweep_number)?;
+        sweeps.push(read_sweep(&stream, elevation_number, &types, &site)?);
+    }
+
+    let (volume_date, volume_millis) = to_archive_date_time(site.volume_time).ok_or(
+        Error::InvalidSigmetFile("volume time is outside the Archive II date range"),
+    )?;
+    let header = VolumeHeaderRecord::new(
+        *b"SIGMET.RAW  ",
+        volume_date.into(),
+        volume_millis,
+        site.radar_id,
+    );
+
+    DataFile::from_parts(header, sweeps)
+}
+
+/// Site and task parameters from the ingest header.
+struct Site {
+    radar_id: [u8; 4],
+    volume_time: NaiveDateTime,
+    volume_data: VolumeData,
+    nyquist_velocity: f32,
+    first_bin_range: f32,
+    bin_step: f32,
+}
+
+/// A data type recorded for each ray, identified by its IRIS data type code.
+#[derive(Debug, Clone)]
+struct DataType {
+    code: u32,
+    product: Option<DataBlockProduct>,
+}
+
+/// Reads the site and task parameters from the ingest header.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+fn read_site(ingest: &[u8]) -> Result<Site> {
+    let name = ingest
+        .get(SITE_NAME_OFFSET..SITE_NAME_OFFSET + 16)
+        .ok_or(Error::InvalidSigmetFile("truncated ingest header"))?;
+    let mut radar_id = *b"    ";
+    for (target, character) in radar_id.iter_mut().zip(
+        name.iter()
+            .filter(|character| character.is_ascii_alphanumeric()),
+    ) {
+        *target = character.to_ascii_uppercase();
+    }
+
+    let latitude = binary_angle_32(read_u32(ingest, LATITUDE_OFFSET)?);
+    let longitude = binary_angle_32(read_u32(ingest, LONGITUDE_OFFSET)?);
+    let site_height = read_i16(ingest, GROUND_HEIGHT_OFFSET)?;
+    let radar_height = read_i16(ingest, RADAR_HEIGHT_OFFSET)?;
+
+    // Nyquist velocity from the PRF and wavelength, extended by dual-PRF unfolding
+    let prf = read_i32(ingest, PRF_OFFSET)? as f32;
+    let wavelength = read_i32(ingest, WAVELENGTH_OFFSET)? as f32 / 10_000.0;
+    let multiplier = match read_u16(ingest, MULTI_PRF_OFFSET)? {
+        1 => 2.0,
+        2 => 3.0,
+        3 => 4.0,
+        _ => 1.0,
+    };
+
+    Ok(Site {
+        radar_id,
+        volume_time: read_time(ingest, VOLUME_TIME_OFFSET)?,
+        volume_data: VolumeData::new(
+            latitude as f32,
+            longitude as f32,
+            site_height,
+            radar_height,
+            0,
+        ),
+        nyquist_velocity: prf * wavelength / 4.0 * multiplier,
+        first_bin_range: read_i32(ingest, FIRST_BIN_RANGE_OFFSET)? as f32 / 100.0,
+        bin_step: read_i32(ingest, OUTPUT_BIN_STEP_OFFSET)? as f32 / 100.0,
+    })
+}
+
+/// Reads the data types recorded for each ray, in the order they are stored, from the current
+/// data type mask.
+fn read_data_types(ingest: &[u8]) -> Result<Vec<DataType>> {
+    // The mask's first word is followed by the extended header type, then the remaining words
+    let words = [
+        read_u32(ingest, DATA_MASK_OFFSET)?,
+        read_u32(ingest, DATA_MASK_OFFSET + 8)?,
+        read_u32(ingest, DATA_MASK_OFFSET + 12)?,
+        read_u32(ingest, DATA_MASK_OFFSET + 16)?,
+        read_u32(ingest, DATA_MASK_OFFSET + 20)?,
+    ];
+
+    let codes: Vec<u32> = (0..160)
+        .filter(|code| words[*code as usize / 32] & (1 << (code % 32)) != 0)
+        .collect();
+
+    // Uncorrected reflectivity is only used if corrected reflectivity wasn't recorded
+    let corrected = codes.iter().any(|code| matches!(code, 2 | 9));
+
+    Ok(codes
+        .into_iter()
+        .map(|code| DataType {
+            code,
+            product: match code {
+                1 | 8 if !corrected => Some(DataBlockProduct::Reflectivity),
+                2 | 9 => Some(DataBlockProduct::Reflectivity),
+                3 | 10 => Some(DataBlockProduct::Velocity),
+                4 | 11 => Some(DataBlockProduct::SpectrumWidth),
+                5 | 12 => Some(DataBlockProduct::DifferentialReflectivity),
+                16 | 24 => Some(DataBlockProduct::DifferentialPhase),
+                19 | 20 => Some(DataBlockProduct::CorrelationCoefficient),
+                _ => None,
+            },
+        })
+        .collect())
+}
+
+/// Reads a sweep from its records' concatenated contents, which begin with an ingest data header
+/// for each data type followed by each ray's compressed data types in turn.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn read_sweep(
+    stream: &[u8],
+    elevation_number: u8,
+    types: &[DataType],
+    site: &Site,
+) -> Result<Sweep> {
+    let mut bits_per_bin = Vec::with_capacity(types.len());
+    let mut sweep_time = site.volume_time;
+    let mut rays_expected = 0;
+    let mut rays_written = 0;
+    for index in 0..types.len() {
+        let header = stream
+            .get(index * INGEST_DATA_HEADER_SIZE..(index + 1) * INGEST_DATA_HEADER_SIZE)
+            .ok_or(Error::InvalidSigmetFile("truncated ingest data header"))?;
+        if read_i16(header, 0)? != INGEST_DATA_HEADER_ID {
+            return Err(Error::InvalidSigmetFile("missing ingest data header").into());
+        }
+
+        sweep_time = read_time(header, 12)?;
+        rays_expected = usize::try_from(read_i16(header, 30)?)?;
+        rays_written = usize::try_from(read_i16(header, 32)?)?;
+        bits_per_bin.push(read_i16(header, 36)?);
+    }
+
+    let mut reader = &stream[types.len() * INGEST_DATA_HEADER_SIZE..];
+    // Each ray expected has a slot, empty if the ray was not written, so slots are read only until
+    // every ray written has been, e.g. leaving the rest of an aborted sweep unread
+    let mut rays = Vec::with_capacity(rays_written);
+    for _ in 0..rays_expected.max(rays_written) {
+        if rays.len() == rays_written {
+            break;
+        }
+        let mut ray = None;
+
+        for (data_type, bits) in types.iter().zip(&bits_per_bin) {
+            let words = decompress_ray(&mut reader)?;
+            if words.len() < 6 {
+                continue;
+            }
+
+            // Each ray begins with a header of its start and end angles, bins, and time offset
+            let (_, moments) = ray.get_or_insert_with(|| (read_ray_header(&words), Vec::new()));
+
+            if let Some(product) = &data_type.product {
+                let bins = usize::from(words[4]);
+                moments.push(read_moment(
+                    data_type.code,
+                    product,
+                    *bits,
+                    &words[6..],
+                    bins,
+                    site,
+                )?);
+            }
+        }
+
+        rays.extend(ray);
+    }
+
+    let count = rays.len();
+    let mut radials = Vec::with_capacity(count);
+    for (index, ((azimuth, azimuth_resolution, elevation, time_offset), moments)) in
+        rays.into_iter().enumerate()
+    {
+        let ray_time = sweep_time + Duration::seconds(time_offset.into());
+        let (ray_date, ray_millis) = to_archive_date_time(ray_time).ok_or(
+            Error::InvalidSigmetFile("ray time is outside the Archive II date range"),
+        )?;
+
+        let mut radial = Message31::new(Message31Header::new(
+            site.radar_id,
+            ray_millis,
+            ray_date,
+            u16::try_from(index + 1)?,
+            azimuth,
+            azimuth_resolution,
+            radial_status(index, count),
+            elevation_number,
+            elevation,
+        ));
+
+        radial.set_volume_data(site.volume_data.clone());
+        radial.set_elevation_data(ElevationData::new([0, 0], 0.0));
+        radial.set_radial_data(RadialData::new(0, (site.nyquist_velocity * 100.0) as u16));
+        for moment in moments {
+            radial.set_data_moment(moment);
+        }
+
+        radials.push(radial);
+    }
+
+    Ok(Sweep::new(elevation_number, radials))
+}
+
+/// Reads a ray header's center azimuth, azimuth resolution code, mean elevation, and time offset
+/// in seconds from the sweep's start.
+fn read_ray_header(words: &[u16]) -> (f32, u8, f32, u16) {
+    let azimuth_start = binary_angle_16(words[0]);
+    let mut azimuth_end = binary_angle_16(words[2]);
+    if azimuth_end < azimuth_start {
+        azimuth_end += 360.0;
+    }
+
+    let elevation = f32::midpoint(
+        signed_binary_angle_16(words[1]),
+        signed_binary_angle_16(words[3]),
+    );
+
+    // Rays half a degree wide or narrower are super resolution
+    let azimuth_resolution = if azimuth_end - azimuth_start < 0.75 {
+        1
+    } else {
+        2
+    };
+
+    (
+        f32::midpoint(azimuth_start, azimuth_end) % 360.0,
+        azimuth_resolution,
+        elevation,
+        words[5],
+    )
+}
+
+/// The radial status of a ray: 0 for the first in a sweep, 2 for the last, and 1 otherwise.
+fn radial_status(index: usize, rays: usize) -> u8 {
+    match index {
+        0 => 0,
+        _ if index + 1 == rays => 2,
+        _ => 1,
+    }
+}
+
+/// Converts a ray's raw bins of a data type into a moment of 16-bit words.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn read_moment(
+    code: u32,
+    product: &DataBlockProduct,
+    bits_per_bin: i16,
+    words: &[u16],
+    bins: usize,
+    site: &Site,
+) -> Result<DataMoment> {
+    let (_, scale, offset) = MOMENT_ENCODINGS
+        .iter()
+        .find(|(encoded, _, _)| encoded == product)
+        .ok_or(Error::UnhandledProduct)?;
+
+    let nyquist = site.nyquist_velocity;
+    let mut moment_data = Vec::with_capacity(bins * 2);
+    for bin in 0..bins {
+        let (raw, max) = if bits_per_bin == 16 {
+            (
+                f32::from(words.get(bin).copied().unwrap_or_default()),
+                65535.0,
+            )
+        } else {
+            let word = words.get(bin / 2).copied().unwrap_or_default();
+            let byte = word.to_le_bytes()[bin % 2];
+            (f32::from(byte), 255.0)
+        };
+
+        // Zero is no data and the largest value an area that wasn't scanned
+        let value = if raw == 0.0 || raw >= max {
+            None
+        } else {
+            Some(match code {
+                1 | 2 => (raw - 64.0) / 2.0,
+                3 => (raw - 128.0) / 127.0 * nyquist,
+                4 => raw / 256.0 * nyquist,
+                5 => (raw - 128.0) / 16.0,
+                16 => 180.0 * (raw - 1.0) / 254.0,
+                19 => ((raw - 1.0) / 253.0).sqrt(),
+                11 => raw / 100.0,
+                24 => 360.0 * (raw - 1.0) / 65534.0,
+                20 => (raw - 1.0) / 65533.0,
+                _ => (raw - 32768.0) / 100.0,
+            })
+        };
+
+        let word = value.map_or(0, |value| {
+            (value * scale + offset).round().clamp(2.0, 65535.0) as u16
+        });
+        moment_data.extend_from_slice(&word.to_be_bytes());
+    }
+
+    let data = GenericData::new(
+        product,
+        u16::try_from(bins)?,
+        site.first_bin_range as u16,
+        site.bin_step as u16,
+        16,
+        *scale,
+        *offset,
+    );
+
+    Ok(DataMoment::new(product.clone(), data, moment_data))
+}
+
+/// Decompresses a ray from the run-length encoded stream, advancing past it. A word with its high
+/// bit set is followed by that many literal words, a word of 1 ends the ray, and any other word
+/// stands for that many zero words. A ray with no words was not recorded.
+fn decompress_ray(reader: &mut &[u8]) -> Result<Vec<u16>> {
+    let mut words = Vec::new();
+
+    loop {
+        let code = read_u16(reader, 0)?;
+        *reader = &reader[2..];
+
+        if code & 0x8000 != 0 {
+            let count = usize::from(code & 0x7FFF);
+            for index in 0..count {
+                words.push(read_u16(reader, index * 2)?);
+            }
+            *reader = &reader[count * 2..];
+        } else if code == 1 {
+            return Ok(words);
+        } else {
+            words.resize(words.len() + usize::from(code), 0);
+        }
+    }
+}
+
+/// Converts a 16-bit binary angle to degrees.
+fn binary_angle_16(angle: u16) -> f32 {
+    f32::from(angle) * 360.0 / 65536.0
+}
+
+/// Converts a 16-bit binary angle to degrees, with angles beyond 180 degrees negative.
+fn signed_binary_angle_16(angle: u16) -> f32 {
+    let degrees = binary_angle_16(angle);
+    if degrees > 180.0 {
+        degrees - 360.0
+    } else {
+        degrees
+    }
+}
+
+/// Converts a 32-bit binary angle to degrees, with angles beyond 180 degrees negative.
+fn binary_angle_32(angle: u32) -> f64 {
+    let degrees = f64::from(angle) * 360.0 / 4_294_967_296.0;
+    if degrees > 180.0 {
+        degrees - 360.0
+    } else {
+        degrees
+    }
+}
+
+/// Reads a time stored as seconds since midnight, milliseconds with flags in the upper bits, and
+/// the year, month, and day.
+fn read_time(data: &[u8], offset: usize) -> Result<NaiveDateTime> {
+    let seconds = read_i32(data, offset)?;
+    let millis = read_u16(data, offset + 4)? & 0x3FF;
+    let year = read_i16(data, offset + 6)?;
+    let month = read_i16(data, offset + 8)?;
+    let day = read_i16(data, offset + 10)?;
+
+    NaiveDate::from_ymd_opt(year.into(), u32::try_from(month)?, u32::try_from(day)?)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| {
+            date + Duration::seconds(seconds.into()) + Duration::milliseconds(millis.into())
+        })
+        .ok_or_else(|| Error::InvalidSigmetFile("invalid time").into())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(read_bytes(data, offset)?))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16> {
+    Ok(i16::from_le_bytes(read_bytes(data, offset)?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(data, offset)?))
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Result<i32> {
+    Ok(i32::from_le_bytes(read_bytes(data, offset)?))
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidSigmetFile("truncated record").into())
+}
@@ -0,0 +1,605 @@
+//!
+//! Provides [``Simulator``] for generating synthetic Archive II volumes at configurable rates,
+//! e.g. to load test ingest services without downloading real data.
+//!
+
+use std::f32::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::decoder::DataFile;
+use crate::encode::{encode_compressed_file, encode_file};
+use crate::error::Error;
+use crate::records::{
+    to_archive_date_time, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+    Message31Header, RadialData, VolumeData, VolumeHeaderRecord,
+};
+
+/// Volume coverage pattern reported by simulated volumes by default.
+const SIMULATED_VCP: u16 = 215;
+
+/// Elevation angle in degrees below which split cuts are scanned, when enabled.
+const SPLIT_CUT_MAX_ELEVATION: f32 = 2.0;
+
+/// Range to the center of the first gate in meters.
+const FIRST_GATE_RANGE: u16 = 2125;
+
+/// Spacing between gates in meters.
+const GATE_INTERVAL: u16 = 250;
+
+/// Nyquist velocity in meters per second.
+const NYQUIST_VELOCITY: f32 = 28.0;
+
+/// Seconds spent collecting each sweep.
+const SWEEP_DURATION_SECS: i64 = 20;
+
+/// Number of storm cells simulated around each site.
+const CELLS_PER_SITE: usize = 4;
+
+/// A radar site to simulate volumes for.
+#[derive(Debug, Clone)]
+pub struct SimulatedSite {
+    radar_id: [u8; 4],
+    lat: f32,
+    long: f32,
+    site_height: i16,
+}
+
+impl SimulatedSite {
+    /// Create a new site with the specified ICAO identifier, e.g. "KDMX", location in degrees, and
+    /// height in meters above mean sea level. Identifiers are truncated or space-padded to four
+    /// characters.
+    #[must_use]
+    pub fn new(radar_id: &str, lat: f32, long: f32, site_height: i16) -> Self {
+        let mut id = [b' '; 4];
+        for (target, source) in id.iter_mut().zip(radar_id.bytes()) {
+            *target = source;
+        }
+
+        Self {
+            radar_id: id,
+            lat,
+            long,
+            site_height,
+        }
+    }
+
+    /// The site's ICAO identifier.
+    #[must_use]
+    pub fn radar_id(&self) -> String {
+        String::from_utf8_lossy(&self.radar_id)
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// Configuration for a [`Simulator`], describing which sites to simulate, how often, and the shape
+/// of each volume.
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    sites: Vec<SimulatedSite>,
+    volumes_per_hour: f32,
+    start: NaiveDateTime,
+    elevations: Vec<f32>,
+    radials_per_sweep: u16,
+    gates: u16,
+    compress: bool,
+    volume_coverage_pattern: u16,
+    split_cuts: bool,
+    seed: Option<u64>,
+}
+
+impl SimulatorConfig {
+    /// Create a configuration producing `volumes_per_hour` volumes for each site. Defaults to four
+    /// tilts of 720 super-resolution radials with 1832 gates, compressed like NOAA's archive.
+    ///
+    /// # Panics
+    /// Panics if no sites are specified or the rate is not positive.
+    #[must_use]
+    pub fn new(sites: Vec<SimulatedSite>, volumes_per_hour: f32) -> Self {
+        assert!(!sites.is_empty(), "at least one site must be simulated");
+        assert!(volumes_per_hour > 0.0, "volume rate must be positive");
+
+        Self {
+            sites,
+            volumes_per_hour,
+            start: NaiveDate::from_ymd_opt(2024, 1, 1)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .expect("is valid date"),
+            elevations: vec![0.5, 0.9, 1.3, 1.8],
+            radials_per_sweep: 720,
+            gates: 1832,
+            compress: true,
+            volume_coverage_pattern: SIMULATED_VCP,
+            split_cuts: false,
+            seed: None,
+        }
+    }
+
+    /// The seed for the simulator's weather and noise, so that the same configuration produces the
+    /// same volumes on every run. By default the seed is taken from the clock.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The seed for the simulator's weather and noise, if one was specified.
+    #[must_use]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The time of the first simulated volume.
+    #[must_use]
+    pub fn with_start(mut self, start: NaiveDateTime) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The elevation angle in degrees of each sweep in a volume.
+    #[must_use]
+    pub fn with_elevations(mut self, elevations: Vec<f32>) -> Self {
+        self.elevations = elevations;
+        self
+    }
+
+    /// The number of radials in each sweep, e.g. 720 for half-degree or 360 for one-degree spacing.
+    #[must_use]
+    pub fn with_radials_per_sweep(mut self, radials_per_sweep: u16) -> Self {
+        self.radials_per_sweep = radials_per_sweep.max(1);
+        self
+    }
+
+    /// The number of gates in each radial.
+    #[must_use]
+    pub fn with_gates(mut self, gates: u16) -> Self {
+        self.gates = gates;
+        self
+    }
+
+    /// Whether volumes are BZIP2-compressed like NOAA's archive, or left uncompressed.
+    #[must_use]
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// The volume coverage pattern number reported by volumes, e.g. 31 or 32 for clear-air mode.
+    /// Defaults to 215.
+    #[must_use]
+    pub fn with_volume_coverage_pattern(mut self, volume_coverage_pattern: u16) -> Self {
+        self.volume_coverage_pattern = volume_coverage_pattern;
+        self
+    }
+
+    /// Whether tilts below two degrees are scanned as split cuts: a surveillance cut with only
+    /// reflectivity followed by a Doppler cut at the same angle which adds velocity.
+    #[must_use]
+    pub fn with_split_cuts(mut self, split_cuts: bool) -> Self {
+        self.split_cuts = split_cuts;
+        self
+    }
+
+    /// The sites being simulated.
+    #[must_use]
+    pub fn sites(&self) -> &[SimulatedSite] {
+        &self.sites
+    }
+
+    /// The interval between consecutive volumes from the same site.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn volume_interval(&self) -> Duration {
+        Duration::milliseconds((3_600_000.0 / f64::from(self.volumes_per_hour)) as i64)
+    }
+}
+
+/// A synthetic volume produced by a [`Simulator`].
+pub struct SimulatedVolume {
+    site: String,
+    time: NaiveDateTime,
+    offset: Duration,
+    data: Vec<u8>,
+}
+
+impl SimulatedVolume {
+    /// The ICAO identifier of the site this volume simulates.
+    #[must_use]
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    /// The time the volume's collection began.
+    #[must_use]
+    pub fn time(&self) -> NaiveDateTime {
+        self.time
+    }
+
+    /// When this volume is due relative to the simulation's start, for pacing delivery in real
+    /// time.
+    #[must_use]
+    pub fn offset(&self) -> Duration {
+        self.offset
+    }
+
+    /// The volume's encoded Archive II data.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes the volume, returning its encoded Archive II data.
+    #[must_use]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Generates a time-ordered stream of synthetic volumes for the configured sites, which ends only
+/// once volume times would overflow. Sites' volumes are staggered evenly across each volume
+/// interval. Each site's weather consists of a few reflectivity cells drifting with a uniform
+/// wind, plus noise.
+pub struct Simulator {
+    config: SimulatorConfig,
+    weather: Vec<SiteWeather>,
+    rng: XorShift,
+    seed: u64,
+    index: u64,
+}
+
+impl Simulator {
+    /// Create a new simulator for the specified configuration, seeded by its seed or else the
+    /// clock.
+    #[must_use]
+    pub fn new(config: SimulatorConfig) -> Self {
+        // Truncating to the low 64 bits keeps the fastest changing part of the time
+        #[allow(clippy::cast_possible_truncation)]
+        let seed = config.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        });
+        let mut rng = XorShift::new(seed);
+
+        let weather = config
+            .sites
+            .iter()
+            .map(|_| SiteWeather::generate(&mut rng))
+            .collect();
+
+        Self {
+            config,
+            weather,
+            rng,
+            seed,
+            index: 0,
+        }
+    }
+
+    /// The simulator's configuration.
+    #[must_use]
+    pub fn config(&self) -> &SimulatorConfig {
+        &self.config
+    }
+
+    /// The seed the simulator was created with. Passing it to [`SimulatorConfig::with_seed`]
+    /// reproduces a clock-seeded simulator's volumes.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Builds the next volume's decoded structure.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    fn simulate_volume(&mut self, site_index: usize, time: NaiveDateTime) -> Result<DataFile> {
+        let config = &self.config;
+        let site = &config.sites[site_index];
+        let weather = &self.weather[site_index];
+        let elapsed_hours = (time - config.start).num_seconds() as f32 / 3600.0;
+
+        let volume_number = (self.index % 999) + 1;
+        let mut filename = *b"AR2V0006.000";
+        filename[9..].copy_from_slice(format!("{volume_number:03}").as_bytes());
+        let (date, millis) = to_archive_date_time(time).ok_or(Error::UnrepresentableTime(time))?;
+        let header = VolumeHeaderRecord::new(filename, u32::from(date), millis, site.radar_id);
+
+        let mut file = DataFile::from_header(header);
+        let azimuth_spacing = 360.0 / f32::from(config.radials_per_sweep);
+        let azm_res = if config.radials_per_sweep >= 720 {
+            1
+        } else {
+            2
+        };
+
+        // Each cut is an elevation angle and whether it collects Doppler moments
+        let cuts: Vec<(f32, bool)> = config
+            .elevations
+            .iter()
+            .flat_map(|elevation| {
+                let split = config.split_cuts && *elevation < SPLIT_CUT_MAX_ELEVATION;
+                let surveillance = split.then_some((*elevation, false));
+                surveillance.into_iter().chain([(*elevation, true)])
+            })
+            .collect();
+        let elevation_count = cuts.len();
+
+        for (elevation_index, (elevation, doppler)) in cuts.iter().enumerate() {
+            let elevation_number = u8::try_from(elevation_index + 1)?;
+            let sweep_start =
+                time + Duration::seconds(SWEEP_DURATION_SECS * elevation_index as i64);
+
+            let mut radials = Vec::with_capacity(config.radials_per_sweep.into());
+            for azimuth_number in 0..config.radials_per_sweep {
+                let azimuth = (f32::from(azimuth_number) + 0.5) * azimuth_spacing;
+                let fraction = f32::from(azimuth_number) / f32::from(config.radials_per_sweep);
+                let ray_time = sweep_start
+                    + Duration::milliseconds(
+                        (fraction * SWEEP_DURATION_SECS as f32 * 1000.0) as i64,
+                    );
+                let (ray_date, ray_millis) =
+                    to_archive_date_time(ray_time).ok_or(Error::UnrepresentableTime(ray_time))?;
+
+                let first_in_sweep = azimuth_number == 0;
+                let last_in_sweep = azimuth_number + 1 == config.radials_per_sweep;
+                let radial_status = match (elevation_index, first_in_sweep, last_in_sweep) {
+                    (0, true, _) => 3,
+                    (_, true, _) => 0,
+                    (index, _, true) if index + 1 == elevation_count => 4,
+                    (_, _, true) => 2,
+                    _ => 1,
+                };
+
+                let mut radial = Message31::new(Message31Header::new(
+                    site.radar_id,
+                    ray_millis,
+                    ray_date,
+                    azimuth_number + 1,
+                    azimuth,
+                    azm_res,
+                    radial_status,
+                    elevation_number,
+                    *elevation,
+                ));
+
+                radial.set_volume_data(VolumeData::new(
+                    site.lat,
+                    site.long,
+                    site.site_height,
+                    20,
+                    config.volume_coverage_pattern,
+                ));
+                radial.set_elevation_data(ElevationData::new([0, 0], 0.0));
+                radial.set_radial_data(RadialData::new(4660, (NYQUIST_VELOCITY * 100.0) as u16));
+
+                let (reflectivity, velocity) = simulate_gates(
+                    weather,
+                    &mut self.rng,
+                    config.gates,
+                    azimuth,
+                    *elevation,
+                    elapsed_hours,
+                );
+
+                radial.set_data_moment(simulated_moment(
+                    DataBlockProduct::Reflectivity,
+                    config.gates,
+                    2.0,
+                    66.0,
+                    reflectivity,
+                ));
+                if *doppler {
+                    radial.set_data_moment(simulated_moment(
+                        DataBlockProduct::Velocity,
+                        config.gates,
+                        2.0,
+                        129.0,
+                        velocity,
+                    ));
+                }
+
+                radials.push(radial);
+            }
+
+            file.elevation_scans_mut().insert(elevation_number, radials);
+        }
+
+        Ok(file)
+    }
+}
+
+impl Iterator for Simulator {
+    type Item = Result<SimulatedVolume>;
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let site_count = self.config.sites.len() as u64;
+        let site_index = (self.index % site_count) as usize;
+        let cycle = i64::try_from(self.index / site_count).ok()?;
+
+        let interval = self.config.volume_interval().num_milliseconds();
+        let stagger = interval / site_count as i64 * site_index as i64;
+        let offset = interval
+            .checked_mul(cycle)
+            .and_then(|milliseconds| milliseconds.checked_add(stagger))
+            .and_then(Duration::try_milliseconds)?;
+        let time = self.config.start.checked_add_signed(offset)?;
+
+        let volume = self.simulate_volume(site_index, time).and_then(|file| {
+            let data = if self.config.compress {
+                encode_compressed_file(&file)?
+            } else {
+                encode_file(&file)?
+            };
+
+            Ok(SimulatedVolume {
+                site: self.config.sites[site_index].radar_id(),
+                time,
+                offset,
+                data,
+            })
+        });
+
+        self.index += 1;
+        Some(volume)
+    }
+}
+
+/// A site's simulated weather: storm cells and a uniform wind that advects them.
+struct SiteWeather {
+    cells: Vec<StormCell>,
+    wind_u: f32,
+    wind_v: f32,
+}
+
+/// A Gaussian reflectivity cell positioned in meters east and north of the radar.
+struct StormCell {
+    x: f32,
+    y: f32,
+    peak: f32,
+    radius: f32,
+}
+
+impl SiteWeather {
+    fn generate(rng: &mut XorShift) -> Self {
+        let cells = (0..CELLS_PER_SITE)
+            .map(|_| StormCell {
+                x: rng.next_symmetric() * 120_000.0,
+                y: rng.next_symmetric() * 120_000.0,
+                peak: 35.0 + rng.next_unit() * 30.0,
+                radius: 5_000.0 + rng.next_unit() * 15_000.0,
+            })
+            .collect();
+
+        Self {
+            cells,
+            wind_u: rng.next_symmetric() * 20.0,
+            wind_v: rng.next_symmetric() * 20.0,
+        }
+    }
+
+    /// Reflectivity in dBZ at a polar location after the cells have drifted for some hours.
+    fn reflectivity(&self, azimuth: f32, range: f32, elapsed_hours: f32) -> f32 {
+        let (x, y) = polar_to_xy(azimuth, range);
+        let drift = elapsed_hours * 3600.0;
+
+        self.cells
+            .iter()
+            .map(|cell| {
+                let dx = x - (cell.x + self.wind_u * drift);
+                let dy = y - (cell.y + self.wind_v * drift);
+                let distance_squared = (dx * dx + dy * dy) / (cell.radius * cell.radius);
+                cell.peak * (-distance_squared).exp()
+            })
+            .fold(-10.0, f32::max)
+    }
+
+    /// The wind's component along the beam in meters per second, positive away from the radar.
+    fn radial_velocity(&self, azimuth: f32, elevation: f32) -> f32 {
+        let azimuth = azimuth * PI / 180.0;
+        (self.wind_u * azimuth.sin() + self.wind_v * azimuth.cos()) * (elevation * PI / 180.0).cos()
+    }
+}
+
+/// Simulates a radial's raw 8-bit reflectivity and velocity gates.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn simulate_gates(
+    weather: &SiteWeather,
+    rng: &mut XorShift,
+    gates: u16,
+    azimuth: f32,
+    elevation: f32,
+    elapsed_hours: f32,
+) -> (Vec<u8>, Vec<u8>) {
+    let mut reflectivity = Vec::with_capacity(gates.into());
+    let mut velocity = Vec::with_capacity(gates.into());
+
+    for gate in 0..gates {
+        let range = f32::from(FIRST_GATE_RANGE) + f32::from(gate) * f32::from(GATE_INTERVAL);
+        let dbz = weather.reflectivity(azimuth, range, elapsed_hours) + rng.next_symmetric() * 2.0;
+
+        if dbz < 0.0 {
+            reflectivity.push(0);
+            velocity.push(0);
+            continue;
+        }
+
+        reflectivity.push(((dbz * 2.0) + 66.0).clamp(2.0, 255.0) as u8);
+
+        let radial_velocity = weather.radial_velocity(azimuth, elevation) + rng.next_symmetric();
+        let folded = (radial_velocity + NYQUIST_VELOCITY).rem_euclid(2.0 * NYQUIST_VELOCITY)
+            - NYQUIST_VELOCITY;
+        velocity.push(((folded * 2.0) + 129.0).clamp(2.0, 255.0) as u8);
+    }
+
+    (reflectivity, velocity)
+}
+
+/// Converts an azimuth in degrees and range in meters to meters east and north of the radar.
+fn polar_to_xy(azimuth: f32, range: f32) -> (f32, f32) {
+    let azimuth = azimuth * PI / 180.0;
+    (range * azimuth.sin(), range * azimuth.cos())
+}
+
+/// Builds an 8-bit moment from raw gate values.
+fn simulated_moment(
+    product: DataBlockProduct,
+    gates: u16,
+    scale: f32,
+    offset: f32,
+    values: Vec<u8>,
+) -> DataMoment {
+    let data = GenericData::new(
+        &product,
+        gates,
+        FIRST_GATE_RANGE,
+        GATE_INTERVAL,
+        8,
+        scale,
+        offset,
+    );
+    DataMoment::new(product, data, values)
+}
+
+/// A small, fast pseudo-random number generator (xorshift64*) for simulated noise.
+struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // Seeds are mixed (with splitmix64's finalizer) so that similar seeds diverge immediately,
+        // and the state must never be zero
+        let mut state = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self {
+            state: (state ^ (state >> 31)) | 1,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A uniformly distributed value in `[0, 1)`.
+    #[allow(clippy::cast_precision_loss)]
+    fn next_unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A uniformly distributed value in `[-1, 1)`.
+    fn next_symmetric(&mut self) -> f32 {
+        self.next_unit() * 2.0 - 1.0
+    }
+}
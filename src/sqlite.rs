@@ -0,0 +1,416 @@
+//!
+//! Provides [``EventArchive``] for recording volume summaries, fine line detections, convective
+//! cells, alert evaluations, and georegistration checks in a `SQLite` database, a queryable
+//! archive of events which needs no server. The schema is stable: its tables and columns are only
+//! ever added to, and its version is kept in the database's `user_version` so archives written by
+//! newer versions are refused rather than corrupted.
+//!
+//! ```sql
+//! SELECT v.time_utc, f.latitude, f.longitude, f.length_m
+//! FROM fine_lines f JOIN volumes v ON v.id = f.volume_id
+//! WHERE v.site = 'KDMX' AND f.mean_convergence > 5;
+//! ```
+//!
+
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::expression::{AlertEvaluation, AlertRule};
+use crate::fine_line::FineLineDetection;
+use crate::geometry::destination;
+use crate::partition::ConvectiveCell;
+use crate::records::{DataMoment, Message31, VolumeData};
+use crate::registration::{RegistrationAnomaly, RegistrationCheck};
+
+/// The version of the schema written, stored in the database's `user_version`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The statements bringing the schema from each version to the next, starting from an empty
+/// database. Times are in UTC as `YYYY-MM-DD HH:MM:SS.SSS`, which `SQLite`'s date and time
+/// functions accept.
+const MIGRATIONS: [&str; SCHEMA_VERSION as usize] = [
+    "
+CREATE TABLE volumes (
+    id INTEGER PRIMARY KEY,
+    site TEXT NOT NULL,
+    time_utc TEXT NOT NULL,
+    latitude REAL,
+    longitude REAL,
+    height_m REAL,
+    vcp INTEGER,
+    sweeps INTEGER NOT NULL,
+    radials INTEGER NOT NULL,
+    max_reflectivity REAL,
+    UNIQUE (site, time_utc)
+);
+
+CREATE TABLE fine_lines (
+    id INTEGER PRIMARY KEY,
+    volume_id INTEGER NOT NULL REFERENCES volumes (id) ON DELETE CASCADE,
+    time_utc TEXT,
+    elevation REAL NOT NULL,
+    centroid_x_m REAL NOT NULL,
+    centroid_y_m REAL NOT NULL,
+    latitude REAL,
+    longitude REAL,
+    length_m REAL NOT NULL,
+    vertices INTEGER NOT NULL,
+    mean_reflectivity REAL NOT NULL,
+    mean_convergence REAL NOT NULL
+);
+
+CREATE INDEX fine_lines_volume ON fine_lines (volume_id);
+
+CREATE TABLE registration_checks (
+    id INTEGER PRIMARY KEY,
+    site TEXT NOT NULL,
+    earlier_time_utc TEXT NOT NULL,
+    later_time_utc TEXT NOT NULL,
+    azimuth_bias REAL NOT NULL,
+    displacement_x_m REAL NOT NULL,
+    displacement_y_m REAL NOT NULL,
+    apparent_speed REAL NOT NULL,
+    correlation REAL NOT NULL,
+    azimuth_shift INTEGER NOT NULL,
+    implausible_motion INTEGER NOT NULL,
+    UNIQUE (site, earlier_time_utc, later_time_utc)
+);
+",
+    "
+CREATE TABLE cells (
+    id INTEGER PRIMARY KEY,
+    volume_id INTEGER NOT NULL REFERENCES volumes (id) ON DELETE CASCADE,
+    centroid_x_m REAL NOT NULL,
+    centroid_y_m REAL NOT NULL,
+    latitude REAL,
+    longitude REAL,
+    area_m2 REAL NOT NULL,
+    max_reflectivity REAL NOT NULL
+);
+
+CREATE INDEX cells_volume ON cells (volume_id);
+
+CREATE TABLE alert_evaluations (
+    id INTEGER PRIMARY KEY,
+    volume_id INTEGER NOT NULL REFERENCES volumes (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    expression TEXT NOT NULL,
+    min_gates INTEGER NOT NULL,
+    matching_gates INTEGER NOT NULL,
+    triggered INTEGER NOT NULL,
+    UNIQUE (volume_id, name)
+);
+",
+];
+
+/// A `SQLite` database of volume summaries and the events detected in them.
+#[derive(Debug)]
+pub struct EventArchive {
+    connection: Connection,
+}
+
+impl EventArchive {
+    /// Opens the archive at the specified path, creating it if it does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the database cannot be opened, was written with a newer schema, or
+    /// already has tables of something other than an event archive.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a new archive held in memory, e.g. for tests.
+    ///
+    /// # Errors
+    /// Returns an error if the database cannot be created.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    /// Creates the schema in a new database or brings an existing archive's schema up to date.
+    /// A database without a version but with tables is not an archive and is left untouched.
+    fn with_connection(mut connection: Connection) -> Result<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+
+        let transaction = connection.transaction()?;
+        let version: u32 =
+            transaction.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(Error::UnsupportedArchiveSchema(version).into());
+        }
+        if version == 0 {
+            let tables: usize = transaction.query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'table'",
+                [],
+                |row| row.get(0),
+            )?;
+            if tables > 0 {
+                return Err(Error::UnrecognizedArchive.into());
+            }
+        }
+        if version < SCHEMA_VERSION {
+            for migration in &MIGRATIONS[version as usize..] {
+                transaction.execute_batch(migration)?;
+            }
+            transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        transaction.commit()?;
+
+        Ok(Self { connection })
+    }
+
+    /// The underlying connection, for querying the archive.
+    #[must_use]
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Records a volume's summary, returning its row's `id`. Recording a volume with the same site
+    /// and start time again updates its summary and keeps its `id`.
+    ///
+    /// # Errors
+    /// Returns an error if the volume has no valid start time or the database fails.
+    pub fn record_volume(&mut self, file: &DataFile) -> Result<i64> {
+        let time = file
+            .volume_header()
+            .date_time()
+            .ok_or(Error::MissingVolumeTime)?;
+        let site = String::from_utf8_lossy(file.volume_header().radar_id()).into_owned();
+        let volume_data = file.first_volume_data();
+
+        let scans = file.elevation_scans();
+        let radials = scans.values().map(Vec::len).sum::<usize>();
+        let max_reflectivity = scans
+            .values()
+            .flatten()
+            .filter_map(Message31::reflectivity_data)
+            .flat_map(DataMoment::values)
+            .filter_map(|value| value.value())
+            .reduce(f32::max);
+
+        let id = self.connection.query_row(
+            "INSERT INTO volumes
+                (site, time_utc, latitude, longitude, height_m, vcp, sweeps, radials,
+                max_reflectivity)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT (site, time_utc) DO UPDATE SET
+                latitude = excluded.latitude,
+                longitude = excluded.longitude,
+                height_m = excluded.height_m,
+                vcp = excluded.vcp,
+                sweeps = excluded.sweeps,
+                radials = excluded.radials,
+                max_reflectivity = excluded.max_reflectivity
+            RETURNING id",
+            params![
+                site,
+                format_time(time),
+                volume_data.as_ref().map(VolumeData::lat),
+                volume_data.as_ref().map(VolumeData::long),
+                volume_data.as_ref().map(VolumeData::antenna_altitude_m),
+                volume_data
+                    .as_ref()
+                    .map(VolumeData::volume_coverage_pattern_number),
+                scans.len(),
+                radials,
+                max_reflectivity,
+            ],
+            |row| row.get(0),
+        )?;
+
+        Ok(id)
+    }
+
+    /// Records the fine lines detected in a recorded volume, replacing any previously recorded
+    /// for it, and returns the number of lines recorded. Lines are located from the volume's site
+    /// if it is known.
+    ///
+    /// # Errors
+    /// Returns an error if the volume is not recorded or the database fails.
+    pub fn record_fine_lines(
+        &mut self,
+        volume: i64,
+        detection: &FineLineDetection,
+    ) -> Result<usize> {
+        let transaction = self.connection.transaction()?;
+        let (lat, long) = volume_site(&transaction, volume)?;
+
+        transaction.execute("DELETE FROM fine_lines WHERE volume_id = ?1", [volume])?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO fine_lines
+                    (volume_id, time_utc, elevation, centroid_x_m, centroid_y_m, latitude,
+                    longitude, length_m, vertices, mean_reflectivity, mean_convergence)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for line in detection.lines() {
+                let (x, y) = line.centroid();
+                let location = lat
+                    .zip(long)
+                    .map(|(lat, long)| destination(lat, long, x.atan2(y).to_degrees(), x.hypot(y)));
+                insert.execute(params![
+                    volume,
+                    detection.time().map(format_time),
+                    detection.elevation(),
+                    x,
+                    y,
+                    location.map(|(lat, _)| lat),
+                    location.map(|(_, long)| long),
+                    line.length_m(),
+                    line.points().len(),
+                    line.mean_reflectivity(),
+                    line.mean_convergence(),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(detection.lines().len())
+    }
+
+    /// Records the convective cells identified in a recorded volume, replacing any previously
+    /// recorded for it, and returns the number of cells recorded. Cells are located from the
+    /// volume's site if it is known.
+    ///
+    /// # Errors
+    /// Returns an error if the volume is not recorded or the database fails.
+    pub fn record_cells(&mut self, volume: i64, cells: &[ConvectiveCell]) -> Result<usize> {
+        let transaction = self.connection.transaction()?;
+        let (lat, long) = volume_site(&transaction, volume)?;
+
+        transaction.execute("DELETE FROM cells WHERE volume_id = ?1", [volume])?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO cells
+                    (volume_id, centroid_x_m, centroid_y_m, latitude, longitude, area_m2,
+                    max_reflectivity)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for cell in cells {
+                let (x, y) = cell.centroid();
+                let location = lat
+                    .zip(long)
+                    .map(|(lat, long)| destination(lat, long, x.atan2(y).to_degrees(), x.hypot(y)));
+                insert.execute(params![
+                    volume,
+                    x,
+                    y,
+                    location.map(|(lat, _)| lat),
+                    location.map(|(_, long)| long),
+                    cell.area_m2(),
+                    cell.max_reflectivity(),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(cells.len())
+    }
+
+    /// Records the evaluation of an alert over a recorded volume, returning its row's `id`.
+    /// Recording the same alert for the volume again updates it and keeps its `id`.
+    ///
+    /// # Errors
+    /// Returns an error if the volume is not recorded or the database fails.
+    pub fn record_alert_evaluation(
+        &mut self,
+        volume: i64,
+        rule: &AlertRule,
+        evaluation: &AlertEvaluation,
+    ) -> Result<i64> {
+        let transaction = self.connection.transaction()?;
+        volume_site(&transaction, volume)?;
+
+        let id = transaction.query_row(
+            "INSERT INTO alert_evaluations
+                (volume_id, name, expression, min_gates, matching_gates, triggered)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (volume_id, name) DO UPDATE SET
+                expression = excluded.expression,
+                min_gates = excluded.min_gates,
+                matching_gates = excluded.matching_gates,
+                triggered = excluded.triggered
+            RETURNING id",
+            params![
+                volume,
+                rule.name(),
+                rule.expression().source(),
+                rule.min_gates(),
+                evaluation.matching_gates(),
+                evaluation.triggered(),
+            ],
+            |row| row.get(0),
+        )?;
+        transaction.commit()?;
+
+        Ok(id)
+    }
+
+    /// Records a georegistration check of a site's consecutive volumes, returning its row's `id`.
+    /// Recording a check of the same volumes again updates it and keeps its `id`.
+    ///
+    /// # Errors
+    /// Returns an error if the database fails.
+    pub fn record_registration_check(
+        &mut self,
+        site: &str,
+        check: &RegistrationCheck,
+    ) -> Result<i64> {
+        let (x, y) = check.displacement();
+        let has = |anomaly| check.anomalies().contains(&anomaly);
+
+        let id = self.connection.query_row(
+            "INSERT INTO registration_checks
+                (site, earlier_time_utc, later_time_utc, azimuth_bias, displacement_x_m,
+                displacement_y_m, apparent_speed, correlation, azimuth_shift, implausible_motion)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT (site, earlier_time_utc, later_time_utc) DO UPDATE SET
+                azimuth_bias = excluded.azimuth_bias,
+                displacement_x_m = excluded.displacement_x_m,
+                displacement_y_m = excluded.displacement_y_m,
+                apparent_speed = excluded.apparent_speed,
+                correlation = excluded.correlation,
+                azimuth_shift = excluded.azimuth_shift,
+                implausible_motion = excluded.implausible_motion
+            RETURNING id",
+            params![
+                site,
+                format_time(check.earlier_time()),
+                format_time(check.later_time()),
+                check.azimuth_bias(),
+                x,
+                y,
+                check.apparent_speed(),
+                check.correlation(),
+                has(RegistrationAnomaly::AzimuthShift),
+                has(RegistrationAnomaly::ImplausibleMotion),
+            ],
+            |row| row.get(0),
+        )?;
+
+        Ok(id)
+    }
+}
+
+/// The latitude and longitude of a recorded volume's site, if known.
+fn volume_site(connection: &Connection, volume: i64) -> Result<(Option<f32>, Option<f32>)> {
+    let site = connection
+        .query_row(
+            "SELECT latitude, longitude FROM volumes WHERE id = ?1",
+            [volume],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(site.ok_or(Error::MissingArchivedVolume(volume))?)
+}
+
+/// Formats a time as the schema's UTC time text.
+fn format_time(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
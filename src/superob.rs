@@ -0,0 +1,326 @@
+//!
+//! Provides [``superob_sweep``] for averaging a sweep's gates into coarse polar bins
+//! (superobservations) with error statistics, a standard preprocessing step for data assimilation
+//! and for transmitting radar data over limited bandwidth.
+//!
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::gate::GateValue;
+use crate::quality::SweepQuality;
+use crate::records::{Message31, Product};
+
+/// How the values within each superobservation are averaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Averaging {
+    /// The arithmetic mean.
+    Mean,
+    /// The median, which is less sensitive to outliers such as unfiltered aliasing.
+    Median,
+}
+
+/// Options controlling the size of superobservations and how their values are averaged.
+/// Deserializable with serde, with unspecified options taking their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SuperobOptions {
+    #[serde(deserialize_with = "deserialize_bin_size")]
+    range_bin_size: f32,
+    #[serde(deserialize_with = "deserialize_bin_size")]
+    azimuth_bin_size: f32,
+    #[serde(deserialize_with = "deserialize_minimum_count")]
+    minimum_count: usize,
+    averaging: Averaging,
+}
+
+impl SuperobOptions {
+    /// Create the default options: 5 km by 5 degree superobservations of at least 5 gates each,
+    /// averaged by their mean.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The range extent of each superobservation in meters.
+    ///
+    /// # Panics
+    /// Panics if the size is not positive.
+    #[must_use]
+    pub fn with_range_bin_size(mut self, range_bin_size: f32) -> Self {
+        assert!(range_bin_size > 0.0, "range bin size must be positive");
+        self.range_bin_size = range_bin_size;
+        self
+    }
+
+    /// The range extent of each superobservation in meters.
+    #[must_use]
+    pub fn range_bin_size(&self) -> f32 {
+        self.range_bin_size
+    }
+
+    /// The azimuthal extent of each superobservation in degrees.
+    ///
+    /// # Panics
+    /// Panics if the size is not positive.
+    #[must_use]
+    pub fn with_azimuth_bin_size(mut self, azimuth_bin_size: f32) -> Self {
+        assert!(azimuth_bin_size > 0.0, "azimuth bin size must be positive");
+        self.azimuth_bin_size = azimuth_bin_size;
+        self
+    }
+
+    /// The azimuthal extent of each superobservation in degrees.
+    #[must_use]
+    pub fn azimuth_bin_size(&self) -> f32 {
+        self.azimuth_bin_size
+    }
+
+    /// The fewest gates with a value a superobservation must average to be kept.
+    #[must_use]
+    pub fn with_minimum_count(mut self, minimum_count: usize) -> Self {
+        self.minimum_count = minimum_count.max(1);
+        self
+    }
+
+    /// The fewest gates with a value a superobservation must average to be kept.
+    #[must_use]
+    pub fn minimum_count(&self) -> usize {
+        self.minimum_count
+    }
+
+    /// How the values within each superobservation are averaged.
+    #[must_use]
+    pub fn with_averaging(mut self, averaging: Averaging) -> Self {
+        self.averaging = averaging;
+        self
+    }
+
+    /// How the values within each superobservation are averaged.
+    #[must_use]
+    pub fn averaging(&self) -> Averaging {
+        self.averaging
+    }
+}
+
+impl Default for SuperobOptions {
+    fn default() -> Self {
+        Self {
+            range_bin_size: 5000.0,
+            azimuth_bin_size: 5.0,
+            minimum_count: 5,
+            averaging: Averaging::Mean,
+        }
+    }
+}
+
+fn deserialize_bin_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let size = f32::deserialize(deserializer)?;
+    if size > 0.0 {
+        Ok(size)
+    } else {
+        Err(serde::de::Error::custom("bin size must be positive"))
+    }
+}
+
+fn deserialize_minimum_count<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<usize, D::Error> {
+    usize::deserialize(deserializer).map(|count| count.max(1))
+}
+
+/// The averaged value of the gates within a polar bin, with statistics of their spread.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Superob {
+    elevation: f32,
+    azimuth: f32,
+    range: f32,
+    value: f32,
+    standard_deviation: f32,
+    minimum: f32,
+    maximum: f32,
+    count: usize,
+}
+
+impl Superob {
+    /// The mean elevation angle of the gates in degrees.
+    #[must_use]
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+
+    /// The azimuth of the bin's center in degrees.
+    #[must_use]
+    pub fn azimuth(&self) -> f32 {
+        self.azimuth
+    }
+
+    /// The range to the bin's center in meters.
+    #[must_use]
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+
+    /// The averaged value in the product's units.
+    #[must_use]
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// The standard deviation of the gates' values about their mean.
+    #[must_use]
+    pub fn standard_deviation(&self) -> f32 {
+        self.standard_deviation
+    }
+
+    /// The standard error of the mean, the standard deviation over the square root of the count.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn standard_error(&self) -> f32 {
+        self.standard_deviation / (self.count as f32).sqrt()
+    }
+
+    /// The smallest of the gates' values.
+    #[must_use]
+    pub fn minimum(&self) -> f32 {
+        self.minimum
+    }
+
+    /// The largest of the gates' values.
+    #[must_use]
+    pub fn maximum(&self) -> f32 {
+        self.maximum
+    }
+
+    /// The number of gates averaged.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Averages a sweep's values of a product into polar bins aligned to north and the radar, keeping
+/// bins with enough gates in azimuth then range order. Gates below threshold or range folded are
+/// not counted. Reflectivity is averaged as linear reflectivity factor, so that a mean is not
+/// biased low by averaging logarithms, while its statistics are of the values in dBZ.
+#[must_use]
+pub fn superob_sweep(
+    radials: &[Message31],
+    product: Product,
+    options: &SuperobOptions,
+) -> Vec<Superob> {
+    superob(radials, product, None, options)
+}
+
+/// Averages a sweep's values of a product into polar bins like [``superob_sweep``], but weights
+/// each gate's value by the quality index of the sweep's reflectivity gate nearest it. Gates
+/// without a quality index or with an index of zero are not counted. Means and standard deviations
+/// are weighted; medians, minimums, and maximums are of the counted gates.
+#[must_use]
+pub fn superob_sweep_weighted(
+    radials: &[Message31],
+    product: Product,
+    quality: &SweepQuality,
+    options: &SuperobOptions,
+) -> Vec<Superob> {
+    superob(radials, product, Some(quality), options)
+}
+
+/// The values and weights of a bin's gates, with the sum of their elevations.
+type Bin = (Vec<(f32, f32)>, f32);
+
+/// Averages a sweep's values of a product into polar bins, weighting each gate by its quality
+/// index if specified and equally otherwise.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn superob(
+    radials: &[Message31],
+    product: Product,
+    quality: Option<&SweepQuality>,
+    options: &SuperobOptions,
+) -> Vec<Superob> {
+    let mut bins: BTreeMap<(usize, usize), Bin> = BTreeMap::new();
+
+    for (radial_index, radial) in radials.iter().enumerate() {
+        let Some(moment) = radial.get_data_moment(&product.into()) else {
+            continue;
+        };
+
+        let azimuth_bin =
+            (radial.header().azm().rem_euclid(360.0) / options.azimuth_bin_size).floor() as usize;
+        let elevation = radial.header().elev();
+
+        for (index, value) in moment.values().enumerate() {
+            let GateValue::Value(value) = value else {
+                continue;
+            };
+
+            let range = moment.data().gate_range_m(index);
+            let weight = match quality {
+                Some(quality) => match quality.at_range(radial_index, range) {
+                    Some(weight) if weight > 0.0 => weight,
+                    _ => continue,
+                },
+                None => 1.0,
+            };
+
+            let range_bin = (range / options.range_bin_size).floor() as usize;
+            let (values, elevations) = bins.entry((azimuth_bin, range_bin)).or_default();
+            values.push((value, weight));
+            *elevations += elevation;
+        }
+    }
+
+    bins.into_iter()
+        .filter(|(_, (values, _))| values.len() >= options.minimum_count)
+        .map(|((azimuth_bin, range_bin), (mut values, elevations))| {
+            let count = values.len();
+            let total_weight = values.iter().map(|(_, weight)| weight).sum::<f32>();
+            let weighted_mean = |f: &dyn Fn(f32) -> f32| {
+                values
+                    .iter()
+                    .map(|(value, weight)| f(*value) * weight)
+                    .sum::<f32>()
+                    / total_weight
+            };
+
+            let mean = weighted_mean(&|value| value);
+            let variance = weighted_mean(&|value| (value - mean).powi(2));
+
+            let value = match options.averaging {
+                Averaging::Mean if product == Product::Reflectivity => {
+                    10.0 * weighted_mean(&|value| 10f32.powf(value / 10.0)).log10()
+                }
+                Averaging::Mean => mean,
+                Averaging::Median => {
+                    values.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+                    if count % 2 == 0 {
+                        f32::midpoint(values[count / 2 - 1].0, values[count / 2].0)
+                    } else {
+                        values[count / 2].0
+                    }
+                }
+            };
+            let (minimum, maximum) = values.iter().fold(
+                (f32::INFINITY, f32::NEG_INFINITY),
+                |(min, max), (value, _)| (min.min(*value), max.max(*value)),
+            );
+
+            Superob {
+                elevation: elevations / count as f32,
+                azimuth: (azimuth_bin as f32 + 0.5) * options.azimuth_bin_size,
+                range: (range_bin as f32 + 0.5) * options.range_bin_size,
+                value,
+                standard_deviation: variance.sqrt(),
+                minimum,
+                maximum,
+                count,
+            }
+        })
+        .collect()
+}
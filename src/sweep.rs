@@ -0,0 +1,301 @@
+//!
+//! Struct definitions for sweeps, the radials collected at a single elevation.
+//!
+
+use crate::blockage::Blockage;
+use crate::quality::{QualityOptions, SweepQuality};
+use crate::records::{ElevationData, Message31, Product, RadialData, VolumeData};
+
+/// A single elevation sweep consisting of the radials collected at that elevation.
+#[derive(Clone)]
+pub struct Sweep {
+    elevation_number: u8,
+    radials: Vec<Message31>,
+    quality: Option<SweepQuality>,
+}
+
+impl Sweep {
+    /// Create a new sweep for the specified elevation number from its radials.
+    #[must_use]
+    pub fn new(elevation_number: u8, radials: Vec<Message31>) -> Self {
+        Self {
+            elevation_number,
+            radials,
+            quality: None,
+        }
+    }
+
+    /// Derives the quality index of each of the sweep's reflectivity gates with the specified beam
+    /// blockage, attaching it to the sweep as an auxiliary field for weighting, e.g. by
+    /// [``superob_sweep_weighted``](crate::superob::superob_sweep_weighted).
+    #[must_use]
+    pub fn with_quality_index<B: Blockage + ?Sized>(
+        mut self,
+        blockage: &B,
+        options: &QualityOptions,
+    ) -> Self {
+        self.quality = Some(SweepQuality::from_radials(&self.radials, blockage, options));
+        self
+    }
+
+    /// The quality index attached to the sweep, if one was derived.
+    #[must_use]
+    pub fn quality_index(&self) -> Option<&SweepQuality> {
+        self.quality.as_ref()
+    }
+
+    /// The elevation number of this sweep within its volume.
+    #[must_use]
+    pub fn elevation_number(&self) -> u8 {
+        self.elevation_number
+    }
+
+    /// The radials making up this sweep.
+    #[must_use]
+    pub fn radials(&self) -> &[Message31] {
+        &self.radials
+    }
+
+    /// The sweep's volume data block, from the first radial carrying one. Volume, elevation, and
+    /// radial data blocks may appear on only some radials; see [`Sweep::propagate_metadata`].
+    #[must_use]
+    pub fn volume_data(&self) -> Option<&VolumeData> {
+        self.radials.iter().find_map(Message31::volume_data)
+    }
+
+    /// The sweep's elevation data block, from the first radial carrying one.
+    #[must_use]
+    pub fn elevation_data(&self) -> Option<&ElevationData> {
+        self.radials.iter().find_map(Message31::elevation_data)
+    }
+
+    /// The sweep's radial data block, from the first radial carrying one. Noise levels may vary
+    /// from radial to radial, though the Nyquist velocity and unambiguous range are constant.
+    #[must_use]
+    pub fn radial_data(&self) -> Option<&RadialData> {
+        self.radials.iter().find_map(Message31::radial_data)
+    }
+
+    /// Copies the volume, elevation, and radial data blocks to every radial lacking them, so that
+    /// each radial carries its applicable metadata.
+    pub fn propagate_metadata(&mut self) {
+        propagate_metadata(&mut self.radials, None);
+    }
+
+    /// A hash of the sweep's content: each radial's azimuth and elevation, and each moment's gate
+    /// geometry, scaling, and data. Sweeps with the same content hash alike however they were
+    /// received, e.g. from an archive and a real-time feed, while times and metadata blocks are
+    /// ignored. The hash is stable across runs and platforms, but is not cryptographic.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        content_hash(&self.radials)
+    }
+
+    /// Which products and resolutions this sweep provides.
+    #[must_use]
+    pub fn capabilities(&self) -> SweepCapabilities {
+        SweepCapabilities::from_radials(&self.radials)
+    }
+
+    /// Copies a product's gate values into a two-dimensional array indexed by radial then gate,
+    /// with gates lacking a value, or beyond a radial's last gate, set to NaN. Returns `None` if no
+    /// radial has the product.
+    #[must_use]
+    #[cfg(feature = "ndarray")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn to_array(&self, product: Product) -> Option<ndarray::Array2<f32>> {
+        let moments: Vec<_> = self
+            .radials
+            .iter()
+            .map(|radial| radial.get_data_moment(&product.into()))
+            .collect();
+
+        let gates = moments
+            .iter()
+            .flatten()
+            .map(|moment| moment.gate_count())
+            .max()?;
+
+        Some(ndarray::Array2::from_shape_fn(
+            (moments.len(), gates),
+            |(radial, gate)| {
+                moments[radial]
+                    .and_then(|moment| moment.value(gate))
+                    .and_then(|value| value.value())
+                    .unwrap_or(f32::NAN)
+            },
+        ))
+    }
+
+    /// Consumes the sweep, returning its radials.
+    #[must_use]
+    pub fn into_radials(self) -> Vec<Message31> {
+        self.radials
+    }
+}
+
+/// Hashes radials' geometry and moments with 64-bit FNV-1a, per [`Sweep::content_hash`].
+pub(crate) fn content_hash(radials: &[Message31]) -> u64 {
+    let mut hasher = Fnv1a::default();
+
+    for radial in radials {
+        hasher.write(&radial.header().azm().to_be_bytes());
+        hasher.write(&radial.header().elev().to_be_bytes());
+
+        for product in Product::ALL {
+            let Some(moment) = radial.get_data_moment(&product.into()) else {
+                continue;
+            };
+
+            let data = moment.data();
+            hasher.write(data.data_name());
+            hasher.write(&data.number_data_moment_gates().to_be_bytes());
+            hasher.write(&data.data_moment_range().to_be_bytes());
+            hasher.write(&data.data_moment_range_sample_interval().to_be_bytes());
+            hasher.write(&[data.data_word_size()]);
+            hasher.write(&data.scale().to_be_bytes());
+            hasher.write(&data.offset().to_be_bytes());
+            hasher.write(moment.moment_data());
+        }
+    }
+
+    hasher.0
+}
+
+/// The 64-bit FNV-1a hash.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xCBF2_9CE4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+}
+
+/// Copies the volume, elevation, and radial data blocks to the radials lacking them. Each radial
+/// receives the blocks of the nearest preceding radial carrying them, or of the first radial
+/// carrying them if none precedes it. The fallback volume data is used if no radial has any.
+pub(crate) fn propagate_metadata(radials: &mut [Message31], volume_data: Option<&VolumeData>) {
+    fill(
+        radials,
+        Message31::volume_data,
+        Message31::set_volume_data,
+        volume_data,
+    );
+    fill(
+        radials,
+        Message31::elevation_data,
+        Message31::set_elevation_data,
+        None,
+    );
+    fill(
+        radials,
+        Message31::radial_data,
+        Message31::set_radial_data,
+        None,
+    );
+}
+
+/// Copies a block to the radials lacking it, carrying the most recent block forward.
+fn fill<T: Clone>(
+    radials: &mut [Message31],
+    get: fn(&Message31) -> Option<&T>,
+    set: fn(&mut Message31, T),
+    fallback: Option<&T>,
+) {
+    let mut current = radials.iter().find_map(get).or(fallback).cloned();
+
+    for radial in radials {
+        if let Some(block) = get(radial) {
+            current = Some(block.clone());
+        } else if let Some(block) = &current {
+            set(radial, block.clone());
+        }
+    }
+}
+
+/// The products and resolutions a sweep provides. Not every sweep of a volume has every moment:
+/// split cuts, used at low tilts by most patterns including clear-air mode, scan once with a long
+/// pulse for reflectivity (a surveillance cut) and again with a short pulse for velocity (a
+/// Doppler cut).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepCapabilities {
+    products: Vec<Product>,
+    super_resolution: bool,
+    reflectivity_resolution: Option<f32>,
+}
+
+impl SweepCapabilities {
+    /// Determines the capabilities of a sweep's radials. A product is available if any radial
+    /// contains it.
+    #[must_use]
+    pub fn from_radials(radials: &[Message31]) -> Self {
+        let products = Product::ALL
+            .into_iter()
+            .filter(|product| {
+                radials
+                    .iter()
+                    .any(|radial| radial.get_data_moment(&(*product).into()).is_some())
+            })
+            .collect();
+
+        let reflectivity_resolution = radials
+            .iter()
+            .find_map(Message31::reflectivity_data)
+            .map(|moment| moment.data().scale())
+            .filter(|scale| *scale > 0.0)
+            .map(|scale| 1.0 / scale);
+
+        Self {
+            products,
+            super_resolution: radials
+                .first()
+                .is_some_and(|radial| radial.header().azm_res() == 1),
+            reflectivity_resolution,
+        }
+    }
+
+    /// The products available in the sweep.
+    #[must_use]
+    pub fn products(&self) -> &[Product] {
+        &self.products
+    }
+
+    /// Whether the sweep contains the specified product.
+    #[must_use]
+    pub fn has(&self, product: Product) -> bool {
+        self.products.contains(&product)
+    }
+
+    /// Whether the sweep contains Doppler moments (velocity), e.g. false for surveillance cuts.
+    #[must_use]
+    pub fn is_doppler(&self) -> bool {
+        self.has(Product::Velocity)
+    }
+
+    /// Whether the sweep contains dual-polarization moments.
+    #[must_use]
+    pub fn is_dual_pol(&self) -> bool {
+        self.has(Product::DifferentialReflectivity) || self.has(Product::CorrelationCoefficient)
+    }
+
+    /// Whether the sweep's radials are spaced at half a degree (super resolution) rather than one.
+    #[must_use]
+    pub fn is_super_resolution(&self) -> bool {
+        self.super_resolution
+    }
+
+    /// The smallest reflectivity increment in dBZ the sweep can represent, typically 0.5 dBZ, if
+    /// it has reflectivity.
+    #[must_use]
+    pub fn reflectivity_resolution(&self) -> Option<f32> {
+        self.reflectivity_resolution
+    }
+}
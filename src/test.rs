@@ -1,8 +1,38 @@
 use std::path::Path;
 
 use anyhow::Result;
+use chrono::NaiveDate;
 
-use crate::DataFile;
+use crate::backfill::{ArchiveSource, ChunkMetadata, ChunkSource};
+use crate::blockage::{Blockage, NoBlockage, TerrainBlockage};
+use crate::climatology::EchoClimatology;
+use crate::composite::{Combination, VolumeLayer, VolumePair};
+use crate::expression::{AlertRule, Expression};
+use crate::fine_line::{detect_fine_lines_in_sweep, FineLineOptions, FineLineTracker};
+use crate::gate::GateIterator;
+use crate::geometry::beam_height_m;
+use crate::grid::{grid_sweep, Grid, GridSpec};
+use crate::hybrid_scan::{HybridScan, HybridScanOptions};
+use crate::metadata::FileMetadata;
+use crate::partition::{
+    identify_cells, partition_grid, partition_sweep, ConvectiveCell, PartitionOptions, RainType,
+    SteinerOptions,
+};
+use crate::phase::{PhaseOptions, ProcessedPhase};
+use crate::precip_type::{
+    surface_precipitation_types, HydrometeorClass, MeltingLayer, PrecipitationTypeInput,
+    SurfacePrecipitationType,
+};
+use crate::qpe::{
+    combine_rain_rates, rain_rate_grid, rain_rate_sweep, rain_rate_sweep_weighted, QpeOptions,
+    ZrPreset, ZrRelation, ZrSelection,
+};
+use crate::records::{
+    DataBlockProduct, DataMoment, GenericData, Message31, Message31Header, VolumeData,
+};
+use crate::render::{render_all_elevations, ColorMap, Palette, RenderOptions};
+use crate::verification::{verify_against, ContingencyTable};
+use crate::{DataFile, DecodeOptions, GateValue, Product, SweepCapabilities};
 
 #[test]
 fn load_file() -> Result<()> {
@@ -43,3 +73,3695 @@ fn load_file() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn into_sweeps() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+
+    let sweeps = DataFile::new(hurricane_harvey)?.into_sweeps();
+    assert_eq!(sweeps.len(), 19);
+
+    let mut previous_elevation_number = 0;
+    for sweep in sweeps {
+        // Ensure sweeps are yielded in elevation order
+        assert!(sweep.elevation_number() > previous_elevation_number);
+        previous_elevation_number = sweep.elevation_number();
+
+        // Ensure the sweep's radials are sorted by azimuth
+        let radials = sweep.into_radials();
+        assert!(!radials.is_empty());
+        assert!(radials
+            .windows(2)
+            .all(|pair| pair[0].header().azm() <= pair[1].header().azm()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn elevation_scans_sorted_by_azimuth() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let data = std::fs::read(hurricane_harvey)?;
+
+    let sorted = DataFile::from_slice(&data)?;
+    for radials in sorted.elevation_scans().values() {
+        assert!(radials
+            .windows(2)
+            .all(|pair| pair[0].header().azm() <= pair[1].header().azm()));
+    }
+
+    // Disabling sorting leaves radials in collection order
+    let options = DecodeOptions::new().with_sort_azimuths(false);
+    let unsorted = DataFile::from_vec_with_options(data, &options)?;
+    for radials in unsorted.elevation_scans().values() {
+        assert!(radials
+            .windows(2)
+            .all(|pair| pair[0].header().azm_num() < pair[1].header().azm_num()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn iter_gates() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+
+    let radials = datafile
+        .elevation_scans()
+        .values()
+        .next()
+        .expect("has scans");
+    let radial = radials.first().expect("has radials");
+    let moment = radial.reflectivity_data().expect("has reflectivity");
+
+    let gates: Vec<_> = GateIterator::from_radial(radial, Product::Reflectivity)
+        .expect("has reflectivity")
+        .collect();
+    assert_eq!(
+        gates.len(),
+        moment.data().number_data_moment_gates() as usize
+    );
+
+    // Ensure geometry is attached to each gate
+    let (first_range, azimuth, elevation, _) = gates[0];
+    assert!((first_range - f32::from(moment.data().data_moment_range())).abs() < f32::EPSILON);
+    assert!((azimuth - radial.header().azm()).abs() < f32::EPSILON);
+    assert!((elevation - radial.header().elev()).abs() < f32::EPSILON);
+    assert!(gates.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    let via_radial: Vec<_> = radial
+        .iter_gates(Product::Reflectivity)
+        .expect("has reflectivity")
+        .collect();
+    assert_eq!(via_radial, gates);
+    assert!(fine_line_sweep(110)[0]
+        .iter_gates(Product::DifferentialPhase)
+        .is_none());
+
+    // Ensure the values are scaled into a sane reflectivity range
+    assert!(gates.iter().any(|gate| gate.3 != GateValue::BelowThreshold));
+    for (_, _, _, value) in gates {
+        if let Some(dbz) = value.value() {
+            assert!((-33.0..=95.0).contains(&dbz));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn echo_climatology() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+
+    let mut climatology = EchoClimatology::new(vec![10.0, 30.0, 50.0], 1.0, 1000.0, 230_000.0);
+    climatology.ingest(&datafile);
+    climatology.ingest(&datafile);
+    assert_eq!(climatology.volume_count(), 2);
+
+    let lowest_elevation = climatology.elevations().next().expect("has elevations");
+    let weak = climatology
+        .frequency_field(lowest_elevation, 0)
+        .expect("has lowest tilt");
+    let strong = climatology
+        .frequency_field(lowest_elevation, 2)
+        .expect("has lowest tilt");
+
+    // Frequencies are fractions and stronger thresholds are exceeded no more often
+    assert!(weak.iter().flatten().any(|frequency| *frequency > 0.0));
+    for (weak, strong) in weak.iter().zip(&strong) {
+        if let (Some(weak), Some(strong)) = (weak, strong) {
+            assert!((0.0..=1.0).contains(weak));
+            assert!(strong <= weak);
+        }
+    }
+
+    // Ensure the accumulator survives a round trip between runs
+    let mut saved = Vec::new();
+    climatology.save(&mut saved)?;
+    assert_eq!(EchoClimatology::load(saved.as_slice())?, climatology);
+
+    // Saved accumulators are big-endian with fixed-width integers, so the range bin count follows
+    // the three thresholds and two resolutions
+    let mut corrupted = saved.clone();
+    corrupted[35] ^= 1;
+    let error = EchoClimatology::load(corrupted.as_slice()).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(crate::error::Error::InvalidClimatology(_))
+    ));
+    let mut corrupted = saved;
+    corrupted[20..24].fill(0);
+    assert!(EchoClimatology::load(corrupted.as_slice()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn antenna_altitude() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+
+    let volume_data = datafile.first_volume_data().expect("has volume data");
+    let altitude = volume_data.antenna_altitude_m();
+    assert!(
+        (altitude - f32::from(volume_data.site_height() + volume_data.feedhorn_height())).abs()
+            < f32::EPSILON
+    );
+
+    // Corpus Christi sits near sea level with a tower-mounted antenna
+    assert!((0.0..100.0).contains(&altitude));
+
+    // The beam starts at the antenna and rises with range
+    assert!((beam_height_m(0.0, 0.5, altitude) - altitude).abs() < 0.01);
+    let far_height = beam_height_m(100_000.0, 0.5, altitude);
+    assert!((far_height - altitude - 1461.0).abs() < 5.0);
+
+    Ok(())
+}
+
+#[test]
+fn decompress_mixed_records() -> Result<()> {
+    use crate::decompressor::decompress_file;
+    use crate::metadata::is_compressed;
+    use std::io::Write;
+
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let decompressed = decompress_file(&std::fs::read(hurricane_harvey)?)?;
+
+    // Recompress the records, the first with BZIP2 and the second with zlib like some mirrors
+    let (header, records) = decompressed.split_at(24);
+    let (first, second) = records.split_at(records.len() / 2);
+
+    let mut bzip2_encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+    bzip2_encoder.write_all(first)?;
+    let first = bzip2_encoder.finish()?;
+
+    let mut zlib_encoder =
+        flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib_encoder.write_all(second)?;
+    let second = zlib_encoder.finish()?;
+
+    let mut recompressed = header.to_vec();
+    recompressed.extend_from_slice(&i32::try_from(first.len())?.to_be_bytes());
+    recompressed.extend_from_slice(&first);
+    recompressed.extend_from_slice(&(-i32::try_from(second.len())?).to_be_bytes());
+    recompressed.extend_from_slice(&second);
+
+    assert!(is_compressed(&recompressed));
+    assert_eq!(decompress_file(&recompressed)?, decompressed);
+
+    // A file whose first record is zlib-compressed is also detected as compressed
+    let mut zlib_only = header.to_vec();
+    zlib_only.extend_from_slice(&(-i32::try_from(second.len())?).to_be_bytes());
+    zlib_only.extend_from_slice(&second);
+    assert!(is_compressed(&zlib_only));
+
+    Ok(())
+}
+
+#[test]
+fn cancelled_decode() -> Result<()> {
+    use crate::cancel::CancellationToken;
+    use crate::error::Error;
+
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let data = std::fs::read(hurricane_harvey)?;
+
+    let token = CancellationToken::new();
+    let options = DecodeOptions::new().with_cancellation_token(token.clone());
+    token.cancel();
+
+    let error = DataFile::from_vec_with_options(data.clone(), &options)
+        .err()
+        .expect("decoding was cancelled");
+    assert!(matches!(error.downcast_ref(), Some(Error::Cancelled)));
+
+    Ok(())
+}
+
+#[test]
+fn cancelled_products() -> Result<()> {
+    use crate::cancel::CancellationToken;
+    use crate::error::Error;
+    use crate::grid::{grid_composite, grid_composite_cancellable, grid_sweep_cancellable};
+    use crate::render::render_sweep_cancellable;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(90)
+        .with_gates(200)
+        .with_seed(7);
+    let mut simulator = Simulator::new(config);
+    let mut next = || DataFile::from_vec(simulator.next().expect("is endless")?.into_data());
+    let (earlier, later) = (next()?, next()?);
+    let spec = GridSpec::new(64, 64, 2000.0);
+
+    // Without cancellation the products are unchanged
+    let token = CancellationToken::new();
+    assert_eq!(
+        grid_composite_cancellable(&earlier, Product::Reflectivity, &spec, &token)?,
+        grid_composite(&earlier, Product::Reflectivity, &spec)
+    );
+    let pair = VolumePair::new(&earlier, &later, chrono::Duration::minutes(10))?;
+    let combined = pair.combine_cancellable(
+        Product::Reflectivity,
+        VolumeLayer::Composite,
+        &spec,
+        Combination::Maximum,
+        &token,
+    )?;
+    assert_eq!(
+        combined,
+        pair.combine(
+            Product::Reflectivity,
+            VolumeLayer::Composite,
+            &spec,
+            Combination::Maximum
+        )
+    );
+
+    let mut climatology = EchoClimatology::new(vec![10.0], 1.0, 1000.0, 230_000.0);
+    climatology.ingest_cancellable(&earlier, &token)?;
+    assert_eq!(climatology.volume_count(), 1);
+
+    // Once cancelled, each stops with a cancellation error
+    token.cancel();
+    let radials = &earlier.elevation_scans()[&1];
+    let palette = Palette::for_product(Product::Reflectivity);
+    let hybrid_options = HybridScanOptions::new();
+    let errors = [
+        grid_sweep_cancellable(radials, Product::Reflectivity, &spec, &token).err(),
+        grid_composite_cancellable(&earlier, Product::Reflectivity, &spec, &token).err(),
+        pair.combine_cancellable(
+            Product::Reflectivity,
+            VolumeLayer::LowestTilt,
+            &spec,
+            Combination::Difference,
+            &token,
+        )
+        .err(),
+        HybridScan::new_cancellable(&earlier, &NoBlockage, &hybrid_options, &token).err(),
+        render_sweep_cancellable(
+            radials,
+            Product::Reflectivity,
+            &palette,
+            &RenderOptions::new(),
+            &token,
+        )
+        .err(),
+    ];
+    for error in errors {
+        let error = error.expect("was cancelled");
+        assert!(matches!(error.downcast_ref(), Some(Error::Cancelled)));
+    }
+
+    // A cancelled ingest leaves the climatology as it was
+    let before = climatology.clone();
+    assert!(climatology.ingest_cancellable(&later, &token).is_err());
+    assert_eq!(climatology, before);
+
+    Ok(())
+}
+
+#[test]
+fn first_tilt() -> Result<()> {
+    use crate::first_tilt::{decode_first_tilt, FirstTiltDecoder};
+
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let data = std::fs::read(hurricane_harvey)?;
+
+    let datafile = DataFile::from_slice(&data)?;
+    let (elevation_number, expected) = datafile
+        .elevation_scans()
+        .first_key_value()
+        .expect("has scans");
+
+    // The fast path produces the same lowest tilt reflectivity as a full decode
+    let sweep = decode_first_tilt(&data)?;
+    assert_eq!(sweep.elevation_number(), *elevation_number);
+    assert_eq!(sweep.radials().len(), expected.len());
+    for (radial, expected) in sweep.radials().iter().zip(expected) {
+        assert_eq!(radial.header().azm_num(), expected.header().azm_num());
+        assert_eq!(
+            radial
+                .reflectivity_data()
+                .expect("has reflectivity")
+                .moment_data(),
+            expected
+                .reflectivity_data()
+                .expect("has reflectivity")
+                .moment_data()
+        );
+        assert!(radial.velocity_data().is_none());
+    }
+
+    // The sweep is also produced when the volume arrives as chunks of whole records
+    let decompressed = crate::decompressor::decompress_file(&data)?;
+    let mut decoder = FirstTiltDecoder::new();
+    let mut chunked = None;
+    for chunk in decompressed.chunks(100_000) {
+        if let Some(sweep) = decoder.push(chunk)? {
+            chunked = Some(sweep);
+            break;
+        }
+    }
+    assert_eq!(
+        chunked.expect("completes lowest tilt").radials().len(),
+        expected.len()
+    );
+
+    // A radial whose message size is shorter than its headers is rejected rather than sliced
+    let mut malformed = decompressed[..24].to_vec();
+    malformed.extend([0; 12]);
+    malformed.extend(4u16.to_be_bytes());
+    malformed.extend([0, 31]);
+    malformed.extend([0; 40]);
+    let error = FirstTiltDecoder::new()
+        .push(&malformed)
+        .err()
+        .expect("malformed message is rejected");
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(crate::error::Error::InvalidMessageSize(4))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn encode_round_trip() -> Result<()> {
+    use crate::encode::{encode_compressed_file, encode_file};
+
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+
+    for encoded in [encode_file(&datafile)?, encode_compressed_file(&datafile)?] {
+        let decoded = DataFile::from_vec(encoded)?;
+        assert_eq!(
+            decoded.elevation_scans().len(),
+            datafile.elevation_scans().len()
+        );
+
+        for (radials, expected) in decoded
+            .elevation_scans()
+            .values()
+            .zip(datafile.elevation_scans().values())
+        {
+            assert_eq!(radials.len(), expected.len());
+            for (radial, expected) in radials.iter().zip(expected) {
+                assert_eq!(radial.header().azm_num(), expected.header().azm_num());
+                assert_eq!(
+                    radial.volume_data().map(VolumeData::lat),
+                    expected.volume_data().map(VolumeData::lat)
+                );
+                for product in [
+                    Product::Reflectivity,
+                    Product::Velocity,
+                    Product::CorrelationCoefficient,
+                ] {
+                    assert_eq!(
+                        radial
+                            .get_data_moment(&product.into())
+                            .map(DataMoment::moment_data),
+                        expected
+                            .get_data_moment(&product.into())
+                            .map(DataMoment::moment_data)
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn simulator() -> Result<()> {
+    use crate::simulate::{SimulatedSite, SimulatedVolume, Simulator, SimulatorConfig};
+
+    let sites = vec![
+        SimulatedSite::new("KDMX", 41.73, -93.72, 299),
+        SimulatedSite::new("KOAX", 41.32, -96.37, 350),
+    ];
+    let config = SimulatorConfig::new(sites, 12.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(360)
+        .with_gates(300);
+
+    let volumes = Simulator::new(config).take(4).collect::<Result<Vec<_>>>()?;
+
+    // Sites alternate and are staggered evenly across the five minute volume interval
+    let sites: Vec<_> = volumes.iter().map(SimulatedVolume::site).collect();
+    assert_eq!(sites, ["KDMX", "KOAX", "KDMX", "KOAX"]);
+    for pair in volumes.windows(2) {
+        assert_eq!((pair[1].time() - pair[0].time()).num_seconds(), 150);
+    }
+
+    for volume in volumes {
+        assert!(crate::metadata::is_compressed(volume.data()));
+
+        let decoded = DataFile::from_vec(volume.into_data())?;
+        assert_eq!(decoded.elevation_scans().len(), 2);
+
+        let volume_data = decoded.first_volume_data().expect("has volume data");
+        assert_eq!(volume_data.volume_coverage_pattern_number(), 215);
+
+        for radials in decoded.elevation_scans().values() {
+            assert_eq!(radials.len(), 360);
+            for radial in radials {
+                let gates = GateIterator::from_radial(radial, Product::Reflectivity)
+                    .expect("has reflectivity");
+                assert_eq!(gates.len(), 300);
+                assert!(radial.velocity_data().is_some());
+            }
+        }
+    }
+
+    // The stream ends rather than overflowing once volume times pass the latest representable
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 1.0)
+        .with_start(chrono::NaiveDateTime::MAX - chrono::Duration::minutes(90))
+        .with_elevations(vec![0.5])
+        .with_radials_per_sweep(36)
+        .with_gates(10)
+        .with_compression(false);
+    assert_eq!(Simulator::new(config).count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn processed_phase() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+
+    let radial = &datafile.elevation_scans()[&1][0];
+    let raw = radial.phi_data().expect("has differential phase");
+    let system_phase = radial
+        .volume_data()
+        .expect("has volume data")
+        .initial_system_differential_phase();
+
+    // By default the volume's system phase offset is removed
+    let processed =
+        ProcessedPhase::from_radial(radial, &PhaseOptions::new()).expect("has differential phase");
+    assert!((processed.system_phase_offset() - system_phase).abs() < f32::EPSILON);
+    assert_eq!(processed.phidp().len(), raw.gate_count());
+    assert_eq!(processed.kdp().len(), raw.gate_count());
+    for (phidp, kdp) in processed.phidp().iter().zip(processed.kdp()) {
+        assert!(phidp.is_some() || kdp.is_none());
+    }
+
+    for (index, phidp) in processed.phidp().iter().enumerate() {
+        let measured = raw.value(index).and_then(|value| value.value());
+        assert_eq!(phidp.is_some(), measured.is_some());
+        if let (Some(phidp), Some(measured)) = (phidp, measured) {
+            assert!(*phidp >= -180.0);
+            assert!((phidp - (measured - system_phase)).rem_euclid(360.0) < 1e-3);
+        }
+    }
+
+    // An override replaces the volume's offset, which shifts PHIDP but not KDP
+    let overridden = ProcessedPhase::from_radial(
+        radial,
+        &PhaseOptions::new().with_system_phase_offset(system_phase - 10.0),
+    )
+    .expect("has differential phase");
+    assert!((overridden.system_phase_offset() - (system_phase - 10.0)).abs() < f32::EPSILON);
+    for (index, phidp) in overridden.phidp().iter().enumerate() {
+        if let (Some(phidp), Some(default)) = (phidp, processed.phidp()[index]) {
+            if default < 160.0 {
+                assert!((phidp - default - 10.0).abs() < 1e-3);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn hybrid_scan() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+    let site = datafile.first_volume_data().expect("has volume data");
+
+    let options = HybridScanOptions::new()
+        .with_resolution(2.0, 2000.0)
+        .with_max_range(100_000.0);
+
+    // Without blockage the lowest tilt is used everywhere
+    let unblocked = HybridScan::new(&datafile, &NoBlockage, &options)?;
+    assert_eq!(unblocked.azimuth_bins(), 180);
+    assert_eq!(unblocked.range_bins(), 50);
+    let lowest = unblocked.elevation(0.0, 1000.0).expect("has a tilt");
+    assert!(unblocked
+        .elevations()
+        .iter()
+        .all(|elevation| *elevation == Some(lowest)));
+    assert!(unblocked.reflectivity().iter().any(Option::is_some));
+
+    // A ridge east of the radar forces higher tilts beyond it
+    let (site_lat, site_long) = (site.lat(), site.long());
+    let ridge = move |lat: f32, long: f32| {
+        let within = long > site_long + 0.2 && (lat - site_lat).abs() < 0.1;
+        Some(if within { 1500.0 } else { 0.0 })
+    };
+    let blockage = TerrainBlockage::new(&ridge, &site, 2.0, 2000.0, 100_000.0);
+    let blocked = HybridScan::new(&datafile, &blockage, &options)?;
+
+    assert_eq!(blocked.elevation(270.0, 50_000.0), Some(lowest));
+    let behind_ridge = blocked.elevation(90.0, 50_000.0).expect("has a tilt");
+    assert!(behind_ridge > lowest);
+    assert!(blocked.blockage().iter().all(|fraction| *fraction <= 0.5));
+
+    // Blockage never decreases with range, and clones agree with the cached profiles
+    let fractions: Vec<f32> = (0..60u8)
+        .map(|step| blockage.blocked_fraction(0.5, 90.0, f32::from(step) * 2000.0))
+        .collect();
+    assert!(fractions.windows(2).all(|pair| pair[1] >= pair[0]));
+    assert!(fractions[59] > 0.0);
+    let cloned = blockage.clone().blocked_fraction(0.5, 90.0, 118_000.0);
+    assert!((cloned - fractions[59]).abs() < f32::EPSILON);
+
+    Ok(())
+}
+
+#[test]
+fn surface_precipitation_type() {
+    let aloft = MeltingLayer::new(1500.0, 1800.0);
+    let at_surface = MeltingLayer::new(0.0, 400.0);
+    let below_surface = MeltingLayer::new(-300.0, 0.0);
+    let elevated_warm_layer = MeltingLayer::new(1000.0, 2000.0).with_subfreezing_surface(true);
+
+    let cells = [
+        (
+            HydrometeorClass::DrySnow,
+            2500.0,
+            aloft,
+            SurfacePrecipitationType::Rain,
+        ),
+        (
+            HydrometeorClass::DrySnow,
+            800.0,
+            at_surface,
+            SurfacePrecipitationType::Mix,
+        ),
+        (
+            HydrometeorClass::DrySnow,
+            800.0,
+            below_surface,
+            SurfacePrecipitationType::Snow,
+        ),
+        (
+            HydrometeorClass::LightModerateRain,
+            800.0,
+            elevated_warm_layer,
+            SurfacePrecipitationType::FreezingRain,
+        ),
+        (
+            HydrometeorClass::Graupel,
+            800.0,
+            elevated_warm_layer,
+            SurfacePrecipitationType::IcePellets,
+        ),
+        (
+            HydrometeorClass::GroundClutter,
+            800.0,
+            aloft,
+            SurfacePrecipitationType::None,
+        ),
+    ];
+
+    let inputs = cells
+        .iter()
+        .map(|(class, height, layer, _)| {
+            PrecipitationTypeInput::new(*class, *height, 100.0, *layer)
+        })
+        .collect();
+    let types = surface_precipitation_types(&Grid::new(3, 2, inputs));
+
+    assert_eq!(types.columns(), 3);
+    assert_eq!(types.rows(), 2);
+    for (actual, (_, _, _, expected)) in types.values().iter().zip(cells) {
+        assert_eq!(*actual, expected);
+    }
+    assert_eq!(types.get(1, 1), Some(&SurfacePrecipitationType::IcePellets));
+
+    // Wet snow melts into rain unless it survives below the melting layer or refreezes
+    let wet_snow = |height, layer| {
+        PrecipitationTypeInput::new(HydrometeorClass::WetSnow, height, 100.0, layer)
+    };
+    assert_eq!(
+        wet_snow(1600.0, aloft).classify(),
+        SurfacePrecipitationType::Rain
+    );
+    assert_eq!(
+        wet_snow(1200.0, aloft).classify(),
+        SurfacePrecipitationType::Mix
+    );
+    assert_eq!(
+        wet_snow(800.0, below_surface).classify(),
+        SurfacePrecipitationType::Snow
+    );
+    assert_eq!(
+        wet_snow(1500.0, elevated_warm_layer).classify(),
+        SurfacePrecipitationType::IcePellets
+    );
+
+    // Precipitation melted aloft refreezes over a subfreezing surface
+    let snow_aloft = PrecipitationTypeInput::new(HydrometeorClass::DrySnow, 2500.0, 100.0, aloft);
+    assert_eq!(snow_aloft.surface_temperature(), None);
+    assert_eq!(
+        snow_aloft.with_surface_temperature(-2.0).classify(),
+        SurfacePrecipitationType::FreezingRain
+    );
+    assert_eq!(
+        snow_aloft.with_surface_temperature(3.0).classify(),
+        SurfacePrecipitationType::Rain
+    );
+    let rain_at_surface =
+        PrecipitationTypeInput::new(HydrometeorClass::HeavyRain, 300.0, 100.0, at_surface);
+    assert_eq!(rain_at_surface.classify(), SurfacePrecipitationType::Rain);
+    assert_eq!(
+        rain_at_surface.with_surface_temperature(-1.0).classify(),
+        SurfacePrecipitationType::FreezingRain
+    );
+
+    assert_eq!(
+        HydrometeorClass::from_level3_code(50),
+        Some(HydrometeorClass::WetSnow)
+    );
+    assert_eq!(HydrometeorClass::from_level3_code(55), None);
+}
+
+/// Builds a one degree sweep with a 20 dBZ line at the specified gate between 30 and 60 degrees
+/// azimuth, with radial velocity converging across it.
+fn fine_line_sweep(line_gate: usize) -> Vec<Message31> {
+    (0..360u16)
+        .map(|azimuth| {
+            let mut radial = Message31::new(Message31Header::new(
+                *b"KTST",
+                0,
+                1,
+                azimuth + 1,
+                f32::from(azimuth) + 0.5,
+                2,
+                1,
+                1,
+                0.5,
+            ));
+
+            let on_line = (30..=60).contains(&azimuth);
+            let mut reflectivity = vec![0u8; 200];
+            let mut velocity = vec![129u8; 200];
+            if on_line {
+                reflectivity[line_gate] = 106;
+                reflectivity[line_gate + 1] = 106;
+                velocity[..=line_gate].fill(141);
+                velocity[line_gate + 1..].fill(117);
+            }
+
+            for (product, offset, values) in [
+                (DataBlockProduct::Reflectivity, 66.0, reflectivity),
+                (DataBlockProduct::Velocity, 129.0, velocity),
+            ] {
+                let data = GenericData::new(&product, 200, 2125, 250, 8, 2.0, offset);
+                radial.set_data_moment(DataMoment::new(product, data, values));
+            }
+
+            radial
+        })
+        .collect()
+}
+
+#[test]
+fn fine_line_detection() {
+    let options = FineLineOptions::new();
+
+    let first = detect_fine_lines_in_sweep(&fine_line_sweep(110), &options);
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].points().len(), 31);
+    assert!((first[0].mean_reflectivity() - 20.0).abs() < 0.5);
+    assert!((first[0].mean_convergence() - 12.0).abs() < 0.5);
+
+    // Moving the line one kilometer outward over five minutes
+    let second = detect_fine_lines_in_sweep(&fine_line_sweep(114), &options);
+    assert_eq!(second.len(), 1);
+
+    let start = chrono::NaiveDate::from_ymd_opt(2024, 6, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("is valid date");
+    let mut line_tracker = FineLineTracker::new(5000.0);
+
+    let tracked = line_tracker.update(start, first);
+    assert!(tracked[0].motion().is_none());
+
+    let tracked = line_tracker.update(start + chrono::Duration::minutes(5), second);
+    assert_eq!(tracked.len(), 1);
+    assert_eq!(tracked[0].id(), 1);
+    let speed = tracked[0].speed().expect("has motion");
+    assert!((speed - 1000.0 / 300.0).abs() < 0.1);
+
+    // Lines without convergence are not detected
+    let mut divergent = fine_line_sweep(110);
+    for radial in &mut divergent {
+        let data = GenericData::new(&DataBlockProduct::Velocity, 200, 2125, 250, 8, 2.0, 129.0);
+        radial.set_data_moment(DataMoment::new(
+            DataBlockProduct::Velocity,
+            data,
+            vec![129; 200],
+        ));
+    }
+    assert!(detect_fine_lines_in_sweep(&divergent, &options).is_empty());
+}
+
+#[test]
+fn motion_estimation_overlap() {
+    use crate::composite::estimate_motion;
+
+    // A band moving a cell east, and a small cluster which matches exactly only at a large shift
+    let field = |offset: usize, bias: f32, cluster: usize| {
+        let mut values = vec![None; 20 * 20];
+        for row in 10..12 {
+            for column in 5..15 {
+                let value = f32::from(u8::try_from(column * column % 7).expect("is small"));
+                values[row * 20 + column + offset] = Some(20.0 + 3.0 * value + bias);
+            }
+        }
+        for row in cluster..cluster + 2 {
+            for column in cluster..cluster + 2 {
+                values[row * 20 + column] = Some(40.0);
+            }
+        }
+        Grid::new(20, 20, values)
+    };
+    let earlier = field(0, 0.0, 0);
+    let later = field(1, 0.5, 3);
+
+    assert_eq!(estimate_motion(&earlier, &later, 3), (1, 0));
+    assert_eq!(
+        estimate_motion(&earlier, &Grid::new(20, 20, vec![None; 400]), 3),
+        (0, 0)
+    );
+}
+
+#[test]
+fn volume_pair_tendency() -> Result<()> {
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let simulate = |radar_id: &str| -> Result<Vec<DataFile>> {
+        let config =
+            SimulatorConfig::new(vec![SimulatedSite::new(radar_id, 41.73, -93.72, 299)], 12.0)
+                .with_elevations(vec![0.5, 1.5])
+                .with_radials_per_sweep(360)
+                .with_gates(300)
+                .with_compression(false);
+
+        Simulator::new(config)
+            .take(2)
+            .map(|volume| DataFile::from_vec(volume?.into_data()))
+            .collect()
+    };
+    let volumes = simulate("KDMX")?;
+    let (earlier, later) = (&volumes[0], &volumes[1]);
+
+    // Volumes must be from the same site, in order, and close enough in time
+    let five_minutes = chrono::Duration::minutes(5);
+    assert!(VolumePair::new(later, earlier, five_minutes).is_err());
+    assert!(VolumePair::new(earlier, later, chrono::Duration::minutes(4)).is_err());
+    assert!(VolumePair::new(earlier, &simulate("KOAX")?[1], five_minutes).is_err());
+
+    let pair = VolumePair::new(earlier, later, five_minutes)?;
+    assert_eq!(pair.elapsed(), five_minutes);
+
+    let spec = GridSpec::new(60, 60, 1000.0);
+    let earlier_grid = grid_sweep(&earlier.elevation_scans()[&1], Product::Reflectivity, &spec);
+    let later_grid = grid_sweep(&later.elevation_scans()[&1], Product::Reflectivity, &spec);
+
+    let difference = pair.combine(
+        Product::Reflectivity,
+        VolumeLayer::LowestTilt,
+        &spec,
+        Combination::Difference,
+    );
+    let maximum = pair.combine(
+        Product::Reflectivity,
+        VolumeLayer::LowestTilt,
+        &spec,
+        Combination::Maximum,
+    );
+    let tendency = pair.tendency(
+        Product::Reflectivity,
+        VolumeLayer::LowestTilt,
+        &spec,
+        chrono::Duration::minutes(10),
+    );
+
+    let mut compared = 0;
+    for index in 0..spec.columns() * spec.rows() {
+        let (Some(before), Some(after)) =
+            (earlier_grid.values()[index], later_grid.values()[index])
+        else {
+            assert!(difference.values()[index].is_none());
+            continue;
+        };
+
+        compared += 1;
+        let change = difference.values()[index].expect("both volumes have values");
+        assert!((change - (after - before)).abs() < 1e-3);
+        assert!((maximum.values()[index].expect("has value") - before.max(after)).abs() < 1e-3);
+        assert!((tendency.values()[index].expect("has value") - change * 2.0).abs() < 1e-3);
+    }
+    assert!(compared > 0);
+
+    Ok(())
+}
+
+#[test]
+fn contingency_scores() -> Result<()> {
+    let forecast = Grid::new(
+        3,
+        2,
+        vec![
+            Some(40.0),
+            Some(40.0),
+            Some(10.0),
+            None,
+            Some(35.0),
+            Some(5.0),
+        ],
+    );
+    let observed = Grid::new(
+        3,
+        2,
+        vec![
+            Some(45.0),
+            Some(20.0),
+            Some(30.0),
+            Some(50.0),
+            Some(36.0),
+            None,
+        ],
+    );
+
+    let table = ContingencyTable::from_grids(&forecast, &observed, 30.0)?;
+    assert_eq!(table.hits(), 2);
+    assert_eq!(table.misses(), 2);
+    assert_eq!(table.false_alarms(), 1);
+    assert_eq!(table.correct_negatives(), 1);
+    assert_eq!(table.pod(), Some(0.5));
+    assert_eq!(table.csi(), Some(0.4));
+    assert!((table.far().expect("has forecasts") - 1.0 / 3.0).abs() < 1e-6);
+    assert_eq!(table.bias(), Some(0.75));
+
+    let mut total = table;
+    total += table;
+    assert_eq!(total.hits(), 4);
+    assert_eq!(total.pod(), Some(0.5));
+
+    // External fields are looked up by valid time and must match the grid
+    let time = chrono::NaiveDate::from_ymd_opt(2024, 6, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("is valid date");
+    let source = std::collections::BTreeMap::from([(time, observed)]);
+
+    let spec = GridSpec::new(3, 2, 1000.0);
+    assert_eq!(
+        verify_against(&source, time, &spec, &forecast, 30.0)?,
+        Some(table)
+    );
+    assert_eq!(
+        verify_against(
+            &source,
+            time + chrono::Duration::minutes(5),
+            &spec,
+            &forecast,
+            30.0
+        )?,
+        None
+    );
+    assert!(verify_against(&source, time, &GridSpec::new(2, 3, 1000.0), &forecast, 30.0).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn radial_trailing_bytes() -> Result<()> {
+    use crate::encode::encode_file;
+
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let mut datafile = DataFile::new(hurricane_harvey)?;
+
+    // Current builds' radials end with their last data block
+    assert!(datafile
+        .elevation_scans()
+        .values()
+        .flatten()
+        .all(|radial| radial.trailing_bytes().is_empty()));
+
+    // Simulate a newer build appending fields to the first sweep's radials
+    let extension = [0xAB, 0xCD, 0xEF];
+    datafile
+        .elevation_scans_mut()
+        .retain(|elevation, _| *elevation <= 2);
+    for radial in datafile
+        .elevation_scans_mut()
+        .get_mut(&1)
+        .expect("has sweep")
+    {
+        radial.set_trailing_bytes(extension.to_vec());
+    }
+
+    let decoded = DataFile::from_vec(encode_file(&datafile)?)?;
+    for (radials, expected) in decoded
+        .elevation_scans()
+        .values()
+        .zip(datafile.elevation_scans().values())
+    {
+        for (radial, expected) in radials.iter().zip(expected) {
+            assert_eq!(radial.trailing_bytes(), expected.trailing_bytes());
+            assert_eq!(
+                radial.reflectivity_data().map(DataMoment::moment_data),
+                expected.reflectivity_data().map(DataMoment::moment_data)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn extended_radial_data() -> Result<()> {
+    use crate::encode::encode_file;
+    use crate::records::{RadialData, VolumeHeaderRecord};
+    use crate::Sweep;
+
+    // Harvey's build predates the extended block
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+    let radial_data = datafile.elevation_scans()[&1][0]
+        .radial_data()
+        .expect("has radial data");
+    assert_eq!(radial_data.lrtup(), 28);
+    assert!(radial_data.extension().is_empty());
+    assert!(radial_data.zdr_bias_estimate().is_none());
+
+    // A Build 19 block, and a later one appending fields this decoder does not recognize
+    let build_19 = RadialData::new(4660, 2650).with_zdr_bias_estimate(-0.25);
+    let mut later = build_19.clone();
+    later.set_extension([build_19.extension(), &[1, 2, 3, 4]].concat())?;
+    assert_eq!((build_19.lrtup(), later.lrtup()), (32, 36));
+
+    let error = later
+        .set_extension(vec![0; usize::from(u16::MAX)])
+        .expect_err("block is too large");
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(crate::error::Error::DataBlockTooLarge(_))
+    ));
+    assert_eq!(later.lrtup(), 36);
+
+    let radials = [build_19, later]
+        .into_iter()
+        .enumerate()
+        .map(|(index, radial_data)| {
+            let azimuth = u16::try_from(index + 1).expect("is small");
+            let header =
+                Message31Header::new(*b"KTST", 43_200_000, 19_875, azimuth, 0.5, 1, 3, 1, 0.5);
+            let data =
+                GenericData::new(&DataBlockProduct::Reflectivity, 4, 2125, 250, 8, 2.0, 66.0);
+            let moment = DataMoment::new(DataBlockProduct::Reflectivity, data, vec![0, 1, 86, 106]);
+            Message31::new(header)
+                .with_radial_data(radial_data)
+                .with_data_moment(moment)
+        })
+        .collect();
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 43_200_000, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, radials)])?;
+
+    let decoded = DataFile::from_vec(encode_file(&file)?)?;
+    for (radial, expected) in decoded.elevation_scans()[&1]
+        .iter()
+        .zip(&file.elevation_scans()[&1])
+    {
+        let radial_data = radial.radial_data().expect("has radial data");
+        let expected_data = expected.radial_data().expect("has radial data");
+        assert_eq!(radial_data.lrtup(), expected_data.lrtup());
+        assert_eq!(radial_data.extension(), expected_data.extension());
+        let bias = radial_data.zdr_bias_estimate().expect("is extended");
+        assert!((bias + 0.25).abs() < 1e-6);
+        assert_eq!(radial_data.nyquist_velocity(), 2650);
+
+        // The extension is part of the block rather than the radial's trailing bytes
+        assert!(radial.trailing_bytes().is_empty());
+        assert_eq!(
+            radial.reflectivity_data().map(DataMoment::moment_data),
+            Some([0, 1, 86, 106].as_slice())
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn reordered_data_block_pointers() -> Result<()> {
+    use crate::encode::encode_file;
+
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let mut datafile = DataFile::new(hurricane_harvey)?;
+    datafile
+        .elevation_scans_mut()
+        .retain(|elevation, _| *elevation == 1);
+    let encoded = encode_file(&datafile)?;
+
+    // The first radial's pointers follow the volume, message, and message 31 headers
+    let pointers_start = 24 + 28 + 32;
+    let block_count = datafile.elevation_scans()[&1][0]
+        .header()
+        .data_block_count() as usize;
+    let pointers = pointers_start..pointers_start + block_count * 4;
+
+    let decode_with_pointers = |order: &dyn Fn(&mut Vec<[u8; 4]>)| {
+        let mut data = encoded.clone();
+        let mut table: Vec<[u8; 4]> = data[pointers.clone()]
+            .chunks_exact(4)
+            .map(|pointer| pointer.try_into().expect("is four bytes"))
+            .collect();
+        order(&mut table);
+        data[pointers.clone()].copy_from_slice(&table.concat());
+
+        DataFile::from_vec(data)
+    };
+
+    // Pointers in descending order, pointing backwards, decode identically
+    let reversed = decode_with_pointers(&|table| table.reverse())?;
+    let rotated = decode_with_pointers(&|table| table.rotate_left(2))?;
+    for decoded in [reversed, rotated] {
+        let radial = &decoded.elevation_scans()[&1][0];
+        let expected = &datafile.elevation_scans()[&1][0];
+        assert!(radial.volume_data().is_some());
+        assert!(radial.trailing_bytes().is_empty());
+        assert_eq!(
+            radial.reflectivity_data().map(DataMoment::moment_data),
+            expected.reflectivity_data().map(DataMoment::moment_data)
+        );
+    }
+
+    // Pointers into the header or beyond the radial are rejected
+    assert!(decode_with_pointers(&|table| table[0] = 8u32.to_be_bytes()).is_err());
+    assert!(decode_with_pointers(&|table| table[1] = u32::MAX.to_be_bytes()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn clear_air_split_cuts() -> Result<()> {
+    use crate::fine_line::detect_fine_lines;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_volume_coverage_pattern(31)
+        .with_split_cuts(true)
+        .with_elevations(vec![0.5, 1.5, 2.5])
+        .with_radials_per_sweep(360)
+        .with_gates(300)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let datafile = DataFile::from_vec(volume.into_data())?;
+
+    let volume_data = datafile.first_volume_data().expect("has volume data");
+    assert_eq!(volume_data.volume_coverage_pattern_number(), 31);
+    assert!(volume_data.is_clear_air());
+
+    // The two lowest angles are split into surveillance and Doppler cuts
+    let capabilities: Vec<_> = datafile
+        .into_sweeps()
+        .map(|sweep| sweep.capabilities())
+        .collect();
+    assert_eq!(capabilities.len(), 5);
+    let doppler: Vec<_> = capabilities
+        .iter()
+        .map(SweepCapabilities::is_doppler)
+        .collect();
+    assert_eq!(doppler, [false, true, false, true, true]);
+    for sweep in &capabilities {
+        assert!(sweep.has(Product::Reflectivity));
+        assert!(!sweep.is_dual_pol());
+        assert!(!sweep.is_super_resolution());
+        assert_eq!(sweep.reflectivity_resolution(), Some(0.5));
+    }
+    assert_eq!(capabilities[0].products(), [Product::Reflectivity]);
+
+    // Derived products use the lowest tilt with the moments they need
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_volume_coverage_pattern(32)
+        .with_split_cuts(true)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(360)
+        .with_gates(300)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let datafile = DataFile::from_vec(volume.into_data())?;
+
+    let detection = detect_fine_lines(&datafile, &FineLineOptions::new()).expect("has Doppler cut");
+    assert!((detection.elevation() - 0.5).abs() < f32::EPSILON);
+
+    let spec = GridSpec::new(20, 20, 2000.0);
+    let velocity = grid_sweep(&datafile.elevation_scans()[&2], Product::Velocity, &spec);
+    assert!(velocity.values().iter().any(Option::is_some));
+    assert!(
+        grid_sweep(&datafile.elevation_scans()[&1], Product::Velocity, &spec)
+            .values()
+            .iter()
+            .all(Option::is_none)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn palette_file() -> Result<()> {
+    let palette = Palette::parse(
+        "; Sample reflectivity palette
+Product: BR
+Units: DBZ
+Step: 5
+
+Color: 10 0 0 0
+Color4: 30 0 100 200 128 0 200 0 255
+SolidColor: 50 255 0 0
+color: 60 255 255 255
+RF: 119 0 158
+",
+    )?;
+
+    assert_eq!(palette.product(), Some("BR"));
+    assert_eq!(palette.units(), Some("DBZ"));
+
+    // Colors blend towards the next breakpoint, or the breakpoint's own second color
+    assert_eq!(palette.color(5.0), None);
+    assert_eq!(palette.color(10.0), Some([0, 0, 0, 255]));
+    assert_eq!(palette.color(20.0), Some([0, 50, 100, 192]));
+    assert_eq!(palette.color(40.0), Some([0, 150, 100, 192]));
+    assert_eq!(palette.color(55.0), Some([255, 0, 0, 255]));
+    assert_eq!(palette.color(75.0), Some([255, 255, 255, 255]));
+
+    assert_eq!(
+        palette.gate_color(GateValue::RangeFolded),
+        Some([119, 0, 158, 255])
+    );
+    assert_eq!(palette.gate_color(GateValue::BelowThreshold), None);
+
+    // Malformed or empty palettes are rejected
+    assert!(Palette::parse("Color: 10 0 0").is_err());
+    assert!(Palette::parse("SolidColor: 10 0 0 0 255 255 255").is_err());
+    assert!(Palette::parse("Product: BR").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn render_elevations() -> Result<()> {
+    let hurricane_harvey = Path::new("resources/KCRP20170825_235733_V06_hurricane_harvey");
+    let datafile = DataFile::new(hurricane_harvey)?;
+
+    let options = RenderOptions::new().with_size(64);
+    let renders = render_all_elevations(&datafile, Product::Velocity, &options)?;
+
+    // Every sweep with velocity is rendered, skipping surveillance cuts
+    let doppler_sweeps = datafile
+        .elevation_scans()
+        .values()
+        .filter(|radials| SweepCapabilities::from_radials(radials).is_doppler())
+        .count();
+    assert_eq!(renders.frames().len(), doppler_sweeps);
+    assert!(doppler_sweeps < datafile.elevation_scans().len());
+
+    let lowest = renders.frames()[0].image();
+    assert_eq!((lowest.width(), lowest.height()), (64, 64));
+    assert!(lowest.pixels().iter().any(|pixel| pixel[3] != 0));
+
+    let sheet = renders.contact_sheet(4);
+    assert_eq!(sheet.width(), 4 * 64);
+    assert_eq!(
+        sheet.height(),
+        u32::try_from(doppler_sweeps.div_ceil(4))? * 64
+    );
+    assert_eq!(sheet.pixel(10, 20), lowest.pixel(10, 20));
+
+    let png = sheet.to_png()?;
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+    Ok(())
+}
+
+#[test]
+fn render_batch_resumes() -> Result<()> {
+    use crate::batch::{render_batch, BatchImage, DirectoryLoader, RenderManifest, VolumeLoader};
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(360)
+        .with_gates(300)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let time = volume.time();
+    let data = volume.into_data();
+
+    let loader = |site: &str, requested: chrono::NaiveDateTime| {
+        if site != "KDMX" || requested != time {
+            return Ok(None);
+        }
+        DataFile::from_vec(data.clone()).map(Some)
+    };
+
+    let directory = std::env::temp_dir().join(format!("nexrad_batch_{}", std::process::id()));
+    let template = directory.join("{site}/{time}_{product}.png");
+    let manifest = RenderManifest::new(
+        vec!["KDMX".to_string(), "KOAX".to_string()],
+        vec![time],
+        vec![
+            Product::Reflectivity,
+            Product::Velocity,
+            Product::CorrelationCoefficient,
+        ],
+        template.to_str().expect("is unicode"),
+    )
+    .with_image(BatchImage::ContactSheet(2))
+    .with_render_options(RenderOptions::new().with_size(32))
+    .with_threads(2);
+
+    let report = render_batch(&manifest, &loader)?;
+    assert_eq!(report.rendered().len(), 2);
+    assert_eq!(report.missing().len(), 3);
+    assert!(report.missing().iter().all(|job| job.site() == "KOAX"));
+    assert_eq!(report.failed().len(), 1);
+    assert_eq!(
+        report.failed()[0].0.product(),
+        Product::CorrelationCoefficient
+    );
+
+    let path = manifest.output_path("KDMX", time, Product::Reflectivity);
+    assert!(path.ends_with(format!("KDMX/{}_ref.png", time.format("%Y%m%d_%H%M%S"))));
+    assert_eq!(&std::fs::read(&path)?[..4], b"\x89PNG");
+
+    // Existing images are skipped when the batch is run again
+    let report = render_batch(&manifest, &loader)?;
+    assert!(report.rendered().is_empty());
+    assert_eq!(report.skipped().len(), 2);
+
+    // Manifests may be deserialized, with unspecified settings taking their defaults
+    let deserialized: RenderManifest = toml::from_str(&format!(
+        "sites = [\"KDMX\"]\ntimes = [\"{}\"]\nproducts = [\"reflectivity\"]\n\
+         output_template = {:?}\nimage = {{ contact_sheet = 2 }}\noptions = {{ size = 32 }}",
+        time.format("%Y-%m-%dT%H:%M:%S"),
+        template.to_str().expect("is unicode")
+    ))?;
+    assert_eq!(
+        deserialized.output_path("KDMX", time, Product::Reflectivity),
+        path
+    );
+    assert_eq!(render_batch(&deserialized, &loader)?.skipped().len(), 1);
+    assert!(toml::from_str::<RenderManifest>(
+        "sites = []\ntimes = []\nproducts = []\noutput_template = \"x\"\noptions = { size = 0 }"
+    )
+    .is_err());
+
+    // Directories of volumes are matched to the nearest start time within a tolerance
+    let volumes = directory.join("volumes");
+    std::fs::create_dir_all(&volumes)?;
+    let start = time + chrono::TimeDelta::seconds(20);
+    std::fs::write(
+        volumes.join(format!("KDMX{}_V06", start.format("%Y%m%d_%H%M%S"))),
+        &data,
+    )?;
+    let loader = DirectoryLoader::new(&volumes);
+    assert!(loader.load("KDMX", time)?.is_some());
+    assert!(loader.load("KOAX", time)?.is_none());
+    let strict = loader.with_tolerance(chrono::TimeDelta::seconds(10));
+    assert!(strict.load("KDMX", time)?.is_none());
+    assert!(strict.load("KDMX", start)?.is_some());
+
+    std::fs::remove_dir_all(directory)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn parallel_decompression() -> Result<()> {
+    use crate::decompressor::{decompress_file, decompress_file_parallel};
+
+    let data = std::fs::read("resources/KCRP20170825_235733_V06_hurricane_harvey")?;
+    assert_eq!(decompress_file_parallel(&data)?, decompress_file(&data)?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ndarray")]
+fn ndarray_conversion() {
+    let grid = Grid::new(3, 2, vec![1, 2, 3, 4, 5, 6]);
+    let array = grid.to_array();
+    assert_eq!(array.dim(), (2, 3));
+    assert_eq!(array[[1, 0]], 4);
+
+    let sweep = crate::Sweep::new(1, fine_line_sweep(110));
+    let reflectivity = sweep
+        .to_array(Product::Reflectivity)
+        .expect("has reflectivity");
+    assert_eq!(reflectivity.dim(), (360, 200));
+    assert!((reflectivity[[30, 110]] - 20.0).abs() < f32::EPSILON);
+    assert!(reflectivity[[0, 0]].is_nan());
+    assert!(sweep.to_array(Product::DifferentialPhase).is_none());
+}
+
+#[test]
+#[cfg(feature = "geo")]
+fn geo_line_strings() {
+    let lines = detect_fine_lines_in_sweep(&fine_line_sweep(110), &FineLineOptions::new());
+
+    let local = lines[0].to_line_string();
+    assert_eq!(local.0.len(), lines[0].points().len());
+
+    // Points east and north of the radar are east and north of its location
+    let geographic = lines[0].to_geographic_line_string(41.73, -93.72);
+    for coordinate in geographic.coords() {
+        assert!(coordinate.x > -93.72 && coordinate.y > 41.73);
+    }
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn image_conversion() {
+    let image = crate::render::render_sweep(
+        &fine_line_sweep(110),
+        Product::Reflectivity,
+        &Palette::for_product(Product::Reflectivity),
+        &RenderOptions::new().with_size(32).with_max_range(60_000.0),
+    );
+
+    let buffer = image.to_rgba_image();
+    assert_eq!(buffer.dimensions(), (32, 32));
+    assert_eq!(
+        buffer.get_pixel(5, 7).0,
+        image.pixel(5, 7).expect("is within image")
+    );
+}
+
+#[test]
+fn data_file_from_parts() -> Result<()> {
+    use crate::records::VolumeHeaderRecord;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use crate::Sweep;
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(360)
+        .with_gates(100)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let original = DataFile::from_vec(volume.into_data())?;
+    let header = original.volume_header();
+
+    // Keep only the upper sweep, renumbered as the first
+    let radials = original.elevation_scans()[&2].clone();
+    let header = VolumeHeaderRecord::new(
+        *header.filename(),
+        header.file_date(),
+        header.file_time(),
+        *b"KDMX",
+    );
+    let assembled = DataFile::from_parts(header, vec![Sweep::new(1, radials)])?;
+    assert_eq!(assembled.elevation_scans().len(), 1);
+    assert_eq!(assembled.elevation_scans()[&1].len(), 360);
+
+    // Assembled files flow through the encoder, where radials keep their own elevation numbers
+    let decoded = DataFile::from_vec(crate::encode::encode_file(&assembled)?)?;
+    assert_eq!(decoded.elevation_scans()[&2].len(), 360);
+
+    let duplicate = vec![Sweep::new(1, Vec::new()), Sweep::new(1, Vec::new())];
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 1, 0, *b"KDMX");
+    assert!(DataFile::from_parts(header, duplicate).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn odim_import() -> Result<()> {
+    use crate::error::Error;
+    use crate::odim::{read_odim_volume, OdimAttribute, OdimTree};
+
+    let text = |value: &str| OdimAttribute::Text(value.to_string());
+    let mut tree = OdimTree::new();
+    tree.set_attribute("what", "object", text("PVOL"));
+    tree.set_attribute("what", "source", text("WMO:02032,NOD:sekrn"));
+    tree.set_attribute("what", "date", text("20240601"));
+    tree.set_attribute("what", "time", text("120000"));
+    tree.set_attribute("where", "lat", OdimAttribute::Real(67.71));
+    tree.set_attribute("where", "lon", OdimAttribute::Real(20.62));
+    tree.set_attribute("where", "height", OdimAttribute::Real(532.0));
+
+    tree.set_attribute("dataset1/where", "elangle", OdimAttribute::Real(0.5));
+    tree.set_attribute("dataset1/where", "nrays", OdimAttribute::Integer(360));
+    tree.set_attribute("dataset1/where", "nbins", OdimAttribute::Integer(10));
+    tree.set_attribute("dataset1/where", "a1gate", OdimAttribute::Integer(90));
+    tree.set_attribute("dataset1/where", "rstart", OdimAttribute::Real(0.0));
+    tree.set_attribute("dataset1/where", "rscale", OdimAttribute::Real(500.0));
+    tree.set_attribute("dataset1/what", "startdate", text("20240601"));
+    tree.set_attribute("dataset1/what", "starttime", text("120000"));
+    tree.set_attribute("dataset1/what", "enddate", text("20240601"));
+    tree.set_attribute("dataset1/what", "endtime", text("120036"));
+
+    // Reflectivity rising by 0.5 dBZ per bin, with undetected first bins
+    tree.set_attribute("dataset1/data1/what", "quantity", text("DBZH"));
+    tree.set_attribute("dataset1/data1/what", "gain", OdimAttribute::Real(0.5));
+    tree.set_attribute("dataset1/data1/what", "offset", OdimAttribute::Real(-32.0));
+    tree.set_attribute("dataset1/data1/what", "nodata", OdimAttribute::Real(255.0));
+    tree.set_attribute("dataset1/data1/what", "undetect", OdimAttribute::Real(0.0));
+    let values = (0..360)
+        .flat_map(|_| 0..10u16)
+        .map(|bin| bin * 40)
+        .collect();
+    tree.set_dataset("dataset1/data1/data", Grid::new(10, 360, values));
+
+    // Quantities this crate does not model are skipped
+    tree.set_attribute("dataset1/data2/what", "quantity", text("TV"));
+
+    let volume = read_odim_volume(&tree)?;
+    assert_eq!(volume.volume_header().radar_id(), b"SEKR");
+    assert_eq!(
+        volume.volume_header().date_time(),
+        chrono::NaiveDate::from_ymd_opt(2024, 6, 1).and_then(|date| date.and_hms_opt(12, 0, 0))
+    );
+
+    let radials = &volume.elevation_scans()[&1];
+    assert_eq!(radials.len(), 360);
+
+    // Radials are ordered by collection, starting from the a1gate ray
+    assert!((radials[0].header().azm() - 90.5).abs() < f32::EPSILON);
+    assert!((radials[0].header().elev() - 0.5).abs() < f32::EPSILON);
+    assert_eq!(radials[359].header().ray_time(), 12 * 3_600_000 + 35_900);
+
+    let moment = radials[0].reflectivity_data().expect("has reflectivity");
+    assert_eq!(moment.value(0), Some(GateValue::BelowThreshold));
+    assert_eq!(moment.value(1), Some(GateValue::Value(-12.0)));
+    assert_eq!(moment.value(9), Some(GateValue::Value(148.0)));
+    assert_eq!(moment.data().data_moment_range(), 250);
+    assert!(radials[0].velocity_data().is_none());
+
+    let volume_data = radials[0].volume_data().expect("has volume data");
+    assert!((volume_data.lat() - 67.71).abs() < 1e-4);
+
+    // Heights beyond 16 bits and raw values colliding with the reserved words are refused
+    tree.set_attribute("where", "height", OdimAttribute::Real(40_000.0));
+    let error = read_odim_volume(&tree).err().expect("height overflows");
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::MissingOdimAttribute(attribute)) if attribute == "where/height"
+    ));
+    tree.set_attribute("where", "height", OdimAttribute::Real(532.0));
+
+    let mut values = vec![40; 3600];
+    values[0] = u16::MAX - 1;
+    tree.set_dataset("dataset1/data1/data", Grid::new(10, 360, values.clone()));
+    let error = read_odim_volume(&tree)
+        .err()
+        .expect("value is unrepresentable");
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::UnrepresentableOdimValue(_, raw)) if *raw == u16::MAX - 1
+    ));
+    tree.set_attribute(
+        "dataset1/data1/what",
+        "nodata",
+        OdimAttribute::Real(65534.0),
+    );
+    tree.set_dataset("dataset1/data1/data", Grid::new(10, 360, values));
+    let volume = read_odim_volume(&tree)?;
+    let moment = volume.elevation_scans()[&1][270]
+        .reflectivity_data()
+        .expect("has reflectivity");
+    assert_eq!(moment.value(0), Some(GateValue::BelowThreshold));
+
+    tree.set_attribute("what", "object", text("COMP"));
+    assert!(read_odim_volume(&tree).is_err());
+
+    Ok(())
+}
+
+/// Builds a Sigmet RAW file with a single sweep of four rays recording an extended header,
+/// reflectivity, and velocity, where the third ray was not recorded and the fourth's elevation
+/// crosses the horizon.
+fn sigmet_raw_file() -> Vec<u8> {
+    fn put(record: &mut [u8], offset: usize, bytes: &[u8]) {
+        record[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn put_time(record: &mut [u8], offset: usize) {
+        put(record, offset, &43_200i32.to_le_bytes());
+        put(record, offset + 6, &2024i16.to_le_bytes());
+        put(record, offset + 8, &6i16.to_le_bytes());
+        put(record, offset + 10, &1i16.to_le_bytes());
+    }
+
+    // Literal words, with runs of zeros compressed, followed by the end of ray code
+    fn compress(words: &[u16], stream: &mut Vec<u8>) {
+        let mut index = 0;
+        while index < words.len() {
+            let zeros = words[index..].iter().take_while(|word| **word == 0).count();
+            if zeros > 1 {
+                stream.extend_from_slice(&u16::try_from(zeros).unwrap().to_le_bytes());
+                index += zeros;
+                continue;
+            }
+
+            let literals = words[index..]
+                .windows(2)
+                .take_while(|pair| pair != &[0, 0])
+                .count()
+                .max(1)
+                .min(words.len() - index);
+            stream.extend_from_slice(&(0x8000 | u16::try_from(literals).unwrap()).to_le_bytes());
+            for word in &words[index..index + literals] {
+                stream.extend_from_slice(&word.to_le_bytes());
+            }
+            index += literals;
+        }
+        stream.extend_from_slice(&1u16.to_le_bytes());
+    }
+
+    let mut file = vec![0u8; 3 * 6144];
+    put(&mut file, 0, &27i16.to_le_bytes());
+
+    let ingest = &mut file[6144..2 * 6144];
+    put(ingest, 0, &23i16.to_le_bytes());
+    put(ingest, 162, b"ktst radar");
+    // Binary angles of 35 and 263 (-97) degrees
+    put(ingest, 180, &417_566_265u32.to_le_bytes());
+    put(ingest, 184, &3_137_712_219u32.to_le_bytes());
+    put(ingest, 188, &350i16.to_le_bytes());
+    put_time(ingest, 100);
+    put(ingest, 628, &0b1101u32.to_le_bytes());
+    put(ingest, 760, &1000i32.to_le_bytes());
+    put(ingest, 1264, &100_000i32.to_le_bytes());
+    put(ingest, 1280, &25_000i32.to_le_bytes());
+    put(ingest, 1744, &530i32.to_le_bytes());
+
+    let mut stream = Vec::new();
+    put(&mut file[2 * 6144..], 2, &1i16.to_le_bytes());
+    for bits_per_bin in [16i16, 8, 8] {
+        let mut header = [0u8; 76];
+        put(&mut header, 0, &24i16.to_le_bytes());
+        put_time(&mut header, 12);
+        put(&mut header, 30, &4i16.to_le_bytes());
+        put(&mut header, 32, &3i16.to_le_bytes());
+        put(&mut header, 36, &bits_per_bin.to_le_bytes());
+        stream.extend_from_slice(&header);
+    }
+
+    for ray in 0..4u16 {
+        if ray == 2 {
+            stream.extend_from_slice(&[1, 0, 1, 0, 1, 0]);
+            continue;
+        }
+
+        let azimuth = ray * 16384;
+        let start_elevation = if ray == 3 { 65_445 } else { 91 };
+        let header = [azimuth, start_elevation, azimuth + 182, 91, 10, ray];
+
+        compress(&[header.as_slice(), &[7, 7]].concat(), &mut stream);
+        for bins in [
+            [0u8, 0, 0, 0, 64, 100, 0, 0, 0, 0],
+            [0, 0, 0, 0, 128, 200, 255, 0, 0, 0],
+        ] {
+            let words = bins
+                .chunks(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+            compress(
+                &header.into_iter().chain(words).collect::<Vec<_>>(),
+                &mut stream,
+            );
+        }
+    }
+
+    put(&mut file[2 * 6144..], 12, &stream);
+    file
+}
+
+#[test]
+fn sigmet_import() -> Result<()> {
+    use crate::sigmet::read_sigmet_raw;
+
+    let volume = read_sigmet_raw(&sigmet_raw_file())?;
+    assert_eq!(volume.volume_header().radar_id(), b"KTST");
+
+    let radials = &volume.elevation_scans()[&1];
+    assert_eq!(radials.len(), 3);
+    assert!((radials[1].header().azm() - 90.5).abs() < 0.01);
+    assert!((radials[1].header().elev() - 0.5).abs() < 0.01);
+    assert_eq!(radials[2].header().ray_time(), 43_203_000);
+    assert_eq!(radials[2].header().radial_status(), 2);
+    assert!(radials[2].header().elev().abs() < 0.01);
+
+    let volume_data = radials[0].volume_data().expect("has volume data");
+    assert!((volume_data.lat() - 35.0).abs() < 1e-4);
+    assert!((volume_data.long() + 97.0).abs() < 1e-4);
+
+    // One-byte reflectivity is in half dBZ from -32 dBZ, with zero meaning no data
+    let reflectivity = radials[0].reflectivity_data().expect("has reflectivity");
+    assert_eq!(reflectivity.data().data_moment_range(), 1000);
+    assert_eq!(reflectivity.data().data_moment_range_sample_interval(), 250);
+    assert_eq!(reflectivity.value(3), Some(GateValue::BelowThreshold));
+    assert_eq!(reflectivity.value(4), Some(GateValue::Value(0.0)));
+    assert_eq!(reflectivity.value(5), Some(GateValue::Value(18.0)));
+
+    // One-byte velocity is scaled by the Nyquist velocity, 13.25 m/s at 1000 Hz and 5.3 cm
+    let velocity = radials[0].velocity_data().expect("has velocity");
+    assert_eq!(velocity.value(4), Some(GateValue::Value(0.0)));
+    let value = velocity.value(5).and_then(|value| value.value());
+    assert!((value.expect("has value") - 72.0 / 127.0 * 13.25).abs() < 0.01);
+    assert_eq!(velocity.value(6), Some(GateValue::BelowThreshold));
+
+    assert!(read_sigmet_raw(&[0; 100]).is_err());
+
+    // Slots beyond the rays written, as in an aborted sweep, are not read
+    let mut aborted = sigmet_raw_file();
+    for moment in 0..3 {
+        let offset = 2 * 6144 + 12 + moment * 76 + 30;
+        aborted[offset..offset + 2].copy_from_slice(&6i16.to_le_bytes());
+    }
+    assert_eq!(read_sigmet_raw(&aborted)?.elevation_scans()[&1].len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn uf_round_trip() -> Result<()> {
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use crate::uf::{encode_uf, read_uf};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(360)
+        .with_gates(100)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let original = DataFile::from_vec(volume.into_data())?;
+
+    let encoded = encode_uf(&original)?;
+    let imported = read_uf(&encoded)?;
+    assert_eq!(imported.volume_header().radar_id(), b"KDMX");
+    assert_eq!(imported.elevation_scans().len(), 2);
+
+    let volume_data = imported.first_volume_data().expect("has volume data");
+    assert!((volume_data.lat() - 41.73).abs() < 1e-4);
+    assert!((volume_data.long() + 93.72).abs() < 1e-4);
+
+    for (elevation_number, radials) in original.elevation_scans() {
+        let imported_radials = &imported.elevation_scans()[elevation_number];
+        assert_eq!(imported_radials.len(), radials.len());
+
+        for (radial, imported) in radials.iter().zip(imported_radials) {
+            assert!((radial.header().azm() - imported.header().azm()).abs() < 1.0 / 64.0);
+
+            for product in [Product::Reflectivity, Product::Velocity] {
+                let moment = radial.get_data_moment(&product.into()).expect("has moment");
+                let imported = imported
+                    .get_data_moment(&product.into())
+                    .expect("has moment");
+                assert_eq!(imported.gate_count(), moment.gate_count());
+
+                for (value, imported) in moment.values().zip(imported.values()) {
+                    match (value.value(), imported.value()) {
+                        (Some(value), Some(imported)) => assert!((value - imported).abs() < 0.01),
+                        (value, imported) => assert_eq!(value.is_some(), imported.is_some()),
+                    }
+                }
+            }
+        }
+    }
+
+    // Records without Fortran record markers are read too
+    let length = usize::try_from(u32::from_be_bytes(encoded[..4].try_into()?))?;
+    let bare = read_uf(&encoded[4..4 + length])?;
+    assert_eq!(bare.elevation_scans()[&1].len(), 1);
+
+    assert!(read_uf(b"not a UF file").is_err());
+
+    // Pointers from the file which are zero or beyond the record are rejected
+    for pointer in [0i16, i16::MAX] {
+        let mut corrupt = encoded.clone();
+        corrupt[4 + 8..4 + 10].copy_from_slice(&pointer.to_be_bytes());
+        let error = read_uf(&corrupt).err().expect("pointer is rejected");
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(crate::error::Error::InvalidUfFile("invalid pointer"))
+        ));
+    }
+
+    // Years past the last day Archive II can count, in 2149, are rejected rather than wrapped
+    let mut future = encoded.clone();
+    future[4 + 50..4 + 52].copy_from_slice(&2200i16.to_be_bytes());
+    let error = read_uf(&future).err().expect("time is rejected");
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(crate::error::Error::InvalidUfFile(
+            "ray time is outside the Archive II date range"
+        ))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn radial_wind_bufr() -> Result<()> {
+    use crate::bufr::{encode_radial_wind_bufr, BufrOptions};
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use crate::superob::{Averaging, SuperobOptions};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(360)
+        .with_gates(400)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let file = DataFile::from_vec(volume.into_data())?;
+
+    // Reads the unsigned integer in the bits following a bit offset
+    let bits = |data: &[u8], offset: usize, width: usize| -> u64 {
+        (offset..offset + width).fold(0, |value, bit| {
+            value << 1 | u64::from(data[bit / 8] >> (7 - bit % 8) & 1)
+        })
+    };
+
+    let subsets = |options: &BufrOptions| -> Result<Vec<(Vec<u8>, usize)>> {
+        let data = encode_radial_wind_bufr(&file, options)?;
+
+        let mut messages = Vec::new();
+        let mut remaining = data.as_slice();
+        while !remaining.is_empty() {
+            assert_eq!(&remaining[..4], b"BUFR");
+            assert_eq!(remaining[7], 4);
+            let length = usize::try_from(bits(remaining, 32, 24))?;
+            assert_eq!(&remaining[length - 4..length], b"7777");
+
+            // Section 3 follows the 8-byte indicator and 22-byte identification sections
+            let subset_count = usize::try_from(bits(remaining, (30 + 4) * 8, 16))?;
+            messages.push((remaining[..length].to_vec(), subset_count));
+            remaining = &remaining[length..];
+        }
+
+        Ok(messages)
+    };
+
+    let messages = subsets(&BufrOptions::new())?;
+    assert_eq!(messages.len(), 2);
+    assert!(messages.iter().all(|(_, count)| *count > 0));
+
+    // The first subset's elevation follows the site, time, and location elements
+    let (message, _) = &messages[0];
+    let data_offset = 30 + usize::try_from(bits(message, 30 * 8, 24))? + 4;
+    let elevation = bits(message, data_offset * 8 + 145, 15);
+    assert_eq!(elevation, 9050);
+
+    // Coarser bins give fewer superobservations
+    let coarse = subsets(
+        &BufrOptions::new().with_superob_options(
+            SuperobOptions::new()
+                .with_range_bin_size(20_000.0)
+                .with_azimuth_bin_size(10.0)
+                .with_averaging(Averaging::Median),
+        ),
+    )?;
+    assert!(coarse[0].1 < messages[0].1);
+
+    Ok(())
+}
+
+#[test]
+fn superobservations() {
+    use crate::superob::{superob_sweep, Averaging, SuperobOptions};
+
+    let sweep = fine_line_sweep(110);
+    let options = SuperobOptions::new();
+
+    // The line spans 30 to 60 degrees, leaving too few gates in the bin beginning at 60 degrees
+    let reflectivity = superob_sweep(&sweep, Product::Reflectivity, &options);
+    assert_eq!(reflectivity.len(), 6);
+    assert!((reflectivity[0].azimuth() - 32.5).abs() < f32::EPSILON);
+    assert!((reflectivity[0].range() - 27_500.0).abs() < f32::EPSILON);
+    assert!(reflectivity
+        .iter()
+        .all(|superob| superob.count() == 10 && (superob.value() - 20.0).abs() < 1e-4));
+
+    // Ahead of the line, 95 gates approach at 6 m/s and 5 behind it recede at 6 m/s
+    let velocity = superob_sweep(&sweep, Product::Velocity, &options);
+    let superob = velocity
+        .iter()
+        .find(|superob| {
+            (superob.azimuth() - 32.5).abs() < 0.01 && (superob.range() - 27_500.0).abs() < 0.01
+        })
+        .expect("has superobservation");
+    assert_eq!(superob.count(), 100);
+    assert!((superob.value() - 5.4).abs() < 1e-4);
+    assert!((superob.minimum() + 6.0).abs() < f32::EPSILON);
+    assert!((superob.maximum() - 6.0).abs() < f32::EPSILON);
+    assert!((superob.standard_error() - superob.standard_deviation() / 10.0).abs() < 1e-6);
+
+    let median = superob_sweep(
+        &sweep,
+        Product::Velocity,
+        &options.with_averaging(Averaging::Median),
+    );
+    assert!(median
+        .iter()
+        .any(|superob| (superob.value() - 6.0).abs() < 1e-4));
+}
+
+#[test]
+fn delta_relay() -> Result<()> {
+    use crate::delta::{DeltaDecoder, DeltaEncoder};
+    use crate::encode::encode_message_31;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5])
+        .with_radials_per_sweep(360)
+        .with_gates(200)
+        .with_compression(false);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let radials = DataFile::from_vec(volume.into_data())?.elevation_scans()[&1].clone();
+
+    let mut encoder = DeltaEncoder::new();
+    let frames = radials
+        .iter()
+        .map(|radial| encoder.encode(radial))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Keyframes start the sweep and recur every 30 radials
+    assert_eq!(frames[0][4], 0);
+    assert_eq!(frames[5][4], 1);
+    assert_eq!(frames[30][4], 0);
+
+    let same = |radial: &Message31, decoded: &Message31| -> Result<bool> {
+        Ok(encode_message_31(radial, 0)? == encode_message_31(decoded, 0)?)
+    };
+
+    // The stream decodes losslessly when received in arbitrary chunks
+    let stream = frames.concat();
+    let mut decoder = DeltaDecoder::new();
+    let received: Vec<Message31> = stream
+        .chunks(1000)
+        .flat_map(|chunk| decoder.decode(chunk))
+        .collect();
+    assert_eq!(received.len(), radials.len());
+    for (radial, decoded) in radials.iter().zip(&received) {
+        assert!(same(radial, decoded)?);
+    }
+
+    // A delta of an unchanged radial is little more than its header
+    let mut encoder = DeltaEncoder::new();
+    encoder.encode(&radials[0])?;
+    assert!(encoder.encode(&radials[0])?.len() < 64);
+
+    // After a lost frame and a corrupted one, deltas are dropped until the next keyframe
+    let mut damaged = frames.clone();
+    damaged[40][20] ^= 0xFF;
+    damaged.remove(5);
+    let mut decoder = DeltaDecoder::new();
+    let received = decoder.decode(&damaged.concat());
+    assert_eq!(received.len(), radials.len() - 1 - 24 - 1 - 19);
+    assert_eq!(decoder.frames_dropped(), 24 + 19);
+    assert!(decoder.bytes_skipped() > 0);
+
+    for decoded in &received {
+        let radial = &radials[usize::from(decoded.header().azm_num()) - 1];
+        assert!(same(radial, decoded)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn polar_pyramid() {
+    use crate::pyramid::{Aggregation, PolarPyramid, PyramidLevel, PyramidOptions};
+
+    let sweep = fine_line_sweep(110);
+    let pyramid = PolarPyramid::from_radials(&sweep, Product::Velocity, &PyramidOptions::new())
+        .expect("has velocity");
+
+    let factors: Vec<usize> = pyramid.levels().iter().map(PyramidLevel::factor).collect();
+    assert_eq!(factors, [1, 2, 4, 8]);
+
+    let level = &pyramid.levels()[1];
+    assert_eq!(level.values().rows(), 180);
+    assert_eq!(level.values().columns(), 100);
+    assert!((level.azimuths()[0] - 1.0).abs() < 1e-4);
+    assert!((level.azimuths()[179] - 359.0).abs() < 1e-3);
+    assert!((level.first_gate_range() - 2250.0).abs() < f32::EPSILON);
+    assert!((level.gate_interval() - 500.0).abs() < f32::EPSILON);
+
+    // Radials 30 and 31 approach at 6 m/s at gate 110 and recede at 6 m/s at gate 111
+    assert_eq!(level.values().get(55, 15), Some(&Some(6.0)));
+
+    let mean = PolarPyramid::from_radials(
+        &sweep,
+        Product::Velocity,
+        &PyramidOptions::new()
+            .with_factors(vec![2])
+            .with_aggregation(Aggregation::Mean),
+    )
+    .expect("has velocity");
+    assert_eq!(mean.levels().len(), 2);
+    assert_eq!(mean.levels()[1].values().get(55, 15), Some(&Some(0.0)));
+
+    // Reflectivity is averaged as linear reflectivity factor: with the line strengthened to 30
+    // dBZ along one of two radials, their gates average to 10 log10((100 + 1000) / 2) dBZ
+    let mut mixed = sweep.clone();
+    let mut strong = vec![0u8; 200];
+    strong[110..112].fill(126);
+    let product = DataBlockProduct::Reflectivity;
+    let data = GenericData::new(&product, 200, 2125, 250, 8, 2.0, 66.0);
+    mixed[31].set_data_moment(DataMoment::new(product, data, strong));
+    let mean = PolarPyramid::from_radials(
+        &mixed,
+        Product::Reflectivity,
+        &PyramidOptions::new()
+            .with_factors(vec![2])
+            .with_aggregation(Aggregation::Mean),
+    )
+    .expect("has reflectivity");
+    let value = mean.levels()[1].values().get(55, 15).copied().flatten();
+    assert!((value.expect("has value") - 10.0 * 550f32.log10()).abs() < 0.01);
+
+    // Radials are aggregated by azimuth whichever the sweep began with
+    let mut rotated = sweep.clone();
+    rotated.rotate_left(91);
+    let rotated = PolarPyramid::from_radials(&rotated, Product::Velocity, &PyramidOptions::new())
+        .expect("has velocity");
+    assert_eq!(rotated, pyramid);
+
+    assert_eq!(pyramid.level_for_resolution(100.0).factor(), 1);
+    assert_eq!(pyramid.level_for_resolution(1000.0).factor(), 4);
+    assert_eq!(pyramid.level_for_resolution(5000.0).factor(), 8);
+    assert!(
+        PolarPyramid::from_radials(&sweep, Product::SpectrumWidth, &PyramidOptions::new())
+            .is_none()
+    );
+}
+
+#[test]
+fn volume_series_interpolation() -> Result<()> {
+    use crate::composite::{InterpolationOptions, VolumeSeries};
+    use crate::records::{to_archive_date_time, VolumeHeaderRecord};
+    use crate::Sweep;
+    use chrono::{Duration, NaiveDate};
+
+    let start = NaiveDate::from_ymd_opt(2024, 6, 1)
+        .and_then(|date| date.and_hms_opt(12, 0, 0))
+        .expect("is valid");
+
+    // A line moving 5 km outward over 10 minutes
+    let volume = |minutes: i64, line_gate: usize| -> Result<DataFile> {
+        let (date, time) =
+            to_archive_date_time(start + Duration::minutes(minutes)).expect("in range");
+        let header = VolumeHeaderRecord::new(*b"AR2V0006.001", date.into(), time, *b"KTST");
+        DataFile::from_parts(header, vec![Sweep::new(1, fine_line_sweep(line_gate))])
+    };
+    let series = VolumeSeries::new(
+        vec![volume(10, 130)?, volume(0, 110)?],
+        Duration::minutes(15),
+    )?;
+    assert_eq!(series.times().next(), Some(start));
+
+    // The mean range of the line's cells
+    let spec = GridSpec::new(100, 100, 1000.0);
+    let line_range = |grid: &Grid<Option<f32>>| -> f32 {
+        let (sum, count) = (0..grid.rows())
+            .flat_map(|row| (0..grid.columns()).map(move |column| (column, row)))
+            .filter(|(column, row)| grid.get(*column, *row).is_some_and(Option::is_some))
+            .fold((0.0, 0.0), |(sum, count), (column, row)| {
+                let (x, y) = spec.cell_center(column, row);
+                (sum + x.hypot(y), count + 1.0)
+            });
+        sum / count
+    };
+
+    let options = InterpolationOptions::new(Product::Reflectivity, spec.clone());
+    let earlier = series.interpolate_at(start, &options)?;
+    assert!((line_range(&earlier) - 29_625.0).abs() < 1000.0);
+
+    // Without advection the line jumps between positions, while with it the line moves
+    let midpoint = start + Duration::minutes(5);
+    let in_place = series.interpolate_at(midpoint, &options)?;
+    assert!((line_range(&in_place) - 34_625.0).abs() < 1000.0);
+    let advected = series.interpolate_at(midpoint, &options.clone().with_advection(15.0))?;
+    assert!((line_range(&advected) - 32_125.0).abs() < 1000.0);
+
+    assert!(series
+        .interpolate_at(start - Duration::minutes(1), &options)
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn modified_radials() -> Result<()> {
+    use crate::encode::encode_file;
+    use crate::records::{ElevationData, RadialData, VolumeHeaderRecord};
+    use crate::Sweep;
+
+    // A radial built from public constructors
+    let data = GenericData::new(&DataBlockProduct::Reflectivity, 4, 2125, 250, 8, 2.0, 66.0)
+        .with_snr_threshold(20);
+    let moment = DataMoment::new(DataBlockProduct::Reflectivity, data, vec![0, 1, 86, 106]);
+    let header = Message31Header::new(*b"KTST", 43_200_000, 19_875, 1, 0.5, 1, 3, 1, 0.5);
+    let mut radial = Message31::new(header)
+        .with_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215))
+        .with_elevation_data(ElevationData::new([0, 0], 0.0))
+        .with_radial_data(RadialData::new(4660, 2650))
+        .with_data_moment(moment);
+
+    // A QC pass: censor a gate, raise another, and nudge the azimuth
+    let moment = radial
+        .data_moment_mut(&DataBlockProduct::Reflectivity)
+        .expect("has reflectivity");
+    moment.set_value(2, GateValue::BelowThreshold)?;
+    moment.set_value(3, GateValue::Value(25.0))?;
+    assert!(moment.set_value(4, GateValue::Value(25.0)).is_err());
+    assert!(moment.set_raw_value(0, 256).is_err());
+    radial.header_mut().set_azm(0.75);
+    assert!(radial
+        .remove_data_moment(&DataBlockProduct::Velocity)
+        .is_none());
+
+    // Modified radials flow back through the encoder
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 43_200_000, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, vec![radial])])?;
+    let decoded = DataFile::from_vec(encode_file(&file)?)?;
+    let radial = &decoded.elevation_scans()[&1][0];
+    assert!((radial.header().azm() - 0.75).abs() < 1e-6);
+
+    let moment = radial.reflectivity_data().expect("has reflectivity");
+    assert_eq!(moment.data().snr_threshold(), 20);
+    let values: Vec<GateValue> = moment.values().collect();
+    assert_eq!(
+        values,
+        [
+            GateValue::BelowThreshold,
+            GateValue::RangeFolded,
+            GateValue::BelowThreshold,
+            GateValue::Value(25.0),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_propagation() -> Result<()> {
+    use crate::encode::encode_file;
+    use crate::records::{ElevationData, RadialData, VolumeHeaderRecord};
+    use crate::Sweep;
+
+    // Metadata blocks on only a few radials of the first sweep, and none on the second
+    let mut radials = fine_line_sweep(100);
+    radials[10].set_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215));
+    radials[10].set_elevation_data(ElevationData::new([0, 1], -44.0));
+    radials[10].set_radial_data(RadialData::new(4660, 2650));
+    radials[20].set_radial_data(RadialData::new(4660, 2800));
+    let mut sweep = Sweep::new(1, radials);
+    assert_eq!(
+        sweep.radial_data().map(RadialData::nyquist_velocity),
+        Some(2650)
+    );
+
+    sweep.propagate_metadata();
+    let radials = sweep.radials();
+    assert!(radials.iter().all(|radial| radial.volume_data().is_some()));
+    assert!(radials
+        .iter()
+        .all(|radial| radial.elevation_data().is_some()));
+    let nyquist = |index: usize| {
+        radials[index]
+            .radial_data()
+            .map(RadialData::nyquist_velocity)
+    };
+    assert_eq!(nyquist(5), Some(2650));
+    assert_eq!(nyquist(15), Some(2650));
+    assert_eq!(nyquist(25), Some(2800));
+    assert_eq!(nyquist(359), Some(2800));
+
+    // Across a file, sweeps without volume data receive the volume's
+    let mut radials = fine_line_sweep(100);
+    radials[0].set_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215));
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 0, *b"KTST");
+    let sweeps = vec![Sweep::new(1, radials), Sweep::new(2, fine_line_sweep(100))];
+    let mut file = DataFile::from_parts(header, sweeps)?;
+    let encoded = encode_file(&file)?;
+
+    let all_have_volume_data = |file: &DataFile| {
+        file.elevation_scans()
+            .values()
+            .flatten()
+            .all(|radial| radial.volume_data().is_some())
+    };
+    assert!(!all_have_volume_data(&file));
+    file.propagate_metadata();
+    assert!(all_have_volume_data(&file));
+
+    // Or while decoding
+    let options = DecodeOptions::new().with_propagate_metadata(true);
+    assert!(!all_have_volume_data(&DataFile::from_slice(&encoded)?));
+    let decoded = DataFile::from_vec_with_options(encoded, &options)?;
+    assert!(all_have_volume_data(&decoded));
+
+    Ok(())
+}
+
+#[test]
+fn radar_pair_transformation() -> Result<()> {
+    use crate::geometry::{destination, ground_range_m};
+    use crate::radar_pair::{match_sweeps, PolarCoordinate, RadarLocation, RadarPair};
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use chrono::Duration;
+
+    // A target radar 60 km east of the source, on lower ground
+    let (lat, long) = destination(41.73, -93.72, 90.0, 60_000.0);
+    let pair = RadarPair::new(
+        RadarLocation::new(41.73, -93.72, 319.0),
+        RadarLocation::new(lat, long, 250.0),
+    );
+    let (distance, azimuth) = pair.baseline();
+    assert!((distance - 60_000.0).abs() < 1.0);
+    assert!((azimuth - 90.0).abs() < 0.01);
+
+    // A gate beyond the target appears ahead of it, and transforms back to itself
+    let gate = PolarCoordinate::new(80_000.0, 90.0, 0.5);
+    let transformed = pair.to_target(&gate);
+    let ground_range = ground_range_m(transformed.range(), transformed.elevation());
+    assert!((ground_range - (ground_range_m(80_000.0, 0.5) - 60_000.0)).abs() < 10.0);
+    assert!((transformed.azimuth() - 90.0).abs() < 0.5);
+    assert!(transformed.elevation() > 0.5);
+
+    let restored = pair.reversed().to_target(&transformed);
+    assert!((restored.range() - gate.range()).abs() < 2.0);
+    assert!((restored.azimuth() - gate.azimuth()).abs() < 0.01);
+    assert!((restored.elevation() - gate.elevation()).abs() < 0.01);
+
+    // Sweeps of overlapping sites pair with those collected nearest in time
+    let config = SimulatorConfig::new(
+        vec![
+            SimulatedSite::new("KDMX", 41.73, -93.72, 299),
+            SimulatedSite::new("KTST", lat, long, 230),
+        ],
+        6.0,
+    )
+    .with_elevations(vec![0.5, 1.5])
+    .with_radials_per_sweep(90)
+    .with_gates(20)
+    .with_compression(false);
+    let mut volumes = Simulator::new(config)
+        .take(2)
+        .map(|volume| DataFile::from_vec(volume?.into_data()));
+    let source = volumes.next().expect("has source")?;
+    let target = volumes.next().expect("has target")?;
+
+    let pair = RadarPair::from_volumes(&source, &target).expect("has volume data");
+    let radial = &source.elevation_scans()[&1][22];
+    let gates: Vec<_> = pair
+        .transform_gates(radial, Product::Reflectivity)
+        .expect("has reflectivity")
+        .collect();
+    assert_eq!(gates.len(), 20);
+
+    let matches = match_sweeps(&source, &target);
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].target_elevation_number(), 1);
+    assert_eq!(matches[0].time_difference(), Duration::minutes(5));
+
+    Ok(())
+}
+
+#[test]
+fn calibration_self_consistency() -> Result<()> {
+    use crate::calibration::{estimate_reflectivity_bias, CalibrationOptions};
+    use crate::records::VolumeHeaderRecord;
+    use crate::Sweep;
+
+    // Rain of 40 dBZ and 1.5 dB ZDR, whose phase accumulates at 0.583 degrees per kilometer, as
+    // measured by a radar reading reflectivity 2 dB high
+    let kdp = 1e-5 * (11.74 - 4.02 * 1.5 - 0.140 * 1.5f32.powi(2) + 0.130 * 1.5f32.powi(3)) * 1e4;
+    let moment = |product: DataBlockProduct, word_size: u8, scale: f32, offset: f32| {
+        let data = GenericData::new(&product, 200, 2125, 250, word_size, scale, offset);
+        DataMoment::new(product, data, vec![0; 200 * usize::from(word_size / 8)])
+    };
+
+    let mut radials = Vec::new();
+    for azimuth in 0..10u16 {
+        let header = Message31Header::new(
+            *b"KTST",
+            0,
+            1,
+            azimuth + 1,
+            f32::from(azimuth),
+            2,
+            1,
+            1,
+            0.5,
+        );
+        let mut reflectivity = moment(DataBlockProduct::Reflectivity, 8, 2.0, 66.0);
+        let mut zdr = moment(DataBlockProduct::DifferentialReflectivity, 8, 16.0, 128.0);
+        let mut correlation = moment(DataBlockProduct::CorrelationCoefficient, 8, 300.0, -60.5);
+        let mut phase = moment(DataBlockProduct::DifferentialPhase, 16, 2.8361, 2.0);
+        for gate in 0..200 {
+            #[allow(clippy::cast_precision_loss)]
+            let range = gate as f32 * 0.25;
+            reflectivity.set_value(gate, GateValue::Value(42.0))?;
+            zdr.set_value(gate, GateValue::Value(1.5))?;
+            correlation.set_value(gate, GateValue::Value(0.99))?;
+            phase.set_value(gate, GateValue::Value(2.0 * kdp * range))?;
+        }
+
+        radials.push(
+            Message31::new(header)
+                .with_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215))
+                .with_data_moment(reflectivity)
+                .with_data_moment(zdr)
+                .with_data_moment(correlation)
+                .with_data_moment(phase),
+        );
+    }
+
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 0, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, radials)])?;
+    let estimate = estimate_reflectivity_bias(&file, &CalibrationOptions::new()).expect("has rain");
+    assert_eq!(estimate.path_count(), 10);
+    assert_eq!(estimate.gate_count(), 2000);
+    assert!((estimate.bias() - 2.0).abs() < 0.1);
+
+    // Without dual-polarization moments there is nothing to check
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 0, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, fine_line_sweep(100))])?;
+    assert!(estimate_reflectivity_bias(&file, &CalibrationOptions::new()).is_none());
+
+    Ok(())
+}
+
+#[test]
+fn configured_pipeline() -> Result<()> {
+    use crate::pipeline::{Pipeline, ProcessingConfig, QcStep};
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let simulation =
+        SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+            .with_elevations(vec![0.5, 1.5])
+            .with_radials_per_sweep(360)
+            .with_gates(100)
+            .with_compression(false);
+    let volume = Simulator::new(simulation).next().expect("is endless")?;
+    let time = volume.time();
+    let data = volume.into_data();
+
+    let directory = std::env::temp_dir().join(format!("nexrad_pipeline_{}", std::process::id()));
+    let config: ProcessingConfig = toml::from_str(&format!(
+        r#"
+        exports = [
+            "archive2",
+            "uf",
+            {{ radial_wind_bufr = {{ options = {{ superob = {{ averaging = "median" }} }} }} }},
+        ]
+        rain_type = {{ columns = 30, rows = 20, cell_size = 2000.0 }}
+
+        [manifest]
+        sites = ["KDMX", "KOAX"]
+        times = ["{}"]
+        products = ["reflectivity", "differentialphase"]
+        output_template = "{}/{{site}}/{{time}}_{{product}}.png"
+        options = {{ size = 64 }}
+        threads = 2
+
+        [[fields]]
+        name = "strong_echo"
+        expression = "REF > 30"
+        grid = {{ columns = 20, rows = 20, cell_size = 2000.0 }}
+
+        [[qc]]
+        step = "propagate_metadata"
+
+        [[qc]]
+        step = "threshold"
+        product = "reflectivity"
+        min = 0.0
+
+        [[qc]]
+        step = "despeckle"
+        product = "reflectivity"
+        min_gates = 3
+
+        [[qc]]
+        step = "range_limit"
+        max = 150000.0
+
+        [[grids]]
+        product = "reflectivity"
+        grid = {{ columns = 50, rows = 40, cell_size = 2000.0, layer = "composite" }}
+        "#,
+        time.format("%Y-%m-%dT%H:%M:%S"),
+        directory.display()
+    ))?;
+    assert_eq!(config.qc()[0], QcStep::PropagateMetadata);
+    assert_eq!(config.manifest().sites().len(), 2);
+    assert!(toml::from_str::<ProcessingConfig>("unknown = 1").is_err());
+    assert!(toml::from_str::<ProcessingConfig>(
+        "[manifest]\nsites = []\ntimes = []\nproducts = []\noutput_template = \"x\"\n\
+         [[fields]]\nname = \"x\"\nexpression = \"REF >\"\n\
+         grid = { columns = 1, rows = 1, cell_size = 1.0 }"
+    )
+    .is_err());
+
+    // Volumes the loader cannot find and products absent from the volume are skipped
+    let loader = |site: &str, requested: chrono::NaiveDateTime| {
+        if site != "KDMX" || requested != time {
+            return Ok(None);
+        }
+        DataFile::from_vec(data.clone()).map(Some)
+    };
+    let pipeline = Pipeline::new(config);
+    let written = pipeline.run_manifest(&loader)?;
+    assert_eq!(written.len(), 7);
+    for (path, suffix) in written.iter().zip([
+        "_ref.png",
+        "_ref_grid.f32",
+        "_strong_echo.f32",
+        "_rain_type.u8",
+        "_archive2.ar2v",
+        "_uf.uf",
+        "_radial_wind_bufr.bufr",
+    ]) {
+        assert!(path.to_string_lossy().ends_with(suffix));
+    }
+    assert_eq!(&std::fs::read(&written[0])?[..4], b"\x89PNG");
+    assert_eq!(std::fs::metadata(&written[1])?.len(), 50 * 40 * 4);
+    let strong_echo = std::fs::read(&written[2])?;
+    assert_eq!(strong_echo.len(), 20 * 20 * 4);
+    assert!(strong_echo.chunks_exact(4).all(|value| {
+        let value = f32::from_le_bytes(value.try_into().expect("4 bytes"));
+        value.is_nan() || value == 0.0 || (value - 1.0).abs() < f32::EPSILON
+    }));
+    let rain_types = std::fs::read(&written[3])?;
+    assert_eq!(rain_types.len(), 30 * 20);
+    assert!(rain_types.iter().all(|code| *code <= 2));
+    assert_eq!(&std::fs::read(&written[6])?[..4], b"BUFR");
+
+    // Quality control was applied before export
+    assert_quality_controlled(&DataFile::new(&written[4])?);
+
+    std::fs::remove_dir_all(directory)?;
+
+    Ok(())
+}
+
+/// Asserts a volume's reflectivity is at least 0 dBZ within 150 km in runs of at least 3 gates.
+fn assert_quality_controlled(file: &DataFile) {
+    for moment in file
+        .elevation_scans()
+        .values()
+        .flatten()
+        .filter_map(Message31::reflectivity_data)
+    {
+        let values: Vec<Option<f32>> = moment.values().map(|value| value.value()).collect();
+        for (index, value) in values.iter().enumerate() {
+            let Some(value) = value else {
+                continue;
+            };
+            assert!(*value >= 0.0);
+            assert!(moment.data().gate_range_m(index) <= 150_000.0);
+            let run = values[index.saturating_sub(2)..(index + 3).min(values.len())]
+                .windows(3)
+                .any(|window| window.iter().all(Option::is_some));
+            assert!(run);
+        }
+    }
+}
+
+#[test]
+fn seeded_simulator() -> Result<()> {
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5])
+        .with_radials_per_sweep(90)
+        .with_gates(50)
+        .with_compression(false);
+    let volumes = |config: SimulatorConfig| -> Result<Vec<Vec<u8>>> {
+        Simulator::new(config)
+            .take(2)
+            .map(|volume| Ok(volume?.into_data()))
+            .collect()
+    };
+
+    // The same seed reproduces the same volumes, and a neighboring seed does not
+    let seeded = volumes(config.clone().with_seed(7))?;
+    assert_eq!(seeded, volumes(config.clone().with_seed(7))?);
+    assert_ne!(seeded, volumes(config.clone().with_seed(6))?);
+
+    // A clock-seeded simulator reports its seed for reproduction
+    let simulator = Simulator::new(config.clone());
+    let seed = simulator.seed();
+    let first = simulator
+        .take(1)
+        .map(|volume| Ok(volume?.into_data()))
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(first[..], volumes(config.with_seed(seed))?[..1]);
+
+    Ok(())
+}
+
+#[test]
+fn sweep_deduplication() -> Result<()> {
+    use crate::composite::VolumeSeries;
+    use crate::encode::encode_compressed_file;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use chrono::Duration;
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(90)
+        .with_gates(50)
+        .with_compression(false)
+        .with_seed(11);
+    let volumes = Simulator::new(config)
+        .take(2)
+        .map(|volume| DataFile::from_vec(volume?.into_data()))
+        .collect::<Result<Vec<_>>>()?;
+
+    // The same volume received in another encoding hashes alike
+    let relayed = DataFile::from_vec(encode_compressed_file(&volumes[0])?)?;
+    assert_eq!(relayed.sweep_hashes(), volumes[0].sweep_hashes());
+    assert_ne!(volumes[1].sweep_hashes(), volumes[0].sweep_hashes());
+
+    let mut sweep = relayed.into_sweeps().next().expect("has sweeps");
+    let hash = sweep.content_hash();
+    let mut radials = sweep.into_radials();
+    radials[0]
+        .data_moment_mut(&DataBlockProduct::Reflectivity)
+        .expect("has reflectivity")
+        .set_value(0, GateValue::RangeFolded)?;
+    sweep = crate::Sweep::new(1, radials);
+    assert_ne!(sweep.content_hash(), hash);
+
+    let relayed = DataFile::from_vec(encode_compressed_file(&volumes[0])?)?;
+    let mut series = VolumeSeries::new(
+        volumes.into_iter().chain([relayed]).collect(),
+        Duration::minutes(15),
+    )?;
+    assert_eq!(series.dedup(), 1);
+    assert_eq!(series.volumes().count(), 2);
+    assert_eq!(series.dedup(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn backfill_stream() -> Result<()> {
+    use crate::backfill::{Backfill, ChunkKind};
+    use crate::encode::encode_compressed_file;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use chrono::{Duration, NaiveDateTime};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5, 1.5, 2.4])
+        .with_radials_per_sweep(90)
+        .with_gates(50)
+        .with_compression(false)
+        .with_seed(5);
+    let volumes = Simulator::new(config)
+        .take(5)
+        .map(|volume| DataFile::from_vec(volume?.into_data()))
+        .collect::<Result<Vec<_>>>()?;
+    let times: Vec<NaiveDateTime> = volumes
+        .iter()
+        .map(|file| file.volume_header().date_time().expect("valid time"))
+        .collect();
+
+    // The first two volumes have reached the archive
+    let mut archive = Vec::new();
+    for (file, time) in volumes.iter().zip(&times).take(2) {
+        let identifier = format!("KDMX{}_V06", time.format("%Y%m%d_%H%M%S"));
+        let meta = FileMetadata::new("KDMX".to_string(), time.date(), identifier);
+        archive.push((meta, encode_compressed_file(file)?));
+    }
+
+    // The feed still holds the second volume, and is publishing the fifth
+    let mut chunks = Vec::new();
+    for (number, (file, time)) in volumes.iter().zip(&times).enumerate().skip(1) {
+        let data = encode_compressed_file(file)?;
+        let mut boundaries = vec![24];
+        while *boundaries.last().expect("has boundary") < data.len() {
+            let start = *boundaries.last().expect("has boundary");
+            let prefix: [u8; 4] = data[start..start + 4].try_into()?;
+            boundaries
+                .push(start + 4 + usize::try_from(i32::from_be_bytes(prefix).unsigned_abs())?);
+        }
+        boundaries[0] = 0;
+        let count = boundaries.len() - 1;
+        assert!(count >= 2);
+
+        let published = if number == 4 { count - 1 } else { count };
+        for sequence in 1..=published {
+            let kind = match sequence {
+                1 => ChunkKind::Start,
+                _ if sequence == count => ChunkKind::End,
+                _ => ChunkKind::Intermediate,
+            };
+            let chunk_time = *time + Duration::seconds(i64::try_from(sequence)? * 10);
+            let meta = ChunkMetadata::new(
+                "KDMX".to_string(),
+                u16::try_from(500 + number)?,
+                if sequence == 1 { *time } else { chunk_time },
+                u16::try_from(sequence)?,
+                kind,
+            );
+            assert_eq!(ChunkMetadata::from_key(&meta.key()).as_ref(), Some(&meta));
+            chunks.push((
+                meta,
+                data[boundaries[sequence - 1]..boundaries[sequence]].to_vec(),
+            ));
+        }
+    }
+
+    let mut backfill = Backfill::new(
+        MemoryArchive(archive),
+        MemoryChunks(chunks),
+        "KDMX",
+        times[0],
+        times[4] + Duration::minutes(1),
+    )
+    .await?;
+    assert_eq!(backfill.times(), times[..4]);
+
+    let mut streamed = Vec::new();
+    while let Some(file) = backfill.next_volume().await {
+        streamed.push(file?);
+    }
+    assert_eq!(backfill.remaining(), 0);
+    assert_eq!(streamed.len(), 4);
+    for (file, expected) in streamed.iter().zip(&volumes) {
+        assert_eq!(file.sweep_hashes(), expected.sweep_hashes());
+    }
+
+    Ok(())
+}
+
+/// An archive of encoded volumes held in memory.
+struct MemoryArchive(Vec<(FileMetadata, Vec<u8>)>);
+
+impl ArchiveSource for MemoryArchive {
+    async fn list_volumes(&self, site: &str, date: NaiveDate) -> Result<Vec<FileMetadata>> {
+        Ok(self
+            .0
+            .iter()
+            .map(|(meta, _)| meta.clone())
+            .filter(|meta| meta.site() == site && *meta.date() == date)
+            .collect())
+    }
+
+    async fn fetch_volume(&self, meta: &FileMetadata) -> Result<Vec<u8>> {
+        let (_, data) = self
+            .0
+            .iter()
+            .find(|(other, _)| other == meta)
+            .expect("listed");
+        Ok(data.clone())
+    }
+}
+
+/// A real-time feed of encoded chunks held in memory.
+struct MemoryChunks(Vec<(ChunkMetadata, Vec<u8>)>);
+
+impl ChunkSource for MemoryChunks {
+    async fn list_chunks(&self, site: &str) -> Result<Vec<ChunkMetadata>> {
+        Ok(self
+            .0
+            .iter()
+            .map(|(chunk, _)| chunk.clone())
+            .filter(|chunk| chunk.site() == site)
+            .collect())
+    }
+
+    async fn fetch_chunk(&self, chunk: &ChunkMetadata) -> Result<Vec<u8>> {
+        let (_, data) = self
+            .0
+            .iter()
+            .find(|(other, _)| other == chunk)
+            .expect("listed");
+        Ok(data.clone())
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "download")]
+async fn volume_source_failover() -> Result<()> {
+    use crate::downloader::{FailoverDownloader, VolumeSource};
+
+    let root = std::env::temp_dir().join(format!("nexrad_failover_{}", std::process::id()));
+    let broken = root.join("broken");
+    let empty = root.join("empty");
+    let cache = root.join("cache");
+    std::fs::create_dir_all(&empty)?;
+    std::fs::create_dir_all(cache.join("2023/04/06/KDMX"))?;
+    std::fs::write(&broken, b"not a directory")?;
+    std::fs::write(
+        cache.join("2023/04/06/KDMX/KDMX20230406_000215_V06"),
+        b"volume",
+    )?;
+
+    let downloader = FailoverDownloader::new(vec![
+        VolumeSource::LocalCache(broken),
+        VolumeSource::LocalCache(empty.clone()),
+        VolumeSource::LocalCache(cache),
+    ])
+    .with_max_consecutive_failures(2);
+
+    let date = NaiveDate::from_ymd_opt(2023, 4, 6).expect("valid date");
+    let metas = downloader.list_files("KDMX", &date).await?;
+    assert_eq!(metas.len(), 1);
+    assert_eq!(metas[0].identifier(), "KDMX20230406_000215_V06");
+
+    let health = downloader.health();
+    assert_eq!(health[0].failures(), 1);
+    assert!(health[0].last_error().is_some());
+    assert_eq!(health[1].misses(), 1);
+    assert_eq!(health[2].successes(), 1);
+
+    assert_eq!(downloader.download_file(&metas[0]).await?, b"volume");
+    assert_eq!(downloader.health()[0].consecutive_failures(), 2);
+
+    // The broken source is now tried last, so the cache answers before it is reached
+    assert_eq!(downloader.download_file(&metas[0]).await?, b"volume");
+    let health = downloader.health();
+    assert_eq!(health[0].failures(), 2);
+    assert_eq!(health[1].failures(), 0);
+    assert_eq!(health[1].misses(), 3);
+    assert_eq!(health[2].successes(), 3);
+
+    let missing = FileMetadata::new("KDMX".to_string(), date, "KDMX_MISSING".to_string());
+    assert!(downloader.download_file(&missing).await.is_err());
+    assert!(FailoverDownloader::new(Vec::new())
+        .list_files("KDMX", &date)
+        .await
+        .is_err());
+
+    // Hourly archives from an NCEI order are read by member, decompressing gzipped volumes
+    let order = root.join("order");
+    std::fs::create_dir_all(&order)?;
+    let mut gzipped = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut gzipped, b"second volume")?;
+    std::fs::write(
+        order.join("NWS_NEXRAD_NXL2DP_KDMX_20230406000000_20230406005959.tar"),
+        tar_archive(&[
+            ("KDMX20230406_000215_V06", b"first volume"),
+            ("KDMX20230406_004512_V06.gz", &gzipped.finish()?),
+        ]),
+    )?;
+    std::fs::write(
+        order.join("NWS_NEXRAD_NXL2DP_KDMX_20230407000000_20230407005959.tar"),
+        tar_archive(&[("KDMX20230407_000114_V06", b"next day")]),
+    )?;
+
+    let ncei = FailoverDownloader::new(vec![VolumeSource::Ncei(order.clone())]);
+    let metas = ncei.list_files("KDMX", &date).await?;
+    let identifiers: Vec<&str> = metas
+        .iter()
+        .map(|meta| meta.identifier().as_str())
+        .collect();
+    assert_eq!(
+        identifiers,
+        ["KDMX20230406_000215_V06", "KDMX20230406_004512_V06"]
+    );
+    assert_eq!(ncei.download_file(&metas[0]).await?, b"first volume");
+    assert_eq!(ncei.download_file(&metas[1]).await?, b"second volume");
+    assert!(ncei.list_files("KTLX", &date).await?.is_empty());
+    let error = ncei.download_file(&missing).await.expect_err("is missing");
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(crate::error::Error::VolumeNotFound(_))
+    ));
+
+    // A cache without the volume misses rather than fails, so it stays ahead of the order
+    let cached = FailoverDownloader::new(vec![
+        VolumeSource::LocalCache(empty),
+        VolumeSource::Ncei(order),
+    ])
+    .with_max_consecutive_failures(1);
+    for _ in 0..2 {
+        assert_eq!(cached.download_file(&metas[0]).await?, b"first volume");
+    }
+    let health = cached.health();
+    assert_eq!(health[0].misses(), 2);
+    assert_eq!(health[0].failures(), 0);
+    assert_eq!(health[0].consecutive_failures(), 0);
+    assert_eq!(health[1].successes(), 2);
+
+    std::fs::remove_dir_all(root)?;
+
+    Ok(())
+}
+
+/// Writes files to a ustar archive.
+#[cfg(feature = "download")]
+fn tar_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (name, data) in files {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[156] = b'0';
+        header[257..265].copy_from_slice(b"ustar\x0000");
+
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|byte| u32::from(*byte)).sum();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+        archive.extend(header);
+        archive.extend(*data);
+        archive.resize(archive.len().next_multiple_of(512), 0);
+    }
+
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
+
+#[test]
+fn compressed_radials_skipped() -> Result<()> {
+    use crate::decoder::SkipReason;
+    use crate::error::Error;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+        .with_elevations(vec![0.5])
+        .with_radials_per_sweep(90)
+        .with_gates(50)
+        .with_compression(false)
+        .with_seed(2);
+    let mut data = Simulator::new(config)
+        .next()
+        .expect("simulates a volume")?
+        .into_data();
+    let complete = DataFile::from_slice(&data)?;
+    assert!(complete.decode_report().is_complete());
+
+    // Mark the third and fourth radials' data blocks as compressed with BZIP2 and zlib
+    let mut offsets = Vec::new();
+    let mut offset = 24;
+    while offset < data.len() {
+        let msg_size = usize::from(u16::from_be_bytes([data[offset + 12], data[offset + 13]]));
+        if data[offset + 15] == 31 {
+            offsets.push(offset);
+        }
+        offset += 12 + msg_size * 2;
+    }
+    data[offsets[2] + 28 + 16] = 1;
+    data[offsets[3] + 28 + 16] = 2;
+
+    let file = DataFile::from_slice(&data)?;
+    let skipped = file.decode_report().skipped();
+    assert_eq!(skipped.len(), 2);
+    assert_eq!(skipped[0].offset(), offsets[2] as u64);
+    assert_eq!(skipped[0].reason(), SkipReason::UnsupportedCompression(1));
+    assert_eq!(skipped[1].reason(), SkipReason::UnsupportedCompression(2));
+    assert_eq!(skipped[1].elevation_number(), 1);
+    assert_eq!(file.elevation_scans()[&1].len(), 88);
+    let moment_data = |file: &DataFile, index: usize| {
+        file.elevation_scans()[&1][index]
+            .reflectivity_data()
+            .map(|moment| moment.moment_data().to_vec())
+    };
+    assert_eq!(moment_data(&file, 2), moment_data(&complete, 4));
+
+    let error = DataFile::from_vec_with_options(data, &DecodeOptions::new().with_strict(true))
+        .err()
+        .expect("strict decoding fails");
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::UnsupportedRadialCompression(1))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn gate_quality_index() -> Result<()> {
+    use crate::blockage::Blockage;
+    use crate::quality::{QualityOptions, SweepQuality};
+    use crate::superob::{superob_sweep, superob_sweep_weighted, SuperobOptions};
+
+    /// Terrain blocking the whole beam north-east of the radar.
+    struct NorthEastBlocked;
+
+    impl Blockage for NorthEastBlocked {
+        fn blocked_fraction(&self, _elevation: f32, azimuth: f32, _range: f32) -> f32 {
+            if azimuth < 90.0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+
+    // Uniform 30 dBZ rain, with non-meteorological correlation to the south-west
+    let mut sweep = Vec::new();
+    for azimuth in 0..360u16 {
+        let mut radial = Message31::new(Message31Header::new(
+            *b"KTST",
+            0,
+            1,
+            azimuth + 1,
+            f32::from(azimuth) + 0.5,
+            2,
+            1,
+            1,
+            0.5,
+        ));
+        let correlation = if (180..270).contains(&azimuth) {
+            0.7
+        } else {
+            0.99
+        };
+        for (product, scale, offset, value) in [
+            (DataBlockProduct::Reflectivity, 2.0, 66.0, 30.0),
+            (
+                DataBlockProduct::CorrelationCoefficient,
+                300.0,
+                -60.5,
+                correlation,
+            ),
+        ] {
+            let data = GenericData::new(&product, 200, 2125, 250, 8, scale, offset);
+            let mut moment = DataMoment::new(product, data, vec![0; 200]);
+            for gate in 0..200 {
+                moment.set_value(gate, GateValue::Value(value))?;
+            }
+            radial.set_data_moment(moment);
+        }
+        sweep.push(radial);
+    }
+
+    // A weak echo near the edge of the radar's sensitivity
+    sweep[135]
+        .data_moment_mut(&DataBlockProduct::Reflectivity)
+        .expect("has reflectivity")
+        .set_value(190, GateValue::Value(-5.0))?;
+
+    let quality = SweepQuality::from_radials(&sweep, &NorthEastBlocked, &QualityOptions::new());
+    let at = |azimuth: usize, gate: usize| {
+        quality.radials()[azimuth]
+            .as_ref()
+            .and_then(|radial| radial.values()[gate])
+            .expect("has quality")
+    };
+    assert!(at(45, 10).abs() < f32::EPSILON);
+    assert!((at(135, 10) - 1.0).abs() < 1e-6);
+    assert!(at(225, 10).abs() < f32::EPSILON);
+    assert!((at(135, 190) - 0.25).abs() < 0.01);
+    assert!(
+        (quality
+            .at_range(135, 2125.0 + 250.0 * 190.0)
+            .expect("in range")
+            - 0.25)
+            .abs()
+            < 0.01
+    );
+
+    let options = SuperobOptions::new();
+    let unweighted = superob_sweep(&sweep, Product::Reflectivity, &options);
+    let weighted = superob_sweep_weighted(&sweep, Product::Reflectivity, &quality, &options);
+    assert!(unweighted.iter().any(|superob| superob.azimuth() < 90.0));
+    assert!(weighted.len() < unweighted.len());
+    assert!(weighted
+        .iter()
+        .all(|superob| (90.0..180.0).contains(&superob.azimuth()) || superob.azimuth() >= 270.0));
+
+    // The index may be attached to a sweep as an auxiliary field
+    assert!(crate::Sweep::new(1, sweep.clone())
+        .quality_index()
+        .is_none());
+    let attached = crate::Sweep::new(1, sweep.clone())
+        .with_quality_index(&NorthEastBlocked, &QualityOptions::new());
+    assert_eq!(attached.quality_index(), Some(&quality));
+
+    // Rain rates are weighted by quality, excluding gates below the minimum
+    let rates =
+        rain_rate_sweep_weighted(&sweep, &quality, &QpeOptions::new().with_min_quality(0.5));
+    let rate_at = |azimuth: usize, gate: usize| rates.values()[azimuth * rates.columns() + gate];
+    assert!(rate_at(45, 10).is_none());
+    assert!(rate_at(135, 190).is_none());
+    let (rate, weight) = rate_at(135, 10).expect("has a rate");
+    assert!((weight - 1.0).abs() < 1e-6);
+    let combined = combine_rain_rates(&[rates.clone(), rates.clone()])?;
+    assert_eq!(combined.values()[135 * rates.columns() + 10], Some(rate));
+    assert!(combined.values()[45 * rates.columns() + 10].is_none());
+    assert!(combine_rain_rates(&[rates, Grid::new(1, 1, vec![None])]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn clutter_quality_factor() {
+    use crate::quality::{QualityOptions, SweepQuality};
+
+    // 30 dBZ rain with clutter filter power removed encoded as in the ICD: codes 2 to 7 flag how
+    // the filter was applied, and from 8 each code is 1 dB more removed
+    let mut radial = Message31::new(Message31Header::new(*b"KTST", 0, 1, 1, 0.5, 2, 1, 1, 0.5));
+    for (product, scale, offset, values) in [
+        (DataBlockProduct::Reflectivity, 2.0, 66.0, vec![126u8; 5]),
+        (
+            DataBlockProduct::ClutterFilterProbability,
+            1.0,
+            8.0,
+            vec![2, 8, 13, 23, 40],
+        ),
+    ] {
+        let data = GenericData::new(&product, 5, 2125, 250, 8, scale, offset);
+        radial.set_data_moment(DataMoment::new(product, data, values));
+    }
+
+    let quality = SweepQuality::from_radials(&[radial], &NoBlockage, &QualityOptions::new());
+    let values: Vec<f32> = quality.radials()[0]
+        .as_ref()
+        .expect("has reflectivity")
+        .values()
+        .iter()
+        .map(|value| value.expect("has quality"))
+        .collect();
+    for (value, expected) in values.iter().zip([1.0, 1.0, 1.0, 0.5, 0.0]) {
+        assert!((value - expected).abs() < 1e-6, "{values:?}");
+    }
+}
+
+#[test]
+fn zr_presets_and_partitioning() {
+    // Reflectivity is encoded with a scale of 2 and an offset of 66
+    let encode = |dbz: f32| {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let raw = (dbz * 2.0 + 66.0) as u8;
+        raw
+    };
+
+    // A smooth stratiform radial, a textured radial with a peaked core, an intense cell, and
+    // echo too weak to be precipitation
+    let profiles: [Vec<f32>; 4] = [
+        vec![25.0; 200],
+        (0..200)
+            .map(|gate| match gate {
+                100 => 36.0,
+                _ if gate % 2 == 0 => 20.0,
+                _ => 30.0,
+            })
+            .collect(),
+        vec![60.0; 200],
+        vec![5.0; 200],
+    ];
+    let sweep: Vec<Message31> = profiles
+        .iter()
+        .zip(0u16..)
+        .map(|(profile, azimuth)| {
+            let mut radial = Message31::new(Message31Header::new(
+                *b"KTST",
+                0,
+                1,
+                azimuth + 1,
+                f32::from(azimuth) + 0.5,
+                2,
+                1,
+                1,
+                0.5,
+            ));
+            let product = DataBlockProduct::Reflectivity;
+            let data = GenericData::new(&product, 200, 2125, 250, 8, 2.0, 66.0);
+            let values = profile.iter().map(|dbz| encode(*dbz)).collect();
+            radial.set_data_moment(DataMoment::new(product, data, values));
+            radial
+        })
+        .collect();
+
+    let marshall_palmer = ZrPreset::Stratiform.relation();
+    assert_eq!((marshall_palmer.a(), marshall_palmer.b()), (200.0, 1.6));
+    assert_eq!(ZrPreset::CoolSeason.relation(), ZrRelation::new(130.0, 2.0));
+    let rate = marshall_palmer.rain_rate(40.0);
+    assert!((marshall_palmer.reflectivity(rate) - 40.0).abs() < 1e-3);
+    let presets: std::collections::HashMap<String, ZrPreset> =
+        toml::from_str("preset = \"cool_season\"").expect("valid preset");
+    assert_eq!(presets["preset"], ZrPreset::CoolSeason);
+
+    let rain_types = partition_sweep(&sweep, &PartitionOptions::new());
+    assert_eq!(rain_types.get(50, 0), Some(&Some(RainType::Stratiform)));
+    assert_eq!(rain_types.get(100, 1), Some(&Some(RainType::Convective)));
+    assert_eq!(rain_types.get(101, 1), Some(&Some(RainType::Stratiform)));
+    assert_eq!(rain_types.get(50, 2), Some(&Some(RainType::Convective)));
+    assert_eq!(rain_types.get(50, 3), Some(&None));
+
+    // The WSR-88D's convective relation is used for every gate by default, with hail capped
+    let convective = ZrPreset::Convective.relation();
+    let fixed = rain_rate_sweep(&sweep, &QpeOptions::new());
+    let rate_at = |grid: &Grid<Option<f32>>, gate: usize, radial: usize| {
+        grid.get(gate, radial).copied().flatten().expect("has rate")
+    };
+    assert!((rate_at(&fixed, 50, 0) - convective.rain_rate(25.0)).abs() < 1e-4);
+    assert!((rate_at(&fixed, 50, 2) - convective.rain_rate(53.0)).abs() < 1e-3);
+    assert!(rate_at(&fixed, 50, 3) > 0.0);
+
+    let tropical = QpeOptions::new().with_selection(ZrSelection::Fixed(ZrPreset::Tropical.into()));
+    let tropical = rain_rate_sweep(&sweep, &tropical);
+    assert!(rate_at(&tropical, 50, 0) > rate_at(&fixed, 50, 0));
+
+    let partitioned = rain_rate_sweep(
+        &sweep,
+        &QpeOptions::new().with_selection(ZrSelection::partitioned()),
+    );
+    assert!((rate_at(&partitioned, 50, 0) - marshall_palmer.rain_rate(25.0)).abs() < 1e-4);
+    assert!((rate_at(&partitioned, 100, 1) - convective.rain_rate(36.0)).abs() < 1e-3);
+    assert!((rate_at(&partitioned, 101, 1) - marshall_palmer.rain_rate(30.0)).abs() < 1e-3);
+    assert_eq!(partitioned.get(50, 3), Some(&None));
+}
+
+#[test]
+fn steiner_partitioning() {
+    // Stratiform rain of 20 dBZ with a weak cell standing out from it, an intense cell, and a
+    // strip too weak to be precipitation, on a 1 km grid
+    let mut values = vec![Some(20.0); 40 * 40];
+    values[20 * 40 + 20] = Some(30.0);
+    values[5 * 40 + 5] = Some(45.0);
+    values[39 * 40..].fill(Some(5.0));
+    values[0] = None;
+    let reflectivity = Grid::new(40, 40, values);
+
+    let rain_types = partition_grid(&reflectivity, 1000.0, &SteinerOptions::new());
+    let at = |column: usize, row: usize| *rain_types.get(column, row).expect("in grid");
+    assert_eq!(at(20, 20), Some(RainType::Convective));
+    assert_eq!(at(21, 21), Some(RainType::Convective));
+    assert_eq!(at(20, 24), Some(RainType::Stratiform));
+    assert_eq!(at(5, 5), Some(RainType::Convective));
+    assert_eq!(at(5, 7), Some(RainType::Convective));
+    assert_eq!(at(30, 30), Some(RainType::Stratiform));
+    assert_eq!(at(10, 39), None);
+    assert_eq!(at(0, 0), None);
+    assert_eq!(RainType::codes(&rain_types)[20 * 40 + 20], 2);
+    assert_eq!(RainType::codes(&rain_types)[39 * 40], 0);
+
+    // Cells standing out from their background are centers regardless of the threshold
+    let strict = SteinerOptions::new().with_convective_reflectivity(50.0);
+    let strict = partition_grid(&reflectivity, 1000.0, &strict);
+    assert_eq!(strict.get(20, 20), Some(&Some(RainType::Convective)));
+
+    let partitioned = QpeOptions::new().with_selection(ZrSelection::partitioned());
+    let rates = rain_rate_grid(&reflectivity, 1000.0, &partitioned);
+    let rate_at = |column: usize, row: usize| rates.get(column, row).copied().flatten();
+    let (convective, stratiform) = (
+        ZrPreset::Convective.relation(),
+        ZrPreset::Stratiform.relation(),
+    );
+    assert!((rate_at(20, 20).expect("has rate") - convective.rain_rate(30.0)).abs() < 1e-4);
+    assert!((rate_at(30, 30).expect("has rate") - stratiform.rain_rate(20.0)).abs() < 1e-4);
+    assert_eq!(rate_at(10, 39), None);
+
+    let fixed = rain_rate_grid(&reflectivity, 1000.0, &QpeOptions::new());
+    let fixed_at = fixed.get(10, 39).copied().flatten().expect("has rate");
+    assert!((fixed_at - convective.rain_rate(5.0)).abs() < 1e-4);
+}
+
+#[test]
+fn convective_cell_identification() {
+    // A weak and an intense cell in stratiform rain on a 1 km grid, and a cell straddling rows
+    let mut values = vec![Some(20.0); 40 * 40];
+    values[20 * 40 + 20] = Some(30.0);
+    values[5 * 40 + 5] = Some(45.0);
+    values[30 * 40 + 10] = Some(42.0);
+    values[31 * 40 + 11] = Some(41.0);
+    let reflectivity = Grid::new(40, 40, values);
+    let spec = GridSpec::new(40, 40, 1000.0);
+
+    let cells = identify_cells(&reflectivity, &spec, &SteinerOptions::new());
+    let rain_types = partition_grid(&reflectivity, 1000.0, &SteinerOptions::new());
+    let convective = rain_types
+        .values()
+        .iter()
+        .filter(|rain_type| **rain_type == Some(RainType::Convective))
+        .count();
+    assert_eq!(cells.len(), 3);
+    assert_eq!(
+        cells.iter().map(ConvectiveCell::grid_cells).sum::<usize>(),
+        convective
+    );
+
+    assert!((cells[0].max_reflectivity() - 45.0).abs() < f32::EPSILON);
+    let (x, y) = cells[0].centroid();
+    assert!((x + 14_500.0).abs() < 1.0 && (y - 14_500.0).abs() < 1.0);
+    #[allow(clippy::cast_precision_loss)]
+    let area = cells[0].grid_cells() as f32 * 1e6;
+    assert!((cells[0].area_m2() - area).abs() < 1.0);
+    assert!((cells[1].max_reflectivity() - 42.0).abs() < f32::EPSILON);
+    assert!((cells[2].max_reflectivity() - 30.0).abs() < f32::EPSILON);
+
+    assert!(identify_cells(
+        &reflectivity,
+        &GridSpec::new(20, 20, 1000.0),
+        &SteinerOptions::new()
+    )
+    .is_empty());
+}
+
+#[test]
+fn gate_expressions() -> Result<()> {
+    let expression: Expression = "REF > 35 && rho < 0.9 || -abs(VEL) <= -min(10, 2 * 6)".parse()?;
+    assert_eq!(
+        expression.products(),
+        [
+            Product::Reflectivity,
+            Product::CorrelationCoefficient,
+            Product::Velocity
+        ]
+    );
+    let gate = |reflectivity, correlation, velocity| {
+        move |product| match product {
+            Product::Reflectivity => Some(reflectivity),
+            Product::CorrelationCoefficient => correlation,
+            Product::Velocity => Some(velocity),
+            _ => None,
+        }
+    };
+    assert_eq!(expression.evaluate(gate(40.0, Some(0.8), 0.0)), Some(1.0));
+    assert_eq!(expression.evaluate(gate(40.0, Some(0.99), 0.0)), Some(0.0));
+    assert_eq!(
+        expression.evaluate(gate(20.0, Some(0.99), -12.0)),
+        Some(1.0)
+    );
+    assert_eq!(expression.evaluate(gate(40.0, None, 0.0)), None);
+
+    let arithmetic = Expression::parse("!(1 - 2 - 3 == -4) + 10 / 4 * 2")?;
+    assert_eq!(arithmetic.evaluate(|_| None), Some(5.0));
+
+    for (source, position) in [
+        ("REF >", 5),
+        ("REF > 35 &", 9),
+        ("(REF", 4),
+        ("HAIL > 1", 0),
+        ("REF 35", 4),
+        ("sqrt(REF)", 0),
+        ("1.2.3", 0),
+    ] {
+        let error = Expression::parse(source).expect_err("is malformed");
+        assert!(
+            matches!(error, crate::error::Error::InvalidExpression(at, _) if at == position),
+            "{source}: {error}"
+        );
+    }
+
+    // Nesting is limited rather than overflowing the stack
+    let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert_eq!(
+        Expression::parse(&nested(64))?.evaluate(|_| None),
+        Some(1.0)
+    );
+    for source in [
+        nested(65),
+        "-".repeat(100_000) + "1",
+        "1".to_string() + &"+1".repeat(100_000),
+    ] {
+        let error = Expression::parse(&source).expect_err("is nested too deeply");
+        assert!(matches!(
+            error,
+            crate::error::Error::InvalidExpression(_, "nested too deeply")
+        ));
+    }
+
+    // The fine line's gates, with velocity toward the radar behind it and away ahead of it
+    let sweep = fine_line_sweep(110);
+    let field = Expression::parse("REF >= 20 && VEL > 0")?.evaluate_sweep(&sweep);
+    assert_eq!((field.columns(), field.rows()), (200, 360));
+    assert_eq!(field.get(110, 40), Some(&Some(1.0)));
+    assert_eq!(field.get(111, 40), Some(&Some(0.0)));
+    assert_eq!(field.get(50, 40), Some(&None));
+    assert_eq!(field.get(110, 0), Some(&None));
+
+    let header = crate::records::VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 0, *b"KTST");
+    let file = DataFile::from_parts(header, vec![crate::Sweep::new(1, sweep)])?;
+    let matching = field
+        .values()
+        .iter()
+        .filter(|value| **value == Some(1.0))
+        .count();
+    let rule = AlertRule::new("line", Expression::parse("REF >= 20 && VEL > 0")?);
+    let evaluation = rule.evaluate(&file);
+    assert_eq!(evaluation.matching_gates(), matching);
+    assert!(evaluation.triggered());
+    assert!(!rule
+        .with_min_gates(matching + 1)
+        .evaluate(&file)
+        .triggered());
+
+    Ok(())
+}
+
+#[test]
+fn volume_registration() -> Result<()> {
+    use crate::composite::VolumeSeries;
+    use crate::records::{to_archive_date_time, VolumeHeaderRecord};
+    use crate::registration::{
+        check_registration, check_series, RegistrationAnomaly, RegistrationOptions,
+    };
+    use crate::simulate::{SimulatedSite, SimulatedVolume, Simulator, SimulatorConfig};
+    use chrono::{Duration, NaiveDateTime};
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 10.0)
+        .with_elevations(vec![0.5])
+        .with_radials_per_sweep(360)
+        .with_gates(600)
+        .with_compression(false)
+        .with_seed(4);
+    let encoded: Vec<Vec<u8>> = Simulator::new(config)
+        .take(2)
+        .map(|volume| volume.map(SimulatedVolume::into_data))
+        .collect::<Result<_>>()?;
+
+    // Decodes a volume with a different start time and its azimuths offset
+    let altered = |volume: &[u8], time: NaiveDateTime, offset: f32| -> Result<DataFile> {
+        let mut file = DataFile::from_slice(volume)?;
+        for radial in file.elevation_scans_mut().values_mut().flatten() {
+            let azimuth = (radial.header().azm() + offset).rem_euclid(360.0);
+            radial.header_mut().set_azm(azimuth);
+        }
+        let (day, millis) = to_archive_date_time(time).expect("in range");
+        let header = file.volume_header();
+        let header = VolumeHeaderRecord::new(
+            *header.filename(),
+            u32::from(day),
+            millis,
+            *header.radar_id(),
+        );
+        DataFile::from_parts(header, file.into_sweeps().collect())
+    };
+
+    let options = RegistrationOptions::new();
+    let earlier = DataFile::from_slice(&encoded[0])?;
+    let start = earlier.volume_header().date_time().expect("has time");
+
+    // Echoes drifting with the wind are neither rotated nor implausibly fast
+    let normal = check_registration(&earlier, &DataFile::from_slice(&encoded[1])?, &options)?;
+    assert!(normal.azimuth_bias().abs() < 0.5);
+    assert!(normal.apparent_speed() > 5.0 && normal.apparent_speed() < 30.0);
+    assert!(normal.correlation() > 0.9);
+    assert!(!normal.is_anomalous());
+
+    // An encoder offset rotates the whole field, including stationary echo
+    let rotated = altered(&encoded[0], start + Duration::minutes(6), 4.0)?;
+    let rotated = check_registration(&earlier, &rotated, &options)?;
+    assert!((rotated.azimuth_bias() - 4.0).abs() < 0.5);
+    assert_eq!(rotated.displacement(), (0.0, 0.0));
+    assert_eq!(rotated.anomalies(), [RegistrationAnomaly::AzimuthShift]);
+
+    // A clock error makes the same drift appear much faster
+    let clock = altered(&encoded[1], start + Duration::seconds(20), 0.0)?;
+    let clock = check_registration(&earlier, &clock, &options)?;
+    assert_eq!(clock.displacement(), normal.displacement());
+    assert_eq!(clock.anomalies(), [RegistrationAnomaly::ImplausibleMotion]);
+
+    let series = VolumeSeries::new(
+        vec![
+            DataFile::from_slice(&encoded[0])?,
+            altered(&encoded[0], start + Duration::minutes(6), 4.0)?,
+            altered(&encoded[0], start + Duration::minutes(40), 4.0)?,
+        ],
+        Duration::hours(1),
+    )?;
+    let checks = check_series(&series, &options);
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].later_time(), start + Duration::minutes(6));
+
+    Ok(())
+}
+
+#[test]
+fn csv_export() -> Result<()> {
+    use crate::csv::{encode_csv, CsvOptions};
+    use crate::error::Error;
+    use crate::records::VolumeHeaderRecord;
+    use crate::simulate::{SimulatedSite, Simulator, SimulatorConfig};
+    use crate::Sweep;
+
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 10.0)
+        .with_elevations(vec![0.5, 1.5])
+        .with_radials_per_sweep(90)
+        .with_gates(200)
+        .with_compression(false)
+        .with_seed(6);
+    let volume = Simulator::new(config).next().expect("is endless")?;
+    let hour = volume.time().format("%Y-%m-%d %H:").to_string();
+    let file = DataFile::from_vec(volume.into_data())?;
+
+    let strong = file
+        .elevation_scans()
+        .values()
+        .flatten()
+        .filter_map(Message31::reflectivity_data)
+        .flat_map(DataMoment::values)
+        .filter(|value| value.value().is_some_and(|value| value >= 10.0))
+        .count();
+    assert!(strong > 0);
+
+    let csv = String::from_utf8(encode_csv(&file, &CsvOptions::new())?)?;
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("time_utc,latitude,longitude,height_m,product,value")
+    );
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), strong);
+    for row in &rows {
+        assert_eq!(row.len(), 6);
+        assert!(row[0].starts_with(&hour));
+        let (lat, long): (f32, f32) = (row[1].parse()?, row[2].parse()?);
+        assert!((lat - 41.73).abs() < 1.0 && (long + 93.72).abs() < 1.5);
+        assert!(row[3].parse::<f32>()? >= 299.0);
+        assert_eq!(row[4], "ref");
+        assert!(row[5].parse::<f32>()? >= 10.0);
+    }
+
+    // Velocity has no threshold unless one is set, and decimation keeps every fourth gate of
+    // every third radial
+    let decimated = |moment: fn(&Message31) -> Option<&DataMoment>, min: f32| {
+        file.elevation_scans()
+            .values()
+            .flat_map(|radials| radials.iter().step_by(3))
+            .filter_map(moment)
+            .flat_map(|moment| moment.values().step_by(4))
+            .filter(|value| value.value().is_some_and(|value| value >= min))
+            .count()
+    };
+    let both = CsvOptions::new()
+        .with_products(vec![Product::Reflectivity, Product::Velocity])
+        .with_decimation(4, 3);
+    let both = String::from_utf8(encode_csv(&file, &both)?)?;
+    let count = |name: &str| both.lines().filter(|line| line.contains(name)).count();
+    assert_eq!(
+        count(",ref,"),
+        decimated(Message31::reflectivity_data, 10.0)
+    );
+    assert_eq!(
+        count(",vel,"),
+        decimated(Message31::velocity_data, f32::MIN)
+    );
+    assert!(count(",ref,") < strong && count(",vel,") > 0);
+
+    let thresholded = CsvOptions::new()
+        .with_products(vec![Product::Velocity])
+        .with_threshold(Product::Velocity, 100.0);
+    assert_eq!(encode_csv(&file, &thresholded)?.len(), 51);
+
+    // Gates cannot be located without the site's location
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 0, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, fine_line_sweep(100))])?;
+    let error = encode_csv(&file, &CsvOptions::new()).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::MissingSiteLocation)
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn sqlite_event_archive() -> Result<()> {
+    use crate::error::Error;
+    use crate::fine_line::detect_fine_lines;
+    use crate::records::{VolumeData, VolumeHeaderRecord};
+    use crate::registration::{check_registration, RegistrationOptions};
+    use crate::simulate::{SimulatedSite, SimulatedVolume, Simulator, SimulatorConfig};
+    use crate::sqlite::{EventArchive, SCHEMA_VERSION};
+    use crate::Sweep;
+
+    let mut archive = EventArchive::open_in_memory()?;
+
+    // A volume with a fine line, located at KDMX
+    let radials = fine_line_sweep(110)
+        .into_iter()
+        .map(|radial| radial.with_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215)))
+        .collect();
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 3_600_000, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, radials)])?;
+    let volume = archive.record_volume(&file)?;
+    assert_eq!(archive.record_volume(&file)?, volume);
+
+    let (site, time, sweeps, radials, max_reflectivity): (String, String, usize, usize, f32) =
+        archive.connection().query_row(
+            "SELECT site, time_utc, sweeps, radials, max_reflectivity FROM volumes",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
+        )?;
+    assert_eq!(site, "KTST");
+    assert_eq!(time, "2024-05-31 01:00:00.000");
+    assert_eq!((sweeps, radials), (1, 360));
+    assert!((max_reflectivity - 20.0).abs() < 0.5);
+
+    // Heights are the antenna's, from the site's height and the feedhorn's above it
+    let height: f32 =
+        archive
+            .connection()
+            .query_row("SELECT height_m FROM volumes", [], |row| row.get(0))?;
+    assert!((height - 319.0).abs() < f32::EPSILON);
+
+    let detection = detect_fine_lines(&file, &FineLineOptions::new()).expect("has a tilt");
+    assert_eq!(archive.record_fine_lines(volume, &detection)?, 1);
+    assert_eq!(archive.record_fine_lines(volume, &detection)?, 1);
+    let (count, lat, long): (usize, f32, f32) = archive.connection().query_row(
+        "SELECT count(*), latitude, longitude FROM fine_lines WHERE volume_id = ?1",
+        [volume],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    assert_eq!(count, 1);
+    // The line lies 30 km northeast of the radar
+    assert!(lat > 41.73 && lat < 42.0 && long > -93.72 && long < -93.4);
+
+    let error = archive
+        .record_fine_lines(volume + 1, &detection)
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::MissingArchivedVolume(_))
+    ));
+
+    // Registration checks of consecutive simulated volumes
+    let config = SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 10.0)
+        .with_elevations(vec![0.5])
+        .with_radials_per_sweep(180)
+        .with_gates(400)
+        .with_compression(false)
+        .with_seed(4);
+    let volumes: Vec<DataFile> = Simulator::new(config)
+        .take(2)
+        .map(|volume| DataFile::from_vec(volume.map(SimulatedVolume::into_data)?))
+        .collect::<Result<_>>()?;
+    let check = check_registration(&volumes[0], &volumes[1], &RegistrationOptions::new())?;
+    let id = archive.record_registration_check("KDMX", &check)?;
+    assert_eq!(archive.record_registration_check("KDMX", &check)?, id);
+    let (count, anomalous): (usize, bool) = archive.connection().query_row(
+        "SELECT count(*), max(azimuth_shift OR implausible_motion) FROM registration_checks
+        WHERE id = ?1",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!(count, 1);
+    assert_eq!(anomalous, check.is_anomalous());
+
+    // Archives from newer versions are refused
+    let path = std::env::temp_dir().join(format!("nexrad-archive-{}.db", std::process::id()));
+    EventArchive::open(&path)?;
+    assert!(EventArchive::open(&path).is_ok());
+    rusqlite::Connection::open(&path)?.pragma_update(None, "user_version", SCHEMA_VERSION + 1)?;
+    let error = EventArchive::open(&path).unwrap_err();
+    std::fs::remove_file(&path)?;
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::UnsupportedArchiveSchema(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn sqlite_cells_and_alerts() -> Result<()> {
+    use crate::error::Error;
+    use crate::records::{VolumeData, VolumeHeaderRecord};
+    use crate::sqlite::{EventArchive, SCHEMA_VERSION};
+    use crate::Sweep;
+
+    let path = std::env::temp_dir().join(format!("nexrad-cells-{}.db", std::process::id()));
+    let mut archive = EventArchive::open(&path)?;
+
+    let radials = fine_line_sweep(110)
+        .into_iter()
+        .map(|radial| radial.with_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215)))
+        .collect();
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 3_600_000, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, radials)])?;
+    let volume = archive.record_volume(&file)?;
+
+    let mut values = vec![Some(20.0); 40 * 40];
+    values[5 * 40 + 5] = Some(45.0);
+    let cells = identify_cells(
+        &Grid::new(40, 40, values),
+        &GridSpec::new(40, 40, 1000.0),
+        &SteinerOptions::new(),
+    );
+    assert_eq!(archive.record_cells(volume, &cells)?, 1);
+    assert_eq!(archive.record_cells(volume, &cells)?, 1);
+    let (count, lat, long): (usize, f32, f32) = archive.connection().query_row(
+        "SELECT count(*), latitude, longitude FROM cells WHERE volume_id = ?1",
+        [volume],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    assert_eq!(count, 1);
+    // The cell lies about 20 km northwest of the radar
+    assert!(lat > 41.73 && lat < 42.0 && long < -93.72 && long > -94.0);
+
+    let rule = AlertRule::new("line", Expression::parse("REF >= 20 && VEL > 0")?);
+    let evaluation = rule.evaluate(&file);
+    let id = archive.record_alert_evaluation(volume, &rule, &evaluation)?;
+    assert_eq!(
+        archive.record_alert_evaluation(volume, &rule, &evaluation)?,
+        id
+    );
+    let (matching, triggered): (usize, bool) = archive.connection().query_row(
+        "SELECT matching_gates, triggered FROM alert_evaluations WHERE id = ?1",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!((matching, triggered), (evaluation.matching_gates(), true));
+    let error = archive
+        .record_alert_evaluation(volume + 1, &rule, &evaluation)
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::MissingArchivedVolume(_))
+    ));
+
+    // Archives of the first version gain the cell and alert tables
+    drop(archive);
+    let connection = rusqlite::Connection::open(&path)?;
+    connection.execute_batch("DROP TABLE cells; DROP TABLE alert_evaluations;")?;
+    connection.pragma_update(None, "user_version", 1)?;
+    drop(connection);
+    let archive = EventArchive::open(&path)?;
+    let (version, tables): (u32, usize) = archive.connection().query_row(
+        "SELECT user_version, (SELECT count(*) FROM sqlite_master
+            WHERE name IN ('cells', 'alert_evaluations'))
+        FROM pragma_user_version",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!((version, tables), (SCHEMA_VERSION, 2));
+    drop(archive);
+
+    // Unrelated databases are refused and left untouched
+    let connection = rusqlite::Connection::open(&path)?;
+    connection.execute_batch("DROP TABLE cells; PRAGMA user_version = 0;")?;
+    drop(connection);
+    let error = EventArchive::open(&path).unwrap_err();
+    let tables: usize = rusqlite::Connection::open(&path)?.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE name = 'cells'",
+        [],
+        |row| row.get(0),
+    )?;
+    std::fs::remove_file(&path)?;
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::UnrecognizedArchive)
+    ));
+    assert_eq!(tables, 0);
+
+    Ok(())
+}
+
+#[test]
+fn cookbook_recipes() -> Result<()> {
+    use crate::cfradial::encode_cfradial;
+    use crate::cookbook::{max_reflectivity_near, render_lowest_tilt};
+    use crate::error::Error;
+    use crate::geometry::destination;
+    use crate::records::VolumeHeaderRecord;
+    use crate::Sweep;
+
+    let header = || VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 43_200_000, *b"KTST");
+    let mut radials = fine_line_sweep(110);
+    let rays = radials.len();
+    let without_site = DataFile::from_parts(header(), vec![Sweep::new(1, radials.clone())])?;
+    assert!(max_reflectivity_near(&without_site, 41.73, -93.72, 1000.0).is_none());
+    let error = encode_cfradial(&without_site).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref(),
+        Some(Error::MissingSiteLocation)
+    ));
+
+    radials[0].set_volume_data(VolumeData::new(41.73, -93.72, 299, 20, 215));
+    let file = DataFile::from_parts(header(), vec![Sweep::new(1, radials)])?;
+
+    // The 20 dBZ line lies about 30 km out between 30 and 60 degrees
+    let (lat, long) = destination(41.73, -93.72, 45.0, 29_600.0);
+    let strongest = max_reflectivity_near(&file, lat, long, 2000.0).expect("is near the line");
+    assert!((strongest - 20.0).abs() < 0.5);
+    let (lat, long) = destination(41.73, -93.72, 225.0, 29_600.0);
+    assert!(max_reflectivity_near(&file, lat, long, 2000.0).is_none());
+
+    let png = render_lowest_tilt(&file, Product::Velocity)?;
+    assert_eq!(&png[1..4], b"PNG");
+    assert!(render_lowest_tilt(&file, Product::SpectrumWidth).is_err());
+
+    // A classic netCDF file with 64-bit offsets whose first dimension, time, counts its rays
+    let netcdf = encode_cfradial(&file)?;
+    assert_eq!(&netcdf[..4], b"CDF\x02");
+    let time = u32::from_be_bytes(netcdf[24..28].try_into()?);
+    assert_eq!(usize::try_from(time)?, rays);
+    let contains = |text: &[u8]| netcdf.windows(text.len()).any(|window| window == text);
+    assert!(contains(b"CF/Radial") && contains(b"DBZ") && contains(b"VEL"));
+    assert!(!contains(b"WIDTH"));
+    assert_eq!(netcdf.len() % 4, 0);
+
+    // Site identifiers are stripped of trailing padding
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 43_200_000, *b"KT\0\0");
+    let sweeps = file.elevation_scans().values().cloned();
+    let padded = DataFile::from_parts(header, sweeps.map(|sweep| Sweep::new(1, sweep)).collect())?;
+    let netcdf = encode_cfradial(&padded)?;
+    let instrument = b"instrument_name\0\0\0\0\x02\0\0\0\x02KT\0\0";
+    assert!(netcdf
+        .windows(instrument.len())
+        .any(|window| window == instrument));
+
+    Ok(())
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_module_paths() {
+    let product: crate::model::Product = Product::Reflectivity;
+    assert_eq!(product, Product::Reflectivity);
+    assert!(!crate::file_metadata::is_compressed(&[]));
+    assert!(crate::decompress::decompress_file(&[]).is_err());
+
+    let file: Option<crate::decode::DataFile> = None;
+    assert!(file.is_none());
+}
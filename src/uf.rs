@@ -0,0 +1,598 @@
+//!
+//! Provides [``read_uf``] and [``encode_uf``] for importing and exporting Universal Format (UF),
+//! the legacy radar interchange format still read and written by much classic radar software.
+//!
+//! A UF file is a sequence of records, one per ray, of big-endian 16-bit words: a mandatory header
+//! describing the site, time, and pointing angles, then a data header listing the ray's fields,
+//! each with its own field header and data. Records are usually wrapped in the 4-byte length
+//! markers of Fortran unformatted files.
+//!
+
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+use crate::decoder::DataFile;
+use crate::error::Error;
+use crate::gate::GateValue;
+use crate::records::{
+    to_archive_date_time, DataBlockProduct, DataMoment, ElevationData, GenericData, Message31,
+    Message31Header, RadialData, VolumeData, VolumeHeaderRecord,
+};
+use crate::sweep::Sweep;
+
+/// The size of the mandatory header in words.
+const MANDATORY_HEADER_WORDS: usize = 45;
+
+/// The size of a field header in words, and of a velocity field header which adds the Nyquist
+/// velocity and a spare word.
+const FIELD_HEADER_WORDS: usize = 19;
+const VELOCITY_FIELD_HEADER_WORDS: usize = 21;
+
+/// The value of gates with no data unless a record specifies otherwise.
+const MISSING_VALUE: i16 = -32768;
+
+/// A moment's UF field: the name and scale factor it is exported with, and the names it is
+/// imported from.
+struct Field {
+    product: DataBlockProduct,
+    name: &'static [u8; 2],
+    aliases: &'static [&'static [u8; 2]],
+    scale_factor: i16,
+}
+
+const FIELDS: [Field; 6] = [
+    Field {
+        product: DataBlockProduct::Reflectivity,
+        name: b"DZ",
+        aliases: &[b"DZ", b"CZ"],
+        scale_factor: 100,
+    },
+    Field {
+        product: DataBlockProduct::Velocity,
+        name: b"VR",
+        aliases: &[b"VR", b"VE", b"VT"],
+        scale_factor: 100,
+    },
+    Field {
+        product: DataBlockProduct::SpectrumWidth,
+        name: b"SW",
+        aliases: &[b"SW"],
+        scale_factor: 100,
+    },
+    Field {
+        product: DataBlockProduct::DifferentialReflectivity,
+        name: b"ZD",
+        aliases: &[b"ZD", b"DR"],
+        scale_factor: 100,
+    },
+    Field {
+        product: DataBlockProduct::DifferentialPhase,
+        name: b"PH",
+        aliases: &[b"PH"],
+        scale_factor: 50,
+    },
+    Field {
+        product: DataBlockProduct::CorrelationCoefficient,
+        name: b"RH",
+        aliases: &[b"RH"],
+        scale_factor: 10000,
+    },
+];
+
+/// Loads a Universal Format file from a file path.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is not a valid UF file.
+pub fn read_uf_file(path: &Path) -> Result<DataFile> {
+    read_uf(&std::fs::read(path)?)
+}
+
+/// Imports Universal Format data, with or without Fortran record markers. Rays are grouped into
+/// sweeps by their sweep number, numbered in the order they appear, and each field this crate
+/// models becomes a moment stored in 16-bit words with the field's own scale factor, so values
+/// are kept exactly. Other fields are skipped.
+///
+/// # Errors
+/// Returns an error if the data is not UF or a record is truncated or inconsistent.
+pub fn read_uf(data: &[u8]) -> Result<DataFile> {
+    let mut rays: Vec<Ray> = Vec::new();
+
+    let mut remaining = data;
+    while !remaining.iter().all(|byte| *byte == 0) {
+        let record = next_record(&mut remaining)?;
+        rays.push(read_ray(record)?);
+    }
+
+    let first = rays
+        .first()
+        .ok_or(Error::InvalidUfFile("no records"))?
+        .clone();
+
+    // Group rays by sweep, in the order each sweep first appears
+    let mut sweep_rays: Vec<(i16, Vec<Ray>)> = Vec::new();
+    for ray in rays {
+        match sweep_rays.last_mut() {
+            Some((sweep_number, rays)) if *sweep_number == ray.sweep_number => rays.push(ray),
+            _ => sweep_rays.push((ray.sweep_number, vec![ray])),
+        }
+    }
+
+    let mut sweeps = Vec::with_capacity(sweep_rays.len());
+    for (index, (_, rays)) in sweep_rays.into_iter().enumerate() {
+        let elevation_number = u8::try_from(index + 1)?;
+        sweeps.push(read_sweep(rays, elevation_number)?);
+    }
+
+    let (volume_date, volume_millis) = to_archive_date_time(first.time).ok_or(
+        Error::InvalidUfFile("volume time is outside the Archive II date range"),
+    )?;
+    let header = VolumeHeaderRecord::new(
+        *b"UF          ",
+        volume_date.into(),
+        volume_millis,
+        first.radar_id,
+    );
+
+    DataFile::from_parts(header, sweeps)
+}
+
+/// Exports a data file as Universal Format: a record for each radial in elevation order, wrapped
+/// in Fortran record markers. Every moment with a UF field is exported; gates below threshold or
+/// range folded, which UF does not distinguish, are written as missing data.
+///
+/// # Errors
+/// Returns an error if a radial is too large to encode.
+pub fn encode_uf(file: &DataFile) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    let mut ray_number = 0;
+    for (elevation_number, radials) in file.elevation_scans() {
+        let fixed_angle = radials.first().map_or(0.0, |radial| radial.header().elev());
+
+        for radial in radials {
+            ray_number += 1;
+            let record = encode_ray(radial, ray_number, *elevation_number, fixed_angle)?;
+
+            let length = u32::try_from(record.len())?.to_be_bytes();
+            data.extend_from_slice(&length);
+            data.extend(record);
+            data.extend_from_slice(&length);
+        }
+    }
+
+    Ok(data)
+}
+
+/// A ray read from a UF record.
+#[derive(Clone)]
+struct Ray {
+    radar_id: [u8; 4],
+    sweep_number: i16,
+    time: NaiveDateTime,
+    azimuth: f32,
+    elevation: f32,
+    volume_data: VolumeData,
+    nyquist_velocity: Option<f32>,
+    moments: Vec<DataMoment>,
+}
+
+/// Takes the next record from the data, skipping its Fortran record markers if it has them.
+fn next_record<'a>(remaining: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let data = *remaining;
+
+    if data.starts_with(b"UF") {
+        let length = usize::from(read_u16(data, 1)?) * 2;
+        let record = data
+            .get(..length)
+            .ok_or(Error::InvalidUfFile("truncated record"))?;
+        *remaining = &data[length..];
+        return Ok(record);
+    }
+
+    if data.get(4..6) == Some(b"UF") {
+        let length = usize::try_from(u32::from_be_bytes(read_bytes(data, 0)?))?;
+        let record = data
+            .get(4..4 + length)
+            .ok_or(Error::InvalidUfFile("truncated record"))?;
+        *remaining = data.get(8 + length..).unwrap_or_default();
+        return Ok(record);
+    }
+
+    Err(Error::InvalidUfFile("missing record").into())
+}
+
+/// Reads a ray from a record. Word positions within a record are numbered from 1.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn read_ray(record: &[u8]) -> Result<Ray> {
+    let word = |position: usize| read_word(record, position);
+    let pointer = |position: usize| read_pointer(record, position);
+
+    let mut radar_id = *b"    ";
+    let name = record
+        .get(20..28)
+        .ok_or(Error::InvalidUfFile("truncated header"))?;
+    for (target, character) in radar_id.iter_mut().zip(
+        name.iter()
+            .filter(|character| character.is_ascii_alphanumeric()),
+    ) {
+        *target = character.to_ascii_uppercase();
+    }
+
+    let angle = |position: usize| -> Result<f64> {
+        let degrees = f64::from(word(position)?);
+        let minutes = f64::from(word(position + 1)?);
+        let seconds = f64::from(word(position + 2)?) / 64.0;
+        Ok(degrees + minutes / 60.0 + seconds / 3600.0)
+    };
+
+    let missing = word(MANDATORY_HEADER_WORDS)?;
+    let mut ray = Ray {
+        radar_id,
+        sweep_number: word(10)?,
+        time: read_time(record)?,
+        azimuth: f32::from(word(33)?) / 64.0,
+        elevation: f32::from(word(34)?) / 64.0,
+        volume_data: VolumeData::new(angle(19)? as f32, angle(22)? as f32, word(25)?, 0, 0),
+        nyquist_velocity: None,
+        moments: Vec::new(),
+    };
+
+    let data_header = pointer(5)?;
+    let fields = count(word(data_header + 2)?)?;
+    for index in 0..fields {
+        let position = data_header + 3 + index * 2;
+        let name = word(position)?.to_be_bytes();
+        let field_header = pointer(position + 1)?;
+
+        let Some(Field { product, .. }) =
+            FIELDS.iter().find(|field| field.aliases.contains(&&name))
+        else {
+            continue;
+        };
+
+        let scale_factor = word(field_header + 1)?;
+        if scale_factor <= 0 {
+            return Err(Error::InvalidUfFile("invalid scale factor").into());
+        }
+
+        if *product == DataBlockProduct::Velocity {
+            ray.nyquist_velocity =
+                Some(f32::from(word(field_header + 19)?) / f32::from(scale_factor));
+        }
+
+        let data = pointer(field_header)?;
+        let bins = count(word(field_header + 5)?)?;
+        let mut moment_data = Vec::with_capacity(bins * 2);
+        for bin in 0..bins {
+            // Missing values are below threshold, which other values are offset clear of
+            let value = word(data + bin)?;
+            let raw = if value == missing {
+                0
+            } else {
+                (i32::from(value) + 32768).max(2) as u16
+            };
+            moment_data.extend_from_slice(&raw.to_be_bytes());
+        }
+
+        let range = i32::from(word(field_header + 2)?) * 1000 + i32::from(word(field_header + 3)?);
+        let generic_data = GenericData::new(
+            product,
+            u16::try_from(bins)?,
+            u16::try_from(range)?,
+            u16::try_from(word(field_header + 4)?)?,
+            16,
+            f32::from(scale_factor),
+            32768.0,
+        );
+
+        ray.moments
+            .push(DataMoment::new(product.clone(), generic_data, moment_data));
+    }
+
+    Ok(ray)
+}
+
+/// Builds a sweep from its rays, which are kept in the order they were recorded.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn read_sweep(rays: Vec<Ray>, elevation_number: u8) -> Result<Sweep> {
+    let count = rays.len();
+
+    // Sweeps of well over 360 rays, taken as more than 400, are at half-degree super resolution
+    let azimuth_resolution = if count > 400 { 1 } else { 2 };
+
+    let radials = rays
+        .into_iter()
+        .enumerate()
+        .map(|(index, ray)| {
+            let (ray_date, ray_millis) = to_archive_date_time(ray.time).ok_or(
+                Error::InvalidUfFile("ray time is outside the Archive II date range"),
+            )?;
+
+            let mut radial = Message31::new(Message31Header::new(
+                ray.radar_id,
+                ray_millis,
+                ray_date,
+                u16::try_from(index + 1).unwrap_or(u16::MAX),
+                ray.azimuth.rem_euclid(360.0),
+                azimuth_resolution,
+                radial_status(index, count),
+                elevation_number,
+                ray.elevation,
+            ));
+
+            radial.set_volume_data(ray.volume_data);
+            radial.set_elevation_data(ElevationData::new([0, 0], 0.0));
+            radial.set_radial_data(RadialData::new(
+                0,
+                (ray.nyquist_velocity.unwrap_or_default() * 100.0) as u16,
+            ));
+            for moment in ray.moments {
+                radial.set_data_moment(moment);
+            }
+
+            Ok(radial)
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Sweep::new(elevation_number, radials))
+}
+
+/// Encodes a radial as a UF record with no optional or local use headers.
+fn encode_ray(
+    radial: &Message31,
+    ray_number: usize,
+    elevation_number: u8,
+    fixed_angle: f32,
+) -> Result<Vec<u8>> {
+    let moments: Vec<_> = FIELDS
+        .iter()
+        .filter_map(|field| {
+            radial
+                .get_data_moment(&field.product)
+                .map(|moment| (moment, field))
+        })
+        .collect();
+
+    // The data header follows the mandatory header, then each field's header and data in turn
+    let data_header = MANDATORY_HEADER_WORDS + 1;
+    let mut position = data_header + 3 + moments.len() * 2;
+    let mut field_positions = Vec::with_capacity(moments.len());
+    for (moment, _) in &moments {
+        field_positions.push(position);
+        position += field_header_words(moment.product()) + moment.gate_count();
+    }
+    let record_words = position - 1;
+
+    let mut words = Vec::with_capacity(record_words);
+    words.extend(encode_mandatory_header(
+        radial,
+        [record_words, data_header, ray_number],
+        elevation_number,
+        fixed_angle,
+    )?);
+
+    words.extend([
+        i16::try_from(moments.len())?,
+        1,
+        i16::try_from(moments.len())?,
+    ]);
+    for ((_, field), field_position) in moments.iter().zip(&field_positions) {
+        words.extend([
+            i16::from_be_bytes(*field.name),
+            i16::try_from(*field_position)?,
+        ]);
+    }
+
+    let nyquist_velocity = radial
+        .radial_data()
+        .map_or(0.0, |data| f32::from(data.nyquist_velocity()) / 100.0);
+    for ((moment, field), field_position) in moments.iter().zip(&field_positions) {
+        let data = moment.data();
+        let header_words = field_header_words(moment.product());
+        let blank = i16::from_be_bytes(*b"  ");
+
+        // Pulse, beam, and polarization parameters are unknown and left as zero
+        words.extend([
+            i16::try_from(field_position + header_words)?,
+            field.scale_factor,
+            i16::try_from(data.data_moment_range() / 1000)?,
+            i16::try_from(data.data_moment_range() % 1000)?,
+            i16::try_from(data.data_moment_range_sample_interval())?,
+            i16::try_from(moment.gate_count())?,
+        ]);
+        words.extend([0; 7]);
+        words.extend([blank, MISSING_VALUE, 0, blank, 0, 16]);
+        if header_words == VELOCITY_FIELD_HEADER_WORDS {
+            words.extend([scale(nyquist_velocity, field.scale_factor), blank]);
+        }
+
+        words.extend(
+            (0..moment.gate_count()).map(|index| match moment.value(index) {
+                Some(GateValue::Value(value)) => scale(value, field.scale_factor),
+                _ => MISSING_VALUE,
+            }),
+        );
+    }
+
+    Ok(words.into_iter().flat_map(i16::to_be_bytes).collect())
+}
+
+/// Encodes a radial's mandatory header given the record's length, the position of its data
+/// header, and the ray's number within the volume.
+#[allow(clippy::cast_possible_truncation)]
+fn encode_mandatory_header(
+    radial: &Message31,
+    [record_words, data_header, ray_number]: [usize; 3],
+    elevation_number: u8,
+    fixed_angle: f32,
+) -> Result<Vec<i16>> {
+    let header = radial.header();
+    let time = header.date_time().unwrap_or_default();
+    let angle = |degrees: f32| (degrees * 64.0).round() as i16;
+    let text = |text: &[u8]| -> Vec<i16> {
+        text.chunks(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+            .collect()
+    };
+
+    // Years are written with two digits
+    let year = i16::try_from(time.year() % 100)?;
+    let month = i16::try_from(time.month())?;
+    let day = i16::try_from(time.day())?;
+
+    let ray_number = i16::try_from(ray_number % 32767)?;
+    let data_header = i16::try_from(data_header)?;
+    let mut words = vec![
+        i16::from_be_bytes(*b"UF"),
+        i16::try_from(record_words)?,
+        data_header,
+        data_header,
+        data_header,
+        ray_number,
+        1,
+        ray_number,
+        1,
+        i16::from(elevation_number),
+    ];
+
+    // The radar and site names are both the radar identifier
+    let mut name = *b"        ";
+    name[..4].copy_from_slice(header.radar_id());
+    words.extend(text(&name));
+    words.extend(text(&name));
+
+    let volume_data = radial.volume_data();
+    let (latitude, longitude) = volume_data.map_or((0.0, 0.0), |data| {
+        (f64::from(data.lat()), f64::from(data.long()))
+    });
+    words.extend(to_degrees_minutes_seconds(latitude));
+    words.extend(to_degrees_minutes_seconds(longitude));
+    words.push(volume_data.map_or(0, |data| data.antenna_altitude_m().round() as i16));
+
+    words.extend([
+        year,
+        month,
+        day,
+        i16::try_from(time.hour())?,
+        i16::try_from(time.minute())?,
+        i16::try_from(time.second())?,
+    ]);
+    words.extend(text(b"UT"));
+
+    // Azimuth, elevation, PPI sweep mode, fixed angle, and sweep rate
+    words.extend([
+        angle(header.azm()),
+        angle(header.elev()),
+        1,
+        angle(fixed_angle),
+        0,
+    ]);
+
+    // Generation date and facility, and the missing data value
+    words.extend([year, month, day]);
+    words.extend(text(b"NEXRAD  "));
+    words.push(MISSING_VALUE);
+
+    Ok(words)
+}
+
+/// The size in words of a product's field header.
+fn field_header_words(product: &DataBlockProduct) -> usize {
+    if *product == DataBlockProduct::Velocity {
+        VELOCITY_FIELD_HEADER_WORDS
+    } else {
+        FIELD_HEADER_WORDS
+    }
+}
+
+/// Scales a value into a word, clamped clear of the missing value.
+#[allow(clippy::cast_possible_truncation)]
+fn scale(value: f32, scale_factor: i16) -> i16 {
+    (value * f32::from(scale_factor))
+        .round()
+        .clamp(-32767.0, 32767.0) as i16
+}
+
+/// Splits an angle into whole degrees, whole minutes, and 64ths of a second, each carrying the
+/// angle's sign.
+#[allow(clippy::cast_possible_truncation)]
+fn to_degrees_minutes_seconds(angle: f64) -> [i16; 3] {
+    let sixty_fourths = (angle.abs() * 3600.0 * 64.0).round() as i64;
+    let sign = if angle < 0.0 { -1 } else { 1 };
+
+    [
+        sixty_fourths / (3600 * 64),
+        sixty_fourths / (60 * 64) % 60,
+        sixty_fourths % (60 * 64),
+    ]
+    .map(|part| (sign * part) as i16)
+}
+
+/// The radial status of a ray: 0 for the first in a sweep, 2 for the last, and 1 otherwise.
+fn radial_status(index: usize, rays: usize) -> u8 {
+    match index {
+        0 => 0,
+        _ if index + 1 == rays => 2,
+        _ => 1,
+    }
+}
+
+/// Reads a ray's time from the mandatory header, where years may be given with two digits.
+fn read_time(record: &[u8]) -> Result<NaiveDateTime> {
+    let word = |position: usize| read_word(record, position);
+
+    let mut year = i32::from(word(26)?);
+    if year < 70 {
+        year += 2000;
+    } else if year < 100 {
+        year += 1900;
+    }
+
+    let mut parts = [0; 5];
+    for (part, position) in parts.iter_mut().zip(27..) {
+        *part = u32::try_from(word(position)?)?;
+    }
+
+    let [month, day, hour, minute, second] = parts;
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(hour, minute, second))
+        .ok_or_else(|| Error::InvalidUfFile("invalid time").into())
+}
+
+/// Reads the word at a position within a record, numbered from 1.
+fn read_word(record: &[u8], position: usize) -> Result<i16> {
+    let word = position
+        .checked_sub(1)
+        .ok_or(Error::InvalidUfFile("invalid pointer"))?;
+    read_i16(record, word)
+}
+
+/// Reads a pointer to a position within a record, which must be numbered from 1 and lie within
+/// the record.
+fn read_pointer(record: &[u8], position: usize) -> Result<usize> {
+    usize::try_from(read_word(record, position)?)
+        .ok()
+        .filter(|pointer| (1..=record.len() / 2).contains(pointer))
+        .ok_or_else(|| Error::InvalidUfFile("invalid pointer").into())
+}
+
+/// Converts a count of fields or bins, which must not be negative.
+fn count(word: i16) -> Result<usize> {
+    usize::try_from(word).map_err(|_| Error::InvalidUfFile("invalid count").into())
+}
+
+fn read_u16(data: &[u8], word: usize) -> Result<u16> {
+    Ok(u16::from_be_bytes(read_bytes(data, word * 2)?))
+}
+
+fn read_i16(data: &[u8], word: usize) -> Result<i16> {
+    Ok(i16::from_be_bytes(read_bytes(data, word * 2)?))
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    data.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidUfFile("truncated record").into())
+}
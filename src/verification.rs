@@ -0,0 +1,173 @@
+//!
+//! Provides [``ContingencyTable``] for scoring gridded fields such as nowcasts or precipitation
+//! estimates against thresholded reference fields, and [``FieldSource``] for supplying external
+//! gridded truth or forecast fields, e.g. from numerical models or gauge analyses.
+//!
+
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+
+use crate::error::Error;
+use crate::grid::{Grid, GridSpec};
+
+/// A source of external gridded fields, e.g. model forecasts or gauge analyses, interpolated onto
+/// a radar-centered grid.
+pub trait FieldSource {
+    /// The field valid at the specified time on the specified grid, or `None` if unavailable.
+    ///
+    /// # Errors
+    /// Returns an error if the field exists but cannot be provided.
+    fn field(
+        &self,
+        valid_time: NaiveDateTime,
+        spec: &GridSpec,
+    ) -> Result<Option<Grid<Option<f32>>>>;
+}
+
+/// Fields already on a common grid, keyed by valid time.
+impl FieldSource for BTreeMap<NaiveDateTime, Grid<Option<f32>>> {
+    fn field(
+        &self,
+        valid_time: NaiveDateTime,
+        spec: &GridSpec,
+    ) -> Result<Option<Grid<Option<f32>>>> {
+        let Some(field) = self.get(&valid_time) else {
+            return Ok(None);
+        };
+
+        if field.columns() != spec.columns() || field.rows() != spec.rows() {
+            return Err(Error::GridMismatch.into());
+        }
+
+        Ok(Some(field.clone()))
+    }
+}
+
+/// Counts of forecast and observed threshold exceedance across grid cells, from which categorical
+/// verification scores are computed. Cells without a value are treated as not exceeding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContingencyTable {
+    hits: u64,
+    misses: u64,
+    false_alarms: u64,
+    correct_negatives: u64,
+}
+
+impl ContingencyTable {
+    /// Compares a forecast field to an observed field cell by cell, counting where each meets or
+    /// exceeds the threshold.
+    ///
+    /// # Errors
+    /// Returns an error if the grids' dimensions differ.
+    pub fn from_grids(
+        forecast: &Grid<Option<f32>>,
+        observed: &Grid<Option<f32>>,
+        threshold: f32,
+    ) -> Result<Self> {
+        if forecast.columns() != observed.columns() || forecast.rows() != observed.rows() {
+            return Err(Error::GridMismatch.into());
+        }
+
+        let exceeds = |value: &Option<f32>| value.is_some_and(|value| value >= threshold);
+
+        let mut table = Self::default();
+        for (forecast, observed) in forecast.values().iter().zip(observed.values()) {
+            match (exceeds(forecast), exceeds(observed)) {
+                (true, true) => table.hits += 1,
+                (false, true) => table.misses += 1,
+                (true, false) => table.false_alarms += 1,
+                (false, false) => table.correct_negatives += 1,
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Cells where both the forecast and observation exceeded the threshold.
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Cells where only the observation exceeded the threshold.
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Cells where only the forecast exceeded the threshold.
+    #[must_use]
+    pub fn false_alarms(&self) -> u64 {
+        self.false_alarms
+    }
+
+    /// Cells where neither exceeded the threshold.
+    #[must_use]
+    pub fn correct_negatives(&self) -> u64 {
+        self.correct_negatives
+    }
+
+    /// Probability of detection: the fraction of observed events which were forecast.
+    #[must_use]
+    pub fn pod(&self) -> Option<f32> {
+        ratio(self.hits, self.hits + self.misses)
+    }
+
+    /// False alarm ratio: the fraction of forecast events which were not observed.
+    #[must_use]
+    pub fn far(&self) -> Option<f32> {
+        ratio(self.false_alarms, self.hits + self.false_alarms)
+    }
+
+    /// Critical success index (threat score): hits over all cells where either exceeded.
+    #[must_use]
+    pub fn csi(&self) -> Option<f32> {
+        ratio(self.hits, self.hits + self.misses + self.false_alarms)
+    }
+
+    /// Frequency bias: how many more or fewer events were forecast than observed.
+    #[must_use]
+    pub fn bias(&self) -> Option<f32> {
+        ratio(self.hits + self.false_alarms, self.hits + self.misses)
+    }
+}
+
+impl AddAssign for ContingencyTable {
+    fn add_assign(&mut self, other: Self) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.false_alarms += other.false_alarms;
+        self.correct_negatives += other.correct_negatives;
+    }
+}
+
+/// Scores a radar-derived field against the source's field valid at the same time. Returns `None`
+/// if the source has no field for that time.
+///
+/// # Errors
+/// Returns an error if the source fails or its field's dimensions differ from the radar field's.
+pub fn verify_against<S: FieldSource + ?Sized>(
+    source: &S,
+    valid_time: NaiveDateTime,
+    spec: &GridSpec,
+    radar_field: &Grid<Option<f32>>,
+    threshold: f32,
+) -> Result<Option<ContingencyTable>> {
+    let Some(reference) = source.field(valid_time, spec)? else {
+        return Ok(None);
+    };
+
+    ContingencyTable::from_grids(radar_field, &reference, threshold).map(Some)
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn ratio(numerator: u64, denominator: u64) -> Option<f32> {
+    if denominator == 0 {
+        return None;
+    }
+
+    Some(numerator as f32 / denominator as f32)
+}
@@ -0,0 +1,217 @@
+//! tests/examples
+//!
+//! Runs each example's workflow against small simulated fixtures, so the examples stay working
+//! without network access or large data files. The examples are compiled in as modules and their
+//! workflow functions called in place of their command-line entry points.
+//!
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use nexrad::high_level::{SimulatedSite, Simulator, SimulatorConfig};
+use nexrad::Product;
+
+#[allow(dead_code)]
+#[path = "../examples/decode.rs"]
+mod decode_example;
+
+#[allow(dead_code)]
+#[path = "../examples/render.rs"]
+mod render_example;
+
+#[allow(dead_code)]
+#[path = "../examples/pipeline.rs"]
+mod pipeline_example;
+
+#[cfg(feature = "download")]
+#[allow(dead_code)]
+#[path = "../examples/download.rs"]
+mod download_example;
+
+#[cfg(feature = "download")]
+#[allow(dead_code)]
+#[path = "../examples/ingest.rs"]
+mod ingest_example;
+
+/// A temporary directory for a test's fixtures, removed when dropped.
+struct Fixtures(PathBuf);
+
+impl Fixtures {
+    /// Creates a directory holding simulated KDMX volumes six minutes apart, compressed and laid
+    /// out like the archive bucket, returning it with the volumes' start times.
+    fn new(name: &str, count: usize) -> Result<(Self, Vec<NaiveDateTime>)> {
+        let root = std::env::temp_dir().join(format!("nexrad_{name}_{}", std::process::id()));
+        let fixtures = Self(root);
+
+        let config =
+            SimulatorConfig::new(vec![SimulatedSite::new("KDMX", 41.73, -93.72, 299)], 6.0)
+                .with_elevations(vec![0.5, 1.5])
+                .with_radials_per_sweep(90)
+                .with_gates(60)
+                .with_seed(3);
+
+        let mut times = Vec::new();
+        for volume in Simulator::new(config).take(count) {
+            let volume = volume?;
+            let directory = fixtures
+                .0
+                .join(volume.time().format("%Y/%m/%d/KDMX").to_string());
+            fs::create_dir_all(&directory)?;
+
+            let identifier = volume.time().format("KDMX%Y%m%d_%H%M%S_V06").to_string();
+            times.push(volume.time());
+            fs::write(directory.join(identifier), volume.into_data())?;
+        }
+
+        Ok((fixtures, times))
+    }
+
+    /// The path of the volume which began at the specified time.
+    fn volume_path(&self, time: NaiveDateTime) -> PathBuf {
+        self.0.join(
+            time.format("%Y/%m/%d/KDMX/KDMX%Y%m%d_%H%M%S_V06")
+                .to_string(),
+        )
+    }
+}
+
+impl Drop for Fixtures {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// examples/decode
+#[test]
+fn decode() -> Result<()> {
+    let (fixtures, times) = Fixtures::new("example_decode", 1)?;
+
+    let file = decode_example::decode(&fixtures.volume_path(times[0]))?;
+    assert_eq!(file.elevation_scans().len(), 2);
+    assert_eq!(file.volume_header().date_time(), Some(times[0]));
+
+    Ok(())
+}
+
+/// examples/render
+#[test]
+fn render() -> Result<()> {
+    let (fixtures, times) = Fixtures::new("example_render", 1)?;
+    let volume = fixtures.volume_path(times[0]);
+
+    for (name, elevation_index) in [("ref", 0), ("vel", 1), ("sw", 0)] {
+        let product: Product = name.parse()?;
+        let path = render_example::render(&volume, product, elevation_index, 64, &fixtures.0)?;
+        assert_eq!(
+            path.file_name().and_then(|name| name.to_str()),
+            Some(
+                format!("render_{product:?}_{elevation_index}.png")
+                    .to_lowercase()
+                    .as_str()
+            )
+        );
+        assert!(fs::read(&path)?.starts_with(b"\x89PNG"));
+    }
+    assert!(render_example::render(&volume, Product::Reflectivity, 2, 64, &fixtures.0).is_err());
+
+    Ok(())
+}
+
+/// examples/pipeline
+#[test]
+fn pipeline() -> Result<()> {
+    let (fixtures, times) = Fixtures::new("example_pipeline", 2)?;
+    let directory = fixtures.volume_path(times[0]);
+    let directory = directory.parent().expect("is in a directory");
+
+    let config = fixtures.0.join("pipeline.toml");
+    fs::write(
+        &config,
+        format!(
+            r#"
+            exports = ["uf"]
+
+            [manifest]
+            sites = ["KDMX"]
+            times = ["{}", "{}"]
+            products = ["reflectivity"]
+            output_template = "{}/out/{{site}}_{{time}}_{{product}}.png"
+            options = {{ size = 32 }}
+            "#,
+            times[0].format("%Y-%m-%dT%H:%M:%S"),
+            times[1].format("%Y-%m-%dT%H:%M:%S"),
+            fixtures.0.display()
+        ),
+    )?;
+
+    let written = pipeline_example::run(&config, directory)?;
+    assert_eq!(written.len(), 4);
+    assert!(written.iter().all(|path| path.is_file()));
+
+    Ok(())
+}
+
+/// examples/download, against a local cache rather than AWS.
+#[cfg(feature = "download")]
+#[tokio::test]
+async fn download() -> Result<()> {
+    use nexrad::high_level::{FailoverDownloader, VolumeSource};
+    use nexrad::raw::is_compressed;
+
+    let (fixtures, times) = Fixtures::new("example_download", 3)?;
+    let downloader = FailoverDownloader::new(vec![VolumeSource::LocalCache(fixtures.0.clone())]);
+
+    // The file nearest a time between the second and third volumes is the second
+    let requested = times[1] + chrono::Duration::minutes(2);
+    let path = download_example::download_nearest(&downloader, "KDMX", requested, &fixtures.0)
+        .await?
+        .expect("has files");
+    assert_eq!(path.file_name(), fixtures.volume_path(times[1]).file_name());
+    assert!(is_compressed(&fs::read(&path)?));
+    assert_eq!(downloader.health()[0].successes(), 2);
+
+    let elsewhere = requested + chrono::Duration::days(3);
+    let missing =
+        download_example::download_nearest(&downloader, "KDMX", elsewhere, &fixtures.0).await?;
+    assert!(missing.is_none());
+
+    Ok(())
+}
+
+/// examples/ingest, against a local cache rather than AWS.
+#[cfg(feature = "download")]
+#[tokio::test]
+async fn ingest() -> Result<()> {
+    use nexrad::high_level::read_uf;
+    use nexrad::high_level::{FailoverDownloader, VolumeSource};
+    use nexrad::DataFile;
+
+    let (fixtures, times) = Fixtures::new("example_ingest", 1)?;
+    let downloader = FailoverDownloader::new(vec![
+        VolumeSource::LocalCache(fixtures.0.join("missing")),
+        VolumeSource::LocalCache(fixtures.0.clone()),
+    ]);
+
+    let output = fixtures.0.join("output");
+    let written = ingest_example::ingest(&downloader, "KDMX", times[0], &output).await?;
+    assert_eq!(downloader.health()[0].misses(), 2);
+    assert_eq!(downloader.health()[0].failures(), 0);
+    assert_eq!(written.len(), 3);
+
+    let file = DataFile::new(&fixtures.volume_path(times[0]))?;
+    let grid = fs::read(&written[0])?;
+    assert_eq!(grid.len(), 460 * 460 * 4);
+    assert!(grid
+        .chunks_exact(4)
+        .any(|value| !f32::from_le_bytes(value.try_into().expect("4 bytes")).is_nan()));
+
+    let uf = read_uf(&fs::read(&written[1])?)?;
+    assert_eq!(uf.elevation_scans().len(), file.elevation_scans().len());
+
+    let archive = DataFile::new(&written[2])?;
+    assert_eq!(archive.sweep_hashes(), file.sweep_hashes());
+
+    Ok(())
+}
@@ -0,0 +1,250 @@
+//! tests/properties
+//!
+//! Property-based tests of the scale and offset arithmetic between raw data words and gate values,
+//! moment sizing, 8- and 16-bit word handling, and moments' round trips through the encoder,
+//! including the edges real volumes rarely reach: zero scales, extreme offsets, and gate counts
+//! at the limits of a radial's size.
+//!
+
+use nexrad::high_level::encode_file;
+use nexrad::raw::{
+    DataBlockProduct, DataMoment, GenericData, Message31, Message31Header, VolumeHeaderRecord,
+};
+use nexrad::{DataFile, GateValue, Sweep};
+use proptest::prelude::*;
+
+/// The most gates a legacy or super-resolution moment has in practice.
+const MAX_GATES: u16 = 1840;
+
+/// A data word size the format defines.
+fn word_size() -> impl Strategy<Value = u8> {
+    prop_oneof![Just(8), Just(16)]
+}
+
+/// Scales from coarse to finer than any product uses.
+fn scale() -> impl Strategy<Value = f32> {
+    0.01f32..=1000.0
+}
+
+/// Offsets beyond either end of the largest word.
+fn offset() -> impl Strategy<Value = f32> {
+    -70_000f32..=70_000.0
+}
+
+/// The largest raw word of a word size.
+fn max_word(word_size: u8) -> u16 {
+    if word_size == 8 {
+        u16::from(u8::MAX)
+    } else {
+        u16::MAX
+    }
+}
+
+/// A moment with the specified raw words and scaling.
+fn moment(word_size: u8, scale: f32, offset: f32, words: &[u16]) -> DataMoment {
+    let gates = u16::try_from(words.len()).expect("fits a halfword");
+    let data = GenericData::new(
+        &DataBlockProduct::Reflectivity,
+        gates,
+        2125,
+        250,
+        word_size,
+        scale,
+        offset,
+    );
+    let moment_data = if word_size == 8 {
+        words
+            .iter()
+            .map(|word| u8::try_from(*word).expect("fits a byte"))
+            .collect()
+    } else {
+        words.iter().flat_map(|word| word.to_be_bytes()).collect()
+    };
+
+    DataMoment::new(DataBlockProduct::Reflectivity, data, moment_data)
+}
+
+/// A velocity moment's header with the specified gates and word size.
+fn velocity(gates: u16, word_size: u8) -> GenericData {
+    GenericData::new(
+        &DataBlockProduct::Velocity,
+        gates,
+        2125,
+        250,
+        word_size,
+        2.0,
+        129.0,
+    )
+}
+
+/// Fails a test case with an error from the crate.
+fn check<T>(result: anyhow::Result<T>) -> Result<T, TestCaseError> {
+    result.map_err(|error| TestCaseError::fail(error.to_string()))
+}
+
+/// A word size with raw words valid for it.
+fn words(max_gates: usize) -> impl Strategy<Value = (u8, Vec<u16>)> {
+    word_size().prop_flat_map(move |word_size| {
+        (
+            Just(word_size),
+            prop::collection::vec(0..=max_word(word_size), 1..=max_gates),
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn reserved_words(scale in prop::num::f32::ANY, offset in prop::num::f32::ANY) {
+        prop_assert_eq!(GateValue::from_raw(0, scale, offset), GateValue::BelowThreshold);
+        prop_assert_eq!(GateValue::from_raw(1, scale, offset), GateValue::RangeFolded);
+    }
+
+    #[test]
+    fn zero_scale_is_unscaled(raw in 2u16.., offset in prop::num::f32::ANY) {
+        prop_assert_eq!(GateValue::from_raw(raw, 0.0, offset), GateValue::Value(f32::from(raw)));
+    }
+
+    #[test]
+    fn scaled_values_invert(raw in 2u16.., scale in scale(), offset in offset()) {
+        let value = GateValue::from_raw(raw, scale, offset).value().expect("is a value");
+        prop_assert!((value * scale + offset - f32::from(raw)).abs() < 0.05);
+    }
+
+    #[test]
+    fn words_round_trip_through_values(
+        (word_size, words) in words(64),
+        scale in scale(),
+        offset in offset(),
+    ) {
+        let original = moment(word_size, scale, offset, &words);
+        let mut copy = moment(word_size, scale, offset, &vec![2; words.len()]);
+        for (index, value) in original.values().enumerate() {
+            check(copy.set_value(index, value))?;
+        }
+
+        prop_assert_eq!(copy.moment_data(), original.moment_data());
+    }
+
+    #[test]
+    fn unscaled_words_round_trip_through_values(
+        (word_size, words) in words(64),
+        offset in offset(),
+    ) {
+        let original = moment(word_size, 0.0, offset, &words);
+        let mut copy = moment(word_size, 0.0, offset, &vec![2; words.len()]);
+        for (index, value) in original.values().enumerate() {
+            check(copy.set_value(index, value))?;
+        }
+
+        prop_assert_eq!(copy.moment_data(), original.moment_data());
+    }
+
+    #[test]
+    fn values_are_clamped_to_words(
+        word_size in word_size(),
+        scale in prop_oneof![Just(0.0), scale()],
+        offset in offset(),
+        value in prop::num::f32::NORMAL | prop::num::f32::ZERO | prop::num::f32::INFINITE,
+    ) {
+        let mut moment = moment(word_size, scale, offset, &[2]);
+        check(moment.set_value(0, GateValue::Value(value)))?;
+
+        // Values never encode as the reserved words, nor overflow the word
+        let raw = moment.raw_value(0).expect("has a gate");
+        prop_assert!((2..=max_word(word_size)).contains(&raw));
+    }
+
+    #[test]
+    fn words_round_trip(
+        (word_size, words) in words(usize::from(MAX_GATES)),
+        scale in prop::num::f32::ANY,
+        offset in prop::num::f32::ANY,
+    ) {
+        let mut moment = moment(word_size, scale, offset, &vec![0; words.len()]);
+        for (index, word) in words.iter().enumerate() {
+            check(moment.set_raw_value(index, *word))?;
+        }
+
+        prop_assert_eq!(moment.gate_count(), words.len());
+        prop_assert_eq!(moment.data().moment_size(), moment.moment_data().len());
+        let raw: Vec<u16> = (0..words.len())
+            .filter_map(|index| moment.raw_value(index))
+            .collect();
+        prop_assert_eq!(raw, words);
+        prop_assert!(moment.raw_value(moment.gate_count()).is_none());
+    }
+
+    #[test]
+    fn oversized_words_are_rejected(word in 256u16.., index in 0usize..4) {
+        let mut moment = moment(8, 2.0, 66.0, &[2; 4]);
+        prop_assert!(moment.set_raw_value(index, word).is_err());
+        prop_assert!(moment.set_raw_value(4, 2).is_err());
+        prop_assert_eq!(moment.moment_data(), &[2; 4]);
+    }
+
+    #[test]
+    fn moment_size(gates in 0u16.., word_size in word_size(), bytes in 0usize..=4096) {
+        let data = velocity(gates, word_size);
+        prop_assert_eq!(data.moment_size(), usize::from(gates) * usize::from(word_size) / 8);
+
+        // Moments shorter than their header claims have only the gates their data holds
+        let word_bytes = usize::from(word_size) / 8;
+        let moment = DataMoment::new(DataBlockProduct::Velocity, data, vec![2; bytes]);
+        prop_assert_eq!(moment.gate_count(), (bytes / word_bytes).min(usize::from(gates)));
+        prop_assert_eq!(moment.values().count(), moment.gate_count());
+    }
+
+    #[test]
+    fn undefined_word_sizes_have_no_gates(word_size in any::<u8>(), gates in 0u16..=MAX_GATES) {
+        prop_assume!(word_size != 8 && word_size != 16);
+        let moment = DataMoment::new(
+            DataBlockProduct::Velocity,
+            velocity(gates, word_size),
+            vec![2; usize::from(gates) * 2],
+        );
+
+        prop_assert_eq!(moment.gate_count(), 0);
+        prop_assert!(moment.raw_value(0).is_none());
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn moments_round_trip_through_encoder(
+        (word_size, words) in words(usize::from(MAX_GATES)),
+        scale in prop_oneof![Just(0.0), scale()],
+        offset in offset(),
+    ) {
+        let original = moment(word_size, scale, offset, &words);
+        let header = Message31Header::new(*b"KTST", 43_200_000, 19_875, 1, 0.5, 1, 3, 1, 0.5);
+        let radial = Message31::new(header).with_data_moment(original.clone());
+        let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 43_200_000, *b"KTST");
+        let file = check(DataFile::from_parts(header, vec![Sweep::new(1, vec![radial])]))?;
+
+        let decoded = check(encode_file(&file).and_then(DataFile::from_vec))?;
+        let moment = decoded.elevation_scans()[&1][0]
+            .reflectivity_data()
+            .expect("has reflectivity");
+        prop_assert_eq!(moment.moment_data(), original.moment_data());
+        prop_assert_eq!(moment.data().data_word_size(), word_size);
+        prop_assert_eq!(moment.data().scale().to_bits(), scale.to_bits());
+        prop_assert_eq!(moment.data().offset().to_bits(), offset.to_bits());
+        prop_assert!(moment.values().eq(original.values()));
+    }
+}
+
+/// Radials too long for their header's halfword length are refused rather than truncated.
+#[test]
+fn oversized_radials_are_rejected() -> anyhow::Result<()> {
+    let words = vec![2; usize::from(u16::MAX)];
+    let header = Message31Header::new(*b"KTST", 43_200_000, 19_875, 1, 0.5, 1, 3, 1, 0.5);
+    let radial = Message31::new(header).with_data_moment(moment(8, 2.0, 66.0, &words));
+    let header = VolumeHeaderRecord::new(*b"AR2V0006.001", 19_875, 43_200_000, *b"KTST");
+    let file = DataFile::from_parts(header, vec![Sweep::new(1, vec![radial])])?;
+
+    assert!(encode_file(&file).is_err());
+
+    Ok(())
+}